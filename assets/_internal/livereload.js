@@ -0,0 +1,33 @@
+(function () {
+  // Injected by `codex serve`. Polls the server for file changes and either
+  // swaps stylesheets in place or reloads the page.
+  let version = null;
+
+  function reloadStyles(v) {
+    document.querySelectorAll('link[rel="stylesheet"]').forEach((link) => {
+      let url = new URL(link.href);
+      url.searchParams.set("codex-reload", v);
+      link.href = url.toString();
+    });
+  }
+
+  async function poll() {
+    try {
+      let res = await fetch(`/_codex/changes?since=${version ?? 0}`);
+      let data = await res.json();
+      if (version !== null && data.version > version) {
+        if (data.full) {
+          window.location.reload();
+          return;
+        }
+        reloadStyles(data.version);
+      }
+      version = data.version;
+    } catch (e) {
+      // The server may be restarting; keep polling.
+    }
+    setTimeout(poll, 1000);
+  }
+
+  poll();
+}());
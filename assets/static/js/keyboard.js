@@ -0,0 +1,31 @@
+(function () {
+  // Key bindings are configured in codex.yml and rendered onto the body tag.
+  let bindings = {};
+  if (document.body.dataset.keyPrevious) {
+    bindings[document.body.dataset.keyPrevious] = "prev";
+  }
+  if (document.body.dataset.keyNext) {
+    bindings[document.body.dataset.keyNext] = "next";
+  }
+
+  document.addEventListener("keydown", (e) => {
+    if (e.altKey || e.ctrlKey || e.metaKey || e.shiftKey) {
+      return;
+    }
+    let target = e.target;
+    if (
+      target.isContentEditable ||
+      ["INPUT", "TEXTAREA", "SELECT"].includes(target.tagName)
+    ) {
+      return;
+    }
+    let rel = bindings[e.key];
+    if (!rel) {
+      return;
+    }
+    let link = document.querySelector(`link[rel="${rel}"]`);
+    if (link) {
+      window.location.href = link.href;
+    }
+  });
+}());
@@ -0,0 +1,54 @@
+(function () {
+  // Hover cards for internal links, filled from the metadata written beside
+  // each page. The url style is rendered onto the body tag.
+  let style = document.body.dataset.linkPreviews;
+  let baseUrl = document.body.dataset.baseUrl || "/";
+  let cache = {};
+
+  function metadataUrl(link) {
+    let url = new URL(link.href, window.location.href);
+    if (url.origin !== window.location.origin || !url.pathname.startsWith(baseUrl)) {
+      return null;
+    }
+    if (style === "file") {
+      return url.pathname.endsWith(".html")
+        ? url.pathname.replace(/\.html$/, ".meta.json")
+        : null;
+    }
+    return url.pathname.replace(/\/?$/, "/index.meta.json");
+  }
+
+  function load(url) {
+    if (!cache[url]) {
+      cache[url] = fetch(url).then((response) => (response.ok ? response.json() : null));
+    }
+    return cache[url];
+  }
+
+  document.querySelectorAll("#content a[href]").forEach((link) => {
+    let url = metadataUrl(link);
+    if (!url || link.getAttribute("href").startsWith("#")) {
+      return;
+    }
+    link.addEventListener("mouseenter", () => {
+      load(url)
+        .then((metadata) => {
+          if (!metadata || link.dataset.previewShown) {
+            return;
+          }
+          link.dataset.previewShown = "true";
+          let popover = new bootstrap.Popover(link, {
+            title: metadata.title,
+            content: metadata.description || metadata.subtitle || "",
+            trigger: "hover focus",
+            placement: "top",
+            customClass: "link-preview",
+          });
+          if (link.matches(":hover")) {
+            popover.show();
+          }
+        })
+        .catch(() => {});
+    });
+  });
+}());
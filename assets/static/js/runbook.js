@@ -0,0 +1,59 @@
+(function () {
+  // Checked steps of each runbook are kept in local storage, so progress
+  // survives reloads while an operator works through a procedure.
+  document.querySelectorAll(".runbook").forEach((runbook) => {
+    let key = "codex-runbook:" + runbook.dataset.runbook;
+    let checks = runbook.querySelectorAll(".step-check");
+    let progress = runbook.querySelector(".runbook-progress");
+
+    function load() {
+      try {
+        return JSON.parse(localStorage.getItem(key)) || [];
+      } catch (e) {
+        return [];
+      }
+    }
+
+    function update() {
+      let done = [];
+      checks.forEach((check) => {
+        check.closest(".step").classList.toggle("done", check.checked);
+        if (check.checked) {
+          done.push(check.dataset.step);
+        }
+      });
+      localStorage.setItem(key, JSON.stringify(done));
+      progress.innerText = done.length + " / " + checks.length + " " + progress.dataset.label;
+    }
+
+    let saved = load();
+    checks.forEach((check) => {
+      check.checked = saved.includes(check.dataset.step);
+      check.addEventListener("change", update);
+    });
+    update();
+
+    runbook.querySelector(".runbook-reset").addEventListener("click", () => {
+      checks.forEach((check) => (check.checked = false));
+      update();
+    });
+
+    let copy = runbook.querySelector(".runbook-copy");
+    let commands = [];
+    runbook.querySelectorAll(".step-content pre").forEach((pre) => {
+      let lines = [];
+      pre.querySelectorAll("code").forEach((line) => lines.push(line.innerText));
+      commands.push(lines.join("\n"));
+    });
+    if (!navigator.clipboard || commands.length === 0) {
+      copy.remove();
+      return;
+    }
+    let label = copy.innerText;
+    copy.addEventListener("click", () => {
+      navigator.clipboard.writeText(commands.join("\n"));
+      copy.innerText = "✓ " + label;
+      setTimeout(() => (copy.innerText = label), 2000);
+    });
+  });
+}());
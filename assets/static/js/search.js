@@ -0,0 +1,116 @@
+(function () {
+  // Client side search over the index generated by `codex build`. Records
+  // are sections of a page, so results link directly to the heading.
+  const indexUrl = document.body.dataset.searchIndex;
+  const input = document.querySelector("#search-input");
+  const results = document.querySelector("#search-results");
+  if (!indexUrl || !input || !results) {
+    return;
+  }
+
+  let index = null;
+
+  async function loadIndex() {
+    if (index === null) {
+      let res = await fetch(indexUrl);
+      index = await res.json();
+    }
+    return index;
+  }
+
+  // Split a query into terms, dropping stop words. Each term is expanded
+  // to the list of words it matches, itself and its synonyms.
+  function terms(query, { synonyms, stop_words }) {
+    return query
+      .toLowerCase()
+      .split(/\s+/)
+      .filter((t) => t.length > 0 && !stop_words.includes(t))
+      .map((t) => [t, ...(Object.hasOwn(synonyms, t) ? synonyms[t] : [])]);
+  }
+
+  // Field weights and folder boosts are configured in codex.yml and
+  // embedded in the index.
+  function score(record, queryTerms, weights) {
+    let fields = {
+      title: record.title.toLowerCase(),
+      heading: record.heading.toLowerCase(),
+      tags: record.tags.join(" ").toLowerCase(),
+      text: record.text.toLowerCase(),
+    };
+    let total = 0;
+    for (let words of queryTerms) {
+      let matched = false;
+      for (let field in fields) {
+        if (words.some((word) => fields[field].includes(word))) {
+          total += weights[field];
+          matched = true;
+        }
+      }
+      if (!matched) {
+        return 0;
+      }
+    }
+    return total * record.boost;
+  }
+
+  function excerpt(text, queryTerms) {
+    let lower = text.toLowerCase();
+    let idx = Math.max(0, ...queryTerms.map((t) => lower.indexOf(t)));
+    let start = Math.max(0, idx - 40);
+    return (start > 0 ? "…" : "") + text.substring(start, start + 120) + "…";
+  }
+
+  function render(matches, queryTerms) {
+    results.innerHTML = "";
+    if (matches.length === 0) {
+      let empty = document.createElement("span");
+      empty.className = "dropdown-item-text text-secondary";
+      empty.innerText = "No results";
+      results.appendChild(empty);
+    }
+    for (let record of matches) {
+      let link = document.createElement("a");
+      link.className = "dropdown-item";
+      link.href = record.url;
+      let title = document.createElement("div");
+      title.className = "fw-bold text-truncate";
+      title.innerText = record.heading
+        ? `${record.title} › ${record.heading}`
+        : record.title;
+      let text = document.createElement("div");
+      text.className = "small text-secondary text-truncate";
+      // The first section of a page shows the page's description
+      text.innerText = record.description || excerpt(record.text, queryTerms);
+      link.appendChild(title);
+      link.appendChild(text);
+      results.appendChild(link);
+    }
+    results.classList.add("show");
+  }
+
+  input.addEventListener("input", async () => {
+    let index = await loadIndex();
+    let queryTerms = terms(input.value, index);
+    if (queryTerms.length === 0) {
+      results.classList.remove("show");
+      return;
+    }
+    let matches = index.records
+      .map((record) => ({
+        record,
+        score: score(record, queryTerms, index.weights),
+      }))
+      .filter((m) => m.score > 0)
+      .sort((a, b) => b.score - a.score)
+      .slice(0, 10)
+      .map((m) => m.record);
+    render(matches, queryTerms.flat());
+  });
+
+  input.addEventListener("focus", loadIndex);
+  document.addEventListener("click", (e) => {
+    if (!results.contains(e.target) && e.target !== input) {
+      results.classList.remove("show");
+    }
+  });
+}());
@@ -0,0 +1,62 @@
+(function () {
+  // Display settings are stored per browser and applied as attributes on the
+  // root element, which the rules in accessibility.css are scoped to.
+  const settings = {
+    contrast: { attribute: "data-contrast", value: "high" },
+    motion: { attribute: "data-motion", value: "reduced" },
+  };
+
+  function apply(name, enabled) {
+    let setting = settings[name];
+    if (enabled) {
+      document.documentElement.setAttribute(setting.attribute, setting.value);
+    } else {
+      document.documentElement.removeAttribute(setting.attribute);
+    }
+  }
+
+  for (let name in settings) {
+    apply(name, localStorage.getItem(`codex-${name}`) === "true");
+  }
+
+  // The color mode is the reader's choice, or the project's default from
+  // the data-color-mode attribute. Automatic follows the system setting.
+  const dark = window.matchMedia("(prefers-color-scheme: dark)");
+
+  function colorMode() {
+    return (
+      localStorage.getItem("codex-color-mode") ||
+      document.documentElement.dataset.colorMode ||
+      "auto"
+    );
+  }
+
+  function applyColorMode() {
+    let mode = colorMode();
+    if (mode === "auto") {
+      mode = dark.matches ? "dark" : "light";
+    }
+    document.documentElement.setAttribute("data-bs-theme", mode);
+  }
+
+  applyColorMode();
+  dark.addEventListener("change", applyColorMode);
+
+  document.addEventListener("DOMContentLoaded", () => {
+    document.querySelectorAll("[data-theme-setting]").forEach((input) => {
+      let name = input.dataset.themeSetting;
+      input.checked = localStorage.getItem(`codex-${name}`) === "true";
+      input.addEventListener("change", () => {
+        localStorage.setItem(`codex-${name}`, input.checked);
+        apply(name, input.checked);
+      });
+    });
+    document.querySelectorAll("[data-color-mode-setting]").forEach((select) => {
+      select.value = colorMode();
+      select.addEventListener("change", () => {
+        localStorage.setItem("codex-color-mode", select.value);
+        applyColorMode();
+      });
+    });
+  });
+}());
@@ -8,10 +8,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     let doc = project.get_document_for_url("/pages/large").unwrap();
 
     let renderer = core::HtmlRenderer {
-        render_context: core::RenderContext {
-            project: &project,
-            document: doc,
-        },
+        render_context: core::RenderContext::new(&project, doc),
     };
 
     c.bench_function("document-body-render", |b| {
@@ -0,0 +1,141 @@
+use markdown::mdast::Node;
+use serde::Deserialize;
+
+use crate::{
+    document::{join_frontmatter, split_frontmatter},
+    project::MarkdownDetails,
+    search::plain_text,
+    Document, Error, Project, Result,
+};
+
+/// Options declared in the front matter of an archetype.
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct ArchetypeDetails {
+    /// Headings every document created from the archetype must contain
+    pub required_sections: Vec<String>,
+}
+
+/// A template for new documents.
+/// Archetypes are markdown files in the `_internal/archetypes` folder. The
+/// archetype's front matter and content are copied into each document
+/// created from it.
+#[derive(Debug, Clone)]
+pub struct Archetype {
+    pub name: String,
+    pub details: ArchetypeDetails,
+    frontmatter: serde_yaml::Mapping,
+    body: String,
+    markdown: MarkdownDetails,
+}
+
+impl Archetype {
+    pub fn load(project: &Project, name: &str) -> Result<Self> {
+        let path = project
+            .path
+            .new_path(format!("_internal/archetypes/{}.md", name));
+        if !path.exists() {
+            return Err(Error::new(format!("Archetype not found: {}", name)));
+        }
+        let content = String::from_utf8(path.read()?.to_vec())?;
+        let (mut frontmatter, body_start) = split_frontmatter(&content)?;
+        let details = serde_yaml::from_value(frontmatter.clone().into())?;
+        frontmatter.remove("required_sections");
+        Ok(Self {
+            name: name.to_string(),
+            details,
+            frontmatter,
+            body: content[body_start..].trim_start().to_string(),
+            markdown: project.details.markdown.clone(),
+        })
+    }
+
+    /// Build the content of a new document from the archetype. The document
+    /// records the archetype it was created from so it can be checked by
+    /// `codex lint`.
+    pub fn new_document(&self, title: &str) -> Result<String> {
+        let mut frontmatter = self.frontmatter.clone();
+        frontmatter.insert("title".into(), title.into());
+        frontmatter.insert("archetype".into(), self.name.clone().into());
+        join_frontmatter(&frontmatter, &self.body)
+    }
+
+    /// The required sections that a document doesn't have a heading for.
+    /// Headings are matched without regard to case or level.
+    pub fn missing_sections(&self, document: &Document) -> Result<Vec<String>> {
+        let ast = crate::document::parse(&document.file_path, &self.markdown)?;
+        let mut headings = vec![];
+        collect_headings(&ast, &mut headings);
+        Ok(self
+            .details
+            .required_sections
+            .iter()
+            .filter(|section| {
+                !headings
+                    .iter()
+                    .any(|h| h.eq_ignore_ascii_case(section.trim()))
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+/// Build the content of a new document that isn't based on an archetype.
+/// The title is written through the YAML serializer, so titles such as
+/// `Setup: part 1` stay valid front matter.
+pub fn blank_document(title: &str) -> Result<String> {
+    let mut frontmatter = serde_yaml::Mapping::new();
+    frontmatter.insert("title".into(), title.into());
+    join_frontmatter(&frontmatter, "")
+}
+
+fn collect_headings(node: &Node, headings: &mut Vec<String>) {
+    if let Node::Heading(_) = node {
+        headings.push(plain_text(node).trim().to_string());
+        return;
+    }
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        collect_headings(child, headings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    use super::{blank_document, Archetype};
+
+    #[test]
+    fn test_new_document() {
+        let project = project_fixture();
+        let archetype = Archetype::load(&project, "guide").unwrap();
+        assert_eq!(
+            archetype.details.required_sections,
+            vec!["Overview", "Examples", "Troubleshooting"]
+        );
+        let content = archetype.new_document("Installing").unwrap();
+        assert!(content.starts_with(
+            "---\ntags:\n- guide\ntitle: Installing\narchetype: guide\n---\n\n# Overview"
+        ));
+        assert!(Archetype::load(&project, "missing").is_err());
+    }
+
+    #[test]
+    fn test_blank_document() {
+        let content = blank_document("Setup: part 1").unwrap();
+        assert_eq!(content, "---\ntitle: 'Setup: part 1'\n---\n\n\n");
+        let (frontmatter, _) = crate::document::split_frontmatter(&content).unwrap();
+        assert_eq!(frontmatter["title"], "Setup: part 1");
+    }
+
+    #[test]
+    fn test_missing_sections() {
+        let project = project_fixture();
+        let archetype = Archetype::load(&project, "guide").unwrap();
+        let doc = project.get_document_for_url("/other/archetype").unwrap();
+        assert_eq!(
+            archetype.missing_sections(doc).unwrap(),
+            vec!["Troubleshooting"]
+        );
+    }
+}
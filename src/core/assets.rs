@@ -1,6 +1,7 @@
-use crate::{Error, Project, Result};
+use crate::{manifest::hash_bytes, Error, Project, Result};
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     path::{Path, PathBuf},
 };
 
@@ -46,6 +47,125 @@ pub fn static_files(project: &Project) -> Result<Vec<CodexPath>> {
     Ok(assets)
 }
 
+/// The path a static file is built to, relative to the static folder.
+/// SCSS stylesheets are built to a CSS file of the same name. SCSS partials,
+/// whose names start with `_`, are only imported by other stylesheets and
+/// aren't built.
+pub fn static_name(file: &CodexPath) -> Option<String> {
+    let name = file
+        .relative_path
+        .strip_prefix("static")
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    if !is_scss(file) {
+        return Some(name);
+    }
+    if file.basename()?.starts_with('_') {
+        return None;
+    }
+    Some(format!("{}.css", name.strip_suffix(".scss")?))
+}
+
+/// The content of a static file as it is built, SCSS stylesheets are
+/// compiled to CSS.
+pub fn read_static(file: &CodexPath) -> Result<Vec<u8>> {
+    if is_scss(file) {
+        Ok(compile_scss(file)?.into_bytes())
+    } else {
+        Ok(file.read()?.to_vec())
+    }
+}
+
+pub fn is_scss(file: &CodexPath) -> bool {
+    file.extension().as_deref() == Some("scss")
+}
+
+/// Compile an SCSS stylesheet. Imports are resolved relative to the file.
+pub fn compile_scss(file: &CodexPath) -> Result<String> {
+    let options = grass::Options::default();
+    let css = if file.exists_on_disk() {
+        grass::from_path(file.disk_path(), &options)
+    } else {
+        grass::from_string(String::from_utf8(file.read()?.to_vec())?, &options)
+    };
+    css.map_err(|e| {
+        Error::new(format!(
+            "Could not compile {}: {}",
+            file.relative_path.display(),
+            e
+        ))
+    })
+}
+
+/// Find the SCSS stylesheet a CSS url in the static folder is built from,
+/// such as `static/css/theme.scss` for `/static/css/theme.css`.
+pub fn stylesheet_source(project: &Project, url: &str) -> Option<CodexPath> {
+    let source = project
+        .path
+        .from_url(&format!("{}.scss", url.strip_suffix(".css")?));
+    (source.exists_on_disk() && static_name(&source).is_some()).then_some(source)
+}
+
+/// The fingerprinted name of every static file, keyed by its path in the
+/// static folder. Project files take precedence over the built in ones.
+pub fn fingerprint_assets(project: &Project) -> Result<BTreeMap<String, String>> {
+    let mut assets = BTreeMap::new();
+    for file in static_files(project)? {
+        let Some(name) = static_name(&file) else {
+            continue;
+        };
+        let fingerprinted = fingerprinted_name(&name, &read_static(&file)?);
+        assets.insert(name, fingerprinted);
+    }
+    Ok(assets)
+}
+
+/// The subresource integrity value of every script and stylesheet in the
+/// static folder, keyed by its path in the static folder. Stylesheets are
+/// compiled and files are minified as the build writes them, so the hashes
+/// match the files that are served.
+pub fn integrity_hashes(project: &Project) -> Result<BTreeMap<String, String>> {
+    use base64::Engine;
+    use sha2::Digest;
+
+    let mut hashes = BTreeMap::new();
+    for file in static_files(project)? {
+        let Some(name) = static_name(&file) else {
+            continue;
+        };
+        if !(name.ends_with(".js") || name.ends_with(".css")) {
+            continue;
+        }
+        let content = read_static(&file)?;
+        let content = match project.details.minify_static {
+            true => crate::minify::static_file(&name, &content)?.unwrap_or(content),
+            false => content,
+        };
+        let hash = base64::engine::general_purpose::STANDARD.encode(sha2::Sha384::digest(&content));
+        hashes.insert(name, format!("sha384-{}", hash));
+    }
+    Ok(hashes)
+}
+
+/// Insert a short hash of the content before the file's extension,
+/// `css/article.css` becomes `css/article.1a2b3c4d.css`.
+pub(crate) fn fingerprinted_name(name: &str, content: &[u8]) -> String {
+    let hash = &hash_bytes(content)[..8];
+    let (folder, file) = name.rsplit_once('/').unwrap_or(("", name));
+    let file = match file.split_once('.').filter(|(stem, _)| !stem.is_empty()) {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, hash, extension),
+        None => format!("{}.{}", file, hash),
+    };
+    if folder.is_empty() {
+        file
+    } else {
+        format!("{}/{}", folder, file)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Serialize)]
 pub struct CodexPath {
     pub(crate) project_root: PathBuf,
@@ -89,6 +209,14 @@ impl CodexPath {
             .map(|s| s.to_string())
     }
 
+    /// The lowercased file extension of the path.
+    pub fn extension(&self) -> Option<String> {
+        self.relative_path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+    }
+
     pub fn for_project<P>(project_root: P) -> Self
     where
         P: Into<PathBuf> + Clone,
@@ -192,7 +320,7 @@ impl CodexPath {
         )
     }
 
-    pub fn read(&self) -> Result<Cow<[u8]>> {
+    pub fn read(&self) -> Result<Cow<'_, [u8]>> {
         if self.exists_on_disk() {
             Ok(Cow::Owned(std::fs::read(self.disk_path())?))
         } else if self.exists_embedded() {
@@ -207,6 +335,16 @@ impl CodexPath {
         }
     }
 
+    /// The path of the folder containing this file.
+    pub fn folder_path(&self) -> Self {
+        self.new_path(self.relative_path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// The URL of the printable view for the folder at this path.
+    pub fn print_url(&self) -> String {
+        format!("{}/print.html", self.root_url().trim_end_matches('/'))
+    }
+
     pub fn is_index(&self) -> bool {
         self.relative_path.file_name().and_then(|s| s.to_str()) == Some("index.md")
     }
@@ -215,12 +353,20 @@ impl CodexPath {
         self.project_root = root_path.to_path_buf();
     }
 
-    pub fn write(&self, destination_root: &Path, strip: PathBuf) -> Result<usize> {
-        let dst = self
+    /// The path `write` copies the file to.
+    pub fn destination(&self, destination_root: &Path, strip: PathBuf) -> Result<PathBuf> {
+        Ok(self
             .project_root
             .join(destination_root)
-            .join(self.relative_path.strip_prefix(strip)?);
+            .join(self.relative_path.strip_prefix(strip)?))
+    }
+
+    pub fn write(&self, destination_root: &Path, strip: PathBuf) -> Result<usize> {
+        self.write_to(&self.destination(destination_root, strip)?)
+    }
 
+    /// Copy the file to `dst`, creating its folder if needed.
+    pub fn write_to(&self, dst: &Path) -> Result<usize> {
         if let Some(parent) = dst.parent() {
             if !parent.exists() {
                 std::fs::create_dir_all(parent)?;
@@ -282,6 +428,92 @@ mod tests {
             ));
     }
 
+    #[test]
+    fn test_scss() {
+        let project = Project::load(PathBuf::from("test").join("fixture"), false).unwrap();
+        let theme = project.path.join("static/styles/theme.scss");
+        assert_eq!(
+            crate::assets::static_name(&theme).as_deref(),
+            Some("styles/theme.css")
+        );
+        assert!(
+            crate::assets::static_name(&project.path.join("static/styles/_variables.scss"))
+                .is_none()
+        );
+        assert_eq!(
+            String::from_utf8(crate::assets::read_static(&theme).unwrap()).unwrap(),
+            ".callout {\n  border-color: #0d6efd;\n}\n.callout .title {\n  font-weight: bold;\n}\n"
+        );
+
+        assert_eq!(
+            crate::assets::stylesheet_source(&project, "/static/styles/theme.css"),
+            Some(theme)
+        );
+        assert!(
+            crate::assets::stylesheet_source(&project, "/static/styles/_variables.css").is_none()
+        );
+        assert!(crate::assets::stylesheet_source(&project, "/static/styles/missing.css").is_none());
+        assert!(crate::assets::fingerprint_assets(&project)
+            .unwrap()
+            .contains_key("styles/theme.css"));
+    }
+
+    #[test]
+    fn test_integrity_hashes() {
+        let mut project = Project::load(PathBuf::from("test").join("fixture"), false).unwrap();
+        let hashes = crate::assets::integrity_hashes(&project).unwrap();
+        let article = hashes.get("css/article.css").unwrap();
+        // 48 byte digests are 64 characters of base64
+        assert!(article.starts_with("sha384-"));
+        assert_eq!(article.len(), "sha384-".len() + 64);
+        assert!(hashes.contains_key("js/theme.js"));
+        assert!(!hashes.keys().any(|k| k.ends_with(".png")));
+
+        project.details.minify_static = true;
+        let minified = crate::assets::integrity_hashes(&project).unwrap();
+        assert_ne!(minified.get("css/article.css").unwrap(), article);
+
+        project.details.integrity = hashes.clone();
+        let page = project
+            .get_document_for_url("/other/alert")
+            .unwrap()
+            .render(&project, crate::OutputFormat::Html)
+            .unwrap();
+        assert!(page.contains(&format!(
+            r#"href="/css/article.css" integrity="{}" crossorigin="anonymous">"#,
+            article
+        )));
+    }
+
+    #[test]
+    fn test_fingerprint_assets() {
+        let project = Project::load(PathBuf::from("test").join("fixture"), false).unwrap();
+        let assets = crate::assets::fingerprint_assets(&project).unwrap();
+        let article = assets.get("css/article.css").unwrap();
+        assert!(article.starts_with("css/article.") && article.ends_with(".css"));
+        assert_eq!(article.len(), "css/article.css".len() + 9);
+        assert!(assets.contains_key("nested/object"));
+
+        assert_eq!(
+            super::fingerprinted_name("js/search.min.js", b"content"),
+            "js/search.ed7002b4.min.js"
+        );
+        assert_eq!(
+            super::fingerprinted_name("LICENSE", b"content"),
+            "LICENSE.ed7002b4"
+        );
+
+        let mut details = project.details.clone();
+        details.base_url = "/docs/".to_string();
+        details.assets = assets.clone();
+        let html = crate::render_template(
+            std::collections::HashMap::from([("project", details)]),
+            r#"{{asset "css/article.css"}} {{asset "js/custom.js"}}"#,
+        )
+        .unwrap();
+        assert_eq!(html, format!("/docs/{} /docs/js/custom.js", article));
+    }
+
     #[test]
     fn test_codex_path() {
         let cp = CodexPath::new(
@@ -329,4 +561,16 @@ mod tests {
         );
         assert_eq!(cp.document_url(), "/one/two/another");
     }
+
+    #[test]
+    fn test_codex_path_print_url() {
+        let cp = CodexPath::new(
+            PathBuf::from("parent").join("root"),
+            PathBuf::from("one").join("two").join("another.md"),
+        );
+        assert_eq!(cp.folder_path().print_url(), "/one/two/print.html");
+
+        let cp = CodexPath::new(PathBuf::from("root"), PathBuf::from("index.md"));
+        assert_eq!(cp.folder_path().print_url(), "/print.html");
+    }
 }
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use crate::{project::BudgetDetails, Result};
+
+/// File extensions counted against the image budget.
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "avif"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetKind {
+    Page,
+    Image,
+    Folder,
+}
+
+impl std::fmt::Display for BudgetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            BudgetKind::Page => "page",
+            BudgetKind::Image => "image",
+            BudgetKind::Folder => "folder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A built file or folder that is larger than its budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetViolation {
+    pub kind: BudgetKind,
+    /// The path of the file or folder, relative to the build folder
+    pub path: String,
+    pub size: u64,
+    pub limit: u64,
+}
+
+/// Compare the files in a build folder against the project's size budgets.
+pub fn check_budgets(budgets: &BudgetDetails, build_path: &Path) -> Result<Vec<BudgetViolation>> {
+    let mut violations = vec![];
+    let mut folder_sizes = budgets
+        .folders
+        .keys()
+        .map(|folder| (folder.trim_matches('/').to_string(), 0))
+        .collect::<Vec<(String, u64)>>();
+
+    for entry in walkdir::WalkDir::new(build_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let size = entry.metadata()?.len();
+        let path = entry
+            .path()
+            .strip_prefix(build_path)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let extension = entry
+            .path()
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let limit = match extension.as_str() {
+            "html" => budgets.page.map(|limit| (BudgetKind::Page, limit)),
+            e if IMAGE_EXTENSIONS.contains(&e) => {
+                budgets.image.map(|limit| (BudgetKind::Image, limit))
+            }
+            _ => None,
+        };
+        if let Some((kind, limit)) = limit {
+            if size > limit {
+                violations.push(BudgetViolation {
+                    kind,
+                    path: path.clone(),
+                    size,
+                    limit,
+                });
+            }
+        }
+
+        for (folder, total) in folder_sizes.iter_mut() {
+            if folder.is_empty() || path.starts_with(&format!("{}/", folder)) {
+                *total += size;
+            }
+        }
+    }
+
+    for (folder, limit) in budgets.folders.iter() {
+        let size = folder_sizes
+            .iter()
+            .find(|(f, _)| f == folder.trim_matches('/'))
+            .map(|(_, size)| *size)
+            .unwrap_or_default();
+        if size > *limit {
+            violations.push(BudgetViolation {
+                kind: BudgetKind::Folder,
+                path: folder.clone(),
+                size,
+                limit: *limit,
+            });
+        }
+    }
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::project::BudgetDetails;
+
+    use super::{check_budgets, BudgetKind, BudgetViolation};
+
+    #[test]
+    fn test_check_budgets() {
+        let root = std::env::temp_dir().join(format!("codex-budget-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("guides/setup")).unwrap();
+        std::fs::write(root.join("index.html"), "a".repeat(10)).unwrap();
+        std::fs::write(root.join("guides/index.html"), "a".repeat(50)).unwrap();
+        std::fs::write(root.join("guides/setup/index.html"), "a".repeat(20)).unwrap();
+        std::fs::write(root.join("guides/setup/diagram.png"), "a".repeat(40)).unwrap();
+
+        let budgets = BudgetDetails {
+            page: Some(30),
+            image: Some(30),
+            folders: BTreeMap::from([("guides".to_string(), 100), ("/".to_string(), 1000)]),
+        };
+        let violations = check_budgets(&budgets, &root).unwrap();
+        assert_eq!(
+            violations,
+            vec![
+                BudgetViolation {
+                    kind: BudgetKind::Page,
+                    path: "guides/index.html".to_string(),
+                    size: 50,
+                    limit: 30,
+                },
+                BudgetViolation {
+                    kind: BudgetKind::Image,
+                    path: "guides/setup/diagram.png".to_string(),
+                    size: 40,
+                    limit: 30,
+                },
+                BudgetViolation {
+                    kind: BudgetKind::Folder,
+                    path: "guides".to_string(),
+                    size: 110,
+                    limit: 100,
+                },
+            ]
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
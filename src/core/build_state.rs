@@ -0,0 +1,156 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    manifest::{document_sources, hash_bytes},
+    Document, Project, Result,
+};
+
+/// The state of an interrupted site build, written to the build folder.
+pub const BUILD_STATE_FILE: &str = ".codex-build-state.json";
+/// The state of an interrupted LaTeX build, written to the build folder.
+pub const LATEX_STATE_FILE: &str = ".codex-latex-state.json";
+
+/// A page finished before a build was interrupted.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CompletedPage {
+    /// The hash of the document and its sources when it was built
+    pub hash: String,
+    /// The size of the page's output in bytes
+    pub size: usize,
+    /// The rendered output, for builds that don't write a file per page
+    pub output: Option<String>,
+}
+
+/// The pages completed by an interrupted build. The next build loads it and
+/// skips pages that haven't changed since, so long builds can be resumed
+/// rather than started over.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BuildState {
+    /// The hash of the settings and templates every page is built with
+    #[serde(default)]
+    pub project: String,
+    pub pages: BTreeMap<String, CompletedPage>,
+}
+
+impl BuildState {
+    /// Load the state left by an interrupted build. Returns an empty state
+    /// if the last build finished, the state can't be read, or the project's
+    /// settings or templates changed since.
+    pub fn load(path: &Path, project: &Project) -> Result<Self> {
+        let project = project_hash(project)?;
+        let state: Self = std::fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        if state.project != project {
+            return Ok(Self {
+                project,
+                ..Default::default()
+            });
+        }
+        Ok(state)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Remove the state once a build finishes.
+    pub fn clear(path: &Path) -> Result<()> {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// The completed page for a url, if it was built from the same content.
+    pub fn completed(&self, url: &str, hash: &str) -> Option<&CompletedPage> {
+        self.pages.get(url).filter(|page| page.hash == hash)
+    }
+
+    pub fn record(&mut self, url: &str, hash: String, size: usize, output: Option<String>) {
+        self.pages
+            .insert(url.to_string(), CompletedPage { hash, size, output });
+    }
+}
+
+/// A hash of everything that affects how every page renders: the project's
+/// `codex.yml`, the files in its `_internal` folder, such as templates and
+/// components, the fingerprinted names and integrity hashes of its static
+/// files, the HTML injected into every page, and the Codex version for the
+/// built in templates.
+pub fn project_hash(project: &Project) -> Result<String> {
+    let root = project.path.disk_path();
+    let mut content = env!("CARGO_PKG_VERSION").as_bytes().to_vec();
+    if let Ok(config) = std::fs::read(root.join("codex.yml")) {
+        content.extend(config);
+    }
+    for entry in walkdir::WalkDir::new(root.join("_internal"))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        content.extend(entry.path().to_string_lossy().as_bytes());
+        content.extend(std::fs::read(entry.path())?);
+    }
+    content.extend(serde_json::to_vec(&project.details.assets)?);
+    content.extend(serde_json::to_vec(&project.details.integrity)?);
+    content.extend(serde_json::to_vec(&project.details.theme)?);
+    // `--strict-templates` checks every page, not just the changed ones
+    content.extend(serde_json::to_vec(&project.details.strict_templates)?);
+    let (head_html, body_html) = project.injected_html()?;
+    content.extend(head_html.as_bytes());
+    content.extend(body_html.as_bytes());
+    Ok(hash_bytes(&content))
+}
+
+/// A hash of a document's markdown and the source files it is generated
+/// from. It changes whenever the page would render differently.
+pub fn page_hash(project: &Project, document: &Document) -> Result<String> {
+    let mut content = document.file_path.read()?.to_vec();
+    for source in document_sources(project, document)? {
+        content.extend(source.hash.as_bytes());
+    }
+    Ok(hash_bytes(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    use super::{page_hash, project_hash, BuildState};
+
+    #[test]
+    fn test_resume_state() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/proto_schema").unwrap();
+        let hash = page_hash(&project, doc).unwrap();
+        assert_eq!(hash.len(), 64);
+
+        let mut state = BuildState::load(&std::env::temp_dir().join("missing"), &project).unwrap();
+        assert_eq!(state.project, project_hash(&project).unwrap());
+        state.record(&doc.url, hash.clone(), 10, Some("output".to_string()));
+
+        let path = std::env::temp_dir().join(format!("codex-state-{}.json", std::process::id()));
+        state.save(&path).unwrap();
+        let state = BuildState::load(&path, &project).unwrap();
+        assert_eq!(
+            state.completed(&doc.url, &hash).unwrap().output.as_deref(),
+            Some("output")
+        );
+        assert!(state.completed(&doc.url, "changed").is_none());
+
+        // Changing the settings or templates starts the build over
+        let mut changed = state.clone();
+        changed.project = "old".to_string();
+        changed.save(&path).unwrap();
+        assert!(BuildState::load(&path, &project).unwrap().pages.is_empty());
+
+        BuildState::clear(&path).unwrap();
+        assert!(BuildState::load(&path, &project).unwrap().pages.is_empty());
+    }
+}
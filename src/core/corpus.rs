@@ -0,0 +1,427 @@
+use markdown::mdast::Node;
+
+use crate::{
+    renderer::slugify, search::plain_text, Document, Folder, OutputTarget, Project, RenderContext,
+    Renderer, Result, TextRenderer,
+};
+
+/// The index of the site for language models, written to the build root.
+pub const LLMS_FILE: &str = "llms.txt";
+
+/// The folder in the build the plain text chunks of each page are written
+/// to.
+pub const CORPUS_FOLDER: &str = "corpus";
+
+/// A heading bounded piece of a page, small enough to embed or to pass to
+/// a language model as context.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Chunk {
+    /// The url of the section the chunk is from, including its anchor
+    pub url: String,
+    pub title: String,
+    /// The heading of the section, empty for content before the first
+    /// heading
+    pub heading: String,
+    pub text: String,
+    /// The estimated number of tokens in the text
+    pub tokens: usize,
+}
+
+/// A rough token count for English text, about four characters a token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// The url of a page, fully qualified when the project's `site_url` is set.
+fn page_url(project: &Project, document: &Document) -> String {
+    let details = &project.details;
+    let url = details.page_url(&document.url, document.is_index());
+    details
+        .absolute_url(&url)
+        .unwrap_or_else(|| format!("{}{}", details.base_url, url.trim_start_matches('/')))
+}
+
+/// The file a page's chunks are written to, relative to the corpus folder.
+pub fn corpus_file(document: &Document) -> String {
+    match document.url.trim_matches('/') {
+        "" => "index.txt".to_string(),
+        url => format!("{}.txt", url),
+    }
+}
+
+/// A part of a page from one heading to the next.
+struct Section {
+    /// The headings containing the section, ending with its own
+    headings: Vec<String>,
+    anchor: Option<String>,
+    /// The plain text of each block in the section
+    blocks: Vec<String>,
+}
+
+/// Split a document's plain text at its headings.
+fn document_sections(
+    project: &Project,
+    document: &Document,
+    renderer: &TextRenderer,
+) -> Result<Vec<Section>> {
+    let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+    let mut sections = vec![Section {
+        headings: vec![],
+        anchor: None,
+        blocks: vec![],
+    }];
+    let mut path: Vec<(u8, String)> = vec![];
+    for node in ast.children().map(|c| c.as_slice()).unwrap_or_default() {
+        if let Node::Heading(heading) = node {
+            let text = plain_text(node);
+            path.retain(|(depth, _)| *depth < heading.depth);
+            path.push((heading.depth, text.clone()));
+            sections.push(Section {
+                headings: path.iter().map(|(_, h)| h.clone()).collect(),
+                anchor: Some(slugify(&text)),
+                blocks: vec![],
+            });
+        } else if let Some(section) = sections.last_mut() {
+            let text = renderer.render_node(node)?;
+            if !text.trim().is_empty() {
+                section.blocks.push(text.trim().to_string());
+            }
+        }
+    }
+    Ok(sections)
+}
+
+/// Join the blocks of a section into pieces of at most `max_tokens`,
+/// splitting between blocks, and between the lines of blocks that are
+/// longer on their own.
+fn split_section(blocks: &[String], max_tokens: usize) -> Vec<String> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    for piece in blocks
+        .iter()
+        .flat_map(|block| split_lines(block, max_tokens))
+    {
+        // The separating blank line can add a token
+        if !text.is_empty() && estimate_tokens(&text) + estimate_tokens(&piece) + 1 > max_tokens {
+            pieces.push(std::mem::take(&mut text));
+        }
+        if !text.is_empty() {
+            text.push_str("\n\n");
+        }
+        text.push_str(&piece);
+    }
+    if !text.is_empty() {
+        pieces.push(text);
+    }
+    pieces
+}
+
+/// Split a document into chunks at its headings. Sections longer than
+/// `max_tokens` are split between blocks, and blocks that are longer on
+/// their own between lines.
+pub fn document_chunks(
+    project: &Project,
+    document: &Document,
+    max_tokens: usize,
+) -> Result<Vec<Chunk>> {
+    let renderer = TextRenderer::new(RenderContext::new(project, document));
+    let url = page_url(project, document);
+
+    let mut chunks = vec![];
+    for section in document_sections(project, document, &renderer)? {
+        let url = match section.anchor {
+            Some(ref anchor) => format!("{}#{}", url, anchor),
+            None => url.clone(),
+        };
+        let heading = section.headings.last().cloned().unwrap_or_default();
+        let mut pieces = split_section(&section.blocks, max_tokens);
+        // Headings without content still get a chunk
+        if pieces.is_empty() && !heading.is_empty() {
+            pieces.push(String::new());
+        }
+        for text in pieces {
+            chunks.push(Chunk {
+                url: url.clone(),
+                title: document.frontmatter.title.clone(),
+                heading: heading.clone(),
+                tokens: estimate_tokens(&text),
+                text,
+            });
+        }
+    }
+    Ok(chunks)
+}
+
+/// A record of the embeddings export. The fields are a stable contract for
+/// the pipelines loading the records into vector databases.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct EmbeddingRecord {
+    /// A unique id for the record, the page's url and anchor followed by
+    /// the chunk's position in the section. Anchors repeated on a page,
+    /// from headings with the same text, are numbered from `-1` on.
+    pub id: String,
+    /// The url of the page, without the anchor
+    pub url: String,
+    /// The anchor of the section, `None` for content before the first
+    /// heading
+    pub anchor: Option<String>,
+    pub title: String,
+    /// The headings containing the section, from the outermost
+    pub heading_path: Vec<String>,
+    pub text: String,
+    pub tags: Vec<String>,
+}
+
+/// Split a document into records for embedding, chunked at its headings.
+/// Long sections are split into chunks of at most `max_tokens`, each
+/// repeating about `overlap` tokens from the end of the one before it.
+pub fn embedding_records(
+    project: &Project,
+    document: &Document,
+    max_tokens: usize,
+    overlap: usize,
+) -> Result<Vec<EmbeddingRecord>> {
+    if overlap >= max_tokens {
+        return Err(crate::Error::new(
+            "The overlap of chunks must be smaller than their length",
+        ));
+    }
+    let renderer = TextRenderer {
+        link_urls: false,
+        ..TextRenderer::new(RenderContext::new(project, document))
+    };
+    let url = page_url(project, document);
+
+    let mut records = vec![];
+    let mut anchors: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+    let sections = document_sections(project, document, &renderer)?;
+    for section in sections.iter() {
+        let anchor = section.anchor.as_deref().unwrap_or_default();
+        let repeats = anchors.entry(anchor).or_default();
+        let key = match *repeats {
+            0 => anchor.to_string(),
+            n => format!("{}-{}", anchor, n),
+        };
+        *repeats += 1;
+        let mut previous: Option<String> = None;
+        for (i, piece) in split_section(&section.blocks, max_tokens - overlap)
+            .into_iter()
+            .enumerate()
+        {
+            let text = match previous {
+                Some(ref previous) if overlap > 0 => {
+                    format!("{}\n\n{}", tail(previous, overlap), piece)
+                }
+                _ => piece.clone(),
+            };
+            records.push(EmbeddingRecord {
+                id: format!("{}#{}:{}", url, key, i),
+                url: url.clone(),
+                anchor: section.anchor.clone(),
+                title: document.frontmatter.title.clone(),
+                heading_path: section.headings.clone(),
+                text,
+                tags: document.frontmatter.tags.clone(),
+            });
+            previous = Some(piece);
+        }
+    }
+    Ok(records)
+}
+
+/// About the last `tokens` tokens of a text, starting at a word.
+fn tail(text: &str, tokens: usize) -> &str {
+    let chars = text.chars().count();
+    if chars <= tokens * 4 {
+        return text;
+    }
+    let start = text
+        .char_indices()
+        .nth(chars - tokens * 4)
+        .map_or(0, |(i, _)| i);
+    match text[start..].find(char::is_whitespace) {
+        Some(space) => text[start + space..].trim_start(),
+        None => &text[start..],
+    }
+}
+
+/// Split a block that's too long for a chunk between its lines.
+fn split_lines(block: &str, max_tokens: usize) -> Vec<String> {
+    if estimate_tokens(block) <= max_tokens {
+        return vec![block.to_string()];
+    }
+    let mut pieces: Vec<String> = vec![];
+    for line in block.lines() {
+        match pieces.last_mut() {
+            Some(piece) if estimate_tokens(piece) + estimate_tokens(line) < max_tokens => {
+                piece.push('\n');
+                piece.push_str(line);
+            }
+            _ => pieces.push(line.to_string()),
+        }
+    }
+    pieces
+}
+
+/// The chunks of a page as a text file, each starting with a block of its
+/// metadata.
+pub fn corpus_text(chunks: &[Chunk]) -> String {
+    chunks
+        .iter()
+        .map(|chunk| {
+            format!(
+                "---\nurl: {}\ntitle: {}\nheading: {}\ntokens: {}\n---\n{}\n",
+                chunk.url, chunk.title, chunk.heading, chunk.tokens, chunk.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The llms.txt index of the site: a list of its pages linking to their
+/// plain text in the corpus folder, grouped by folder.
+pub fn llms_txt(project: &Project) -> Result<String> {
+    let details = &project.details;
+    let link = |document: &Document| -> Result<String> {
+        let path = format!("/{}/{}", CORPUS_FOLDER, corpus_file(document));
+        let url = details
+            .absolute_url(&path)
+            .unwrap_or_else(|| format!("{}{}", details.base_url, path.trim_start_matches('/')));
+        let title = match document.frontmatter.title.as_str() {
+            "" => document.url.as_str(),
+            title => title,
+        };
+        Ok(match document.description() {
+            Some(description) => format!("- [{}]({}): {}\n", title, url, description),
+            None => format!("- [{}]({})\n", title, url),
+        })
+    };
+    let pages = |folder: &Folder| -> Result<String> {
+        folder
+            .documents
+            .iter()
+            .filter(|d| d.has_format(OutputTarget::Html))
+            .map(link)
+            .collect()
+    };
+
+    let mut out = format!("# {}\n\n", details.name);
+    let root = pages(&project.root_folder)?;
+    if !root.is_empty() {
+        out.push_str(&root);
+        out.push('\n');
+    }
+    for folder in project.root_folder.iter_all_folders() {
+        if folder.path == project.root_folder.path {
+            continue;
+        }
+        let list = pages(folder)?;
+        if !list.is_empty() {
+            out.push_str(&format!("## {}\n\n{}\n", folder.get_name(), list));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    #[test]
+    fn test_document_chunks() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/id_target").unwrap();
+        let chunks = super::document_chunks(&project, doc, 500).unwrap();
+        assert_eq!(
+            chunks,
+            vec![super::Chunk {
+                url: "/other/id_target#environments".into(),
+                title: "Deploying".into(),
+                heading: "Environments".into(),
+                text: "Each environment is deployed separately.".into(),
+                tokens: 10,
+            }]
+        );
+
+        // Long sections are split between paragraphs
+        let doc = project.get_document_for_url("/pages/large").unwrap();
+        let whole = super::document_chunks(&project, doc, 100_000).unwrap();
+        let chunks = super::document_chunks(&project, doc, 200).unwrap();
+        assert!(chunks.len() > whole.len());
+        assert!(chunks.iter().all(|c| c.tokens <= 200));
+    }
+
+    #[test]
+    fn test_embedding_records() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/id_target").unwrap();
+        let records = super::embedding_records(&project, doc, 500, 50).unwrap();
+        assert_eq!(
+            records,
+            vec![super::EmbeddingRecord {
+                id: "/other/id_target#environments:0".into(),
+                url: "/other/id_target".into(),
+                anchor: Some("environments".into()),
+                title: "Deploying".into(),
+                heading_path: vec!["Environments".into()],
+                text: "Each environment is deployed separately.".into(),
+                tags: vec!["deploy".into()],
+            }]
+        );
+
+        // Split sections repeat the end of the chunk before
+        let doc = project.get_document_for_url("/pages/large").unwrap();
+        let records = super::embedding_records(&project, doc, 200, 20).unwrap();
+        let (first, second) = (&records[0], &records[1]);
+        assert_eq!(first.anchor, second.anchor);
+        let repeated = second.text.split("\n\n").next().unwrap();
+        assert!(first.text.ends_with(repeated));
+        assert!(records
+            .iter()
+            .all(|r| super::estimate_tokens(&r.text) <= 200 + 1));
+
+        assert!(super::embedding_records(&project, doc, 50, 50).is_err());
+
+        // Headings with the same text get their own ids
+        let root = crate::refactor::tests::writable_fixture("embedding_ids");
+        std::fs::write(
+            root.join("other").join("repeated.md"),
+            "# Setup
+
+First
+
+# Setup
+
+Second
+
+# Setup
+
+Third
+",
+        )
+        .unwrap();
+        let project = crate::Project::load(&root, false).unwrap();
+        let doc = project.get_document_for_url("/other/repeated").unwrap();
+        let records = super::embedding_records(&project, doc, 500, 50).unwrap();
+        assert_eq!(
+            records.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(),
+            vec![
+                "/other/repeated#setup:0",
+                "/other/repeated#setup-1:0",
+                "/other/repeated#setup-2:0"
+            ]
+        );
+        assert!(records.iter().all(|r| r.anchor.as_deref() == Some("setup")));
+    }
+
+    #[test]
+    fn test_llms_txt() {
+        let project = project_fixture();
+        let llms = super::llms_txt(&project).unwrap();
+        assert!(llms.starts_with(&format!("# {}\n\n", project.details.name)));
+        assert!(llms.contains("\n## other\n\n"));
+        assert!(llms.contains(
+            "- [Deploying](/corpus/other/id_target.txt): Each environment is deployed separately.\n"
+        ));
+    }
+}
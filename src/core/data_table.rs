@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{assets::CodexPath, json_schema::load_value, Error, Result};
+
+/// A cell of a data table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DataCell {
+    pub text: String,
+    /// Render the cell as inline code
+    pub code: bool,
+}
+
+/// A table built from an array of objects in a JSON, YAML, or TOML file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DataTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<DataCell>>,
+}
+
+/// Options read from the attributes of a `DataTable` component.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DataTableOptions {
+    /// The dot separated path to the array inside the file
+    pub path: Option<String>,
+    /// Dot separated paths to the value of each column
+    pub columns: Vec<String>,
+    /// Labels for the column headers, in the same order as the columns
+    pub headers: Vec<String>,
+    /// Formats applied to the values of a column, keyed by the column path
+    pub formats: HashMap<String, String>,
+    pub limit: Option<usize>,
+}
+
+impl DataTableOptions {
+    pub fn from_attrs(attrs: &HashMap<String, String>) -> Result<Self> {
+        let list = |name: &str| {
+            attrs
+                .get(name)
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default()
+        };
+        let formats = attrs
+            .get("formats")
+            .map(|formats| {
+                formats
+                    .split(',')
+                    .map(|f| {
+                        f.split_once(':')
+                            .map(|(column, format)| {
+                                (column.trim().to_string(), format.trim().to_string())
+                            })
+                            .ok_or_else(|| {
+                                Error::new(format!(
+                                    "Invalid format: {}, formats must be written as column:format",
+                                    f
+                                ))
+                            })
+                    })
+                    .collect::<Result<HashMap<_, _>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let limit = attrs
+            .get("limit")
+            .map(|limit| {
+                limit
+                    .parse::<usize>()
+                    .map_err(|_| Error::new(format!("Invalid limit: {}", limit)))
+            })
+            .transpose()?;
+        Ok(Self {
+            path: attrs.get("path").cloned(),
+            columns: list("columns"),
+            headers: list("headers"),
+            formats,
+            limit,
+        })
+    }
+}
+
+/// Build a table from a data file. Each object in the array becomes a row.
+/// When no columns are given the keys of the first object are used.
+pub(crate) fn build_data_table(
+    data: &[u8],
+    file: &CodexPath,
+    options: &DataTableOptions,
+) -> Result<DataTable> {
+    let root = load_value(data, file)?;
+    let items = match options.path {
+        Some(ref path) => lookup(&root, path)
+            .ok_or_else(|| Error::new(format!("Data not found at path: {}", path)))?,
+        None => &root,
+    };
+    let Value::Array(items) = items else {
+        return Err(Error::new("DataTable data must be an array of objects"));
+    };
+
+    let columns = if options.columns.is_empty() {
+        items
+            .first()
+            .and_then(|item| item.as_object())
+            .map(|item| item.keys().cloned().collect())
+            .unwrap_or_default()
+    } else {
+        options.columns.clone()
+    };
+
+    let headers = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            options
+                .headers
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| column.clone())
+        })
+        .collect();
+
+    let rows = items
+        .iter()
+        .take(options.limit.unwrap_or(usize::MAX))
+        .map(|item| {
+            columns
+                .iter()
+                .map(|column| {
+                    format_value(
+                        lookup(item, column).unwrap_or(&Value::Null),
+                        options.formats.get(column).map(|f| f.as_str()),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(DataTable { headers, rows })
+}
+
+/// Find a value by a dot separated path. Array items are selected by index,
+/// such as `authors.0.name`.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn format_value(value: &Value, format: Option<&str>) -> Result<DataCell> {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        v => v.to_string(),
+    };
+    match format {
+        None => Ok(DataCell { text, code: false }),
+        Some("code") => Ok(DataCell { text, code: true }),
+        Some("number") => Ok(DataCell {
+            text: value.as_f64().map(|_| group_digits(&text)).unwrap_or(text),
+            code: false,
+        }),
+        Some("yes_no") => Ok(DataCell {
+            text: match value {
+                Value::Bool(true) => "Yes".to_string(),
+                Value::Bool(false) => "No".to_string(),
+                _ => text,
+            },
+            code: false,
+        }),
+        Some(f) => Err(Error::new(format!("Unknown column format: {}", f))),
+    }
+}
+
+/// Add thousands separators to the integer part of a number.
+fn group_digits(number: &str) -> String {
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(n) => ("-", n),
+        None => ("", number),
+    };
+    let (integer, fraction) = match number.split_once('.') {
+        Some((i, f)) => (i, format!(".{}", f)),
+        None => (number, String::new()),
+    };
+    let mut grouped = String::new();
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::assets::CodexPath;
+
+    use super::{build_data_table, group_digits, DataCell, DataTableOptions};
+
+    #[test]
+    fn test_build_data_table() {
+        let data = r#"
+packages:
+  - name: codex
+    version: 0.4.5
+    downloads: 1234567
+    stable: true
+    meta:
+      license: MIT
+  - name: syntect
+    version: 5.1.0
+    downloads: 950
+    stable: false
+    meta:
+      license: MIT
+"#;
+        let attrs = HashMap::from([
+            ("path".to_string(), "packages".to_string()),
+            (
+                "columns".to_string(),
+                "name, meta.license, downloads, stable".to_string(),
+            ),
+            ("headers".to_string(), "Name, License".to_string()),
+            (
+                "formats".to_string(),
+                "name:code, downloads:number, stable:yes_no".to_string(),
+            ),
+            ("limit".to_string(), "1".to_string()),
+        ]);
+        let options = DataTableOptions::from_attrs(&attrs).unwrap();
+        let table =
+            build_data_table(data.as_bytes(), &CodexPath::new(".", "data.yml"), &options).unwrap();
+        assert_eq!(
+            table.headers,
+            vec!["Name", "License", "downloads", "stable"]
+        );
+        let cell = |text: &str, code: bool| DataCell {
+            text: text.to_string(),
+            code,
+        };
+        assert_eq!(
+            table.rows,
+            vec![vec![
+                cell("codex", true),
+                cell("MIT", false),
+                cell("1,234,567", false),
+                cell("Yes", false),
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_columns_keep_file_order() {
+        let data = r#"[{"zone": "eu", "name": "codex", "active": true}]"#;
+        let options = DataTableOptions::from_attrs(&HashMap::new()).unwrap();
+        let table =
+            build_data_table(data.as_bytes(), &CodexPath::new(".", "data.json"), &options).unwrap();
+        assert_eq!(table.headers, vec!["zone", "name", "active"]);
+    }
+
+    #[test]
+    fn test_group_digits() {
+        assert_eq!(group_digits("950"), "950");
+        assert_eq!(group_digits("1000"), "1,000");
+        assert_eq!(group_digits("-1234567.25"), "-1,234,567.25");
+    }
+}
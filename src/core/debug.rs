@@ -0,0 +1,255 @@
+use markdown::mdast::{AttributeContent, AttributeValue, Node};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{
+    renderer::{is_builtin_component, suggest_component},
+    Document, Project, Result,
+};
+
+/// Where the template or code rendering part of a page comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// A file in the project, overriding Codex's own when there is one
+    Project,
+    /// A template built into Codex
+    Codex,
+    /// Rendered by Codex without a template
+    Builtin,
+    /// Nothing renders it
+    Unknown,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::Project => "project",
+            Source::Codex => "codex",
+            Source::Builtin => "builtin",
+            Source::Unknown => "unknown",
+        })
+    }
+}
+
+/// A component used by a page and how it is rendered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentUse {
+    pub name: String,
+    /// The line of the document the component is on
+    pub line: Option<usize>,
+    pub source: Source,
+    /// The template the component is rendered with, relative to the
+    /// project root
+    pub template: Option<String>,
+    /// The component an unknown name most likely meant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+/// A page template used to render a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateUse {
+    pub path: String,
+    pub source: Source,
+}
+
+/// What a page is made of, for diagnosing why it renders the way it does.
+#[derive(Debug, Clone, Serialize)]
+pub struct PageDebug<'a> {
+    pub url: &'a str,
+    /// The path of the document, relative to the project root
+    pub path: &'a std::path::Path,
+    pub components: Vec<ComponentUse>,
+    pub templates: Vec<TemplateUse>,
+    /// The document's markdown syntax tree, as it is parsed for rendering
+    pub ast: Value,
+}
+
+/// Describe how a document is parsed and which templates render it.
+pub fn page_debug<'a>(project: &Project, document: &'a Document) -> Result<PageDebug<'a>> {
+    let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+    let mut components = vec![];
+    collect_components(project, &ast, &mut components);
+
+    let mut templates = vec!["article.html", "footer.html"];
+    if has_code(&ast) {
+        templates.push("code.html");
+    }
+    let templates = templates
+        .into_iter()
+        .map(|name| {
+            let path = format!("_internal/templates/{}", name);
+            TemplateUse {
+                source: template_source(project, &path),
+                path,
+            }
+        })
+        .collect();
+    Ok(PageDebug {
+        url: &document.url,
+        path: &document.file_path.relative_path,
+        components,
+        templates,
+        ast: ast_json(&ast),
+    })
+}
+
+/// Wrap the output of a template in comments naming it, so authors
+/// customizing templates can see which one produced each part of a page.
+/// A document type declaration stays at the start of the page.
+pub(crate) fn trace_template(project: &Project, path: &str, html: &str) -> String {
+    let source = template_source(project, path);
+    let (doctype, html) = match html.find('>') {
+        Some(end) if html[..end].eq_ignore_ascii_case("<!doctype html") => html.split_at(end + 1),
+        _ => ("", html),
+    };
+    format!(
+        "{}<!-- begin {} ({}) -->{}<!-- end {} -->",
+        doctype, path, source, html, path
+    )
+}
+
+/// Whether a template is the project's own or Codex's.
+fn template_source(project: &Project, path: &str) -> Source {
+    let file = project.path.new_path(path);
+    if file.exists_on_disk() {
+        Source::Project
+    } else if file.exists_embedded() {
+        Source::Codex
+    } else {
+        Source::Unknown
+    }
+}
+
+fn collect_components(project: &Project, node: &Node, components: &mut Vec<ComponentUse>) {
+    let name = match node {
+        Node::MdxJsxFlowElement(el) => el.name.as_deref(),
+        Node::MdxJsxTextElement(el) => el.name.as_deref(),
+        _ => None,
+    };
+    if let Some(name) = name {
+        // Components are looked up the same way the HTML renderer does
+        let path = format!("_internal/components/{}.html", name.to_lowercase());
+        let source = match template_source(project, &path) {
+            Source::Unknown if is_builtin_component(name) => Source::Builtin,
+            source => source,
+        };
+        components.push(ComponentUse {
+            name: name.to_string(),
+            line: node.position().map(|p| p.start.line),
+            template: matches!(source, Source::Project | Source::Codex).then_some(path),
+            suggestion: (source == Source::Unknown)
+                .then(|| suggest_component(project, name))
+                .flatten(),
+            source,
+        });
+    }
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        collect_components(project, child, components);
+    }
+}
+
+/// The syntax tree as mdast JSON. Component attributes are written as a map
+/// of names to values, because the parser's own representation of them
+/// can't be serialized.
+fn ast_json(node: &Node) -> Value {
+    let mut node = node.clone();
+    let children = node.children_mut().map(std::mem::take);
+    let attributes = match node {
+        Node::MdxJsxFlowElement(ref mut el) => Some(std::mem::take(&mut el.attributes)),
+        Node::MdxJsxTextElement(ref mut el) => Some(std::mem::take(&mut el.attributes)),
+        _ => None,
+    };
+    let mut value = serde_json::to_value(&node).unwrap_or_default();
+    if let Some(children) = children {
+        value["children"] = children.iter().map(ast_json).collect();
+    }
+    if let Some(attributes) = attributes {
+        let mut map = serde_json::Map::new();
+        for attribute in attributes {
+            let AttributeContent::Property(property) = attribute else {
+                continue;
+            };
+            let value = match property.value {
+                None => Value::Bool(true),
+                Some(AttributeValue::Literal(literal)) => Value::String(literal),
+                Some(AttributeValue::Expression(expression)) => {
+                    json!({ "expression": expression.value })
+                }
+            };
+            map.insert(property.name, value);
+        }
+        value["attributes"] = Value::Object(map);
+    }
+    value
+}
+
+fn has_code(node: &Node) -> bool {
+    matches!(node, Node::Code(_))
+        || node
+            .children()
+            .is_some_and(|children| children.iter().any(has_code))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    use super::Source;
+
+    #[test]
+    fn test_page_debug() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/steps").unwrap();
+        let debug = super::page_debug(&project, doc).unwrap();
+        let steps = &debug.components[0];
+        assert_eq!(steps.name, "Steps");
+        assert_eq!(steps.source, Source::Codex);
+        assert_eq!(
+            steps.template.as_deref(),
+            Some("_internal/components/steps.html")
+        );
+        assert_eq!(debug.templates[0].path, "_internal/templates/article.html");
+
+        let json = serde_json::to_value(&debug).unwrap();
+        assert_eq!(json["ast"]["type"], "root");
+        let element = &json["ast"]["children"][0];
+        assert_eq!(element["type"], "mdxJsxFlowElement");
+        assert_eq!(element["children"][0]["name"], "Step");
+        assert_eq!(
+            element["children"][0]["attributes"]["title"],
+            "Install codex"
+        );
+        assert_eq!(json["components"][0]["source"], "codex");
+
+        // Components from the project's own folder override Codex's
+        let doc = project
+            .get_document_for_url("/other/custom_component")
+            .unwrap();
+        let debug = super::page_debug(&project, doc).unwrap();
+        assert!(debug.components.iter().all(|c| c.source == Source::Project));
+    }
+
+    #[test]
+    fn test_trace_templates() {
+        let mut project = project_fixture();
+        project.details.trace_templates = true;
+        let doc = project.get_document_for_url("/other/steps").unwrap();
+        let html = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        assert!(html.starts_with("<!-- begin _internal/templates/article.html (codex) -->"));
+        assert!(html.contains("<!-- begin _internal/components/steps.html (codex) -->"));
+        assert!(html.contains("<!-- end _internal/components/steps.html -->"));
+
+        let html = super::trace_template(
+            &project,
+            "_internal/templates/redirect.html",
+            "<!DOCTYPE html><html></html>",
+        );
+        assert_eq!(
+            html,
+            "<!DOCTYPE html><!-- begin _internal/templates/redirect.html (codex) -->\
+             <html></html><!-- end _internal/templates/redirect.html -->"
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build_state::{BUILD_STATE_FILE, LATEX_STATE_FILE},
+    manifest::{hash_bytes, MANIFEST_FILE},
+    Result,
+};
+
+/// The name of the file, stored in the deploy target, that records the
+/// deployed files.
+pub const DEPLOY_STATE_FILE: &str = ".codex-deploy.json";
+
+/// Files in the build folder that are used by Codex itself and are never
+/// deployed.
+const INTERNAL_FILES: [&str; 3] = [MANIFEST_FILE, BUILD_STATE_FILE, LATEX_STATE_FILE];
+
+/// The files copied to a deploy target by previous deploys.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DeployState {
+    /// The SHA-256 hash of each deployed file, keyed by its path relative to
+    /// the target
+    pub files: BTreeMap<String, String>,
+}
+
+impl DeployState {
+    /// Load the state stored in a deploy target. A target that hasn't been
+    /// deployed to has an empty state.
+    pub fn load(target: &Path) -> Result<Self> {
+        let path = target.join(DEPLOY_STATE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    fn save(&self, target: &Path) -> Result<()> {
+        std::fs::write(
+            target.join(DEPLOY_STATE_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// The changes made to a deploy target.
+#[derive(Debug, Clone, Default)]
+pub struct DeploySummary {
+    /// Files that were new or changed since the last deploy
+    pub uploaded: Vec<String>,
+    /// The number of files that were already up to date
+    pub unchanged: usize,
+    /// Files removed from the target because they are no longer built
+    pub deleted: Vec<String>,
+    /// Files in the target that are no longer built but were kept
+    pub orphaned: Vec<String>,
+}
+
+/// Copy a built site to a deploy target. Only files whose hash changed since
+/// the last deploy are copied, so repeated deploys of an unchanged site don't
+/// write anything. Files from previous deploys that are no longer part of the
+/// site are removed when `delete_orphans` is set. Files in the target that
+/// were not placed there by a deploy are never touched, and Codex's own files
+/// in the build folder are never copied.
+pub fn deploy(build_path: &Path, target: &Path, delete_orphans: bool) -> Result<DeploySummary> {
+    std::fs::create_dir_all(target)?;
+    let previous = DeployState::load(target)?;
+    let mut state = DeployState::default();
+    let mut summary = DeploySummary::default();
+
+    for entry in walkdir::WalkDir::new(build_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let relative_path = entry
+            .path()
+            .strip_prefix(build_path)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if INTERNAL_FILES.contains(&relative_path.as_str()) {
+            continue;
+        }
+        let content = std::fs::read(entry.path())?;
+        let hash = hash_bytes(&content);
+        let destination = target.join(&relative_path);
+        if previous.files.get(&relative_path) == Some(&hash) && destination.exists() {
+            summary.unchanged += 1;
+        } else {
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&destination, content)?;
+            summary.uploaded.push(relative_path.clone());
+        }
+        state.files.insert(relative_path, hash);
+    }
+
+    for (path, hash) in previous.files.into_iter() {
+        if state.files.contains_key(&path) {
+            continue;
+        }
+        let destination = target.join(&path);
+        if delete_orphans {
+            if destination.exists() {
+                std::fs::remove_file(&destination)?;
+            }
+            remove_empty_parents(target, &destination);
+            summary.deleted.push(path);
+        } else {
+            summary.orphaned.push(path.clone());
+            state.files.insert(path, hash);
+        }
+    }
+
+    state.save(target)?;
+    Ok(summary)
+}
+
+/// The name of the branch being deployed. CI providers check out a detached
+/// commit, so the branch is read from their environment variables before
+/// falling back to git.
+pub fn current_branch(root: &Path) -> Option<String> {
+    for var in [
+        "GITHUB_HEAD_REF",
+        "GITHUB_REF_NAME",
+        "CI_COMMIT_REF_NAME",
+        "BRANCH_NAME",
+    ] {
+        if let Some(branch) = std::env::var(var).ok().filter(|b| !b.is_empty()) {
+            return Some(branch);
+        }
+    }
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(root)
+        .output()
+        .ok()?;
+    let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !branch.is_empty() && branch != "HEAD").then_some(branch)
+}
+
+/// The path of a preview deploy for a branch. Characters that aren't safe in
+/// a url are replaced, so `feature/Search` becomes `feature-search`.
+pub fn preview_path(pattern: &str, branch: &str) -> String {
+    let mut name = String::new();
+    for c in branch.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    pattern
+        .replace("{branch}", name.trim_end_matches('-'))
+        .trim_matches('/')
+        .to_string()
+}
+
+/// Remove the folders left empty by deleting a file, up to the target folder.
+fn remove_empty_parents(target: &Path, file: &Path) {
+    let mut folder: Option<PathBuf> = file.parent().map(|p| p.to_path_buf());
+    while let Some(path) = folder {
+        if path == target || std::fs::remove_dir(&path).is_err() {
+            break;
+        }
+        folder = path.parent().map(|p| p.to_path_buf());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deploy, preview_path, DeployState};
+
+    #[test]
+    fn test_preview_path() {
+        assert_eq!(
+            preview_path("previews/{branch}", "feature/Search_UI"),
+            "previews/feature-search-ui"
+        );
+        assert_eq!(preview_path("/pr/{branch}/", "12"), "pr/12");
+    }
+
+    #[test]
+    fn test_deploy() {
+        let root = std::env::temp_dir().join(format!("codex-deploy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let build = root.join("dist");
+        let target = root.join("target");
+        std::fs::create_dir_all(build.join("guide")).unwrap();
+        std::fs::write(build.join("index.html"), "home").unwrap();
+        std::fs::write(build.join("guide/index.html"), "guide").unwrap();
+        std::fs::write(build.join(crate::manifest::MANIFEST_FILE), "{}").unwrap();
+
+        let summary = deploy(&build, &target, false).unwrap();
+        assert_eq!(summary.uploaded, vec!["guide/index.html", "index.html"]);
+        assert_eq!(
+            std::fs::read_to_string(target.join("guide/index.html")).unwrap(),
+            "guide"
+        );
+
+        // Only changed files are copied
+        std::fs::write(build.join("index.html"), "new home").unwrap();
+        let summary = deploy(&build, &target, false).unwrap();
+        assert_eq!(summary.uploaded, vec!["index.html"]);
+        assert_eq!(summary.unchanged, 1);
+
+        // Orphans are kept unless deleting them is requested
+        std::fs::remove_dir_all(build.join("guide")).unwrap();
+        let summary = deploy(&build, &target, false).unwrap();
+        assert_eq!(summary.orphaned, vec!["guide/index.html"]);
+        assert!(target.join("guide/index.html").exists());
+
+        let summary = deploy(&build, &target, true).unwrap();
+        assert_eq!(summary.deleted, vec!["guide/index.html"]);
+        assert!(!target.join("guide").exists());
+        let state = DeployState::load(&target).unwrap();
+        assert_eq!(state.files.keys().collect::<Vec<_>>(), vec!["index.html"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
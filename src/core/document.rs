@@ -1,6 +1,117 @@
 use markdown::{mdast::Node, MdxExpressionKind, MdxSignal};
 
-use crate::{assets::CodexPath, Result};
+use crate::{
+    assets::CodexPath, events::Event, project::MarkdownDetails, HtmlRenderer, LatexRenderer,
+    OutputFormat, OutputTarget, Project, RenderContext, Renderer, Result, TextRenderer,
+};
+
+/// The longest description taken from a document's first paragraph.
+const DESCRIPTION_LENGTH: usize = 200;
+
+/// The text of the first paragraph of a document, shortened to
+/// `DESCRIPTION_LENGTH` at a word boundary.
+fn summary(ast: &Node) -> Option<String> {
+    let paragraph = ast
+        .children()
+        .into_iter()
+        .flatten()
+        .find(|n| matches!(n, Node::Paragraph(_)))?;
+    let text = crate::search::plain_text(paragraph)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.chars().count() <= DESCRIPTION_LENGTH {
+        return Some(text);
+    }
+    let cut = text
+        .char_indices()
+        .nth(DESCRIPTION_LENGTH)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let end = text[..cut].rfind(' ').unwrap_or(cut);
+    Some(format!(
+        "{}…",
+        text[..end].trim_end_matches(['.', ',', ';', ':'])
+    ))
+}
+
+/// The kind of card social sites show for a link to a page.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SocialCard {
+    Summary,
+    SummaryLargeImage,
+}
+
+/// How a document's content is presented.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PageType {
+    #[default]
+    Article,
+    /// A list of questions, each level two heading is a question answered
+    /// by the content below it
+    Faq,
+    /// An operational procedure, its steps can be checked off as they are
+    /// done
+    Runbook,
+}
+
+/// Page layout hints for a document's section of the LaTeX output.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct LatexLayout {
+    /// Turn the section's pages sideways, for wide tables
+    pub landscape: bool,
+    /// The number of columns the section is set in
+    pub columns: usize,
+    /// Whether the next section starts on a new page
+    pub newpage: bool,
+}
+
+impl Default for LatexLayout {
+    fn default() -> Self {
+        Self {
+            landscape: false,
+            columns: 1,
+            newpage: true,
+        }
+    }
+}
+
+/// The extension of the folder holding a document's files.
+pub const ASSETS_EXTENSION: &str = "assets";
+
+/// The file written beside each built page with its metadata, when
+/// `page_metadata` is enabled.
+pub const PAGE_METADATA_FILE: &str = "index.meta.json";
+
+/// Structured metadata for a built page, so sites that embed the
+/// documentation can read it without parsing the page's HTML.
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct PageMetadata {
+    pub title: String,
+    pub subtitle: Option<String>,
+    /// The url of the page, including the project's base url
+    pub url: String,
+    pub tags: Vec<String>,
+    /// The description from the front matter, or the first paragraph
+    pub description: Option<String>,
+    pub toc: Vec<crate::renderer::TocEntry>,
+    pub modified: Option<String>,
+    /// The last commit that changed the document
+    pub revision: Option<crate::history::Revision>,
+    pub word_count: usize,
+    /// The questions and answers of a FAQ page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faq: Vec<crate::faq::FaqEntry>,
+    /// The steps of a runbook
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<crate::runbook::RunbookStep>,
+    /// The custom fields of the document's front matter
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub extra: std::collections::BTreeMap<String, serde_json::Value>,
+}
 
 /// The front matter of a document
 /// This is used to store metadata about a document
@@ -24,6 +135,85 @@ pub struct FrontMatter {
     /// document.
     pub json_schema: Option<String>,
     pub pdf_exclude: bool,
+    /// The outputs the document is included in. Defaults to the formats of
+    /// its folder, or every output.
+    pub formats: Option<Vec<OutputTarget>>,
+    /// Anchors of the document that moved to other pages, mapped to their
+    /// new urls. Visitors following a moved anchor are redirected.
+    pub moved_anchors: std::collections::BTreeMap<String, String>,
+    /// The archetype the document was created from
+    pub archetype: Option<String>,
+    /// A stable id other documents can link to with `id:<id>`, so the links
+    /// keep working when the document moves
+    pub id: Option<String>,
+    /// A short summary of the document for search engines and link
+    /// previews. Defaults to the document's first paragraph.
+    pub description: Option<String>,
+    /// The image shown in link previews, such as `/images/card.png`.
+    /// Defaults to the project's social image.
+    pub image: Option<String>,
+    /// The kind of card shown in link previews. Defaults to a large image
+    /// card when the page has an image.
+    pub social_card: Option<SocialCard>,
+    /// The layout of the document's section in the LaTeX output
+    pub latex: LatexLayout,
+    /// How the document's content is presented
+    #[serde(rename = "type")]
+    pub page_type: PageType,
+    /// Fields the project adds to its documents. Templates read them like
+    /// the built in ones, such as `{{document.owner}}`, or all together
+    /// from `document.extra`.
+    #[serde(
+        flatten,
+        serialize_with = "serialize_extra",
+        deserialize_with = "deserialize_extra"
+    )]
+    pub extra: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+/// Write the custom fields of the front matter beside the built in ones,
+/// and again as a map under `extra` for templates that list them.
+fn serialize_extra<S: serde::Serializer>(
+    extra: &std::collections::BTreeMap<String, serde_json::Value>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    use serde::ser::SerializeMap;
+    let mut map = serializer.serialize_map(Some(extra.len() + 1))?;
+    for (name, value) in extra {
+        map.serialize_entry(name, value)?;
+    }
+    map.serialize_entry("extra", extra)?;
+    map.end()
+}
+
+/// Read the custom fields of the front matter. A map under `extra`, as the
+/// front matter is serialized, is merged in, with fields set beside the
+/// built in ones taking precedence.
+fn deserialize_extra<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<std::collections::BTreeMap<String, serde_json::Value>, D::Error> {
+    let mut extra: std::collections::BTreeMap<String, serde_json::Value> =
+        serde::Deserialize::deserialize(deserializer)?;
+    if let Some(serde_json::Value::Object(fields)) = extra.remove("extra") {
+        for (name, value) in fields {
+            extra.entry(name).or_insert(value);
+        }
+    }
+    Ok(extra)
+}
+
+impl FrontMatter {
+    /// A copy of the front matter with some fields replaced, such as to
+    /// preview a variant of a page. Values are read as YAML, as they would
+    /// be in the document.
+    pub fn with_overrides(&self, fields: &[(String, String)]) -> Result<FrontMatter> {
+        let mut frontmatter = serde_json::to_value(self)?;
+        for (name, value) in fields {
+            frontmatter[name] = serde_yaml::from_str(value)
+                .unwrap_or_else(|_| serde_json::Value::String(value.clone()));
+        }
+        Ok(serde_json::from_value(frontmatter)?)
+    }
 }
 
 /// A document or page in the project
@@ -33,6 +223,13 @@ pub struct Document {
     pub file_path: CodexPath,
     pub frontmatter: FrontMatter,
     pub url: String,
+    /// The language the document is written in, set when the project is
+    /// loaded
+    pub language: String,
+    /// The text of the document's first paragraph, shortened at a word
+    /// boundary
+    #[serde(default)]
+    pub summary: Option<String>,
 }
 
 impl std::fmt::Debug for Document {
@@ -45,10 +242,22 @@ impl std::fmt::Debug for Document {
 }
 
 impl Document {
-    pub fn load(file_path: CodexPath) -> Result<Self> {
+    /// Whether the document is its folder's index page, or a translation
+    /// of it.
+    pub fn is_index(&self) -> bool {
+        self.file_path.is_index()
+            || self
+                .file_path
+                .relative_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                == Some(format!("index.{}", self.language).as_str())
+    }
+
+    pub fn load(file_path: CodexPath, markdown: &MarkdownDetails) -> Result<Self> {
         // Generate the table of contents
 
-        let ast = parse(&file_path)?;
+        let ast = parse(&file_path, markdown)?;
 
         // Extract the front matter
         let frontmatter = ast
@@ -68,45 +277,314 @@ impl Document {
             frontmatter,
             url: file_path.document_url(),
             file_path,
+            language: String::new(),
+            summary: summary(&ast),
         })
     }
+
+    /// Whether the document is included in an output. `pdf_exclude` keeps
+    /// a document out of the PDF regardless of its formats.
+    pub fn has_format(&self, target: OutputTarget) -> bool {
+        if target == OutputTarget::Pdf && self.frontmatter.pdf_exclude {
+            return false;
+        }
+        self.frontmatter
+            .formats
+            .as_ref()
+            .is_none_or(|formats| formats.contains(&target))
+    }
+
+    /// The folder for files that belong to the document, `page.assets`
+    /// beside `page.md`.
+    pub fn assets_path(&self) -> CodexPath {
+        self.file_path.new_path(
+            self.file_path
+                .relative_path
+                .with_extension(ASSETS_EXTENSION),
+        )
+    }
+
+    /// The files in the document's assets folder, they are copied to the
+    /// same path in the build.
+    pub fn attachments(&self) -> Result<Vec<CodexPath>> {
+        let folder = self.assets_path();
+        if !folder.exists_on_disk() {
+            return Ok(vec![]);
+        }
+        let mut files = vec![];
+        for entry in walkdir::WalkDir::new(folder.disk_path()).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                files.push(folder.new_path(entry.path().strip_prefix(&folder.project_root)?));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Render the document's article as HTML. This is the same markup the
+    /// server and `codex build` produce for the page body, without the
+    /// surrounding page template.
+    pub fn body(&self, project: &Project) -> Result<String> {
+        HtmlRenderer::new(RenderContext::new(project, self)).render_article()
+    }
+
+    /// The description of the document from its front matter, or else the
+    /// summary of its first paragraph.
+    pub fn description(&self) -> Option<String> {
+        self.frontmatter
+            .description
+            .clone()
+            .or_else(|| self.summary.clone())
+    }
+
+    /// The image shown in link previews of the document.
+    pub fn image(&self, project: &Project) -> Option<String> {
+        self.frontmatter
+            .image
+            .clone()
+            .or_else(|| project.details.social.image.clone())
+    }
+
+    /// The metadata written beside the document's page.
+    pub fn metadata(&self, project: &Project) -> Result<PageMetadata> {
+        let renderer = HtmlRenderer::new(RenderContext::new(project, self));
+        let word_count = crate::search::document_records(project, self)?
+            .iter()
+            .map(|r| r.heading.split_whitespace().count() + r.text.split_whitespace().count())
+            .sum();
+        Ok(PageMetadata {
+            title: self.frontmatter.title.clone(),
+            subtitle: self.frontmatter.subtitle.clone(),
+            url: format!(
+                "{}{}",
+                project.details.base_url,
+                self.url.trim_start_matches('/')
+            ),
+            tags: self.frontmatter.tags.clone(),
+            description: self.description(),
+            toc: renderer
+                .parse(&self.file_path)?
+                .children()
+                .map(|v| renderer.toc(v))
+                .unwrap_or_default(),
+            modified: renderer.last_modified().ok(),
+            revision: project.revision(self).cloned(),
+            word_count,
+            faq: crate::faq::entries(project, self)?,
+            steps: crate::runbook::steps(project, self)?,
+            extra: self.frontmatter.extra.clone(),
+        })
+    }
+
+    /// Render the document's article as a fragment for embedding in another
+    /// application, see [`HtmlRenderer::render_fragment`].
+    pub fn render_fragment(&self, project: &Project) -> Result<String> {
+        let content = HtmlRenderer::new(RenderContext::fragment(project, self))
+            .render_fragment()
+            .map_err(|e| e.in_file(&self.file_path.relative_path))?;
+        project.events.emit(Event::DocumentRendered {
+            url: self.url.clone(),
+            size: content.len(),
+        });
+        Ok(content)
+    }
+
+    /// Render the document in the given format.
+    pub fn render(&self, project: &Project, format: OutputFormat) -> Result<String> {
+        let context = RenderContext::new(project, self);
+        let content = match format {
+            OutputFormat::Html => HtmlRenderer::new(context).render(),
+            OutputFormat::Latex => LatexRenderer::new(context).render(),
+            OutputFormat::Text => TextRenderer::new(context).render(),
+        }
+        .map_err(|e| e.in_file(&self.file_path.relative_path))?;
+        project.events.emit(Event::DocumentRendered {
+            url: self.url.clone(),
+            size: content.len(),
+        });
+        Ok(content)
+    }
 }
 
-fn parse(file_path: &CodexPath) -> Result<Node> {
+pub(crate) fn parse(file_path: &CodexPath, markdown: &MarkdownDetails) -> Result<Node> {
     let content = String::from_utf8(file_path.read()?.to_vec())?;
-    match parse_ast(&content) {
+    let (content, _) = crate::review::strip_review_comments(&content);
+    match parse_ast(&content, markdown) {
         Ok(ast) => Ok(ast),
-        Err(e) => {
-            parse_ast(&format!(r#"<Alert title="Parsing error" style="danger">{}</Alert>"#, e.to_string()))
-        }
+        Err(e) => parse_ast(
+            &format!(
+                r#"<Alert title="Parsing error" style="danger">{}</Alert>"#,
+                e
+            ),
+            &MarkdownDetails::default(),
+        ),
     }
 }
 
-fn parse_ast(content: &str) -> Result<Node> {
-    let parser_options = markdown::ParseOptions {
+pub(crate) fn parse_ast(content: &str, markdown: &MarkdownDetails) -> Result<Node> {
+    Ok(markdown::to_mdast(content, &parse_options(markdown))?)
+}
+
+/// Parse markdown that is edited in place. Review comments are blanked out
+/// rather than removed, so node positions match the original content.
+pub(crate) fn parse_source(content: &str, markdown: &MarkdownDetails) -> Result<Node> {
+    parse_ast(&crate::review::mask_review_comments(content), markdown)
+}
+
+/// The parser options for the syntax features enabled in the project.
+fn parse_options(markdown: &MarkdownDetails) -> markdown::ParseOptions {
+    markdown::ParseOptions {
         constructs: markdown::Constructs {
             code_indented: false,
             frontmatter: true,
-            mdx_jsx_flow: true,
-            html_flow: false,
-            html_text: false,
+            mdx_jsx_flow: !markdown.html,
+            mdx_jsx_text: !markdown.html,
+            html_flow: markdown.html,
+            html_text: markdown.html,
             mdx_esm: true,
-            mdx_expression_flow: true,
-            mdx_expression_text: true,
-            gfm_task_list_item: true,
-            gfm_strikethrough: true,
-            mdx_jsx_text: true,
-            gfm_table: true,
+            mdx_expression_flow: markdown.mdx_expressions,
+            mdx_expression_text: markdown.mdx_expressions,
+            gfm_task_list_item: markdown.gfm_task_list_item,
+            gfm_strikethrough: markdown.gfm_strikethrough,
+            gfm_table: markdown.gfm_table,
+            gfm_autolink_literal: markdown.gfm_autolink_literal,
+            math_flow: markdown.math,
+            math_text: markdown.math,
             ..Default::default()
         },
         mdx_expression_parse: Some(Box::new(parse_expression)),
         gfm_strikethrough_single_tilde: true,
         math_text_single_dollar: true,
         ..markdown::ParseOptions::mdx()
+    }
+}
+
+/// Separate the front matter of a document from its body. Returns the front
+/// matter and the offset where the body starts. Only the front matter is
+/// parsed, so the body's syntax doesn't need to be valid.
+pub(crate) fn split_frontmatter(content: &str) -> Result<(serde_yaml::Mapping, usize)> {
+    let options = markdown::ParseOptions {
+        constructs: markdown::Constructs {
+            frontmatter: true,
+            ..markdown::Constructs::default()
+        },
+        ..markdown::ParseOptions::default()
     };
-    Ok(markdown::to_mdast(content, &parser_options)?)
+    let ast = markdown::to_mdast(content, &options)?;
+    for node in ast.children().map(|c| c.as_slice()).unwrap_or_default() {
+        if let (Node::Yaml(yaml), Some(position)) = (node, node.position()) {
+            let frontmatter = serde_yaml::from_str(&yaml.value)?;
+            return Ok((frontmatter, position.end.offset));
+        }
+    }
+    Ok((serde_yaml::Mapping::new(), 0))
+}
+
+pub(crate) fn join_frontmatter(frontmatter: &serde_yaml::Mapping, body: &str) -> Result<String> {
+    Ok(format!(
+        "---\n{}---\n\n{}",
+        serde_yaml::to_string(frontmatter)?,
+        body.trim_end().to_string() + "\n"
+    ))
 }
 
 pub fn parse_expression(_value: &str, _kind: &MdxExpressionKind) -> MdxSignal {
     MdxSignal::Ok
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        project::tests::project_fixture, HtmlRenderer, OutputFormat, RenderContext, Renderer,
+        SocialCard,
+    };
+
+    #[test]
+    fn test_frontmatter_overrides() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/faq").unwrap();
+        let overrides = [
+            ("type".to_string(), "runbook".to_string()),
+            ("menu_exclude".to_string(), "true".to_string()),
+            ("audience".to_string(), "internal".to_string()),
+        ];
+        let frontmatter = doc.frontmatter.with_overrides(&overrides).unwrap();
+        assert_eq!(frontmatter.page_type, crate::PageType::Runbook);
+        assert!(frontmatter.menu_exclude);
+        assert_eq!(frontmatter.extra["audience"], "internal");
+        assert_eq!(frontmatter.title, doc.frontmatter.title);
+        // Overriding a custom field replaces it rather than nesting it
+        let overrides = [("audience".to_string(), "public".to_string())];
+        let frontmatter = frontmatter.with_overrides(&overrides).unwrap();
+        assert_eq!(frontmatter.extra.len(), 1);
+        assert_eq!(frontmatter.extra["audience"], "public");
+
+        let invalid = [("type".to_string(), "poster".to_string())];
+        assert!(doc.frontmatter.with_overrides(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_body() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let renderer = HtmlRenderer::new(RenderContext::new(&project, doc));
+        assert_eq!(
+            doc.body(&project).unwrap(),
+            renderer.render_article().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/id_target").unwrap();
+        let metadata = doc.metadata(&project).unwrap();
+        assert_eq!(metadata.title, "Deploying");
+        assert_eq!(metadata.url, "/other/id_target");
+        assert_eq!(metadata.tags, vec!["deploy"]);
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Each environment is deployed separately.")
+        );
+        assert_eq!(metadata.toc.len(), 1);
+        assert_eq!(metadata.toc[0].slug, "environments");
+        assert!(metadata.modified.is_some());
+        // The heading and the paragraph
+        assert_eq!(metadata.word_count, 6);
+    }
+
+    #[test]
+    fn test_social_metadata() {
+        let mut project = project_fixture();
+        let doc = project.get_document_for_url("/other/id_target").unwrap();
+        assert_eq!(
+            doc.description().as_deref(),
+            Some("Each environment is deployed separately.")
+        );
+        let mut doc = doc.clone();
+        doc.frontmatter.description = Some("Deploy \"safely\"".into());
+        let html = doc.render(&project, OutputFormat::Html).unwrap();
+        assert!(html.contains(r#"<meta name="description" content="Deploy &quot;safely&quot;">"#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+
+        project.details.social.image = Some("/images/logo.png".into());
+        assert_eq!(doc.image(&project).as_deref(), Some("/images/logo.png"));
+        let html = doc.render(&project, OutputFormat::Html).unwrap();
+        assert!(html.contains(r#"<meta property="og:image" content="/images/logo.png">"#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+        doc.frontmatter.social_card = Some(SocialCard::Summary);
+        let html = doc.render(&project, OutputFormat::Html).unwrap();
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+    }
+
+    #[test]
+    fn test_render() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let html = doc.render(&project, OutputFormat::Html).unwrap();
+        assert!(html.contains(r#"<html lang="en" data-color-mode="auto">"#));
+        assert!(html.contains(&doc.body(&project).unwrap()));
+        let latex = doc.render(&project, OutputFormat::Latex).unwrap();
+        assert!(latex.contains("\\begin{tcolorbox}"));
+    }
+}
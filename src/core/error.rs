@@ -1,113 +1,247 @@
-use std::path::StripPrefixError;
+use std::{ops::Range, path::PathBuf, path::StripPrefixError};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// What kind of problem an error is, for callers that handle some errors
+/// differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A file couldn't be read or written
+    Io,
+    /// A file, such as a data file or the project configuration, couldn't
+    /// be parsed
+    Parse,
+    /// A template couldn't be compiled or rendered
+    Template,
+    /// A component in a document is missing attributes or couldn't be
+    /// rendered
+    Component,
+    Other,
+}
+
+impl ErrorKind {
+    /// The code diagnostics show for the kind, such as `codex::component`.
+    fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Io => "codex::io",
+            ErrorKind::Parse => "codex::parse",
+            ErrorKind::Template => "codex::template",
+            ErrorKind::Component => "codex::component",
+            ErrorKind::Other => "codex::error",
+        }
+    }
+}
+
+/// Where in a project's files an error occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The path of the file, relative to the project root
+    pub path: PathBuf,
+    /// The byte range of the content the error is about
+    pub span: Option<Range<usize>>,
+    /// The line the error occurred on, starting at 1
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
 #[derive(Debug)]
 pub struct Error {
+    pub kind: ErrorKind,
     pub message: String,
+    /// Boxed, along with the source code, to keep results small
+    pub location: Option<Box<SourceLocation>>,
+    /// The component the error occurred in, such as `Gallery`
+    pub component: Option<String>,
+    /// The content of the file the error occurred in, shown around the
+    /// error's span in diagnostics
+    source_code: Option<Box<miette::NamedSource<String>>>,
 }
 
 impl Error {
     pub fn new<S>(message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::with_kind(ErrorKind::Other, message)
+    }
+
+    pub fn with_kind<S>(kind: ErrorKind, message: S) -> Self
     where
         S: Into<String>,
     {
         Self {
+            kind,
             message: message.into(),
+            location: None,
+            component: None,
+            source_code: None,
         }
     }
+
+    /// Record the file the error occurred in, unless a more precise
+    /// location is already known.
+    pub fn in_file(mut self, path: impl Into<PathBuf>) -> Self {
+        if self.location.is_none() {
+            self.location = Some(Box::new(SourceLocation {
+                path: path.into(),
+                span: None,
+                line: None,
+                column: None,
+            }));
+        }
+        self
+    }
+
+    /// Record the component the error occurred in and where it is in its
+    /// document, unless the error occurred in a component inside it. The
+    /// document's content lets diagnostics show the component.
+    pub fn in_component(
+        mut self,
+        name: &str,
+        location: SourceLocation,
+        source_code: Option<String>,
+    ) -> Self {
+        if self.component.is_some() {
+            return self;
+        }
+        if self.kind == ErrorKind::Other {
+            self.kind = ErrorKind::Component;
+        }
+        self.component = Some(name.to_string());
+        self.source_code = source_code.map(|code| {
+            Box::new(miette::NamedSource::new(
+                location.path.to_string_lossy(),
+                code,
+            ))
+        });
+        self.location = Some(Box::new(location));
+        self
+    }
 }
 
 impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(ref location) = self.location {
+            write!(f, "{}", location.path.display())?;
+            if let (Some(line), Some(column)) = (location.line, location.column) {
+                write!(f, ":{}:{}", line, column)?;
+            }
+            write!(f, ": ")?;
+        }
+        if let Some(ref component) = self.component {
+            write!(f, "<{}> ", component)?;
+        }
         write!(f, "{}", self.message)
     }
 }
 
+/// Errors render as diagnostics pointing at the component they occurred in.
+impl miette::Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.kind.code()))
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.source_code
+            .as_ref()
+            .map(|code| code.as_ref() as &dyn miette::SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let span = self.location.as_ref()?.span.clone()?;
+        self.source_code.as_ref()?;
+        let label = self
+            .component
+            .as_ref()
+            .map(|name| format!("in this {} component", name));
+        Some(Box::new(std::iter::once(
+            miette::LabeledSpan::new_primary_with_span(label, span),
+        )))
+    }
+}
+
 impl From<String> for Error {
     fn from(err: String) -> Self {
-        Self { message: err }
+        Self::with_kind(ErrorKind::Other, err)
     }
 }
 
 impl From<serde_yaml::Error> for Error {
     fn from(err: serde_yaml::Error) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Parse, err.to_string())
     }
 }
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Io, err.to_string())
     }
 }
 
 impl From<handlebars::RenderError> for Error {
     fn from(err: handlebars::RenderError) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Template, err.to_string())
     }
 }
 
 impl From<handlebars::TemplateError> for Error {
     fn from(err: handlebars::TemplateError) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Template, err.to_string())
     }
 }
 
 impl From<std::string::FromUtf16Error> for Error {
     fn from(err: std::string::FromUtf16Error) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Parse, err.to_string())
     }
 }
 
 impl From<std::string::FromUtf8Error> for Error {
     fn from(err: std::string::FromUtf8Error) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Parse, err.to_string())
     }
 }
 
 impl From<StripPrefixError> for Error {
     fn from(err: StripPrefixError) -> Self {
-        Self {
-            message: err.to_string(),
-        }
+        Self::with_kind(ErrorKind::Other, err.to_string())
     }
 }
 
 impl From<walkdir::Error> for Error {
     fn from(value: walkdir::Error) -> Self {
-        Self {
-            message: value.to_string(),
-        }
+        Self::with_kind(ErrorKind::Io, value.to_string())
     }
 }
 
 impl From<csv::Error> for Error {
     fn from(value: csv::Error) -> Self {
-        Self {
-            message: value.to_string(),
-        }
+        Self::with_kind(ErrorKind::Parse, value.to_string())
     }
 }
 
 impl From<serde_json::Error> for Error {
     fn from(value: serde_json::Error) -> Self {
-        Self {
-            message: value.to_string(),
-        }
+        Self::with_kind(ErrorKind::Parse, value.to_string())
+    }
+}
+
+impl From<qrcode::types::QrError> for Error {
+    fn from(value: qrcode::types::QrError) -> Self {
+        Self::with_kind(ErrorKind::Other, value.to_string())
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(value: toml::de::Error) -> Self {
+        Self::with_kind(ErrorKind::Parse, value.to_string())
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(value: image::ImageError) -> Self {
+        Self::with_kind(ErrorKind::Other, value.to_string())
     }
 }
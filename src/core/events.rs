@@ -0,0 +1,163 @@
+use std::{path::PathBuf, sync::Arc};
+
+use serde::Serialize;
+
+/// Something that happened while loading, rendering, or building a project.
+/// Applications embedding Codex can subscribe to these to report progress
+/// without parsing its output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A document was found while scanning the project
+    DocumentDiscovered { path: PathBuf, url: String },
+    /// A build started with the given number of documents
+    BuildStarted { documents: usize },
+    /// A document was rendered, `size` is the length of the output in bytes
+    DocumentRendered { url: String, size: usize },
+    /// A document built by an interrupted build was kept instead of being
+    /// rendered again
+    DocumentRestored { url: String, size: usize },
+    /// A problem that doesn't stop the operation. `path` is the document
+    /// the problem was found in, relative to the project root.
+    Warning {
+        kind: WarningKind,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// A file was written to the build folder
+    FileWritten { path: PathBuf, size: usize },
+}
+
+/// What a warning is about, so tools can filter or count warnings without
+/// matching on their messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// A component without a template
+    UnknownComponent,
+    /// An image that isn't in the project
+    MissingAsset,
+    /// A link to a page that isn't in the project
+    BrokenLink,
+    /// A heading without any text to link it by
+    EmptyHeading,
+    /// Built files larger than their budget
+    Budget,
+    /// A component or code block that failed and was rendered as an error
+    RenderError,
+    Other,
+}
+
+impl std::fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WarningKind::UnknownComponent => "unknown component",
+            WarningKind::MissingAsset => "missing asset",
+            WarningKind::BrokenLink => "broken link",
+            WarningKind::EmptyHeading => "empty heading",
+            WarningKind::Budget => "budget",
+            WarningKind::RenderError => "render error",
+            WarningKind::Other => "other",
+        })
+    }
+}
+
+type Handler = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// The handlers subscribed to a project's events.
+#[derive(Clone, Default)]
+pub struct Events {
+    handlers: Vec<Handler>,
+}
+
+impl Events {
+    /// Call `handler` with every event emitted.
+    pub fn subscribe<F>(&mut self, handler: F)
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.handlers.push(Arc::new(handler));
+    }
+
+    pub fn emit(&self, event: Event) {
+        for handler in self.handlers.iter() {
+            handler(&event);
+        }
+    }
+}
+
+impl std::fmt::Debug for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Events")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use crate::{OutputFormat, Project};
+
+    use super::{Event, Events, WarningKind};
+
+    #[test]
+    fn test_project_events() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let mut events = Events::default();
+        let r = received.clone();
+        events.subscribe(move |e| r.lock().unwrap().push(e.clone()));
+
+        let project =
+            Project::load_with_events(PathBuf::from("test").join("fixture"), false, events)
+                .unwrap();
+        assert!(received
+            .lock()
+            .unwrap()
+            .contains(&Event::DocumentDiscovered {
+                path: PathBuf::from("other/alert.md"),
+                url: "/other/alert".to_string(),
+            }));
+
+        received.lock().unwrap().clear();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let html = doc.render(&project, OutputFormat::Html).unwrap();
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![Event::DocumentRendered {
+                url: "/other/alert".to_string(),
+                size: html.len(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_warning_json() {
+        let event = Event::Warning {
+            kind: WarningKind::BrokenLink,
+            path: Some(PathBuf::from("index.md")),
+            message: "Broken link: /missing".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"warning","kind":"broken_link","path":"index.md","message":"Broken link: /missing"}"#
+        );
+    }
+
+    #[test]
+    fn test_event_json() {
+        let event = Event::FileWritten {
+            path: PathBuf::from("dist/index.html"),
+            size: 10,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"file_written","path":"dist/index.html","size":10}"#
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use markdown::mdast::{Heading, Node};
+
+use crate::{
+    renderer::slugify, search::plain_text, Document, PageType, Project, RenderContext, Renderer,
+    Result, TextRenderer,
+};
+
+/// A question on a FAQ page and its answer.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FaqEntry {
+    pub question: String,
+    /// The anchor linking to the question on its page
+    pub anchor: String,
+    /// The plain text of the answer
+    pub answer: String,
+}
+
+/// A part of a FAQ page, either a question with the content answering it
+/// or content outside any question.
+pub enum FaqPart<'a> {
+    Content(&'a [Node]),
+    Question(&'a Heading, &'a [Node]),
+}
+
+/// Split the content of a FAQ page at its level two headings, which are
+/// its questions. An answer runs until the next question or top level
+/// heading.
+pub fn parts(nodes: &[Node]) -> Vec<FaqPart<'_>> {
+    let mut parts = vec![];
+    let mut question: Option<&Heading> = None;
+    let mut start = 0;
+    for (i, node) in nodes.iter().enumerate() {
+        let Node::Heading(heading) = node else {
+            continue;
+        };
+        if heading.depth > 2 {
+            continue;
+        }
+        push_part(&mut parts, question, &nodes[start..i]);
+        if heading.depth == 2 {
+            question = Some(heading);
+            start = i + 1;
+        } else {
+            question = None;
+            start = i;
+        }
+    }
+    push_part(&mut parts, question, &nodes[start..]);
+    parts
+}
+
+fn push_part<'a>(parts: &mut Vec<FaqPart<'a>>, question: Option<&'a Heading>, nodes: &'a [Node]) {
+    match question {
+        Some(heading) => parts.push(FaqPart::Question(heading, nodes)),
+        None if !nodes.is_empty() => parts.push(FaqPart::Content(nodes)),
+        None => {}
+    }
+}
+
+/// The questions and answers of a FAQ page, empty for other pages.
+pub fn entries(project: &Project, document: &Document) -> Result<Vec<FaqEntry>> {
+    if document.frontmatter.page_type != PageType::Faq {
+        return Ok(vec![]);
+    }
+    let mut renderer = TextRenderer::new(RenderContext::new(project, document));
+    renderer.link_urls = false;
+    let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+    let mut entries = vec![];
+    for part in parts(ast.children().map(|c| c.as_slice()).unwrap_or_default()) {
+        let FaqPart::Question(heading, answer) = part else {
+            continue;
+        };
+        let question = heading.children.iter().map(plain_text).collect::<String>();
+        // Headings are linked by their first text, as on the page
+        let anchor = heading.children.iter().find_map(|child| match child {
+            Node::Text(text) => Some(slugify(&text.value)),
+            _ => None,
+        });
+        entries.push(FaqEntry {
+            anchor: anchor.unwrap_or_default(),
+            answer: renderer.render_nodes(answer)?.trim().to_string(),
+            question,
+        });
+    }
+    Ok(entries)
+}
+
+/// The `FAQPage` structured data search engines read from a FAQ page, as
+/// JSON-LD.
+pub fn structured_data(entries: &[FaqEntry]) -> serde_json::Value {
+    serde_json::json!({
+        "@context": "https://schema.org",
+        "@type": "FAQPage",
+        "mainEntity": entries
+            .iter()
+            .map(|entry| {
+                serde_json::json!({
+                    "@type": "Question",
+                    "name": entry.question,
+                    "acceptedAnswer": {
+                        "@type": "Answer",
+                        "text": entry.answer,
+                    },
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    #[test]
+    pub fn test_faq_entries() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/faq").unwrap();
+        let entries = super::entries(&project, doc).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].question, "How do I install codex?");
+        assert_eq!(entries[0].anchor, "how-do-i-install-codex");
+        assert_eq!(
+            entries[0].answer,
+            "Download the latest release.\n\nThen add it to your path."
+        );
+        // The answer ends at the next top level heading
+        assert_eq!(entries[1].answer, "Run codex build.");
+
+        let data = super::structured_data(&entries);
+        assert_eq!(data["@type"], "FAQPage");
+        assert_eq!(data["mainEntity"][1]["name"], "Where is the output?");
+        assert_eq!(
+            data["mainEntity"][1]["acceptedAnswer"]["text"],
+            "Run codex build."
+        );
+    }
+}
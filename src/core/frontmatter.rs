@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{document::split_frontmatter, ErrorKind, Project, Result};
+
+/// The rules for a front matter field, declared under `frontmatter` in
+/// `codex.yml`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct FieldSchema {
+    /// Every document must set the field
+    pub required: bool,
+    /// The values the field can have. Each item of a list must be one of
+    /// them. Any value is allowed when it is empty.
+    pub values: Vec<String>,
+}
+
+/// A document whose front matter doesn't follow the project's schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// The path of the document, relative to the project root
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Check the front matter of every document against the fields declared in
+/// `codex.yml`.
+pub fn violations(project: &Project) -> Result<Vec<SchemaViolation>> {
+    let schema = &project.details.frontmatter;
+    let mut violations = vec![];
+    if schema.is_empty() {
+        return Ok(violations);
+    }
+    for document in project.root_folder.iter_all_documents() {
+        let content = String::from_utf8(document.file_path.read()?.to_vec())?;
+        let (frontmatter, _) = split_frontmatter(&content)?;
+        for (name, field) in schema {
+            let message = match frontmatter.get(name) {
+                None | Some(Value::Null) if field.required => {
+                    format!("{} is required", name)
+                }
+                Some(value) if !value.is_null() && !field.values.is_empty() => {
+                    let items = match value {
+                        Value::Sequence(items) => items.iter().collect::<Vec<_>>(),
+                        value => vec![value],
+                    };
+                    let Some(invalid) = items
+                        .into_iter()
+                        .map(scalar)
+                        .find(|v| !field.values.contains(v))
+                    else {
+                        continue;
+                    };
+                    format!(
+                        "{} is \"{}\", expected one of {}",
+                        name,
+                        invalid,
+                        field.values.join(", ")
+                    )
+                }
+                _ => continue,
+            };
+            violations.push(SchemaViolation {
+                path: document.file_path.relative_path.clone(),
+                message,
+            });
+        }
+    }
+    Ok(violations)
+}
+
+/// Fail with every violation of the front matter schema, so the project
+/// doesn't load with documents missing the fields its templates rely on.
+pub fn validate(project: &Project) -> Result<()> {
+    let violations = violations(project)?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!(
+        "{} documents don't match the front matter schema in codex.yml",
+        violations.len()
+    );
+    for violation in violations {
+        message.push_str(&format!(
+            "\n  {}: {}",
+            violation.path.display(),
+            violation.message
+        ));
+    }
+    Err(crate::Error::with_kind(ErrorKind::Parse, message))
+}
+
+/// A front matter value as it is written, for comparing with the allowed
+/// values.
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::FieldSchema;
+
+    #[test]
+    fn test_frontmatter_schema() {
+        let root = crate::refactor::tests::writable_fixture("frontmatter_schema");
+        std::fs::write(
+            root.join("other").join("owned.md"),
+            "---\ntitle: Owned\nowner: platform\nstatus: draft\ntags: [api, beta]\n---\n",
+        )
+        .unwrap();
+        let mut project = crate::Project::load(&root, false).unwrap();
+        project.details.frontmatter.insert(
+            "owner".into(),
+            FieldSchema {
+                required: true,
+                values: vec![],
+            },
+        );
+        project.details.frontmatter.insert(
+            "tags".into(),
+            FieldSchema {
+                required: false,
+                values: vec!["api".into()],
+            },
+        );
+        let violations = super::violations(&project).unwrap();
+        let owned = violations
+            .iter()
+            .filter(|v| v.path == Path::new("other").join("owned.md"))
+            .map(|v| v.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(owned, vec!["tags is \"beta\", expected one of api"]);
+        // Every other document is missing the owner
+        assert!(violations
+            .iter()
+            .any(|v| v.path == Path::new("other").join("alert.md")
+                && v.message == "owner is required"));
+        assert!(super::validate(&project).is_err());
+
+        // Custom fields are passed to templates with the document
+        let doc = project.get_document_for_url("/other/owned").unwrap();
+        assert_eq!(doc.frontmatter.extra["owner"], "platform");
+        assert_eq!(doc.frontmatter.extra["status"], "draft");
+        assert!(!doc.frontmatter.extra.contains_key("title"));
+        let json = serde_json::to_value(&doc.frontmatter).unwrap();
+        assert_eq!(json["owner"], "platform");
+        assert_eq!(json["extra"]["tags"], serde_json::Value::Null);
+        assert_eq!(json["extra"]["status"], "draft");
+        let metadata = doc.metadata(&project).unwrap();
+        assert_eq!(metadata.extra["owner"], "platform");
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use markdown::mdast::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::{renderer::slugify, Project, Result};
+
+/// The longest acronym found by its definition in a document's text.
+const MAX_ACRONYM_LENGTH: usize = 8;
+
+/// A place a glossary term is used.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TermReference {
+    /// The url of the page, without the section's anchor
+    pub url: String,
+    pub title: String,
+    /// The heading of the section the term is used in
+    pub heading: Option<String>,
+    pub anchor: Option<String>,
+}
+
+/// A term of the glossary, along with every section using it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GlossaryTerm {
+    pub term: String,
+    pub definition: Option<String>,
+    /// The anchor of the term in the glossary
+    pub anchor: String,
+    pub references: Vec<TermReference>,
+}
+
+/// Build the glossary of a project: the terms set in its `glossary`, and
+/// acronyms defined in its documents such as "Command Line Interface (CLI)",
+/// each with the sections of the documents that use it.
+pub fn build(project: &Project) -> Result<Vec<GlossaryTerm>> {
+    let mut definitions: BTreeMap<String, Option<String>> = project
+        .details
+        .glossary
+        .iter()
+        .map(|(term, definition)| (term.clone(), Some(definition.clone())))
+        .collect();
+    let mut sections = vec![];
+    for document in project.root_folder.iter_all_documents() {
+        let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+        let mut section = (None, String::new());
+        for node in ast.children().map(|c| c.as_slice()).unwrap_or_default() {
+            if let Node::Heading(heading) = node {
+                sections.push((document, section));
+                let text = heading.children.iter().find_map(|child| match child {
+                    Node::Text(text) => Some(text.value.clone()),
+                    _ => None,
+                });
+                section = (text, String::new());
+                continue;
+            }
+            if matches!(node, Node::Code(_)) {
+                continue;
+            }
+            section.1.push(' ');
+            section.1.push_str(&crate::search::plain_text(node));
+        }
+        sections.push((document, section));
+    }
+    for (_, (_, text)) in sections.iter() {
+        for (acronym, definition) in find_acronyms(text) {
+            definitions.entry(acronym).or_insert(Some(definition));
+        }
+    }
+
+    let mut terms = vec![];
+    for (term, definition) in definitions {
+        let mut references: Vec<TermReference> = vec![];
+        for (document, (heading, text)) in sections.iter() {
+            if !contains_word(text, &term) {
+                continue;
+            }
+            references.push(TermReference {
+                url: document.url.clone(),
+                title: document.frontmatter.title.clone(),
+                anchor: heading.as_deref().map(slugify),
+                heading: heading.clone(),
+            });
+        }
+        terms.push(GlossaryTerm {
+            anchor: format!("term-{}", slugify(&term)),
+            term,
+            definition,
+            references,
+        });
+    }
+    terms.sort_by_key(|t| t.term.to_lowercase());
+    Ok(terms)
+}
+
+/// Whether a text contains a term as a whole word.
+fn contains_word(text: &str, term: &str) -> bool {
+    text.match_indices(term).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + term.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+/// Find acronyms following their definition in parentheses, where the
+/// initials of the words before them spell the acronym, such as
+/// "Application Programming Interface (API)".
+fn find_acronyms(text: &str) -> Vec<(String, String)> {
+    let mut found = vec![];
+    for (start, _) in text.match_indices('(') {
+        let Some(end) = text[start..].find(')').map(|e| start + e) else {
+            continue;
+        };
+        let acronym = &text[start + 1..end];
+        let length = acronym.chars().count();
+        if !(2..=MAX_ACRONYM_LENGTH).contains(&length)
+            || !acronym
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            || !acronym.starts_with(|c: char| c.is_ascii_uppercase())
+        {
+            continue;
+        }
+        let words = text[..start].split_whitespace().collect::<Vec<_>>();
+        if words.len() < length {
+            continue;
+        }
+        let words = &words[words.len() - length..];
+        let initials = words
+            .iter()
+            .filter_map(|w| w.chars().next())
+            .collect::<String>();
+        if initials.eq_ignore_ascii_case(acronym) {
+            found.push((acronym.to_string(), words.join(" ")));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{refactor::tests::writable_fixture, OutputFormat, Project};
+
+    use super::{build, contains_word, find_acronyms};
+
+    #[test]
+    fn test_find_acronyms() {
+        assert_eq!(
+            find_acronyms("Use the Command Line Interface (CLI) or the API (see below)."),
+            vec![("CLI".to_string(), "Command Line Interface".to_string())]
+        );
+        assert!(contains_word("Run the CLI.", "CLI"));
+        assert!(!contains_word("CLIENT", "CLI"));
+    }
+
+    #[test]
+    fn test_glossary() {
+        let root = writable_fixture("glossary");
+        let config = std::fs::read_to_string(root.join("codex.yml")).unwrap();
+        std::fs::write(
+            root.join("codex.yml"),
+            config + "glossary:\n  SDK: Software Development Kit\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("other/tools.md"),
+            "---\ntitle: Tools\n---\n\nThe Command Line Interface (CLI) wraps the SDK.\n\n# Scripts\n\nScripts call the CLI.\n\n```\nCLI --help\n```\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("other/terms.md"),
+            "---\ntitle: Terms\n---\n\n<Glossary />\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+        let terms = build(&project).unwrap();
+        let cli = terms.iter().find(|t| t.term == "CLI").unwrap();
+        assert_eq!(cli.definition.as_deref(), Some("Command Line Interface"));
+        assert_eq!(cli.references.len(), 2);
+        assert_eq!(cli.references[1].anchor.as_deref(), Some("scripts"));
+        let sdk = terms.iter().find(|t| t.term == "SDK").unwrap();
+        assert_eq!(sdk.references[0].url, "/other/tools");
+
+        let doc = project.get_document_for_url("/other/terms").unwrap();
+        let html = doc.body(&project).unwrap();
+        assert!(html.contains(r#"id="term-cli""#));
+        assert!(html.contains(r#"href="/other/tools#scripts""#));
+        let latex = doc.render(&project, OutputFormat::Latex).unwrap();
+        assert!(latex.contains("\\item[CLI]"));
+        assert!(latex.contains("\\pageref{sec:other-tools-scripts}"));
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Separates the commits in the log output.
+const COMMIT_SEPARATOR: char = '\u{1e}';
+/// Separates the fields of a commit in the log output.
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// The last commit that changed a document.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub hash: String,
+    pub author: String,
+    /// The commit date, in UTC
+    pub date: String,
+}
+
+/// Someone who committed changes to a document.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Contributor {
+    pub name: String,
+    pub email: Option<String>,
+    /// The url of the contributor's avatar image
+    pub avatar: Option<String>,
+    /// The number of commits changing the document
+    pub commits: usize,
+}
+
+/// The last commit changing each file in the project, read from git. File
+/// modification times are reset when a repository is checked out, so the
+/// history is used for the date documents were last changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GitHistory {
+    /// The revisions keyed by the path of the file relative to the project
+    /// root
+    pub files: HashMap<PathBuf, Revision>,
+    /// The authors of the commits changing each file, most commits first
+    pub contributors: HashMap<PathBuf, Vec<Contributor>>,
+}
+
+impl GitHistory {
+    /// Read the history of the files in a folder, or `None` when it isn't
+    /// in a git repository or git isn't installed.
+    pub fn load(root: &Path) -> Option<Self> {
+        let format = format!(
+            "--format={}%H{}%an{}%ae{}%aI",
+            COMMIT_SEPARATOR, FIELD_SEPARATOR, FIELD_SEPARATOR, FIELD_SEPARATOR
+        );
+        let output = std::process::Command::new("git")
+            .args(["log", &format, "--name-only", "--no-renames", "--relative"])
+            .current_dir(root)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parse the output of `git log`, newest commit first.
+    fn parse(log: &str) -> Self {
+        let mut history = GitHistory::default();
+        for commit in log.split(COMMIT_SEPARATOR) {
+            let mut lines = commit.lines();
+            let Some(header) = lines.next() else {
+                continue;
+            };
+            let mut fields = header.split(FIELD_SEPARATOR);
+            let (Some(hash), Some(author), Some(email), Some(date)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let Ok(date) = chrono::DateTime::parse_from_rfc3339(date) else {
+                continue;
+            };
+            let revision = Revision {
+                hash: hash.to_string(),
+                author: author.to_string(),
+                date: date.to_utc().format("%Y-%m-%dT%H:%M:%S%z").to_string(),
+            };
+            for file in lines.filter(|l| !l.is_empty()) {
+                history
+                    .files
+                    .entry(PathBuf::from(file))
+                    .or_insert_with(|| revision.clone());
+                let contributors = history.contributors.entry(PathBuf::from(file)).or_default();
+                // Authors are the same person when their emails match
+                match contributors.iter_mut().find(|c| {
+                    c.email
+                        .as_deref()
+                        .unwrap_or_default()
+                        .eq_ignore_ascii_case(email)
+                }) {
+                    Some(contributor) => contributor.commits += 1,
+                    None => contributors.push(Contributor {
+                        name: author.to_string(),
+                        email: Some(email.to_string()),
+                        avatar: None,
+                        commits: 1,
+                    }),
+                }
+            }
+        }
+        for contributors in history.contributors.values_mut() {
+            contributors.sort_by_key(|c| std::cmp::Reverse(c.commits));
+        }
+        history
+    }
+}
+
+/// The commit checked out in the repository containing a folder, and
+/// whether the folder has uncommitted changes.
+pub fn head_commit(root: &Path) -> Option<(String, bool)> {
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let hash = git(&["rev-parse", "HEAD"])?;
+    let changes = git(&["status", "--porcelain", "--", "."])?;
+    Some((hash, !changes.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{project::tests::project_fixture, refactor::tests::writable_fixture, Project};
+
+    use super::GitHistory;
+
+    #[test]
+    fn test_parse_log() {
+        let log = "\u{1e}c3\u{1f}Alice\u{1f}alice@example.com\u{1f}2024-04-01T00:00:00Z\n\nindex.md\n\n\u{1e}b2\u{1f}Bob\u{1f}bob@example.com\u{1f}2024-03-02T10:00:00+02:00\n\nguide.md\n\n\u{1e}a1\u{1f}A. Smith\u{1f}Alice@example.com\u{1f}2024-01-01T00:00:00Z\n\nguide.md\nindex.md\n";
+        let history = GitHistory::parse(log);
+        let guide = &history.files[Path::new("guide.md")];
+        assert_eq!(guide.hash, "b2");
+        assert_eq!(guide.author, "Bob");
+        assert_eq!(guide.date, "2024-03-02T08:00:00+0000");
+        assert_eq!(history.files[Path::new("index.md")].author, "Alice");
+
+        let names = |file: &str| {
+            history.contributors[Path::new(file)]
+                .iter()
+                .map(|c| (c.name.as_str(), c.commits))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("index.md"), vec![("Alice", 2)]);
+        assert_eq!(names("guide.md"), vec![("Bob", 1), ("A. Smith", 1)]);
+    }
+
+    #[test]
+    fn test_revision() {
+        // The fixture is committed with the repository, when there is one
+        if GitHistory::load(Path::new(".")).is_some() {
+            let project = project_fixture();
+            let doc = project.get_document_for_url("/other/alert").unwrap();
+            let revision = project.revision(doc).unwrap();
+            assert_eq!(revision.hash.len(), 40);
+            assert!(doc
+                .metadata(&project)
+                .unwrap()
+                .modified
+                .is_some_and(|m| m == revision.date));
+        }
+
+        let mut project = project_fixture();
+        let doc = project
+            .get_document_for_url("/other/alert")
+            .unwrap()
+            .clone();
+        assert!(project.contributors(&doc).is_none());
+        project.details.contributors.enabled = true;
+        project.details.contributors.avatar_url = Some("https://avatars.example.com/{hash}".into());
+        let contributors = project.contributors(&doc).unwrap();
+        if project.revision(&doc).is_some() {
+            assert!(contributors[0].email.is_none());
+            assert_eq!(
+                contributors[0].avatar.as_ref().unwrap().len(),
+                "https://avatars.example.com/".len() + 64
+            );
+        }
+
+        // Outside a repository the file's modification time is used
+        let project = Project::load(writable_fixture("history"), false).unwrap();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        assert!(project.revision(doc).is_none());
+        assert!(doc.metadata(&project).unwrap().modified.is_some());
+    }
+}
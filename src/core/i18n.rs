@@ -0,0 +1,241 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Document, Project, ProjectDetails, Result};
+
+/// The built in text of the site's interface, such as labels and headings
+/// in the templates, keyed by the message's name.
+const DEFAULT_MESSAGES: [(&str, &str); 29] = [
+    ("color_mode", "Color mode"),
+    ("color_mode_auto", "Automatic"),
+    ("color_mode_dark", "Dark"),
+    ("color_mode_light", "Light"),
+    ("contributors", "Contributors"),
+    ("copy_commands", "Copy all commands"),
+    ("default_value", "Default value:"),
+    (
+        "deprecated",
+        "This field has been deprecated and will be removed in a future version.",
+    ),
+    ("display_settings", "Display settings"),
+    ("edit_page", "Edit this page"),
+    ("example", "Example"),
+    ("fields", "Fields"),
+    ("generated_by", "Generated by"),
+    ("go_home", "Go Home"),
+    ("high_contrast", "High contrast"),
+    ("home_page", "Home page"),
+    ("language", "Language"),
+    ("on_this_page", "On this page"),
+    ("page_navigation", "Page navigation"),
+    ("page_not_found", "Page Not Found"),
+    (
+        "page_not_found_text",
+        "The page you are looking for does not exist.",
+    ),
+    ("print", "Print"),
+    ("print_section", "Print this section"),
+    ("reduce_motion", "Reduce motion"),
+    ("reset_progress", "Reset progress"),
+    ("search", "Search"),
+    ("skip_to_content", "Skip to content"),
+    ("source_code", "Source code"),
+    ("steps_completed", "steps completed"),
+];
+
+/// The interface text for a language. Messages set for the language in the
+/// project's `messages` come first, then those set for the default
+/// language, then the built in English text.
+pub fn messages(details: &ProjectDetails, language: &str) -> BTreeMap<String, String> {
+    let mut messages = DEFAULT_MESSAGES
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    for language in [details.default_language.as_str(), language] {
+        if let Some(overrides) = details.messages.get(language) {
+            messages.extend(overrides.clone());
+        }
+    }
+    messages
+}
+
+/// A single message of the interface text for a language.
+pub fn message(details: &ProjectDetails, language: &str, name: &str) -> String {
+    messages(details, language)
+        .remove(name)
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Whether a document has been translated to a language.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslationState {
+    Missing,
+    /// The original was changed after the translation
+    Outdated,
+    Current,
+}
+
+/// The translations of a document in the default language.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TranslationStatus {
+    pub url: String,
+    pub path: PathBuf,
+    /// The state of the document's translation to each language
+    pub translations: BTreeMap<String, TranslationState>,
+}
+
+/// A version of a page in another language, for the language switcher.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TranslationLink {
+    pub language: String,
+    pub title: String,
+    pub url: String,
+}
+
+/// The url of a document with its language removed, which is the same for
+/// a document and its translations.
+pub fn translation_key(document: &Document) -> &str {
+    let prefix = format!("/{}", document.language);
+    match document.url.strip_prefix(&prefix) {
+        Some("") => "/",
+        Some(url) if url.starts_with('/') => url,
+        _ => &document.url,
+    }
+}
+
+/// The versions of a document in every other language.
+pub fn translations_of<'a>(project: &'a Project, document: &Document) -> Vec<&'a Document> {
+    let key = translation_key(document);
+    let mut translations = project
+        .root_folder
+        .iter_all_documents()
+        .filter(|d| d.language != document.language && translation_key(d) == key)
+        .collect::<Vec<_>>();
+    let languages = project.details.languages().collect::<Vec<_>>();
+    translations.sort_by_key(|d| languages.iter().position(|l| *l == d.language));
+    translations
+}
+
+/// Report which documents in the default language are missing a
+/// translation, or were changed since they were translated.
+pub fn translation_status(project: &Project) -> Result<Vec<TranslationStatus>> {
+    let mut report = vec![];
+    for document in project
+        .root_folder
+        .iter_all_documents()
+        .filter(|d| d.language == project.details.default_language)
+    {
+        let modified = std::fs::metadata(document.file_path.disk_path())?.modified()?;
+        let translations = translations_of(project, document);
+        let mut states = BTreeMap::new();
+        for language in project.details.translations.iter() {
+            let state = match translations.iter().find(|d| &d.language == language) {
+                None => TranslationState::Missing,
+                Some(translation) => {
+                    let translated =
+                        std::fs::metadata(translation.file_path.disk_path())?.modified()?;
+                    if translated < modified {
+                        TranslationState::Outdated
+                    } else {
+                        TranslationState::Current
+                    }
+                }
+            };
+            states.insert(language.clone(), state);
+        }
+        report.push(TranslationStatus {
+            url: document.url.clone(),
+            path: document.file_path.relative_path.clone(),
+            translations: states,
+        });
+    }
+    report.sort_by(|a, b| a.url.cmp(&b.url));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::{refactor::tests::writable_fixture, Project};
+
+    use super::{
+        message, messages, translation_key, translation_status, translations_of, TranslationState,
+    };
+
+    #[test]
+    fn test_translations() {
+        let root = writable_fixture("i18n");
+        let config = std::fs::read_to_string(root.join("codex.yml")).unwrap();
+        std::fs::write(
+            root.join("codex.yml"),
+            config + "translations:\n  - de\n  - fr\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("other/alert.de.md"), "---\ntitle: Hinweis\n---\n").unwrap();
+        std::fs::write(root.join("pages/index.de.md"), "---\ntitle: Seiten\n---\n").unwrap();
+        std::fs::create_dir_all(root.join("fr/other")).unwrap();
+        std::fs::write(root.join("fr/other/alert.md"), "---\ntitle: Alerte\n---\n").unwrap();
+        // The original changed after the French translation
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("fr/other/alert.md"))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+        let project = Project::load(&root, false).unwrap();
+
+        let german = project.get_document_for_url("/de/other/alert").unwrap();
+        assert_eq!(german.language, "de");
+        assert_eq!(translation_key(german), "/other/alert");
+        assert_eq!(
+            project.get_document_for_url("/de/pages").unwrap().language,
+            "de"
+        );
+        let french = project.get_document_for_url("/fr/other/alert").unwrap();
+        assert_eq!(french.language, "fr");
+        let original = project.get_document_for_url("/other/alert").unwrap();
+        assert_eq!(original.language, "en");
+        assert_eq!(
+            translations_of(&project, original)
+                .iter()
+                .map(|d| d.url.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/de/other/alert", "/fr/other/alert"]
+        );
+
+        let report = translation_status(&project).unwrap();
+        let alert = report.iter().find(|s| s.url == "/other/alert").unwrap();
+        assert_eq!(alert.translations["de"], TranslationState::Current);
+        assert_eq!(alert.translations["fr"], TranslationState::Outdated);
+        let pages = report.iter().find(|s| s.url == "/pages").unwrap();
+        assert_eq!(pages.translations["de"], TranslationState::Current);
+        assert_eq!(pages.translations["fr"], TranslationState::Missing);
+        assert!(report.iter().all(|s| !s.url.starts_with("/fr")));
+    }
+
+    #[test]
+    fn test_messages() {
+        let mut details = crate::ProjectDetails {
+            default_language: "de".into(),
+            ..Default::default()
+        };
+        details.messages.insert(
+            "de".into(),
+            [("search".to_string(), "Suche".to_string())].into(),
+        );
+        details.messages.insert(
+            "fr".into(),
+            [("on_this_page".to_string(), "Sur cette page".to_string())].into(),
+        );
+        assert_eq!(message(&details, "de", "search"), "Suche");
+        assert_eq!(message(&details, "de", "fields"), "Fields");
+        // Missing translations fall back to the default language
+        let french = messages(&details, "fr");
+        assert_eq!(french["on_this_page"], "Sur cette page");
+        assert_eq!(french["search"], "Suche");
+        assert_eq!(message(&details, "fr", "unknown"), "unknown");
+    }
+}
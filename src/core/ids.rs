@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    document::{join_frontmatter, split_frontmatter},
+    manifest::hash_bytes,
+    Error, Project, Result,
+};
+
+/// The registry of document ids, written to the project root.
+pub const ID_REGISTRY_FILE: &str = "ids.yml";
+/// Links starting with this prefix target a document by its id, such as
+/// `id:3f2a1b9c#usage`.
+pub const ID_LINK_PREFIX: &str = "id:";
+
+/// The id of every document, mapped to the url the document was at when the
+/// registry was written. Links use ids instead of urls so they keep working
+/// when documents move.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub struct IdRegistry {
+    pub ids: BTreeMap<String, String>,
+}
+
+impl IdRegistry {
+    /// Collect the ids set in the front matter of the project's documents.
+    pub fn build(project: &Project) -> Result<Self> {
+        let mut ids = BTreeMap::new();
+        for document in project.root_folder.iter_all_documents() {
+            let Some(ref id) = document.frontmatter.id else {
+                continue;
+            };
+            if let Some(url) = ids.insert(id.clone(), document.url.clone()) {
+                return Err(Error::new(format!(
+                    "The id {} is used by both {} and {}",
+                    id, url, document.url
+                )));
+            }
+        }
+        Ok(Self { ids })
+    }
+
+    pub fn save(&self, project: &Project) -> Result<()> {
+        std::fs::write(
+            project.path.disk_path().join(ID_REGISTRY_FILE),
+            serde_yaml::to_string(self)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Give every document without an id one generated from a hash of its url,
+/// written to its front matter, and write the registry. Returns the paths
+/// of the documents that were given an id.
+pub fn assign_ids(project: &Project) -> Result<Vec<PathBuf>> {
+    let mut registry = IdRegistry::build(project)?;
+    let mut assigned = vec![];
+    for document in project.root_folder.iter_all_documents() {
+        if document.frontmatter.id.is_some() {
+            continue;
+        }
+        let id = generate_id(&document.url, &registry);
+        let content = std::fs::read_to_string(document.file_path.disk_path())?;
+        let (mut frontmatter, body_start) = split_frontmatter(&content)?;
+        frontmatter.insert("id".into(), id.clone().into());
+        std::fs::write(
+            document.file_path.disk_path(),
+            join_frontmatter(&frontmatter, content[body_start..].trim_start())?,
+        )?;
+        registry.ids.insert(id, document.url.clone());
+        assigned.push(document.file_path.relative_path.clone());
+    }
+    registry.save(project)?;
+    Ok(assigned)
+}
+
+/// The first 8 characters of the hash of the url, rehashed until it doesn't
+/// collide with an id already in use.
+fn generate_id(url: &str, registry: &IdRegistry) -> String {
+    let mut content = url.to_string();
+    loop {
+        let id = hash_bytes(content.as_bytes())[..8].to_string();
+        if !registry.ids.contains_key(&id) {
+            return id;
+        }
+        content.push('#');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{project::tests::project_fixture, refactor::tests::writable_fixture, Project};
+
+    use super::{assign_ids, IdRegistry, ID_REGISTRY_FILE};
+
+    #[test]
+    fn test_registry() {
+        let project = project_fixture();
+        let registry = IdRegistry::build(&project).unwrap();
+        assert_eq!(
+            registry.ids.get("deploy-guide").unwrap(),
+            "/other/id_target"
+        );
+        assert_eq!(
+            project.get_document_for_id("deploy-guide").unwrap().url,
+            "/other/id_target"
+        );
+        assert!(project.get_document_for_id("missing").is_none());
+    }
+
+    #[test]
+    fn test_assign_ids() {
+        let root = writable_fixture("ids");
+        let project = Project::load(&root, false).unwrap();
+        let assigned = assign_ids(&project).unwrap();
+        assert!(assigned.contains(&"other/alert.md".into()));
+        assert!(!assigned.contains(&"other/id_target.md".into()));
+        assert!(std::fs::read_to_string(root.join("other/alert.md"))
+            .unwrap()
+            .starts_with("---\nid: "));
+
+        let project = Project::load(&root, false).unwrap();
+        let registry = IdRegistry::build(&project).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<IdRegistry>(
+                &std::fs::read_to_string(root.join(ID_REGISTRY_FILE)).unwrap()
+            )
+            .unwrap(),
+            registry
+        );
+        // Ids are kept, running it again changes nothing
+        assert!(assign_ids(&project).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+use image::{
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+        webp::WebPEncoder,
+    },
+    DynamicImage, ImageFormat,
+};
+
+use crate::{assets::CodexPath, Error, ImageDetails, Project, Result};
+
+/// A version of a static image generated when the site is built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageVariant {
+    /// The path of the variant, relative to the static folder
+    pub name: String,
+    /// The width the image is resized to, `None` keeps its size
+    pub width: Option<u32>,
+    pub format: ImageFormat,
+}
+
+/// The `srcset` attributes of an image with generated variants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageSources {
+    pub srcset: String,
+    /// The WebP variants, when they are generated
+    pub webp: Option<String>,
+}
+
+/// Whether the build processes the image, only PNG and JPEG images are.
+pub fn is_processed(name: &str) -> bool {
+    matches!(
+        ImageFormat::from_path(name),
+        Ok(ImageFormat::Png) | Ok(ImageFormat::Jpeg)
+    )
+}
+
+/// The variants built for an image that is `width` pixels wide: a copy for
+/// each configured size smaller than the image, and WebP versions of the
+/// image and each copy when enabled.
+pub fn variants(details: &ImageDetails, name: &str, width: u32) -> Vec<ImageVariant> {
+    let Ok(format) = ImageFormat::from_path(name) else {
+        return vec![];
+    };
+    let Some((stem, extension)) = name.rsplit_once('.') else {
+        return vec![];
+    };
+    let mut widths = details
+        .sizes
+        .iter()
+        .filter(|w| **w < width)
+        .map(|w| Some(*w))
+        .collect::<Vec<_>>();
+    widths.sort();
+    widths.dedup();
+    let mut variants = vec![];
+    for width in widths.iter() {
+        variants.push(ImageVariant {
+            name: format!("{}-{}w.{}", stem, width.unwrap_or_default(), extension),
+            width: *width,
+            format,
+        });
+    }
+    if details.webp {
+        widths.push(None);
+        for width in widths {
+            variants.push(ImageVariant {
+                name: match width {
+                    Some(width) => format!("{}-{}w.webp", stem, width),
+                    None => format!("{}.webp", stem),
+                },
+                width,
+                format: ImageFormat::WebP,
+            });
+        }
+    }
+    variants
+}
+
+/// The variants built for an image file.
+pub fn variants_of(
+    details: &ImageDetails,
+    name: &str,
+    content: &[u8],
+) -> Result<Vec<ImageVariant>> {
+    let (width, _) = image::ImageReader::new(std::io::Cursor::new(content))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    Ok(variants(details, name, width))
+}
+
+/// Recompress an image. The original is returned when recompressing
+/// doesn't make it smaller.
+pub fn optimize(details: &ImageDetails, name: &str, content: &[u8]) -> Result<Vec<u8>> {
+    let format = ImageFormat::from_path(name)?;
+    let image = image::load_from_memory_with_format(content, format)?;
+    let optimized = encode(details, &image, format)?;
+    if optimized.len() < content.len() {
+        Ok(optimized)
+    } else {
+        Ok(content.to_vec())
+    }
+}
+
+/// Generate a variant of an image.
+pub fn build_variant(
+    details: &ImageDetails,
+    variant: &ImageVariant,
+    content: &[u8],
+) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(content)?;
+    if let Some(width) = variant.width {
+        let height = (image.height() as u64 * width as u64 / image.width() as u64).max(1);
+        image = image.resize(width, height as u32, image::imageops::FilterType::Lanczos3);
+    }
+    encode(details, &image, variant.format)
+}
+
+fn encode(details: &ImageDetails, image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut out = vec![];
+    match format {
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new_with_quality(
+            &mut out,
+            CompressionType::Best,
+            FilterType::Adaptive,
+        ))?,
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(
+            JpegEncoder::new_with_quality(&mut out, details.jpeg_quality.clamp(1, 100)),
+        )?,
+        ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut out))?,
+        _ => return Err(Error::new(format!("Can't encode {:?} images", format))),
+    }
+    Ok(out)
+}
+
+/// The static image an image url refers to, with its path relative to the
+/// static folder and its width.
+fn source_image(project: &Project, url: &str) -> Option<(CodexPath, String, u32)> {
+    // Fragments can link to static files from their own url
+    let name = project
+        .details
+        .fragments
+        .asset_url
+        .iter()
+        .chain([&project.details.base_url])
+        .find_map(|prefix| url.strip_prefix(prefix.as_str()))
+        .unwrap_or(url)
+        .trim_start_matches('/');
+    if !is_processed(name) {
+        return None;
+    }
+    let file = project.path.join("static").join(name);
+    let (width, _) = image::image_dimensions(file.disk_path()).ok()?;
+    Some((file, name.to_string(), width))
+}
+
+/// The `srcset` attributes listing the variants of a static image, when
+/// the project generates any.
+pub fn sources(project: &Project, url: &str) -> Option<ImageSources> {
+    let (_, name, width) = source_image(project, url)?;
+    let variants = variants(&project.details.images, &name, width);
+    if variants.is_empty() {
+        return None;
+    }
+    // Variants are in the same folder as the image
+    let folder = url.rsplit_once('/').map(|(f, _)| f).unwrap_or_default();
+    let srcset = |format: ImageFormat, original: Option<&str>| {
+        let mut entries = variants
+            .iter()
+            .filter(|v| v.format == format)
+            .map(|v| {
+                let file = v.name.rsplit('/').next().unwrap_or_default();
+                (format!("{}/{}", folder, file), v.width.unwrap_or(width))
+            })
+            .collect::<Vec<_>>();
+        if let Some(original) = original {
+            entries.push((original.to_string(), width));
+        }
+        entries
+            .into_iter()
+            .map(|(url, width)| format!("{} {}w", url, width))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let format = ImageFormat::from_path(&name).ok()?;
+    Some(ImageSources {
+        srcset: srcset(format, Some(url)),
+        webp: project
+            .details
+            .images
+            .webp
+            .then(|| srcset(ImageFormat::WebP, None)),
+    })
+}
+
+/// Find the static image and variant served at a url, so variants can be
+/// generated when they are requested from the development server.
+pub fn variant_for_url(project: &Project, url: &str) -> Option<(CodexPath, ImageVariant)> {
+    let name = url.trim_start_matches('/');
+    let (path, extension) = name.rsplit_once('.')?;
+    let stem = path
+        .rsplit_once('-')
+        .filter(|(_, w)| {
+            w.strip_suffix('w')
+                .is_some_and(|w| w.parse::<u32>().is_ok())
+        })
+        .map(|(stem, _)| stem)
+        .unwrap_or(path);
+    let extensions = match extension {
+        "webp" => vec!["png", "jpg", "jpeg"],
+        extension => vec![extension],
+    };
+    extensions.into_iter().find_map(|extension| {
+        let (file, source, width) = source_image(project, &format!("/{}.{}", stem, extension))?;
+        variants(&project.details.images, &source, width)
+            .into_iter()
+            .find(|v| v.name == name)
+            .map(|v| (file, v))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ImageFormat;
+
+    use crate::{project::tests::project_fixture, ImageDetails};
+
+    use super::{build_variant, optimize, sources, variant_for_url, variants};
+
+    #[test]
+    fn test_variants() {
+        let details = ImageDetails {
+            webp: true,
+            sizes: vec![1200, 320, 640],
+            ..Default::default()
+        };
+        let names = variants(&details, "images/shot.png", 800)
+            .into_iter()
+            .map(|v| (v.name, v.width, v.format))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (
+                    "images/shot-320w.png".to_string(),
+                    Some(320),
+                    ImageFormat::Png
+                ),
+                (
+                    "images/shot-640w.png".to_string(),
+                    Some(640),
+                    ImageFormat::Png
+                ),
+                (
+                    "images/shot-320w.webp".to_string(),
+                    Some(320),
+                    ImageFormat::WebP
+                ),
+                (
+                    "images/shot-640w.webp".to_string(),
+                    Some(640),
+                    ImageFormat::WebP
+                ),
+                ("images/shot.webp".to_string(), None, ImageFormat::WebP),
+            ]
+        );
+        assert!(variants(&ImageDetails::default(), "images/shot.png", 800).is_empty());
+    }
+
+    #[test]
+    fn test_image_sources() {
+        let mut project = project_fixture();
+        let file = project.path.join("static/images/screenshot.png");
+        let content = file.read().unwrap().to_vec();
+        let (width, _) = image::image_dimensions(file.disk_path()).unwrap();
+        assert!(sources(&project, "/images/screenshot.png").is_none());
+
+        project.details.images.webp = true;
+        project.details.images.sizes = vec![width / 2];
+        let half = format!("/images/screenshot-{}w.png", width / 2);
+        assert_eq!(
+            sources(&project, "/images/screenshot.png").unwrap().srcset,
+            format!("{} {}w, /images/screenshot.png {}w", half, width / 2, width)
+        );
+
+        let (source, variant) = variant_for_url(&project, &half).unwrap();
+        assert_eq!(source, file);
+        let resized = build_variant(&project.details.images, &variant, &content).unwrap();
+        assert_eq!(
+            image::load_from_memory(&resized).unwrap().width(),
+            width / 2
+        );
+        let (_, variant) = variant_for_url(&project, "/images/screenshot.webp").unwrap();
+        assert_eq!(variant.format, ImageFormat::WebP);
+        assert!(variant_for_url(&project, "/images/screenshot-7w.png").is_none());
+
+        let optimized =
+            optimize(&project.details.images, "images/screenshot.png", &content).unwrap();
+        assert!(optimized.len() < content.len());
+    }
+}
@@ -2,11 +2,142 @@
 
 use std::collections::HashMap;
 
-use crate::Result;
+use crate::{assets::CodexPath, Error, Result};
 use serde::{Deserialize, Serialize};
 
-pub(crate) fn parse_schema(schema: &[u8]) -> Result<Vec<SchemaField>> {
-    let json_schema: JsonSchema = serde_json::from_slice(schema)?;
+/// Deserialize a schema file into a JSON value. The format is detected from
+/// the file extension, YAML and TOML files are supported alongside JSON.
+pub(crate) fn load_value(schema: &[u8], file: &CodexPath) -> Result<serde_json::Value> {
+    match file.extension().as_deref() {
+        Some("yaml") | Some("yml") => Ok(serde_yaml::from_slice(schema)?),
+        Some("toml") => Ok(toml::from_str(&String::from_utf8(schema.to_vec())?)?),
+        _ => Ok(serde_json::from_slice(schema)?),
+    }
+}
+
+/// Load a schema and resolve all of its references.
+fn load_schema(schema: &[u8], file: &CodexPath) -> Result<JsonSchema> {
+    let root = load_value(schema, file)?;
+    let resolved = resolve_refs(&root, &root, file, &mut vec![])?;
+    Ok(serde_json::from_value(resolved)?)
+}
+
+/// Replace every `$ref` in a schema with the schema it refers to. References
+/// can point to local definitions, such as `#/definitions/address`, or to
+/// other files relative to the current one, such as
+/// `address.json#/definitions/street`. Keywords next to a `$ref` override
+/// those of the referenced schema.
+///
+/// References that are already being resolved form a cycle. These are
+/// replaced with an object type rather than being expanded forever.
+fn resolve_refs(
+    value: &serde_json::Value,
+    root: &serde_json::Value,
+    file: &CodexPath,
+    stack: &mut Vec<String>,
+) -> Result<serde_json::Value> {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get("$ref") {
+                let (path, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+                let (target_file, target_root) = if path.is_empty() {
+                    (file.clone(), root.clone())
+                } else {
+                    let target_file = file.new_path(
+                        file.relative_path
+                            .parent()
+                            .unwrap_or(std::path::Path::new(""))
+                            .join(path),
+                    );
+                    let target_root = load_value(&target_file.read()?, &target_file)?;
+                    (target_file, target_root)
+                };
+                let key = format!("{}#{}", target_file.relative_path.display(), pointer);
+
+                let mut resolved = if stack.contains(&key) {
+                    serde_json::json!({ "type": "object" })
+                } else {
+                    let target = target_root.pointer(pointer).ok_or_else(|| {
+                        Error::new(format!("Schema reference not found: {}", reference))
+                    })?;
+                    stack.push(key);
+                    let resolved = resolve_refs(target, &target_root, &target_file, stack)?;
+                    stack.pop();
+                    resolved
+                };
+
+                if let Value::Object(ref mut resolved_map) = resolved {
+                    for (k, v) in map.iter().filter(|(k, _)| *k != "$ref") {
+                        resolved_map.insert(k.clone(), resolve_refs(v, root, file, stack)?);
+                    }
+                }
+                return Ok(resolved);
+            }
+
+            let mut resolved = serde_json::Map::new();
+            for (k, v) in map.iter() {
+                resolved.insert(k.clone(), resolve_refs(v, root, file, stack)?);
+            }
+            Ok(Value::Object(resolved))
+        }
+        Value::Array(items) => Ok(Value::Array(
+            items
+                .iter()
+                .map(|v| resolve_refs(v, root, file, stack))
+                .collect::<Result<Vec<_>>>()?,
+        )),
+        v => Ok(v.clone()),
+    }
+}
+
+/// The files a schema refers to with `$ref`, including the files those
+/// refer to in turn.
+pub(crate) fn referenced_files(file: &CodexPath) -> Result<Vec<CodexPath>> {
+    let mut files = vec![];
+    collect_referenced_files(file, &mut files)?;
+    Ok(files)
+}
+
+fn collect_referenced_files(file: &CodexPath, files: &mut Vec<CodexPath>) -> Result<()> {
+    let mut references = vec![];
+    collect_refs(&load_value(&file.read()?, file)?, &mut references);
+    for reference in references {
+        let path = reference.split('#').next().unwrap_or_default();
+        if path.is_empty() {
+            continue;
+        }
+        let target = file.new_path(
+            file.relative_path
+                .parent()
+                .unwrap_or(std::path::Path::new(""))
+                .join(path),
+        );
+        if target == *file || files.contains(&target) {
+            continue;
+        }
+        files.push(target.clone());
+        collect_referenced_files(&target, files)?;
+    }
+    Ok(())
+}
+
+fn collect_refs(value: &serde_json::Value, references: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            if let Some(serde_json::Value::String(reference)) = map.get("$ref") {
+                references.push(reference.clone());
+            }
+            map.values().for_each(|v| collect_refs(v, references));
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|v| collect_refs(v, references)),
+        _ => {}
+    }
+}
+
+pub(crate) fn parse_schema(schema: &[u8], file: &CodexPath) -> Result<Vec<SchemaField>> {
+    let json_schema = load_schema(schema, file)?;
     Ok(convert_schema_to_fields("", &json_schema))
 }
 
@@ -17,6 +148,8 @@ pub struct SchemaField {
     pub data_type: String,
     pub required: bool,
     pub deprecated: bool,
+    pub deprecated_message: Option<String>,
+    pub default: Option<String>,
     pub children: String,
 }
 
@@ -33,17 +166,18 @@ pub enum SchemaType {
     Null,
 }
 
-impl ToString for SchemaType {
-    fn to_string(&self) -> String {
-        match self {
-            SchemaType::String => "String".to_string(),
-            SchemaType::Number => "Number".to_string(),
-            SchemaType::Integer => "Integer".to_string(),
-            SchemaType::Boolean => "Boolean".to_string(),
-            SchemaType::Object => "Object".to_string(),
-            SchemaType::Array => "Array".to_string(),
-            SchemaType::Null => "null".to_string(),
-        }
+impl std::fmt::Display for SchemaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            SchemaType::String => "String",
+            SchemaType::Number => "Number",
+            SchemaType::Integer => "Integer",
+            SchemaType::Boolean => "Boolean",
+            SchemaType::Object => "Object",
+            SchemaType::Array => "Array",
+            SchemaType::Null => "null",
+        };
+        write!(f, "{}", name)
     }
 }
 
@@ -57,6 +191,27 @@ pub(crate) struct JsonSchema {
     pub items: Option<Box<JsonSchema>>,
     pub required: Vec<String>,
     pub format: String,
+    #[serde(rename = "enum")]
+    pub enum_values: Vec<serde_json::Value>,
+    #[serde(rename = "const")]
+    pub const_value: Option<serde_json::Value>,
+    pub default: Option<serde_json::Value>,
+    pub examples: Vec<serde_json::Value>,
+    pub minimum: Option<serde_json::Number>,
+    pub maximum: Option<serde_json::Number>,
+    #[serde(rename = "minLength")]
+    pub min_length: Option<u64>,
+    #[serde(rename = "maxLength")]
+    pub max_length: Option<u64>,
+    pub pattern: Option<String>,
+    #[serde(rename = "minItems")]
+    pub min_items: Option<u64>,
+    #[serde(rename = "additionalProperties")]
+    pub additional_properties: Option<serde_json::Value>,
+    pub deprecated: bool,
+    /// An explanation of the deprecation, such as the field that replaces it
+    #[serde(rename = "x-deprecated-message")]
+    pub deprecated_message: Option<String>,
 }
 
 fn convert_schema_to_fields(prefix: &str, schema: &JsonSchema) -> Vec<SchemaField> {
@@ -72,6 +227,63 @@ fn convert_schema_to_fields(prefix: &str, schema: &JsonSchema) -> Vec<SchemaFiel
     fields
 }
 
+/// Describe the format, allowed values and validation constraints of a
+/// schema. Each entry is a line of markdown appended to the field description.
+fn schema_details(schema: &JsonSchema) -> Vec<String> {
+    let mut details = vec![];
+    if !schema.format.is_empty() {
+        details.push(format!("**Format:** {}", schema.format));
+    }
+
+    if let Some(ref value) = schema.const_value {
+        details.push(format!("**Value:** `{}`", value));
+    } else if !schema.enum_values.is_empty() {
+        let values = schema
+            .enum_values
+            .iter()
+            .map(|v| format!("`{}`", v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        details.push(format!("**Allowed values:** {}", values));
+    }
+
+    if let Some(ref minimum) = schema.minimum {
+        details.push(format!("**Minimum:** {}", minimum));
+    }
+    if let Some(ref maximum) = schema.maximum {
+        details.push(format!("**Maximum:** {}", maximum));
+    }
+    if let Some(min_length) = schema.min_length {
+        details.push(format!("**Minimum length:** {}", min_length));
+    }
+    if let Some(max_length) = schema.max_length {
+        details.push(format!("**Maximum length:** {}", max_length));
+    }
+    if let Some(ref pattern) = schema.pattern {
+        details.push(format!("**Pattern:** `{}`", pattern));
+    }
+    if let Some(min_items) = schema.min_items {
+        details.push(format!("**Minimum items:** {}", min_items));
+    }
+    match schema.additional_properties {
+        Some(serde_json::Value::Bool(false)) => {
+            details.push("**Additional properties:** not allowed".to_string())
+        }
+        Some(serde_json::Value::Object(ref additional)) => {
+            if let Ok(additional) =
+                serde_json::from_value::<JsonSchema>(serde_json::Value::Object(additional.clone()))
+            {
+                details.push(format!(
+                    "**Additional properties:** {}",
+                    parse_type(&additional)
+                ));
+            }
+        }
+        _ => {}
+    }
+    details
+}
+
 fn parse_property(
     required: &[String],
     prefix: &str,
@@ -91,13 +303,16 @@ fn parse_property(
         children: schema.description.to_string(),
         data_type: parse_type(schema),
         required,
-        deprecated: false,
+        deprecated: schema.deprecated || schema.deprecated_message.is_some(),
+        deprecated_message: schema.deprecated_message.clone(),
+        default: schema.default.as_ref().map(|v| v.to_string()),
     };
 
-    if !schema.format.is_empty() {
+    let details = schema_details(schema);
+    if !details.is_empty() {
         root_field
             .children
-            .push_str(format!("\n\n---\n**Format:** {}\n", schema.format).as_str());
+            .push_str(format!("\n\n---\n{}\n", details.join("\n\n")).as_str());
     }
 
     fields.push(root_field);
@@ -133,13 +348,24 @@ fn parse_type(schema: &JsonSchema) -> String {
     }
 }
 
-pub(crate) fn build_example(schema_str: &[u8]) -> Result<String> {
-    let schema: JsonSchema = serde_json::from_slice(schema_str)?;
+pub(crate) fn build_example(schema_str: &[u8], file: &CodexPath) -> Result<String> {
+    let schema = load_schema(schema_str, file)?;
     let res = build_example_node(&schema);
     Ok(serde_json::to_string_pretty(&res)?)
 }
 
 pub fn build_example_node(schema: &JsonSchema) -> serde_json::Value {
+    if let Some(ref value) = schema.const_value {
+        return value.clone();
+    }
+    if let Some(value) = schema
+        .examples
+        .first()
+        .or(schema.default.as_ref())
+        .or(schema.enum_values.first())
+    {
+        return value.clone();
+    }
     match schema.data_type {
         SchemaType::Object => {
             let mut map = serde_json::Map::new();
@@ -156,19 +382,22 @@ pub fn build_example_node(schema: &JsonSchema) -> serde_json::Value {
                 serde_json::Value::Array(vec![])
             }
         }
-        SchemaType::String { .. } => serde_json::Value::String("Value".to_string()),
-        SchemaType::Number { .. } => serde_json::Value::Number(42.into()),
-        SchemaType::Integer { .. } => serde_json::Value::Number(42.into()),
-        SchemaType::Boolean { .. } => serde_json::Value::Bool(false),
-        SchemaType::Null { .. } => serde_json::Value::Null,
+        SchemaType::String => serde_json::Value::String("Value".to_string()),
+        SchemaType::Number => serde_json::Value::Number(42.into()),
+        SchemaType::Integer => serde_json::Value::Number(42.into()),
+        SchemaType::Boolean => serde_json::Value::Bool(false),
+        SchemaType::Null => serde_json::Value::Null,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::json_schema::{convert_schema_to_fields, JsonSchema};
+    use crate::{
+        assets::CodexPath,
+        json_schema::{convert_schema_to_fields, JsonSchema},
+    };
 
-    use super::build_example;
+    use super::{build_example, parse_schema};
 
     #[test]
     fn test_build_example() {
@@ -191,7 +420,11 @@ mod tests {
             }
         }
     }"#;
-        let ex = build_example(json_schema_str.as_bytes()).unwrap();
+        let ex = build_example(
+            json_schema_str.as_bytes(),
+            &CodexPath::new(".", "schema.json"),
+        )
+        .unwrap();
         let res =
             "{\n  \"subOne\": {\n    \"subTwo\": {\n      \"subThree\": \"Value\"\n    }\n  }\n}";
         assert_eq!(ex, res);
@@ -327,4 +560,219 @@ mod tests {
             "String"
         );
     }
+
+    #[test]
+    fn test_yaml_and_toml() {
+        let yaml = r#"
+type: object
+required: [name]
+properties:
+  name:
+    type: string
+  tags:
+    type: array
+    items:
+      type: string
+"#;
+        let fields = parse_schema(yaml.as_bytes(), &CodexPath::new(".", "schema.yml")).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert!(fields.iter().find(|f| f.name == "name").unwrap().required);
+
+        let toml = r#"
+type = "object"
+required = ["name"]
+
+[properties.name]
+type = "string"
+
+[properties.tags]
+type = "array"
+items = { type = "string" }
+"#;
+        let fields = parse_schema(toml.as_bytes(), &CodexPath::new(".", "schema.toml")).unwrap();
+        assert_eq!(
+            fields.iter().find(|f| f.name == "tags").unwrap().data_type,
+            "Array(String)"
+        );
+    }
+
+    #[test]
+    fn test_local_refs() {
+        let json_schema_str = r##"
+    {
+        "type": "object",
+        "definitions": {
+            "address": {
+                "type": "object",
+                "properties": {
+                    "city": { "type": "string" }
+                }
+            },
+            "node": {
+                "type": "object",
+                "properties": {
+                    "children": {
+                        "type": "array",
+                        "items": { "$ref": "#/definitions/node" }
+                    }
+                }
+            }
+        },
+        "properties": {
+            "home": {
+                "$ref": "#/definitions/address",
+                "description": "The home address"
+            },
+            "tree": { "$ref": "#/definitions/node" }
+        }
+    }"##;
+        let fields = parse_schema(
+            json_schema_str.as_bytes(),
+            &CodexPath::new(".", "schema.json"),
+        )
+        .unwrap();
+        let home = fields.iter().find(|f| f.name == "home").unwrap();
+        assert_eq!(home.children, "The home address");
+        assert!(fields.iter().any(|f| f.name == "home.city"));
+        assert!(fields.iter().any(|f| f.name == "tree.children"));
+        // The recursive reference is not expanded again
+        assert!(!fields.iter().any(|f| f.name == "tree.children.children"));
+    }
+
+    #[test]
+    fn test_file_refs() {
+        let schema = CodexPath::new("test/fixture", "other/schemas/person.json");
+        let fields = parse_schema(&schema.read().unwrap(), &schema).unwrap();
+        assert!(fields.iter().any(|f| f.name == "address.street"));
+        assert_eq!(
+            fields
+                .iter()
+                .find(|f| f.name == "address.street")
+                .unwrap()
+                .data_type,
+            "String"
+        );
+    }
+
+    #[test]
+    fn test_enum_and_const() {
+        let json_schema_str = r#"
+    {
+        "type": "object",
+        "properties": {
+            "status": {
+                "type": "string",
+                "enum": ["active", "disabled"]
+            },
+            "version": {
+                "type": "integer",
+                "const": 2
+            }
+        }
+    }"#;
+        let json_schema: JsonSchema = serde_json::from_str(json_schema_str).unwrap();
+        let fields = convert_schema_to_fields("", &json_schema);
+        let status = fields.iter().find(|f| f.name == "status").unwrap();
+        assert!(status
+            .children
+            .contains(r#"**Allowed values:** `"active"`, `"disabled"`"#));
+        let version = fields.iter().find(|f| f.name == "version").unwrap();
+        assert!(version.children.contains("**Value:** `2`"));
+
+        let ex = build_example(
+            json_schema_str.as_bytes(),
+            &CodexPath::new(".", "schema.json"),
+        )
+        .unwrap();
+        let ex: serde_json::Value = serde_json::from_str(&ex).unwrap();
+        assert_eq!(ex["status"], "active");
+        assert_eq!(ex["version"], 2);
+    }
+
+    #[test]
+    fn test_constraints() {
+        let json_schema_str = r#"
+    {
+        "type": "object",
+        "properties": {
+            "age": { "type": "integer", "minimum": 0, "maximum": 150 },
+            "code": { "type": "string", "minLength": 2, "maxLength": 8, "pattern": "^[A-Z]+$" },
+            "tags": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+            "labels": { "type": "object", "additionalProperties": { "type": "string" } },
+            "strict": { "type": "object", "additionalProperties": false }
+        }
+    }"#;
+        let json_schema: JsonSchema = serde_json::from_str(json_schema_str).unwrap();
+        let fields = convert_schema_to_fields("", &json_schema);
+        let field = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+        assert!(field("age")
+            .children
+            .contains("**Minimum:** 0\n\n**Maximum:** 150"));
+        assert!(field("code").children.contains("**Minimum length:** 2"));
+        assert!(field("code").children.contains("**Maximum length:** 8"));
+        assert!(field("code").children.contains("**Pattern:** `^[A-Z]+$`"));
+        assert!(field("tags").children.contains("**Minimum items:** 1"));
+        assert!(field("labels")
+            .children
+            .contains("**Additional properties:** String"));
+        assert!(field("strict")
+            .children
+            .contains("**Additional properties:** not allowed"));
+    }
+
+    #[test]
+    fn test_deprecated() {
+        let json_schema_str = r#"
+    {
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "username": { "type": "string", "deprecated": true },
+            "login": { "type": "string", "x-deprecated-message": "Use `username` instead" }
+        }
+    }"#;
+        let json_schema: JsonSchema = serde_json::from_str(json_schema_str).unwrap();
+        let fields = convert_schema_to_fields("", &json_schema);
+        let field = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+        assert!(!field("name").deprecated);
+        assert!(field("username").deprecated);
+        assert_eq!(field("username").deprecated_message, None);
+        assert!(field("login").deprecated);
+        assert_eq!(
+            field("login").deprecated_message.as_deref(),
+            Some("Use `username` instead")
+        );
+    }
+
+    #[test]
+    fn test_default_and_examples() {
+        let json_schema_str = r#"
+    {
+        "type": "object",
+        "properties": {
+            "name": {
+                "type": "string",
+                "default": "guest",
+                "examples": ["Jane Doe"]
+            },
+            "retries": {
+                "type": "integer",
+                "default": 3
+            }
+        }
+    }"#;
+        let json_schema: JsonSchema = serde_json::from_str(json_schema_str).unwrap();
+        let fields = convert_schema_to_fields("", &json_schema);
+        let retries = fields.iter().find(|f| f.name == "retries").unwrap();
+        assert_eq!(retries.default.as_deref(), Some("3"));
+
+        let ex = build_example(
+            json_schema_str.as_bytes(),
+            &CodexPath::new(".", "schema.json"),
+        )
+        .unwrap();
+        let ex: serde_json::Value = serde_json::from_str(&ex).unwrap();
+        assert_eq!(ex["name"], "Jane Doe");
+        assert_eq!(ex["retries"], 3);
+    }
 }
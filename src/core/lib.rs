@@ -1,13 +1,50 @@
+pub mod archetype;
 pub mod assets;
+pub mod budget;
+pub mod build_state;
+pub mod corpus;
+mod data_table;
+pub mod debug;
+pub mod deploy;
 mod document;
 mod error;
+pub mod events;
+pub mod faq;
+pub mod frontmatter;
+pub mod glossary;
+pub mod history;
+pub mod i18n;
+pub mod ids;
+pub mod images;
 mod json_schema;
+pub mod links;
+pub mod lint;
+pub mod manifest;
+pub mod minify;
 mod project;
+mod proto_schema;
+pub mod provenance;
+pub mod refactor;
 mod renderer;
+pub mod review;
+pub mod runbook;
+pub mod search;
+pub mod snippets;
 mod template;
 
-pub use document::{Document, FrontMatter};
-pub use error::{Error, Result};
-pub use project::{Folder, FolderDetails, Project, ProjectDetails};
-pub use renderer::{DataContext, HtmlRenderer, LatexRenderer, RenderContext, Renderer};
+pub use document::{
+    Document, FrontMatter, LatexLayout, PageMetadata, PageType, SocialCard, PAGE_METADATA_FILE,
+};
+pub use error::{Error, ErrorKind, Result, SourceLocation};
+pub use project::{
+    AccessibilityDetails, BudgetDetails, ColorMode, ContributorDetails, CorpusDetails,
+    DeployDetails, EditDetails, Folder, FolderDetails, FragmentDetails, ImageDetails, IncludeMode,
+    InjectDetails, KeyBindings, LatexDetails, LintDetails, MarkdownDetails, OutputTarget, Project,
+    ProjectDetails, SearchDetails, SearchWeights, SocialDetails, ThemeDetails, TrailingSlash,
+    UrlStyle,
+};
+pub use renderer::{
+    Breadcrumb, DataContext, HtmlRenderer, LatexRenderer, OutputFormat, RenderContext, Renderer,
+    TextRenderer, INCLUDES_FOLDER,
+};
 pub use template::render_template;
@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use markdown::mdast::Node;
+
+use crate::{ids::ID_LINK_PREFIX, refactor::link_attributes, Document, Project, Result};
+
+/// The links between the project's documents, used to list the pages that
+/// link to a document.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LinkGraph {
+    /// The urls of the documents linking to each document, keyed by the
+    /// linked document's url
+    pub backlinks: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LinkGraph {
+    /// Collect the links in every document of the project. Links to
+    /// missing documents and a document's links to itself are left out, as
+    /// are documents that can't be parsed, which fail when they are rendered.
+    pub fn build(project: &Project) -> Result<Self> {
+        let mut graph = LinkGraph::default();
+        for document in project.root_folder.iter_all_documents() {
+            let content = String::from_utf8(document.file_path.read()?.to_vec())?;
+            let Ok(ast) = crate::document::parse_source(&content, &project.details.markdown) else {
+                continue;
+            };
+            let mut targets = vec![];
+            collect_link_targets(&ast, &mut targets);
+            for target in targets {
+                let Some(linked) = resolve_link(project, &target) else {
+                    continue;
+                };
+                if linked.url != document.url {
+                    graph
+                        .backlinks
+                        .entry(linked.url.clone())
+                        .or_default()
+                        .insert(document.url.clone());
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// The urls of the documents linking to the document at a url.
+    pub fn backlinks(&self, url: &str) -> impl Iterator<Item = &str> {
+        self.backlinks
+            .get(url)
+            .into_iter()
+            .flatten()
+            .map(|u| u.as_str())
+    }
+}
+
+/// The document a link points to, for links to documents by their url or id.
+fn resolve_link<'a>(project: &'a Project, target: &str) -> Option<&'a Document> {
+    let target = target.split(['#', '?']).next().unwrap_or_default();
+    if let Some(id) = target.strip_prefix(ID_LINK_PREFIX) {
+        return project.get_document_for_id(id);
+    }
+    if !target.starts_with('/') {
+        return None;
+    }
+    project.get_document_for_link(target)
+}
+
+/// The targets of the markdown links, link definitions, and component link
+/// attributes in a document.
+fn collect_link_targets(node: &Node, targets: &mut Vec<String>) {
+    match node {
+        Node::Link(link) => targets.push(link.url.clone()),
+        Node::Definition(definition) => targets.push(definition.url.clone()),
+        Node::MdxJsxFlowElement(el) => targets.extend(link_attributes(&el.attributes)),
+        Node::MdxJsxTextElement(el) => targets.extend(link_attributes(&el.attributes)),
+        _ => {}
+    }
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        collect_link_targets(child, targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{project::tests::project_fixture, refactor::tests::writable_fixture, Project};
+
+    use super::LinkGraph;
+
+    #[test]
+    fn test_backlinks() {
+        let graph = LinkGraph::build(&project_fixture()).unwrap();
+        // Both links to the same page count once
+        assert_eq!(
+            graph.backlinks("/other/id_target").collect::<Vec<_>>(),
+            vec!["/other/id_links"]
+        );
+        assert_eq!(graph.backlinks("/other/id_links").count(), 0);
+
+        let root = writable_fixture("backlinks");
+        std::fs::write(
+            root.join("other/related.md"),
+            "---\ntitle: Related\n---\n\nSee [alerts](/other/alert/#usage), [the guide](id:deploy-guide) and [this page](/other/related).\n",
+        )
+        .unwrap();
+        let alert = std::fs::read_to_string(root.join("other/alert.md")).unwrap();
+        std::fs::write(root.join("other/alert.md"), alert + "\n<Backlinks />\n").unwrap();
+        let project = Project::load(&root, false).unwrap();
+
+        let related = project.get_document_for_url("/other/related").unwrap();
+        assert!(project.backlinks(related).unwrap().is_empty());
+        let target = project.get_document_for_url("/other/id_target").unwrap();
+        assert_eq!(
+            project
+                .backlinks(target)
+                .unwrap()
+                .iter()
+                .map(|d| d.url.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/other/id_links", "/other/related"]
+        );
+        let html = project
+            .get_document_for_url("/other/alert")
+            .unwrap()
+            .body(&project)
+            .unwrap();
+        assert!(html.contains(r#"<li><a href="/other/related">Related</a></li>"#));
+    }
+}
@@ -0,0 +1,329 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use markdown::mdast::{AttributeContent, AttributeValue, MdxJsxAttribute, Node};
+use serde::Serialize;
+
+use crate::{archetype::Archetype, search::plain_text, Document, Project, Result};
+
+/// A check `codex lint` runs on every document. Each can be turned off in
+/// the `lint` section of `codex.yml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// The document has no title in its front matter
+    MissingTitle,
+    /// Another document in the same language has the same title
+    DuplicateTitle,
+    /// A heading is more than one level below the heading before it
+    HeadingJump,
+    /// An image has no alt text
+    ImageAlt,
+    /// A line in a code block ends with whitespace
+    CodeTrailingWhitespace,
+    /// A line is longer than `lint.max_line_length`
+    LineLength,
+    /// A section required by the document's archetype is missing
+    ArchetypeSections,
+}
+
+impl LintRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::MissingTitle => "missing_title",
+            LintRule::DuplicateTitle => "duplicate_title",
+            LintRule::HeadingJump => "heading_jump",
+            LintRule::ImageAlt => "image_alt",
+            LintRule::CodeTrailingWhitespace => "code_trailing_whitespace",
+            LintRule::LineLength => "line_length",
+            LintRule::ArchetypeSections => "archetype_sections",
+        }
+    }
+}
+
+impl std::fmt::Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A problem found in a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintIssue {
+    /// The path of the document, relative to the project root
+    pub path: PathBuf,
+    /// The line the problem is on, when it is on a single line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    pub rule: LintRule,
+    pub message: String,
+}
+
+/// Check every document in the project for structural and content
+/// problems, skipping the rules turned off in `lint.ignore`.
+pub fn lint(project: &Project) -> Result<Vec<LintIssue>> {
+    let settings = &project.details.lint;
+    let mut issues = vec![];
+    let mut archetypes: HashMap<String, Option<Archetype>> = HashMap::new();
+    let mut titles: HashMap<(&str, &str), &PathBuf> = HashMap::new();
+    for document in project.root_folder.iter_all_documents() {
+        let path = &document.file_path.relative_path;
+        let mut issue = |rule: LintRule, line: Option<usize>, message: String| {
+            if !settings.ignore.iter().any(|r| r == rule.name()) {
+                issues.push(LintIssue {
+                    path: path.clone(),
+                    line,
+                    rule,
+                    message,
+                });
+            }
+        };
+        let content = String::from_utf8(document.file_path.read()?.to_vec())?;
+        let ast = crate::document::parse_source(&content, &project.details.markdown)?;
+
+        let title = document.frontmatter.title.trim();
+        if title.is_empty() {
+            issue(LintRule::MissingTitle, None, "Document has no title".into());
+        } else if let Some(other) = titles.get(&(document.language.as_str(), title)) {
+            issue(
+                LintRule::DuplicateTitle,
+                None,
+                format!(
+                    "The title \"{}\" is also used by {}",
+                    title,
+                    other.display()
+                ),
+            );
+        } else {
+            titles.insert((document.language.as_str(), title), path);
+        }
+
+        let mut nodes = vec![];
+        collect_nodes(&ast, &mut nodes);
+        let mut previous_depth = None;
+        for node in nodes {
+            let line = node.position().map(|p| p.start.line);
+            match node {
+                Node::Heading(heading) => {
+                    if let Some(previous) = previous_depth {
+                        if heading.depth > previous + 1 {
+                            issue(
+                                LintRule::HeadingJump,
+                                line,
+                                format!(
+                                    "Heading \"{}\" skips from level {} to level {}",
+                                    heading.children.iter().map(plain_text).collect::<String>(),
+                                    previous,
+                                    heading.depth
+                                ),
+                            );
+                        }
+                    }
+                    previous_depth = Some(heading.depth);
+                }
+                Node::Code(code) => {
+                    for (i, code_line) in code.value.lines().enumerate() {
+                        if code_line.ends_with([' ', '\t']) {
+                            issue(
+                                LintRule::CodeTrailingWhitespace,
+                                // The code starts on the line after the fence
+                                line.map(|l| l + i + 1),
+                                "Code block line ends with whitespace".into(),
+                            );
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(url) = image_without_alt(node) {
+                        issue(
+                            LintRule::ImageAlt,
+                            line,
+                            format!("Image without alt text: {}", url),
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(max) = settings.max_line_length {
+            for (i, source_line) in content.lines().enumerate() {
+                let length = source_line.chars().count();
+                if length > max {
+                    issue(
+                        LintRule::LineLength,
+                        Some(i + 1),
+                        format!("Line is {} characters, longer than {}", length, max),
+                    );
+                }
+            }
+        }
+
+        for message in archetype_issues(project, document, &mut archetypes)? {
+            issue(LintRule::ArchetypeSections, None, message);
+        }
+    }
+    Ok(issues)
+}
+
+/// The problems with a document's sections according to its archetype.
+fn archetype_issues(
+    project: &Project,
+    document: &Document,
+    archetypes: &mut HashMap<String, Option<Archetype>>,
+) -> Result<Vec<String>> {
+    let Some(ref name) = document.frontmatter.archetype else {
+        return Ok(vec![]);
+    };
+    let archetype = archetypes
+        .entry(name.clone())
+        .or_insert_with(|| Archetype::load(project, name).ok());
+    let Some(archetype) = archetype else {
+        return Ok(vec![format!("Archetype not found: {}", name)]);
+    };
+    Ok(archetype
+        .missing_sections(document)?
+        .into_iter()
+        .map(|section| {
+            format!(
+                "Missing section \"{}\" required by the {} archetype",
+                section, name
+            )
+        })
+        .collect())
+}
+
+/// Every node below `node`, in document order.
+fn collect_nodes<'a>(node: &'a Node, nodes: &mut Vec<&'a Node>) {
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        nodes.push(child);
+        collect_nodes(child, nodes);
+    }
+}
+
+/// The url of a markdown image or `Image` component without alt text.
+fn image_without_alt(node: &Node) -> Option<String> {
+    match node {
+        Node::Image(img) if img.alt.trim().is_empty() => Some(img.url.clone()),
+        Node::MdxJsxFlowElement(el) if el.name.as_deref() == Some("Image") => {
+            image_attrs_without_alt(&el.attributes)
+        }
+        Node::MdxJsxTextElement(el) if el.name.as_deref() == Some("Image") => {
+            image_attrs_without_alt(&el.attributes)
+        }
+        _ => None,
+    }
+}
+
+fn image_attrs_without_alt(attributes: &[AttributeContent]) -> Option<String> {
+    let mut src = String::new();
+    let mut alt = String::new();
+    for attr in attributes {
+        if let AttributeContent::Property(MdxJsxAttribute {
+            name,
+            value: Some(AttributeValue::Literal(value)),
+        }) = attr
+        {
+            match name.as_str() {
+                "src" => src = value.clone(),
+                "alt" => alt = value.clone(),
+                _ => {}
+            }
+        }
+    }
+    alt.trim().is_empty().then_some(src)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::project::tests::project_fixture;
+
+    use super::{lint, LintIssue, LintRule};
+
+    #[test]
+    fn test_required_sections() {
+        let project = project_fixture();
+        let issues = lint(&project).unwrap();
+        let issues = issues
+            .into_iter()
+            .filter(|i| i.path == Path::new("other/archetype.md"))
+            .filter(|i| i.rule == LintRule::ArchetypeSections)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![LintIssue {
+                path: PathBuf::from("other/archetype.md"),
+                line: None,
+                rule: LintRule::ArchetypeSections,
+                message: "Missing section \"Troubleshooting\" required by the guide archetype"
+                    .to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_image_alt_text() {
+        let project = project_fixture();
+        let issues = lint(&project).unwrap();
+        let messages = issues
+            .iter()
+            .filter(|i| i.path == Path::new("other/image.md"))
+            .filter(|i| i.rule == LintRule::ImageAlt)
+            .map(|i| i.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "Image without alt text: /images/missing-alt.png",
+                "Image without alt text: /images/no-alt.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_content_rules() {
+        let root = crate::refactor::tests::writable_fixture("lint_rules");
+        std::fs::write(
+            root.join("other").join("quality.md"),
+            "---\ntitle: Alerts\n---\n# Setup\n\n### Details\n\n\
+             ```sh\nmake \nmake install\n```\n\nA line that is long enough\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("other").join("alerts.md"),
+            "---\ntitle: Alerts\n---\nThe same title\n",
+        )
+        .unwrap();
+        let mut project = crate::Project::load(&root, false).unwrap();
+        project.details.lint.max_line_length = Some(20);
+        let issues = lint(&project).unwrap();
+        let of = |name: &str| {
+            issues
+                .iter()
+                .filter(|i| i.path == Path::new("other").join(name))
+                .map(|i| (i.rule, i.line))
+                .collect::<Vec<_>>()
+        };
+        let quality = of("quality.md");
+        assert!(quality.contains(&(LintRule::HeadingJump, Some(6))));
+        assert!(quality.contains(&(LintRule::CodeTrailingWhitespace, Some(9))));
+        assert!(quality.contains(&(LintRule::LineLength, Some(13))));
+        // One of the two documents titled "Alerts" is reported
+        assert_eq!(
+            [of("quality.md"), of("alerts.md")]
+                .concat()
+                .iter()
+                .filter(|(rule, _)| *rule == LintRule::DuplicateTitle)
+                .count(),
+            1
+        );
+
+        project.details.lint.ignore = vec!["heading_jump".to_string(), "line_length".into()];
+        let issues = lint(&project).unwrap();
+        assert!(!issues
+            .iter()
+            .any(|i| matches!(i.rule, LintRule::HeadingJump | LintRule::LineLength)));
+        let json = serde_json::to_value(&issues[0]).unwrap();
+        assert!(json["rule"].is_string());
+    }
+}
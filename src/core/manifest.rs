@@ -0,0 +1,316 @@
+use markdown::mdast::{AttributeContent, AttributeValue, MdxJsxAttribute, Node};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{assets::CodexPath, Document, Project, Result};
+
+/// Components that generate page content from a JSON schema, which can refer
+/// to other schema files.
+const SCHEMA_COMPONENTS: [&str; 2] = ["JsonSchemaFields", "JsonSchemaExample"];
+
+/// Components that generate page content from a source file.
+const GENERATOR_COMPONENTS: [&str; 5] = [
+    "JsonSchemaFields",
+    "JsonSchemaExample",
+    "ProtoSchema",
+    "CsvTable",
+    "DataTable",
+];
+
+/// The name of the build manifest written to the build folder.
+pub const MANIFEST_FILE: &str = "codex-manifest.json";
+
+/// A file used to generate part of a page.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// The path of the file, relative to the project root
+    pub path: String,
+    /// The SHA-256 hash of the file's content
+    pub hash: String,
+}
+
+/// The state of a page when the site was built.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageManifest {
+    pub url: String,
+    /// The SHA-256 hash of the document's markdown
+    pub hash: String,
+    pub sources: Vec<SourceFile>,
+}
+
+/// A record of the pages in a build and the sources they were generated
+/// from. It is written to the build folder so later checks can tell which
+/// sources changed since the last build.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BuildManifest {
+    pub pages: Vec<PageManifest>,
+}
+
+/// A page whose generating source changed while its prose did not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleSource {
+    pub url: String,
+    pub source: String,
+}
+
+impl BuildManifest {
+    pub fn build(project: &Project) -> Result<Self> {
+        let mut pages = vec![];
+        for document in project.root_folder.iter_all_documents() {
+            pages.push(PageManifest {
+                url: document.url.clone(),
+                hash: hash_file(&document.file_path)?,
+                sources: document_sources(project, document)?,
+            });
+        }
+        Ok(Self { pages })
+    }
+
+    /// Carry source hashes over from the previous build for pages that
+    /// weren't edited since. The manifest records each source as it was when
+    /// its page's prose was last changed, so source changes are reported
+    /// until the page is reviewed and edited, however many builds happen in
+    /// between.
+    pub fn since(mut self, previous: &BuildManifest) -> Self {
+        for page in self.pages.iter_mut() {
+            let Some(old) = previous.pages.iter().find(|p| p.url == page.url) else {
+                continue;
+            };
+            if old.hash != page.hash {
+                continue;
+            }
+            for source in page.sources.iter_mut() {
+                if let Some(old_source) = old.sources.iter().find(|s| s.path == source.path) {
+                    source.hash = old_source.hash.clone();
+                }
+            }
+        }
+        self
+    }
+
+    /// Load the manifest from a previous build. Returns `None` if the
+    /// project hasn't been built.
+    pub fn load(project: &Project) -> Result<Option<Self>> {
+        let path = project
+            .path
+            .disk_path()
+            .join(&project.details.build_path)
+            .join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&std::fs::read(path)?)?))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Find pages whose sources changed since the previous build even though
+    /// the page itself wasn't edited.
+    pub fn stale_sources(&self, previous: &BuildManifest) -> Vec<StaleSource> {
+        let mut stale = vec![];
+        for page in self.pages.iter() {
+            let Some(old) = previous.pages.iter().find(|p| p.url == page.url) else {
+                continue;
+            };
+            if old.hash != page.hash {
+                continue;
+            }
+            for source in page.sources.iter() {
+                let changed = old
+                    .sources
+                    .iter()
+                    .any(|s| s.path == source.path && s.hash != source.hash);
+                if changed {
+                    stale.push(StaleSource {
+                        url: page.url.clone(),
+                        source: source.path.clone(),
+                    });
+                }
+            }
+        }
+        stale
+    }
+}
+
+/// Collect the source files a document generates content from. This
+/// includes the `json_schema` front matter field, the `file` attribute of
+/// generator components, and the files schemas refer to with `$ref`.
+pub fn document_sources(project: &Project, document: &Document) -> Result<Vec<SourceFile>> {
+    let mut paths = vec![];
+    let mut schemas = vec![];
+    if let Some(ref schema) = document.frontmatter.json_schema {
+        schemas.push(schema.clone());
+    }
+    collect_sources(
+        &crate::document::parse(&document.file_path, &project.details.markdown)?,
+        &mut paths,
+        &mut schemas,
+    );
+    for schema in schemas {
+        let file = document.file_path.new_path(&schema);
+        paths.push(schema);
+        if !file.exists_on_disk() {
+            continue;
+        }
+        // Unreadable references are reported when the page renders
+        for referenced in crate::json_schema::referenced_files(&file).unwrap_or_default() {
+            paths.push(referenced.relative_path.to_string_lossy().to_string());
+        }
+    }
+
+    let mut sources: Vec<SourceFile> = vec![];
+    for path in paths {
+        if sources.iter().any(|s| s.path == path) {
+            continue;
+        }
+        let file = document.file_path.new_path(&path);
+        if !file.exists_on_disk() {
+            continue;
+        }
+        sources.push(SourceFile {
+            hash: hash_file(&file)?,
+            path,
+        });
+    }
+    Ok(sources)
+}
+
+fn collect_sources(node: &Node, paths: &mut Vec<String>, schemas: &mut Vec<String>) {
+    let element = match node {
+        Node::MdxJsxFlowElement(el) => Some((&el.name, &el.attributes)),
+        Node::MdxJsxTextElement(el) => Some((&el.name, &el.attributes)),
+        _ => None,
+    };
+    if let Some((Some(element_name), attributes)) = element {
+        if GENERATOR_COMPONENTS.contains(&element_name.as_str()) {
+            for attr in attributes {
+                if let AttributeContent::Property(MdxJsxAttribute {
+                    name,
+                    value: Some(AttributeValue::Literal(value)),
+                }) = attr
+                {
+                    if name == "file" {
+                        paths.push(value.clone());
+                    }
+                    if name == "file" && SCHEMA_COMPONENTS.contains(&element_name.as_str()) {
+                        schemas.push(value.clone());
+                    }
+                }
+            }
+        }
+    }
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        collect_sources(child, paths, schemas);
+    }
+}
+
+fn hash_file(path: &CodexPath) -> Result<String> {
+    Ok(hash_bytes(&path.read()?))
+}
+
+/// The hex encoded SHA-256 hash of some content.
+pub(crate) fn hash_bytes(content: &[u8]) -> String {
+    let digest = Sha256::digest(content);
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    use super::{document_sources, BuildManifest, StaleSource};
+
+    #[test]
+    fn test_document_sources() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/proto_schema").unwrap();
+        let sources = document_sources(&project, doc).unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].path, "other/service.proto");
+        assert_eq!(sources[0].hash.len(), 64);
+
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        assert!(document_sources(&project, doc).unwrap().is_empty());
+
+        let doc = project
+            .get_document_for_url("/other/json_schema_ref")
+            .unwrap();
+        let paths: Vec<_> = document_sources(&project, doc)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["other/schemas/person.json", "other/schemas/common.yml"]
+        );
+    }
+
+    #[test]
+    fn test_since_keeps_sources_until_edited() {
+        let project = project_fixture();
+        let mut previous = BuildManifest::build(&project).unwrap();
+        let page = previous
+            .pages
+            .iter_mut()
+            .find(|p| p.url == "/other/proto_schema")
+            .unwrap();
+        page.sources[0].hash = "old".to_string();
+
+        // Rebuilding without editing the page keeps reporting the source
+        let current = BuildManifest::build(&project).unwrap().since(&previous);
+        assert_eq!(current.stale_sources(&previous).len(), 0);
+        assert_eq!(
+            BuildManifest::build(&project)
+                .unwrap()
+                .stale_sources(&current)
+                .len(),
+            1
+        );
+
+        // Once the page is edited the current sources are recorded
+        previous
+            .pages
+            .iter_mut()
+            .find(|p| p.url == "/other/proto_schema")
+            .unwrap()
+            .hash = "old".to_string();
+        let current = BuildManifest::build(&project).unwrap().since(&previous);
+        assert!(BuildManifest::build(&project)
+            .unwrap()
+            .stale_sources(&current)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_stale_sources() {
+        let project = project_fixture();
+        let current = BuildManifest::build(&project).unwrap();
+
+        let mut previous = current.clone();
+        let page = previous
+            .pages
+            .iter_mut()
+            .find(|p| p.url == "/other/proto_schema")
+            .unwrap();
+        page.sources[0].hash = "old".to_string();
+        assert_eq!(
+            current.stale_sources(&previous),
+            vec![StaleSource {
+                url: "/other/proto_schema".to_string(),
+                source: "other/service.proto".to_string(),
+            }]
+        );
+
+        // Editing the page along with its source is not reported
+        let page = previous
+            .pages
+            .iter_mut()
+            .find(|p| p.url == "/other/proto_schema")
+            .unwrap();
+        page.hash = "old".to_string();
+        assert!(current.stale_sources(&previous).is_empty());
+    }
+}
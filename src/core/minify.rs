@@ -0,0 +1,90 @@
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+
+use crate::{Error, Result};
+
+/// Minify a generated page. Inline styles are minified along with the
+/// markup, inline scripts are left as they are.
+pub fn html(content: &str) -> String {
+    let cfg = minify_html::Cfg {
+        minify_css: true,
+        keep_closing_tags: true,
+        keep_html_and_head_opening_tags: true,
+        // Pages can include shared parts with server side includes
+        keep_ssi_comments: true,
+        ..minify_html::Cfg::spec_compliant()
+    };
+    String::from_utf8_lossy(&minify_html::minify(content.as_bytes(), &cfg)).into_owned()
+}
+
+pub fn css(content: &str) -> Result<String> {
+    let mut sheet = StyleSheet::parse(content, ParserOptions::default())
+        .map_err(|e| Error::new(e.to_string()))?;
+    sheet
+        .minify(MinifyOptions::default())
+        .map_err(|e| Error::new(e.to_string()))?;
+    let out = sheet
+        .to_css(PrinterOptions {
+            minify: true,
+            ..Default::default()
+        })
+        .map_err(|e| Error::new(e.to_string()))?;
+    Ok(out.code)
+}
+
+pub fn js(content: &str) -> Result<String> {
+    let session = minify_js::Session::new();
+    let mut out = vec![];
+    minify_js::minify(
+        &session,
+        minify_js::TopLevelMode::Global,
+        content.as_bytes(),
+        &mut out,
+    )
+    .map_err(|e| Error::new(format!("{:?}", e)))?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Minify a stylesheet or script copied from the static folder. Returns
+/// `None` for other files and files that are already minified, such as
+/// `bootstrap.min.css`.
+pub fn static_file(name: &str, content: &[u8]) -> Result<Option<Vec<u8>>> {
+    if name.contains(".min.") {
+        return Ok(None);
+    }
+    let Ok(content) = std::str::from_utf8(content) else {
+        return Ok(None);
+    };
+    let minified = match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("css") => css(content)?,
+        Some("js") => js(content)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(minified.into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_minify() {
+        let html = super::html(
+            "<html>\n  <head><style>p { color: #ff0000; }</style></head>\n  <body>\n    <p>Some   text</p>\n  </body>\n</html>",
+        );
+        assert_eq!(
+            html,
+            "<html><head><style>p{color:red}</style></head><body><p>Some text</p></body></html>"
+        );
+
+        let css = super::static_file("css/article.css", b"p {\n  margin: 0px;\n}\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(css, b"p{margin:0}");
+        let js = super::static_file("js/theme.js", b"let theme = 'dark';\nconsole.log(theme);")
+            .unwrap()
+            .unwrap();
+        assert!(js.len() < 39);
+        assert!(super::static_file("css/bootstrap.min.css", b"p{}")
+            .unwrap()
+            .is_none());
+        assert!(super::static_file("webfonts/a.ttf", b"").unwrap().is_none());
+    }
+}
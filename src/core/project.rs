@@ -1,15 +1,108 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
 
 use serde::{Deserialize, Serialize};
+use syntect::parsing::{SyntaxSet, SyntaxSetBuilder};
 
-use crate::{assets::CodexPath, Document, Result};
+use crate::{
+    assets::CodexPath,
+    document::ASSETS_EXTENSION,
+    events::{Event, Events},
+    history::{Contributor, GitHistory, Revision},
+    links::LinkGraph,
+    Document, Renderer, Result,
+};
 
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
 #[serde(default)]
 pub struct FolderDetails {
     pub name: Option<String>,
+    /// A short introduction to the folder, shown in the overview starting
+    /// its part of the LaTeX document
+    pub description: Option<String>,
     pub menu_position: i32,
     pub menu_exclude: bool,
+    /// The outputs the folder's documents are included in, such as `[html]`
+    /// to keep a section out of the PDF. Subfolders inherit them.
+    pub formats: Option<Vec<OutputTarget>>,
+}
+
+/// An output a document can be included in.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputTarget {
+    /// The static site and the dev server
+    Html,
+    /// The LaTeX document and printable views
+    Pdf,
+}
+
+/// The server side include syntax pages use to include the parts shared
+/// by every page.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncludeMode {
+    /// Server side includes, supported by nginx and Apache
+    Ssi,
+    /// Edge side includes, supported by Varnish and CDNs
+    Esi,
+}
+
+impl IncludeMode {
+    /// The directive that includes the file at a url.
+    pub fn directive(&self, url: &str) -> String {
+        match self {
+            IncludeMode::Ssi => format!(r#"<!--#include virtual="{}" -->"#, url),
+            IncludeMode::Esi => format!(r#"<esi:include src="{}" />"#, url),
+        }
+    }
+}
+
+/// How the urls of pages are formed.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlStyle {
+    /// Directory urls such as `/guide/install`, built to
+    /// `guide/install/index.html`
+    #[default]
+    Pretty,
+    /// File urls such as `/guide/install.html`, for hosts that can't serve
+    /// urls without an extension
+    File,
+}
+
+impl UrlStyle {
+    /// The url of a page from its url without an extension. Index pages are
+    /// their folder's `index.html` file.
+    pub fn page_url(&self, url: &str, index: bool) -> String {
+        match self {
+            UrlStyle::Pretty => url.to_string(),
+            UrlStyle::File if index => format!("{}/index.html", url.trim_end_matches('/')),
+            UrlStyle::File => format!("{}.html", url),
+        }
+    }
+
+    /// The file in the build folder the page at a url is written to.
+    pub fn output_file(&self, url: &str) -> PathBuf {
+        let path = PathBuf::from(url.trim_start_matches('/'));
+        match self {
+            UrlStyle::Pretty => path.join("index.html"),
+            UrlStyle::File => path,
+        }
+    }
+}
+
+/// Whether directory urls end with a slash.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// `/guide/install/`
+    Always,
+    /// `/guide/install`
+    Never,
 }
 
 /// A folder in the project.
@@ -39,7 +132,86 @@ impl Folder {
         raw_name.replace("-", " ").replace("_", " ")
     }
 
-    pub fn iter_all_documents<'a>(&'a self) -> Box<dyn Iterator<Item = &Document> + 'a> {
+    pub fn iter_all_folders<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Folder> + 'a> {
+        Box::new(
+            std::iter::once(self).chain(self.folders.iter().flat_map(|f| f.iter_all_folders())),
+        )
+    }
+
+    /// Give documents the formats declared by their folder, or the nearest
+    /// parent folder that declares them. Formats set in a document's front
+    /// matter take precedence.
+    fn inherit_formats(&mut self, inherited: Option<&Vec<OutputTarget>>) {
+        let formats = self.details.formats.clone().or(inherited.cloned());
+        for document in self.documents.iter_mut() {
+            if document.frontmatter.formats.is_none() {
+                document.frontmatter.formats = formats.clone();
+            }
+        }
+        for folder in self.folders.iter_mut() {
+            folder.inherit_formats(formats.as_ref());
+        }
+    }
+
+    /// Set the language of each document. Documents in a top level folder
+    /// named after a language, such as `de/guide.md`, are in that language.
+    /// A translation beside the original, such as `guide.de.md`, is in the
+    /// language before its extension and is served under the language's
+    /// url, `/de/guide`. Other documents are in the default language.
+    fn assign_languages(&mut self, details: &ProjectDetails, folder_language: Option<&str>) {
+        for document in self.documents.iter_mut() {
+            let stem = document
+                .file_path
+                .relative_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            let translation = stem
+                .rsplit_once('.')
+                .filter(|(_, language)| details.translations.iter().any(|t| t == language));
+            match translation {
+                Some((name, language)) => {
+                    let url = document
+                        .url
+                        .strip_suffix(&format!(".{}", language))
+                        .unwrap_or(&document.url);
+                    let url = if name == "index" {
+                        url.strip_suffix("index").unwrap_or(url)
+                    } else {
+                        url
+                    };
+                    document.url = format!("/{}{}", language, url.trim_end_matches('/'));
+                    document.language = language.to_string();
+                }
+                None => {
+                    document.language = folder_language
+                        .unwrap_or(&details.default_language)
+                        .to_string();
+                }
+            }
+        }
+        let top_level = self.path.relative_path.as_os_str().is_empty();
+        for folder in self.folders.iter_mut() {
+            let language = match folder_language {
+                None if top_level => details.languages().find(|l| *l == folder.name),
+                language => language,
+            };
+            folder.assign_languages(details, language);
+        }
+    }
+
+    /// Give documents urls in the project's url style, once their language
+    /// is known.
+    fn apply_url_style(&mut self, details: &ProjectDetails) {
+        for document in self.documents.iter_mut() {
+            document.url = details.page_url(&document.url, document.is_index());
+        }
+        for folder in self.folders.iter_mut() {
+            folder.apply_url_style(details);
+        }
+    }
+
+    pub fn iter_all_documents<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Document> + 'a> {
         Box::new(
             self.documents
                 .iter()
@@ -48,6 +220,360 @@ impl Folder {
     }
 }
 
+/// Keys used to navigate between pages.
+/// Values are matched against `KeyboardEvent.key` in the browser. Setting a
+/// binding to `~` disables it.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub previous_page: Option<String>,
+    pub next_page: Option<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            previous_page: Some("ArrowLeft".to_string()),
+            next_page: Some("ArrowRight".to_string()),
+        }
+    }
+}
+
+/// Accessibility options for the generated site.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct AccessibilityDetails {
+    /// Render a skip to content link at the top of each page
+    pub skip_links: bool,
+    /// Draw a visible outline around focused elements
+    pub focus_outlines: bool,
+    /// Show a display settings menu in the page header, which lets readers
+    /// switch to the high contrast and reduced motion variants of the theme
+    pub settings_menu: bool,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for AccessibilityDetails {
+    fn default() -> Self {
+        Self {
+            skip_links: true,
+            focus_outlines: true,
+            settings_menu: true,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+/// The color mode pages are shown in before the reader picks one.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Follow the reader's system setting
+    Auto,
+    Light,
+    Dark,
+}
+
+/// The light and dark variants of the site's theme.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ThemeDetails {
+    pub mode: ColorMode,
+    /// The syntect theme code blocks are highlighted with in light mode
+    pub code_light: String,
+    /// The syntect theme code blocks are highlighted with in dark mode
+    pub code_dark: String,
+}
+
+impl Default for ThemeDetails {
+    fn default() -> Self {
+        Self {
+            mode: ColorMode::Auto,
+            code_light: "InspiredGitHub".to_string(),
+            code_dark: "Solarized (dark)".to_string(),
+        }
+    }
+}
+
+/// Markdown syntax features enabled for the project's documents.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MarkdownDetails {
+    /// GitHub flavored tables
+    pub gfm_table: bool,
+    /// GitHub flavored strikethrough with `~text~` or `~~text~~`
+    pub gfm_strikethrough: bool,
+    /// GitHub flavored task list items, such as `- [x] done`
+    pub gfm_task_list_item: bool,
+    /// Turn bare urls such as `www.example.com` into links
+    pub gfm_autolink_literal: bool,
+    /// Math with `$inline$` and `$$` blocks
+    pub math: bool,
+    /// MDX expressions such as `{value}`. When disabled braces are plain
+    /// text.
+    pub mdx_expressions: bool,
+    /// Pass raw HTML through to the page. Components can't be used when
+    /// this is enabled, as HTML takes precedence over component syntax.
+    pub html: bool,
+}
+
+impl Default for MarkdownDetails {
+    fn default() -> Self {
+        Self {
+            gfm_table: true,
+            gfm_strikethrough: true,
+            gfm_task_list_item: true,
+            gfm_autolink_literal: false,
+            math: false,
+            mdx_expressions: true,
+            html: false,
+        }
+    }
+}
+
+/// Size limits for the built site, in bytes. Files over a limit are
+/// reported when the site is built.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct BudgetDetails {
+    /// The maximum size of each HTML page
+    pub page: Option<u64>,
+    /// The maximum size of each image
+    pub image: Option<u64>,
+    /// The maximum total size of the built files in a folder, keyed by the
+    /// folder's path relative to the site root
+    pub folders: BTreeMap<String, u64>,
+}
+
+/// Settings for `codex lint`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct LintDetails {
+    /// The names of the rules to skip, such as `missing_title`
+    pub ignore: Vec<String>,
+    /// The longest a line of a document can be, in characters. Line length
+    /// isn't checked when it isn't set.
+    pub max_line_length: Option<usize>,
+}
+
+/// Processing applied to the PNG and JPEG images in the static folder when
+/// the site is built.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ImageDetails {
+    /// Recompress images, keeping the original when it is already smaller
+    pub optimize: bool,
+    /// Generate a WebP version of each image and resized copy
+    pub webp: bool,
+    /// Widths, in pixels, to generate resized copies of each image at.
+    /// Pages let the browser choose the smallest copy that fits.
+    pub sizes: Vec<u32>,
+    /// The quality recompressed JPEG images are saved with, from 1 to 100
+    pub jpeg_quality: u8,
+}
+
+impl Default for ImageDetails {
+    fn default() -> Self {
+        Self {
+            optimize: false,
+            webp: false,
+            sizes: vec![],
+            jpeg_quality: 80,
+        }
+    }
+}
+
+/// Options for `codex build --fragment`, which builds each page's article
+/// without the site layout so it can be embedded in another application.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct FragmentDetails {
+    /// The folder fragments are built to, relative to the project root
+    pub build_path: String,
+    /// A handlebars template, relative to the project root, that each
+    /// fragment is rendered with. It receives the same data as the article
+    /// template. Fragments are only the article body when it isn't set.
+    pub template: Option<String>,
+    /// The url static files and page attachments are linked from, when the
+    /// application serves them from somewhere other than the base url
+    pub asset_url: Option<String>,
+}
+
+impl Default for FragmentDetails {
+    fn default() -> Self {
+        Self {
+            build_path: "fragments".to_string(),
+            template: None,
+            asset_url: None,
+        }
+    }
+}
+
+/// Options for the contributors listed on each page.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct ContributorDetails {
+    /// List the authors of the commits changing each page
+    pub enabled: bool,
+    /// Include the contributors' email addresses
+    pub emails: bool,
+    /// The url of a contributor's avatar, where `{hash}` is replaced with
+    /// the SHA-256 hash of their email address
+    pub avatar_url: Option<String>,
+}
+
+/// Options for the `deploy` command.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct DeployDetails {
+    /// The folder the built site is copied to
+    pub target: Option<String>,
+    /// Remove files from the target that are no longer part of the site
+    pub delete_orphans: bool,
+    /// The public url of the deploy target, used to print the address of
+    /// preview deploys
+    pub url: Option<String>,
+    /// The path of preview deploys inside the target and the site. The
+    /// `{branch}` placeholder is replaced with the name of the branch.
+    pub preview_path: String,
+}
+
+impl Default for DeployDetails {
+    fn default() -> Self {
+        Self {
+            target: None,
+            delete_orphans: false,
+            url: None,
+            preview_path: "previews/{branch}".to_string(),
+        }
+    }
+}
+
+/// Raw HTML added to every page, such as analytics scripts, fonts, or meta
+/// tags, without ejecting the page template.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct InjectDetails {
+    /// HTML added at the end of the `<head>`
+    pub head: Option<String>,
+    /// A file, relative to the project root, added after `head`
+    pub head_file: Option<String>,
+    /// HTML added at the end of the `<body>`
+    pub body: Option<String>,
+    /// A file, relative to the project root, added after `body`
+    pub body_file: Option<String>,
+}
+
+/// Options for the previews social sites show for links to pages.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct SocialDetails {
+    /// The image used for pages without their own, such as the project's
+    /// logo
+    pub image: Option<String>,
+    /// The site's Twitter account, such as `@codex`
+    pub twitter: Option<String>,
+}
+
+/// Options for the "Edit this page" link on every page.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct EditDetails {
+    /// The url of a document's editor, where `{path}` is replaced with the
+    /// document's path in the repository and `{branch}` with the branch.
+    /// GitHub and GitLab `repo_url`s are used when it isn't set.
+    pub url: Option<String>,
+    pub branch: String,
+    /// The folder of the project inside the repository
+    pub path: String,
+}
+
+impl Default for EditDetails {
+    fn default() -> Self {
+        Self {
+            url: None,
+            branch: "main".to_string(),
+            path: String::new(),
+        }
+    }
+}
+
+/// Options for the plain text corpus written for language models.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CorpusDetails {
+    /// Write `llms.txt` and the chunks of each page when building
+    pub enabled: bool,
+    /// The most tokens in a chunk, estimated at four characters a token
+    pub chunk_tokens: usize,
+}
+
+impl Default for CorpusDetails {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chunk_tokens: 500,
+        }
+    }
+}
+
+/// Options for the site search.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SearchDetails {
+    /// Generate a search index and display a search box in the page header
+    pub enabled: bool,
+    pub weights: SearchWeights,
+    /// Score multipliers for documents inside a folder, keyed by the folder's
+    /// path relative to the project root. The most specific folder applies.
+    pub folder_boosts: HashMap<String, f32>,
+    /// Alternative words matched by a search term, keyed by the term
+    pub synonyms: HashMap<String, Vec<String>>,
+    /// Words ignored when they appear in a search query
+    pub stop_words: Vec<String>,
+}
+
+impl Default for SearchDetails {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            weights: SearchWeights::default(),
+            folder_boosts: HashMap::new(),
+            synonyms: HashMap::new(),
+            stop_words: vec![],
+        }
+    }
+}
+
+/// The score added when a search term matches each field of a record.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SearchWeights {
+    pub title: f32,
+    pub heading: f32,
+    pub text: f32,
+    pub tags: f32,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            title: 3.0,
+            heading: 2.0,
+            text: 1.0,
+            tags: 2.0,
+        }
+    }
+}
+
+/// Options used when generating LaTeX documents.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct LatexDetails {
+    /// Place a QR code in the margin next to each external link
+    pub link_qr_codes: bool,
+}
+
 /// The project details.
 /// This is the content of the `codex.yml` file.
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -57,8 +583,199 @@ pub struct ProjectDetails {
     pub build_path: String,
     pub repo_url: Option<String>,
     pub project_page: Option<String>,
+    pub edit: EditDetails,
+    pub social: SocialDetails,
+    pub contributors: ContributorDetails,
     pub base_url: String,
+    /// The public address of the site, such as `https://docs.example.com`,
+    /// used for the canonical url of each page
+    pub site_url: Option<String>,
     pub author: Option<String>,
+    pub accessibility: AccessibilityDetails,
+    pub theme: ThemeDetails,
+    /// Generate a printable view for each folder, which contains all of the
+    /// folder's documents in a single page.
+    pub print_views: bool,
+    /// Copy static files to names containing a hash of their content when
+    /// building, so they can be cached indefinitely.
+    pub fingerprint_assets: bool,
+    /// Write a JSON file with each page's title, table of contents, tags,
+    /// modification time and word count beside the page.
+    pub page_metadata: bool,
+    /// Show a card with the title and description of the target page when
+    /// hovering over an internal link. Page metadata is written for the
+    /// cards to read.
+    pub link_previews: bool,
+    /// Terms and their definitions for the glossary
+    pub glossary: BTreeMap<String, String>,
+    pub inject: InjectDetails,
+    /// The language documents are written in, unless they are a
+    /// translation
+    pub default_language: String,
+    /// The languages documents are translated to
+    pub translations: Vec<String>,
+    /// The interface text of the site for each language, replacing the
+    /// built in English messages
+    pub messages: BTreeMap<String, BTreeMap<String, String>>,
+    /// Include the navigation menu and footer in built pages with server
+    /// side includes, instead of repeating them in every page
+    pub includes: Option<IncludeMode>,
+    /// Whether page urls are directories or HTML files
+    pub url_style: UrlStyle,
+    /// Whether directory urls end with a slash. Links to pages are written
+    /// in this form and the development server redirects to it. Urls are
+    /// left as they are written when it isn't set.
+    pub trailing_slash: Option<TrailingSlash>,
+    /// Minify the HTML of built pages.
+    pub minify: bool,
+    /// Minify the stylesheets and scripts copied from the static folder.
+    pub minify_static: bool,
+    /// The fingerprinted names of the static files, keyed by their path in
+    /// the static folder. Set while building, the `asset` template helper
+    /// resolves paths through it.
+    #[serde(skip_deserializing)]
+    pub assets: BTreeMap<String, String>,
+    /// Add `integrity` and `crossorigin` attributes to the scripts and
+    /// stylesheets pages link to when building.
+    pub subresource_integrity: bool,
+    /// Fail when a template outputs a variable that doesn't exist, instead
+    /// of leaving it empty
+    pub strict_templates: bool,
+    /// Render a component or code block that fails as an error card in its
+    /// place, so the rest of the page still renders, instead of failing
+    /// the page. Off when `strict_templates` is on.
+    pub safe_mode: bool,
+    /// Mark the output of each HTML template with comments naming the
+    /// template and whether it is the project's or Codex's. Set by
+    /// `codex serve --debug`.
+    #[serde(skip_deserializing)]
+    pub trace_templates: bool,
+    /// The integrity value of each script and stylesheet, keyed by its path
+    /// in the static folder. Set while building, the `integrity` template
+    /// helper reads it.
+    #[serde(skip_deserializing)]
+    pub integrity: BTreeMap<String, String>,
+    pub latex: LatexDetails,
+    pub search: SearchDetails,
+    pub corpus: CorpusDetails,
+    /// Urls that redirect to another page, keyed by the old url
+    pub redirects: BTreeMap<String, String>,
+    pub deploy: DeployDetails,
+    pub budgets: BudgetDetails,
+    pub lint: LintDetails,
+    /// The rules for front matter fields, keyed by the field's name.
+    /// Documents are checked against them when the project loads.
+    pub frontmatter: BTreeMap<String, crate::frontmatter::FieldSchema>,
+    pub images: ImageDetails,
+    pub fragments: FragmentDetails,
+    pub markdown: MarkdownDetails,
+}
+
+impl ProjectDetails {
+    /// Read the settings of the project at `path` from its `codex.yml`, or
+    /// the defaults if it doesn't have one.
+    pub fn load(path: &Path, ignore_base_url: bool) -> Result<Self> {
+        let config = path.join("codex.yml");
+        let mut details = if config.exists() {
+            serde_yaml::from_reader(std::fs::File::open(config)?)?
+        } else {
+            ProjectDetails::default()
+        };
+        if ignore_base_url {
+            details.base_url = "/".to_string();
+        }
+        if !details.base_url.ends_with('/') {
+            details.base_url.push('/');
+        }
+        Ok(details)
+    }
+
+    /// The names of the top level settings that differ from `other`.
+    pub fn changed_settings(&self, other: &ProjectDetails) -> Vec<String> {
+        let (Ok(serde_json::Value::Object(a)), Ok(serde_json::Value::Object(b))) =
+            (serde_json::to_value(self), serde_json::to_value(other))
+        else {
+            return vec![];
+        };
+        let mut changed = a
+            .keys()
+            .chain(b.keys().filter(|k| !a.contains_key(*k)))
+            .filter(|k| a.get(*k) != b.get(*k))
+            .cloned()
+            .collect::<Vec<_>>();
+        changed.sort();
+        changed
+    }
+
+    /// The url of a page from its url without an extension or trailing
+    /// slash, in the project's url style.
+    pub fn page_url(&self, url: &str, index: bool) -> String {
+        let url = self.url_style.page_url(url, index);
+        match (self.url_style, self.trailing_slash) {
+            (UrlStyle::Pretty, Some(TrailingSlash::Always)) if url != "/" => format!("{}/", url),
+            _ => url,
+        }
+    }
+
+    /// The fully qualified url of a page or file in the site, including the
+    /// base url, or `None` when `site_url` isn't set. Urls that are already
+    /// absolute are returned as they are.
+    pub fn absolute_url(&self, url: &str) -> Option<String> {
+        if url.contains("://") {
+            return Some(url.to_string());
+        }
+        let site_url = self.site_url.as_ref()?;
+        Some(format!(
+            "{}{}{}",
+            site_url.trim_end_matches('/'),
+            self.base_url,
+            url.trim_start_matches('/')
+        ))
+    }
+
+    /// Whether the metadata of each page is written beside it, either
+    /// because it was asked for or because link previews read it.
+    pub fn writes_metadata(&self) -> bool {
+        self.page_metadata || self.link_previews
+    }
+
+    /// The url of the editor for a file, from its path relative to the
+    /// project root.
+    pub fn edit_url(&self, path: &Path) -> Option<String> {
+        let pattern = match self.edit.url {
+            Some(ref url) => url.clone(),
+            None => {
+                let repo = self.repo_url.as_deref()?.trim_end_matches('/');
+                let repo = repo.strip_suffix(".git").unwrap_or(repo);
+                if repo.contains("github.com") {
+                    format!("{}/edit/{{branch}}/{{path}}", repo)
+                } else if repo.contains("gitlab") {
+                    format!("{}/-/edit/{{branch}}/{{path}}", repo)
+                } else {
+                    return None;
+                }
+            }
+        };
+        let path = std::iter::once(self.edit.path.trim_matches('/').to_string())
+            .chain(
+                path.components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string()),
+            )
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        Some(
+            pattern
+                .replace("{branch}", &self.edit.branch)
+                .replace("{path}", &path),
+        )
+    }
+
+    /// The default language followed by each translation.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.default_language.as_str())
+            .chain(self.translations.iter().map(|t| t.as_str()))
+    }
 }
 
 impl Default for ProjectDetails {
@@ -68,12 +785,58 @@ impl Default for ProjectDetails {
             build_path: "dist".to_string(),
             repo_url: None,
             project_page: None,
+            edit: EditDetails::default(),
+            social: SocialDetails::default(),
+            contributors: ContributorDetails::default(),
             base_url: "/".to_string(),
+            site_url: None,
             author: None,
+            accessibility: AccessibilityDetails::default(),
+            theme: ThemeDetails::default(),
+            print_views: false,
+            fingerprint_assets: false,
+            page_metadata: false,
+            link_previews: false,
+            glossary: BTreeMap::new(),
+            inject: InjectDetails::default(),
+            default_language: "en".to_string(),
+            translations: vec![],
+            messages: BTreeMap::new(),
+            includes: None,
+            url_style: UrlStyle::default(),
+            trailing_slash: None,
+            minify: false,
+            minify_static: false,
+            assets: BTreeMap::new(),
+            subresource_integrity: false,
+            strict_templates: false,
+            safe_mode: true,
+            trace_templates: false,
+            integrity: BTreeMap::new(),
+            latex: LatexDetails::default(),
+            search: SearchDetails::default(),
+            corpus: CorpusDetails::default(),
+            redirects: BTreeMap::new(),
+            deploy: DeployDetails::default(),
+            budgets: BudgetDetails::default(),
+            lint: LintDetails::default(),
+            frontmatter: BTreeMap::new(),
+            images: ImageDetails::default(),
+            fragments: FragmentDetails::default(),
+            markdown: MarkdownDetails::default(),
         }
     }
 }
 
+/// The folder, relative to the project root, that custom syntax definitions
+/// for code highlighting are loaded from.
+pub(crate) const SYNTAX_FOLDER: &str = "syntaxes";
+/// The name of a template helper and its rhai script.
+pub type ScriptHelper = (String, String);
+
+/// The folder holding the project's template helper scripts.
+pub(crate) const HELPERS_FOLDER: &str = "_internal/helpers";
+
 /// The project.
 /// This is the main structure that contains all the information about the project.
 #[derive(Clone, Debug)]
@@ -81,6 +844,23 @@ pub struct Project {
     pub details: ProjectDetails,
     pub root_folder: Folder,
     pub path: CodexPath,
+    /// Handlers notified as the project is scanned, rendered, and built
+    pub events: Events,
+    /// Syntax definitions loaded from the project's syntaxes folder, built
+    /// the first time a code block needs them
+    syntaxes: Arc<OnceLock<std::result::Result<Option<SyntaxSet>, String>>>,
+    /// Template helpers written as scripts in the project's helpers folder,
+    /// read the first time a template is rendered
+    helpers: Arc<OnceLock<std::result::Result<Vec<ScriptHelper>, String>>>,
+    /// The navigation menu of each language, rendered once and shared by
+    /// every page
+    navigation: Arc<Mutex<HashMap<String, String>>>,
+    /// The links between documents, collected the first time a page lists
+    /// its backlinks
+    links: Arc<OnceLock<std::result::Result<LinkGraph, String>>>,
+    /// The git history of the project's files, read the first time a page
+    /// needs its last modified date
+    history: Arc<OnceLock<Option<GitHistory>>>,
 }
 
 impl Default for Project {
@@ -92,43 +872,281 @@ impl Default for Project {
                 "Unnamed".to_string(),
                 CodexPath::new(PathBuf::from("."), PathBuf::from(".")),
             ),
+            events: Events::default(),
+            syntaxes: Arc::default(),
+            helpers: Arc::default(),
+            navigation: Arc::default(),
+            links: Arc::default(),
+            history: Arc::default(),
         }
     }
 }
 
 impl Project {
     pub fn load<P>(path: P, ignore_base_url: bool) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        Self::load_with_events(path, ignore_base_url, Events::default())
+    }
+
+    /// Load a project, notifying `events` of each document discovered. The
+    /// handlers are kept for later reloads and renders.
+    pub fn load_with_events<P>(path: P, ignore_base_url: bool, events: Events) -> Result<Self>
     where
         P: Into<PathBuf>,
     {
         let path = path.into();
-        let mut project = if path.join("codex.yml").exists() {
-            Project {
-                details: serde_yaml::from_reader(std::fs::File::open(path.join("codex.yml"))?)?,
-                ..Default::default()
-            }
-        } else {
-            Project::default()
+        let mut project = Project {
+            details: ProjectDetails::load(&path, ignore_base_url)?,
+            ..Default::default()
         };
-        if ignore_base_url {
-            project.details.base_url = "/".to_string();
-        }
-        if !project.details.base_url.ends_with('/') {
-            project.details.base_url.push('/');
-        }
         let proj_path = CodexPath::for_project(path.clone());
         project.path = proj_path.clone();
         project.root_folder.path = project.path.clone();
-        project.root_folder = scan_folder(&proj_path)?;
+        project.events = events;
+        project.root_folder = scan_folder(&proj_path, &project.details.markdown, &project.events)?;
+        project.root_folder.inherit_formats(None);
+        project.root_folder.assign_languages(&project.details, None);
+        project.root_folder.apply_url_style(&project.details);
+        crate::frontmatter::validate(&project)?;
         Ok(project)
     }
 
+    /// Read `codex.yml` again and reload the project with the new settings.
+    pub fn reload_details(&mut self, ignore_base_url: bool) -> Result<()> {
+        self.details = ProjectDetails::load(&self.path.disk_path(), ignore_base_url)?;
+        self.reload()
+    }
+
     pub fn reload(&mut self) -> Result<()> {
         let path = self.path.clone();
-        self.root_folder = scan_folder(&path)?;
+        self.root_folder = scan_folder(&path, &self.details.markdown, &self.events)?;
+        self.root_folder.inherit_formats(None);
+        self.root_folder.assign_languages(&self.details, None);
+        self.root_folder.apply_url_style(&self.details);
+        crate::frontmatter::validate(self)?;
+        self.syntaxes = Arc::default();
+        self.helpers = Arc::default();
+        self.navigation = Arc::default();
+        self.links = Arc::default();
+        self.history = Arc::default();
         Ok(())
     }
 
+    /// The syntax definitions in the project's `syntaxes` folder, or `None`
+    /// if the project doesn't have any. They are loaded once and kept until
+    /// the project is reloaded.
+    pub(crate) fn syntaxes(&self) -> Result<Option<&SyntaxSet>> {
+        self.syntaxes
+            .get_or_init(|| {
+                let folder = self.path.disk_path().join(SYNTAX_FOLDER);
+                if !folder.is_dir() {
+                    return Ok(None);
+                }
+                let mut builder = SyntaxSetBuilder::new();
+                builder
+                    .add_from_folder(&folder, true)
+                    .map_err(|e| format!("Couldn't load syntax definitions: {}", e))?;
+                Ok(Some(builder.build()))
+            })
+            .as_ref()
+            .map(|s| s.as_ref())
+            .map_err(|e| crate::Error::new(e.clone()))
+    }
+
+    /// The helpers defined by the `.rhai` scripts in the project's
+    /// `_internal/helpers` folder, named after their files. They are read
+    /// once and kept until the project is reloaded.
+    pub fn helpers(&self) -> Result<&[ScriptHelper]> {
+        self.helpers
+            .get_or_init(|| {
+                let folder = self.path.disk_path().join(HELPERS_FOLDER);
+                if !folder.is_dir() {
+                    return Ok(vec![]);
+                }
+                let mut helpers = vec![];
+                let mut entries = std::fs::read_dir(&folder)
+                    .map_err(|e| e.to_string())?
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+                    .collect::<Vec<_>>();
+                entries.sort();
+                for path in entries {
+                    let name = path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    if crate::template::BUILTIN_HELPERS.contains(&name.as_str()) {
+                        return Err(format!(
+                            "The helper {} can't be replaced, rename {}",
+                            name,
+                            path.display()
+                        ));
+                    }
+                    let script = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                    helpers.push((name, script));
+                }
+                Ok(helpers)
+            })
+            .as_ref()
+            .map(|h| h.as_slice())
+            .map_err(|e| crate::Error::new(e.clone()))
+    }
+
+    /// Render a template with the project's scripted helpers, naming it in
+    /// errors with `name`. The `markdown` helper renders links relative to
+    /// the page the template is rendered for, or the project root.
+    pub fn render_template<T>(&self, name: &str, data: T, template: &str) -> Result<String>
+    where
+        T: serde::Serialize,
+    {
+        let markdown = |text: &str, data: &serde_json::Value| -> Result<String> {
+            let root;
+            let document = match data["current_url"]
+                .as_str()
+                .and_then(|url| self.root_folder.iter_all_documents().find(|d| d.url == url))
+            {
+                Some(document) => document,
+                None => {
+                    root = Document {
+                        file_path: self.path.new_path("index.md"),
+                        frontmatter: Default::default(),
+                        url: "/".to_string(),
+                        language: self.details.default_language.clone(),
+                        summary: None,
+                    };
+                    &root
+                }
+            };
+            let renderer = crate::HtmlRenderer::new(crate::RenderContext::new(self, document));
+            renderer.render_node(&renderer.parse_ast(text)?)
+        };
+        crate::template::render_template_with_options(
+            data,
+            template,
+            &crate::template::TemplateOptions {
+                name,
+                helpers: self.helpers()?,
+                markdown: Some(&markdown),
+                strict: self.details.strict_templates,
+            },
+        )
+    }
+
+    /// Render one of the project's template files, such as
+    /// `_internal/templates/article.html`.
+    pub fn render_template_file<T>(&self, path: &str, data: T) -> Result<String>
+    where
+        T: serde::Serialize,
+    {
+        let template = String::from_utf8(self.path.new_path(path).read()?.to_vec())?;
+        let html = self.render_template(path, data, &template)?;
+        if self.details.trace_templates && path.ends_with(".html") {
+            return Ok(crate::debug::trace_template(self, path, &html));
+        }
+        Ok(html)
+    }
+
+    /// The navigation menu for a language, rendered with `render` the first
+    /// time a page in the language needs it and kept until the project is
+    /// reloaded.
+    pub(crate) fn navigation(
+        &self,
+        language: &str,
+        render: impl FnOnce() -> Result<String>,
+    ) -> Result<String> {
+        let mut menus = self.navigation.lock().unwrap();
+        if let Some(menu) = menus.get(language) {
+            return Ok(menu.clone());
+        }
+        let menu = render()?;
+        menus.insert(language.to_string(), menu.clone());
+        Ok(menu)
+    }
+
+    /// The last commit that changed a document, or `None` when the project
+    /// isn't in a git repository or the document hasn't been committed.
+    pub fn revision(&self, document: &Document) -> Option<&Revision> {
+        self.git_history()?
+            .files
+            .get(&document.file_path.relative_path)
+    }
+
+    /// The authors of the commits changing a document, most commits first,
+    /// or `None` unless contributors are enabled.
+    pub fn contributors(&self, document: &Document) -> Option<Vec<Contributor>> {
+        let details = &self.details.contributors;
+        if !details.enabled {
+            return None;
+        }
+        let Some(contributors) = self
+            .git_history()
+            .and_then(|h| h.contributors.get(&document.file_path.relative_path))
+        else {
+            return Some(vec![]);
+        };
+        Some(
+            contributors
+                .iter()
+                .map(|c| {
+                    let email = c.email.clone().unwrap_or_default();
+                    let hash = crate::manifest::hash_bytes(email.trim().to_lowercase().as_bytes());
+                    Contributor {
+                        email: details.emails.then_some(email),
+                        avatar: details
+                            .avatar_url
+                            .as_ref()
+                            .map(|url| url.replace("{hash}", &hash)),
+                        ..c.clone()
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn git_history(&self) -> Option<&GitHistory> {
+        self.history
+            .get_or_init(|| GitHistory::load(&self.path.disk_path()))
+            .as_ref()
+    }
+
+    /// The HTML injected at the end of the head and the body of every page:
+    /// each inline snippet followed by the content of its file.
+    pub fn injected_html(&self) -> Result<(String, String)> {
+        let read = |html: &Option<String>, file: &Option<String>| -> Result<String> {
+            let mut out = html.clone().unwrap_or_default();
+            if let Some(file) = file {
+                out.push_str(&String::from_utf8(
+                    self.path.new_path(file).read()?.to_vec(),
+                )?);
+            }
+            Ok(out)
+        };
+        let inject = &self.details.inject;
+        Ok((
+            read(&inject.head, &inject.head_file)?,
+            read(&inject.body, &inject.body_file)?,
+        ))
+    }
+
+    /// The documents linking to a document, sorted by title. Links are
+    /// collected once and kept until the project is reloaded.
+    pub fn backlinks(&self, document: &Document) -> Result<Vec<&Document>> {
+        let links = self
+            .links
+            .get_or_init(|| LinkGraph::build(self).map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| crate::Error::new(e.clone()))?;
+        let mut documents = links
+            .backlinks(&document.url)
+            .filter_map(|url| self.root_folder.iter_all_documents().find(|d| d.url == url))
+            .collect::<Vec<_>>();
+        documents.sort_by(|a, b| a.frontmatter.title.cmp(&b.frontmatter.title));
+        Ok(documents)
+    }
+
     pub fn get_document<P>(&self, path: P) -> Option<&Document>
     where
         P: Into<PathBuf> + std::convert::AsRef<std::path::Path>,
@@ -140,17 +1158,81 @@ impl Project {
     }
 
     pub fn get_document_for_url(&self, url: &str) -> Option<&Document> {
+        let url = format!(
+            "/{}",
+            url.strip_prefix(&self.details.base_url).unwrap_or_default()
+        );
+        self.root_folder.iter_all_documents().find(|d| d.url == url)
+    }
+
+    /// Find the document a link written without the url style's extension
+    /// points to, such as `/guide/install` for `/guide/install.html`.
+    pub fn get_document_for_link(&self, url: &str) -> Option<&Document> {
+        let url = format!("/{}", url.trim_matches('/'));
+        self.root_folder
+            .iter_all_documents()
+            .find(|d| d.url == url || d.url == self.details.page_url(&url, d.is_index()))
+    }
+
+    /// Find the document with the given id in its front matter.
+    pub fn get_document_for_id(&self, id: &str) -> Option<&Document> {
+        self.root_folder
+            .iter_all_documents()
+            .find(|d| d.frontmatter.id.as_deref() == Some(id))
+    }
+
+    /// Find the file in a document's assets folder served at the given url.
+    pub fn get_attachment_for_url(&self, url: &str) -> Option<CodexPath> {
         let url = format!(
             "/{}",
             url.strip_prefix(&self.details.base_url).unwrap_or_default()
         );
         self.root_folder
             .iter_all_documents()
-            .find(|d| d.file_path.document_url() == url)
+            .filter(|d| url.starts_with(&format!("{}/", d.assets_path().root_url())))
+            .find_map(|d| {
+                d.attachments()
+                    .ok()?
+                    .into_iter()
+                    .find(|f| f.root_url() == url)
+            })
+    }
+
+    /// The url a request should be redirected to, if the url has a
+    /// configured redirect.
+    pub fn get_redirect_for_url(&self, url: &str) -> Option<String> {
+        let url = format!(
+            "/{}",
+            url.strip_prefix(&self.details.base_url)
+                .unwrap_or_default()
+                .trim_end_matches('/')
+        );
+        self.details.redirects.get(&url).map(|to| {
+            let to = self
+                .get_document_for_link(to)
+                .map(|d| d.url.as_str())
+                .unwrap_or(to);
+            format!("{}{}", self.details.base_url, to.trim_start_matches('/'))
+        })
+    }
+
+    /// Find the folder whose printable view is served at the given url.
+    pub fn get_folder_for_print_url(&self, url: &str) -> Option<&Folder> {
+        let url = format!(
+            "/{}",
+            url.strip_prefix(&self.details.base_url).unwrap_or_default()
+        );
+        self.root_folder
+            .iter_all_folders()
+            .find(|f| f.path.print_url() == url)
     }
 }
 
-pub fn scan_folder(root_path: &CodexPath) -> Result<Folder> {
+pub fn scan_folder(
+    root_path: &CodexPath,
+    markdown: &MarkdownDetails,
+    events: &Events,
+) -> Result<Folder> {
     let folder_name = root_path.basename().unwrap_or("Unnamed".to_string());
     let mut folder = Folder::new(folder_name, root_path.clone());
     folder.details = std::fs::File::open(root_path.disk_path().join("group.yml"))
@@ -166,21 +1248,34 @@ pub fn scan_folder(root_path: &CodexPath) -> Result<Folder> {
         if path.file_name().and_then(|s| s.to_str()) == Some("_internal") {
             continue;
         }
+        if path.file_name().and_then(|s| s.to_str()) == Some(SYNTAX_FOLDER) {
+            continue;
+        }
         if path.file_name().and_then(|s| s.to_str()) == Some("dist") {
             continue;
         }
         if path.file_name().and_then(|s| s.to_str()) == Some(".git") {
             continue;
         }
+        // Document assets folders hold files, not documents
+        if path.is_dir() && path.extension().and_then(|s| s.to_str()) == Some(ASSETS_EXTENSION) {
+            continue;
+        }
 
         if path.is_dir() {
             folder.folders.push(scan_folder(
                 &root_path.new_path(path.to_path_buf().strip_prefix(&root_path.project_root)?),
+                markdown,
+                events,
             )?);
         } else if path.extension().and_then(|s| s.to_str()) == Some("md") {
             let file_path =
                 root_path.new_path(path.to_path_buf().strip_prefix(&root_path.project_root)?);
-            let document = Document::load(file_path)?;
+            let document = Document::load(file_path, markdown)?;
+            events.emit(Event::DocumentDiscovered {
+                path: document.file_path.relative_path.clone(),
+                url: document.url.clone(),
+            });
             folder.documents.push(document);
         }
     }
@@ -194,7 +1289,6 @@ pub fn scan_folder(root_path: &CodexPath) -> Result<Folder> {
 }
 
 #[cfg(test)]
-
 pub mod tests {
     use crate::{HtmlRenderer, RenderContext, Renderer};
 
@@ -252,6 +1346,204 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_file_urls() {
+        let root = crate::refactor::tests::writable_fixture("file_urls");
+        std::fs::write(
+            root.join("other/guide.md"),
+            "---\ntitle: Guide\n---\n\nSee [alerts](/other/alert#usage) and [pages](/pages/).\n",
+        )
+        .unwrap();
+        let mut project = Project::load(&root, false).unwrap();
+        project.details.url_style = UrlStyle::File;
+        project.reload().unwrap();
+
+        let doc = project.get_document_for_url("/other/guide.html").unwrap();
+        assert_eq!(
+            project.get_document_for_link("/pages").unwrap().url,
+            "/pages/index.html"
+        );
+        let renderer = HtmlRenderer::new(RenderContext::new(&project, doc));
+        assert_eq!(
+            renderer.render_body().unwrap(),
+            r#"<p>See <a href="/other/alert.html#usage" alt="">alerts</a> and <a href="/pages/index.html" alt="">pages</a>.</p>"#,
+        );
+        assert_eq!(
+            UrlStyle::File.output_file("/pages/index.html"),
+            PathBuf::from("pages/index.html")
+        );
+        assert_eq!(
+            UrlStyle::Pretty.output_file("/pages"),
+            PathBuf::from("pages/index.html")
+        );
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        let root = crate::refactor::tests::writable_fixture("trailing_slash");
+        std::fs::write(
+            root.join("other/guide.md"),
+            "---\ntitle: Guide\n---\n\nSee [alerts](/other/alert#usage) and [pages](/pages/).\n",
+        )
+        .unwrap();
+        let mut project = Project::load(&root, false).unwrap();
+        project
+            .details
+            .redirects
+            .insert("/old".to_string(), "/other/alert".to_string());
+        let render = |project: &Project, url: &str| {
+            let doc = project.get_document_for_url(url).unwrap();
+            HtmlRenderer::new(RenderContext::new(project, doc))
+                .render_body()
+                .unwrap()
+        };
+
+        project.details.trailing_slash = Some(TrailingSlash::Always);
+        project.reload().unwrap();
+        assert_eq!(
+            render(&project, "/other/guide/"),
+            r#"<p>See <a href="/other/alert/#usage" alt="">alerts</a> and <a href="/pages/" alt="">pages</a>.</p>"#,
+        );
+        assert_eq!(
+            project.get_redirect_for_url("/old").unwrap(),
+            "/other/alert/"
+        );
+
+        project.details.trailing_slash = Some(TrailingSlash::Never);
+        project.reload().unwrap();
+        assert_eq!(
+            render(&project, "/other/guide"),
+            r#"<p>See <a href="/other/alert#usage" alt="">alerts</a> and <a href="/pages" alt="">pages</a>.</p>"#,
+        );
+    }
+
+    #[test]
+    fn test_canonical_url() {
+        let mut project = project_fixture();
+        let doc = project
+            .get_document_for_url("/other/alert")
+            .unwrap()
+            .clone();
+        let page = |project: &Project| {
+            HtmlRenderer::new(RenderContext::new(project, &doc))
+                .page_data()
+                .unwrap()
+                .canonical_url
+        };
+        assert_eq!(page(&project), None);
+
+        project.details.site_url = Some("https://docs.example.com/".into());
+        project.details.base_url = "/v2/".into();
+        assert_eq!(
+            page(&project).unwrap(),
+            "https://docs.example.com/v2/other/alert"
+        );
+        assert_eq!(
+            project
+                .details
+                .absolute_url("https://cdn.example.com/card.png")
+                .unwrap(),
+            "https://cdn.example.com/card.png"
+        );
+    }
+
+    #[test]
+    fn test_injected_html() {
+        let root = crate::refactor::tests::writable_fixture("inject");
+        std::fs::write(
+            root.join("analytics.html"),
+            "<script src=\"/a.js\"></script>",
+        )
+        .unwrap();
+        let mut project = Project::load(&root, false).unwrap();
+        project.details.inject.head = Some(r#"<meta name="robots" content="noindex">"#.into());
+        project.details.inject.body_file = Some("analytics.html".into());
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let page = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        let head_end = page.find("</head>").unwrap();
+        let meta = page
+            .find(r#"<meta name="robots" content="noindex">"#)
+            .unwrap();
+        assert!(meta < head_end);
+        let script = page.find(r#"<script src="/a.js"></script>"#).unwrap();
+        assert!(script > head_end && script < page.find("</body>").unwrap());
+    }
+
+    #[test]
+    fn test_script_helpers() {
+        let project = project_fixture();
+        assert_eq!(
+            project
+                .render_template("shout", serde_json::json!({}), r#"{{shout "hello"}}"#)
+                .unwrap(),
+            "HELLO!"
+        );
+
+        let root = crate::refactor::tests::writable_fixture("helpers");
+        std::fs::write(root.join(HELPERS_FOLDER).join("mul.rhai"), "0").unwrap();
+        let project = Project::load(&root, false).unwrap();
+        assert!(project.helpers().is_err());
+    }
+
+    #[test]
+    fn test_markdown_helper() {
+        let project = project_fixture();
+        assert_eq!(
+            project
+                .render_template(
+                    "markdown",
+                    serde_json::json!({ "text": "Some *markdown*" }),
+                    "{{markdown text}}"
+                )
+                .unwrap(),
+            r#"<p>Some <span class="fst-italic">markdown</span></p>"#
+        );
+    }
+
+    #[test]
+    fn test_edit_url() {
+        let mut details = ProjectDetails::default();
+        let path = Path::new("other").join("alert.md");
+        assert_eq!(details.edit_url(&path), None);
+
+        details.repo_url = Some("https://github.com/me/project.git".into());
+        details.edit.path = "docs/".into();
+        assert_eq!(
+            details.edit_url(&path).unwrap(),
+            "https://github.com/me/project/edit/main/docs/other/alert.md"
+        );
+        details.repo_url = Some("https://gitlab.com/me/project/".into());
+        details.edit.path = String::new();
+        assert_eq!(
+            details.edit_url(&path).unwrap(),
+            "https://gitlab.com/me/project/-/edit/main/other/alert.md"
+        );
+        details.edit.url = Some("https://git.example.com/src/{branch}/{path}?edit".into());
+        details.edit.branch = "dev".into();
+        assert_eq!(
+            details.edit_url(&path).unwrap(),
+            "https://git.example.com/src/dev/other/alert.md?edit"
+        );
+    }
+
+    #[test]
+    fn test_reload_details() {
+        let root = crate::refactor::tests::writable_fixture("reload_details");
+        let mut project = Project::load(&root, true).unwrap();
+        let previous = project.details.clone();
+        let config = std::fs::read_to_string(root.join("codex.yml")).unwrap();
+        std::fs::write(
+            root.join("codex.yml"),
+            config.replace("name: Testing Project", "name: Renamed"),
+        )
+        .unwrap();
+        project.reload_details(true).unwrap();
+        assert_eq!(project.details.name, "Renamed");
+        // The base url is still ignored, as it was when the project loaded
+        assert_eq!(project.details.base_url, "/");
+        assert_eq!(previous.changed_settings(&project.details), vec!["name"]);
+    }
+
     #[test]
     fn project_load_path() {
         project_fixture();
@@ -295,4 +1587,59 @@ pub mod tests {
 
         assert_eq!(renderer.render_body().unwrap().trim(), "hello Alice");
     }
+
+    #[test]
+    fn folder_formats() {
+        let project = project_fixture();
+        let runbook = project.get_document_for_url("/internal/runbook").unwrap();
+        assert!(runbook.has_format(OutputTarget::Html));
+        assert!(!runbook.has_format(OutputTarget::Pdf));
+        // Subfolders inherit the formats, front matter overrides them
+        let nested = project
+            .get_document_for_url("/internal/nested/escalation")
+            .unwrap();
+        assert!(!nested.has_format(OutputTarget::Pdf));
+        let printed = project.get_document_for_url("/internal/printed").unwrap();
+        assert!(printed.has_format(OutputTarget::Pdf));
+
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        assert!(doc.has_format(OutputTarget::Html) && doc.has_format(OutputTarget::Pdf));
+    }
+
+    #[test]
+    fn attachment_for_url() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/attachments").unwrap();
+        assert_eq!(doc.attachments().unwrap().len(), 2);
+        // The assets folder isn't scanned for documents
+        assert!(project
+            .root_folder
+            .iter_all_folders()
+            .all(|f| f.name != "attachments.assets"));
+
+        let file = project
+            .get_attachment_for_url("/other/attachments.assets/nested/notes.txt")
+            .unwrap();
+        assert_eq!(
+            file.disk_path(),
+            PathBuf::from("test/fixture/other/attachments.assets/nested/notes.txt")
+        );
+        assert!(project
+            .get_attachment_for_url("/other/attachments.assets/missing.txt")
+            .is_none());
+    }
+
+    #[test]
+    fn folder_for_print_url() {
+        let project = project_fixture();
+        let folder = project
+            .get_folder_for_print_url("/other/print.html")
+            .unwrap();
+        assert_eq!(folder.name, "other");
+        let folder = project.get_folder_for_print_url("/print.html").unwrap();
+        assert_eq!(folder.path, project.root_folder.path);
+        assert!(project
+            .get_folder_for_print_url("/missing/print.html")
+            .is_none());
+    }
 }
@@ -0,0 +1,619 @@
+use crate::{Error, Result};
+use serde::Serialize;
+
+/// The documented contents of a `.proto` file.
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoFile {
+    pub package: Option<String>,
+    pub messages: Vec<ProtoMessage>,
+    pub enums: Vec<ProtoEnum>,
+    pub services: Vec<ProtoService>,
+}
+
+impl ProtoFile {
+    /// The heading text documenting a message or enum type defined in this
+    /// file. Types can be referenced by their full or nested name.
+    pub fn heading_for(&self, name: &str) -> Option<String> {
+        let name = match &self.package {
+            Some(package) => name.strip_prefix(&format!("{}.", package)).unwrap_or(name),
+            None => name,
+        };
+        let matches = |defined: &str| defined == name || defined.ends_with(&format!(".{}", name));
+        self.messages
+            .iter()
+            .find(|m| matches(&m.name))
+            .map(|m| format!("Message {}", m.name))
+            .or_else(|| {
+                self.enums
+                    .iter()
+                    .find(|e| matches(&e.name))
+                    .map(|e| format!("Enum {}", e.name))
+            })
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoMessage {
+    /// The message name. Nested messages use dot notation.
+    pub name: String,
+    pub description: String,
+    pub fields: Vec<ProtoField>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub number: String,
+    /// The field label, `repeated`, `optional`, or `required`, if any
+    pub label: Option<String>,
+    /// The name of the oneof group containing the field
+    pub oneof: Option<String>,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoEnum {
+    pub name: String,
+    pub description: String,
+    pub values: Vec<ProtoEnumValue>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoEnumValue {
+    pub name: String,
+    pub number: String,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoService {
+    pub name: String,
+    pub description: String,
+    pub methods: Vec<ProtoMethod>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ProtoMethod {
+    pub name: String,
+    pub input: String,
+    pub output: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub description: String,
+}
+
+pub(crate) fn parse_proto(source: &[u8]) -> Result<ProtoFile> {
+    let source = String::from_utf8(source.to_vec())?;
+    let mut parser = Parser {
+        tokens: tokenize(&source)?,
+        pos: 0,
+    };
+    parser.parse_file()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Symbol(char),
+    Comment(String),
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                chars.next();
+                let mut comment = String::new();
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                    comment.push(c);
+                }
+                tokens.push((Token::Comment(comment.trim().to_string()), line));
+                line += 1;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let start = line;
+                let mut comment = String::new();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        comment.pop();
+                        break;
+                    }
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    comment.push(c);
+                    prev = c;
+                }
+                let comment = comment
+                    .lines()
+                    .map(|l| l.trim().trim_start_matches('*').trim())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                tokens.push((Token::Comment(comment.trim().to_string()), start));
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                while let Some(s) = chars.next() {
+                    if s == c {
+                        break;
+                    }
+                    if s == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            value.push(escaped);
+                        }
+                        continue;
+                    }
+                    value.push(s);
+                }
+                tokens.push((Token::Str(value), line));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '+' => {
+                let mut ident = c.to_string();
+                while let Some(&n) = chars.peek() {
+                    if n.is_alphanumeric() || n == '_' || n == '.' {
+                        ident.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push((Token::Ident(ident), line));
+            }
+            '{' | '}' | '(' | ')' | '[' | ']' | '<' | '>' | ';' | '=' | ',' | ':' => {
+                tokens.push((Token::Symbol(c), line))
+            }
+            c => {
+                return Err(Error::new(format!(
+                    "Invalid proto file: unexpected character '{}' on line {}",
+                    c, line
+                )))
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Collect the comments directly before the next declaration.
+    fn take_comments(&mut self) -> String {
+        let mut lines = vec![];
+        while let Some((Token::Comment(c), _)) = self.tokens.get(self.pos) {
+            lines.push(c.clone());
+            self.pos += 1;
+        }
+        lines.join("\n")
+    }
+
+    /// Add a comment on the same line as the end of the declaration just
+    /// read, as in `string id = 1; // The id`, to its description.
+    fn take_trailing_comment(&mut self, description: String) -> String {
+        let Some(line) = self
+            .pos
+            .checked_sub(1)
+            .and_then(|p| self.tokens.get(p))
+            .map(|(_, l)| *l)
+        else {
+            return description;
+        };
+        match self.tokens.get(self.pos) {
+            Some((Token::Comment(c), l)) if *l == line => {
+                let comment = c.clone();
+                self.pos += 1;
+                if description.is_empty() {
+                    comment
+                } else {
+                    format!("{}\n{}", description, comment)
+                }
+            }
+            _ => description,
+        }
+    }
+
+    fn peek(&mut self) -> Option<Token> {
+        while let Some((Token::Comment(_), _)) = self.tokens.get(self.pos) {
+            self.pos += 1;
+        }
+        self.tokens.get(self.pos).map(|(t, _)| t.clone())
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .peek()
+            .ok_or_else(|| Error::new("Invalid proto file: unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn error(&self, expected: &str) -> Error {
+        let line = self
+            .tokens
+            .get(self.pos.saturating_sub(1))
+            .map(|(_, l)| *l)
+            .unwrap_or_default();
+        Error::new(format!(
+            "Invalid proto file: expected {} on line {}",
+            expected, line
+        ))
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(i) => Ok(i),
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<()> {
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            _ => Err(self.error(&format!("'{}'", symbol))),
+        }
+    }
+
+    /// Skip a statement up to and including its terminating semicolon.
+    fn skip_statement(&mut self) -> Result<()> {
+        loop {
+            match self.next()? {
+                Token::Symbol(';') => return Ok(()),
+                Token::Symbol('{') => self.skip_block()?,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skip the rest of a block whose opening brace was already consumed.
+    fn skip_block(&mut self) -> Result<()> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip field options such as `[deprecated = true]`.
+    fn skip_options(&mut self) -> Result<()> {
+        if self.peek() == Some(Token::Symbol('[')) {
+            while self.next()? != Token::Symbol(']') {}
+        }
+        Ok(())
+    }
+
+    fn parse_file(&mut self) -> Result<ProtoFile> {
+        let mut file = ProtoFile::default();
+        loop {
+            let description = self.take_comments();
+            let Some(token) = self.peek() else {
+                break;
+            };
+            match token {
+                Token::Ident(i) if i == "package" => {
+                    self.next()?;
+                    file.package = Some(self.ident()?);
+                    self.expect(';')?;
+                }
+                Token::Ident(i) if i == "message" => {
+                    self.next()?;
+                    self.parse_message("", description, &mut file)?;
+                }
+                Token::Ident(i) if i == "enum" => {
+                    self.next()?;
+                    self.parse_enum("", description, &mut file)?;
+                }
+                Token::Ident(i) if i == "service" => {
+                    self.next()?;
+                    self.parse_service(description, &mut file)?;
+                }
+                Token::Symbol(';') => {
+                    self.next()?;
+                }
+                _ => self.skip_statement()?,
+            }
+        }
+        Ok(file)
+    }
+
+    fn parse_message(
+        &mut self,
+        prefix: &str,
+        description: String,
+        file: &mut ProtoFile,
+    ) -> Result<()> {
+        let name = format!("{}{}", prefix, self.ident()?);
+        self.expect('{')?;
+        let mut message = ProtoMessage {
+            name: name.clone(),
+            description,
+            fields: vec![],
+        };
+        self.parse_message_body(&name, None, &mut message, file)?;
+        file.messages.push(message);
+        Ok(())
+    }
+
+    fn parse_message_body(
+        &mut self,
+        name: &str,
+        oneof: Option<String>,
+        message: &mut ProtoMessage,
+        file: &mut ProtoFile,
+    ) -> Result<()> {
+        loop {
+            let description = self.take_comments();
+            match self.next()? {
+                Token::Symbol('}') => return Ok(()),
+                Token::Symbol(';') => {}
+                Token::Ident(i) if i == "message" => {
+                    self.parse_message(&format!("{}.", name), description, file)?
+                }
+                Token::Ident(i) if i == "enum" => {
+                    self.parse_enum(&format!("{}.", name), description, file)?
+                }
+                Token::Ident(i) if i == "oneof" => {
+                    let group = self.ident()?;
+                    self.expect('{')?;
+                    self.parse_message_body(name, Some(group), message, file)?;
+                }
+                Token::Ident(i)
+                    if ["option", "reserved", "extensions", "extend"].contains(&i.as_str()) =>
+                {
+                    self.skip_statement()?
+                }
+                Token::Ident(i) => {
+                    let (label, data_type) = match i.as_str() {
+                        "repeated" | "optional" | "required" => (Some(i), self.field_type()?),
+                        _ => (None, self.map_type(i)?),
+                    };
+                    let field_name = self.ident()?;
+                    self.expect('=')?;
+                    let number = self.ident()?;
+                    self.skip_options()?;
+                    self.expect(';')?;
+                    let description = self.take_trailing_comment(description);
+                    message.fields.push(ProtoField {
+                        name: field_name,
+                        data_type,
+                        number,
+                        label,
+                        oneof: oneof.clone(),
+                        description,
+                    });
+                }
+                _ => return Err(self.error("field")),
+            }
+        }
+    }
+
+    fn field_type(&mut self) -> Result<String> {
+        let i = self.ident()?;
+        self.map_type(i)
+    }
+
+    /// Read the remainder of a `map<K, V>` type. Other types are returned
+    /// unchanged.
+    fn map_type(&mut self, ident: String) -> Result<String> {
+        if ident != "map" || self.peek() != Some(Token::Symbol('<')) {
+            return Ok(ident);
+        }
+        self.expect('<')?;
+        let key = self.ident()?;
+        self.expect(',')?;
+        let value = self.ident()?;
+        self.expect('>')?;
+        Ok(format!("map<{}, {}>", key, value))
+    }
+
+    fn parse_enum(
+        &mut self,
+        prefix: &str,
+        description: String,
+        file: &mut ProtoFile,
+    ) -> Result<()> {
+        let name = format!("{}{}", prefix, self.ident()?);
+        self.expect('{')?;
+        let mut proto_enum = ProtoEnum {
+            name,
+            description,
+            values: vec![],
+        };
+        loop {
+            let description = self.take_comments();
+            match self.next()? {
+                Token::Symbol('}') => break,
+                Token::Symbol(';') => {}
+                Token::Ident(i) if i == "option" || i == "reserved" => self.skip_statement()?,
+                Token::Ident(value_name) => {
+                    self.expect('=')?;
+                    let number = self.ident()?;
+                    self.skip_options()?;
+                    self.expect(';')?;
+                    let description = self.take_trailing_comment(description);
+                    proto_enum.values.push(ProtoEnumValue {
+                        name: value_name,
+                        number,
+                        description,
+                    });
+                }
+                _ => return Err(self.error("enum value")),
+            }
+        }
+        file.enums.push(proto_enum);
+        Ok(())
+    }
+
+    fn parse_service(&mut self, description: String, file: &mut ProtoFile) -> Result<()> {
+        let mut service = ProtoService {
+            name: self.ident()?,
+            description,
+            methods: vec![],
+        };
+        self.expect('{')?;
+        loop {
+            let description = self.take_comments();
+            match self.next()? {
+                Token::Symbol('}') => break,
+                Token::Symbol(';') => {}
+                Token::Ident(i) if i == "rpc" => {
+                    let name = self.ident()?;
+                    let (client_streaming, input) = self.method_type()?;
+                    if self.ident()? != "returns" {
+                        return Err(self.error("'returns'"));
+                    }
+                    let (server_streaming, output) = self.method_type()?;
+                    match self.next()? {
+                        Token::Symbol(';') => {}
+                        Token::Symbol('{') => self.skip_block()?,
+                        _ => return Err(self.error("';'")),
+                    }
+                    let description = self.take_trailing_comment(description);
+                    service.methods.push(ProtoMethod {
+                        name,
+                        input,
+                        output,
+                        client_streaming,
+                        server_streaming,
+                        description,
+                    });
+                }
+                Token::Ident(_) => self.skip_statement()?,
+                _ => return Err(self.error("rpc")),
+            }
+        }
+        file.services.push(service);
+        Ok(())
+    }
+
+    /// Read a parenthesized method type, returning whether it is streamed.
+    fn method_type(&mut self) -> Result<(bool, String)> {
+        self.expect('(')?;
+        let mut ident = self.ident()?;
+        let stream = ident == "stream";
+        if stream {
+            ident = self.ident()?;
+        }
+        self.expect(')')?;
+        Ok((stream, ident))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_proto;
+
+    const PROTO: &str = r#"
+syntax = "proto3";
+
+package users.v1;
+
+import "google/protobuf/timestamp.proto";
+
+// Manages user accounts
+service UserService {
+  // Fetch a single user
+  rpc GetUser(GetUserRequest) returns (User);
+  rpc WatchUsers(WatchRequest) returns (stream User) {
+    option deprecated = true;
+  }
+}
+
+/* A user account */
+message User {
+  // The unique id
+  string id = 1;
+  repeated string emails = 2 [packed = true]; // Verified addresses
+  // Free form labels
+  map<string, string> labels = 3; // Set by admins
+  Status status = 4;
+  oneof contact {
+    string phone = 5;
+  }
+
+  message Address {
+    string city = 1;
+  }
+
+  enum Status {
+    // Not yet verified
+    PENDING = 0;
+    ACTIVE = 1; // Verified
+  }
+}
+"#;
+
+    #[test]
+    fn test_parse_services() {
+        let file = parse_proto(PROTO.as_bytes()).unwrap();
+        assert_eq!(file.package.as_deref(), Some("users.v1"));
+        let service = &file.services[0];
+        assert_eq!(service.name, "UserService");
+        assert_eq!(service.description, "Manages user accounts");
+        assert_eq!(service.methods.len(), 2);
+        assert_eq!(service.methods[0].description, "Fetch a single user");
+        assert_eq!(service.methods[0].input, "GetUserRequest");
+        assert!(service.methods[1].server_streaming);
+        assert!(!service.methods[1].client_streaming);
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let file = parse_proto(PROTO.as_bytes()).unwrap();
+        let user = file.messages.iter().find(|m| m.name == "User").unwrap();
+        assert_eq!(user.description, "A user account");
+        assert_eq!(user.fields.len(), 5);
+        assert_eq!(user.fields[0].description, "The unique id");
+        assert_eq!(user.fields[1].label.as_deref(), Some("repeated"));
+        assert_eq!(user.fields[1].description, "Verified addresses");
+        assert_eq!(
+            user.fields[2].description,
+            "Free form labels\nSet by admins"
+        );
+        assert_eq!(user.fields[3].description, "");
+        assert_eq!(user.fields[2].data_type, "map<string, string>");
+        assert_eq!(user.fields[4].oneof.as_deref(), Some("contact"));
+        assert!(file.messages.iter().any(|m| m.name == "User.Address"));
+
+        let status = file.enums.iter().find(|e| e.name == "User.Status").unwrap();
+        assert_eq!(status.values.len(), 2);
+        assert_eq!(status.values[0].description, "Not yet verified");
+        assert_eq!(status.values[1].description, "Verified");
+        assert_eq!(
+            file.heading_for("users.v1.User").as_deref(),
+            Some("Message User")
+        );
+        assert_eq!(
+            file.heading_for("Status").as_deref(),
+            Some("Enum User.Status")
+        );
+        assert_eq!(file.heading_for("GetUserRequest"), None);
+    }
+
+    #[test]
+    fn test_invalid_proto() {
+        let err = parse_proto(b"message User { string id 1; }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid proto file: expected '=' on line 1"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    history::head_commit,
+    manifest::{hash_bytes, SourceFile},
+    Project, Result,
+};
+
+/// The name of the provenance record written to the build folder.
+pub const PROVENANCE_FILE: &str = "codex-provenance.json";
+
+/// A record of what produced a build: the Codex version, the commit of the
+/// content, and a hash of every input file, so a published site can be
+/// traced back to the exact sources it was built from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    pub codex_version: String,
+    /// The commit the project was built from, when it is in a git repository
+    pub commit: Option<String>,
+    /// Whether the project had uncommitted changes when it was built
+    pub modified: bool,
+    /// The kind of build, such as `site` or `fragments`
+    pub profile: String,
+    /// The SHA-256 hash of the project's `codex.yml`
+    pub config: Option<String>,
+    /// Every file in the project, except build folders and hidden files
+    pub inputs: Vec<SourceFile>,
+}
+
+impl Provenance {
+    pub fn build(project: &Project, profile: &str) -> Result<Self> {
+        let root = project.path.disk_path();
+        let outputs = [
+            &project.details.build_path,
+            &project.details.fragments.build_path,
+        ]
+        .map(|p| root.join(p));
+        let mut inputs = vec![];
+        for entry in walkdir::WalkDir::new(&root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0
+                    || !(e.file_name().to_string_lossy().starts_with('.')
+                        || outputs.iter().any(|p| p == e.path()))
+            })
+        {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(path) = entry.path().strip_prefix(&root) else {
+                continue;
+            };
+            inputs.push(SourceFile {
+                path: path.to_string_lossy().to_string(),
+                hash: hash_bytes(&std::fs::read(entry.path())?),
+            });
+        }
+        let commit = head_commit(&root);
+        Ok(Self {
+            codex_version: env!("CARGO_PKG_VERSION").to_string(),
+            modified: commit.as_ref().is_some_and(|(_, modified)| *modified),
+            commit: commit.map(|(hash, _)| hash),
+            profile: profile.to_string(),
+            config: inputs
+                .iter()
+                .find(|f| f.path == "codex.yml")
+                .map(|f| f.hash.clone()),
+            inputs,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{refactor::tests::writable_fixture, Project};
+
+    use super::Provenance;
+
+    #[test]
+    fn test_provenance() {
+        let root = writable_fixture("provenance");
+        std::fs::create_dir_all(root.join("dist/other")).unwrap();
+        std::fs::write(root.join("dist/other/index.html"), "built").unwrap();
+        std::fs::write(root.join(".env"), "hidden").unwrap();
+        let project = Project::load(&root, false).unwrap();
+
+        let provenance = Provenance::build(&project, "site").unwrap();
+        assert_eq!(provenance.codex_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance.profile, "site");
+        assert_eq!(provenance.config.as_ref().unwrap().len(), 64);
+        let paths = provenance
+            .inputs
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect::<Vec<_>>();
+        assert!(paths.contains(&"codex.yml"));
+        assert!(paths.contains(&"other/alert.md"));
+        assert!(paths
+            .iter()
+            .all(|p| !p.starts_with("dist") && !p.starts_with('.')));
+    }
+}
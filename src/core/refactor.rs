@@ -0,0 +1,801 @@
+use std::path::{Path, PathBuf};
+
+use markdown::mdast::{AttributeContent, AttributeValue, MdxJsxAttribute, Node};
+
+use crate::{
+    document::{join_frontmatter, split_frontmatter},
+    project::MarkdownDetails,
+    renderer::slugify,
+    search::plain_text,
+    Document, Error, Project, Result,
+};
+
+/// Component attributes that can contain a link to another page.
+const LINK_ATTRIBUTES: [&str; 2] = ["href", "url"];
+
+/// The changes made when a document was moved to a new url.
+#[derive(Debug, Default)]
+pub struct RenameSummary {
+    /// The original path of the document, relative to the project root
+    pub from: PathBuf,
+    /// The new path of the document, relative to the project root
+    pub to: PathBuf,
+    /// The number of links that were updated
+    pub links: usize,
+    /// The number of documents containing updated links
+    pub files: usize,
+}
+
+/// Move the document at one url to another. Links to the old url across the
+/// project are updated and a redirect from the old url is added to the
+/// project configuration.
+pub fn rename_url(project: &Project, from: &str, to: &str) -> Result<RenameSummary> {
+    let from = normalize_url(from);
+    let to = normalize_url(to);
+    let document = project
+        .get_document_for_link(&from)
+        .ok_or_else(|| Error::new(format!("No document found for {}", from)))?;
+    if project.get_document_for_link(&to).is_some() {
+        return Err(Error::new(format!("A document already exists at {}", to)));
+    }
+
+    let destination = if document.file_path.is_index() {
+        PathBuf::from(to.trim_start_matches('/')).join("index.md")
+    } else {
+        PathBuf::from(format!("{}.md", to.trim_start_matches('/')))
+    };
+    let destination_path = project.path.disk_path().join(&destination);
+    if destination_path.exists() {
+        return Err(Error::new(format!(
+            "File already exists: {}",
+            destination.display()
+        )));
+    }
+
+    let mut summary = RenameSummary {
+        from: document.file_path.relative_path.clone(),
+        to: destination,
+        ..Default::default()
+    };
+
+    let rewrites = rewrite_project_links(project, &[], |path, anchor| {
+        if path != from {
+            return None;
+        }
+        Some(match anchor {
+            Some(anchor) => format!("{}#{}", to, anchor),
+            None => to.clone(),
+        })
+    })?;
+    (summary.links, summary.files) = (rewrites.links, rewrites.files());
+
+    rewrites.write()?;
+    if let Some(parent) = destination_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(document.file_path.disk_path(), &destination_path)?;
+    add_redirect(project, &from, &to)?;
+
+    Ok(summary)
+}
+
+/// The updated content of documents whose links were rewritten. Nothing is
+/// written until every document has been rewritten, so an error leaves the
+/// project unchanged.
+#[derive(Debug, Default)]
+struct LinkRewrites {
+    documents: Vec<(PathBuf, String)>,
+    links: usize,
+}
+
+impl LinkRewrites {
+    /// The number of documents containing rewritten links.
+    fn files(&self) -> usize {
+        self.documents.len()
+    }
+
+    fn write(&self) -> Result<()> {
+        for (path, content) in self.documents.iter() {
+            std::fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrite links in every document of the project, except those in `skip`.
+/// The function receives the normalized url and anchor of each link to
+/// another page.
+fn rewrite_project_links(
+    project: &Project,
+    skip: &[&Document],
+    replace: impl Fn(&str, Option<&str>) -> Option<String>,
+) -> Result<LinkRewrites> {
+    let mut rewrites = LinkRewrites::default();
+    for doc in project.root_folder.iter_all_documents() {
+        if skip.iter().any(|d| d.file_path == doc.file_path) {
+            continue;
+        }
+        let content = String::from_utf8(doc.file_path.read()?.to_vec())?;
+        let (updated, count) = rewrite_links(&content, &project.details.markdown, |url| {
+            let (path, anchor) = match url.split_once('#') {
+                Some((path, anchor)) => (path, Some(anchor)),
+                None => (url, None),
+            };
+            if path.is_empty() || path.contains("://") {
+                return None;
+            }
+            replace(&normalize_url(path), anchor)
+        })?;
+        if count > 0 {
+            rewrites
+                .documents
+                .push((doc.file_path.disk_path(), updated));
+            rewrites.links += count;
+        }
+    }
+    Ok(rewrites)
+}
+
+/// Rewrite the link targets in a markdown document. The function receives
+/// every link target and returns its replacement, or `None` to leave it
+/// unchanged. Markdown links, link definitions, and link attributes of
+/// components are rewritten. Returns the new content and the number of
+/// rewritten links.
+pub(crate) fn rewrite_links(
+    content: &str,
+    markdown: &MarkdownDetails,
+    replace: impl Fn(&str) -> Option<String>,
+) -> Result<(String, usize)> {
+    let ast = crate::document::parse_source(content, markdown)?;
+    let mut edits = vec![];
+    collect_link_edits(&ast, content, &replace, &mut edits);
+    edits.sort_by_key(|(start, _, _)| *start);
+
+    let mut output = content.to_string();
+    for (start, end, replacement) in edits.iter().rev() {
+        output.replace_range(start..end, replacement);
+    }
+    Ok((output, edits.len()))
+}
+
+fn collect_link_edits(
+    node: &Node,
+    content: &str,
+    replace: &impl Fn(&str) -> Option<String>,
+    edits: &mut Vec<(usize, usize, String)>,
+) {
+    let mut targets = vec![];
+    match node {
+        Node::Link(link) => targets.push(link.url.clone()),
+        Node::Definition(definition) => targets.push(definition.url.clone()),
+        Node::MdxJsxFlowElement(el) => targets.extend(link_attributes(&el.attributes)),
+        Node::MdxJsxTextElement(el) => targets.extend(link_attributes(&el.attributes)),
+        _ => {}
+    }
+
+    if let Some(position) = node.position() {
+        let (start, end) = (position.start.offset, position.end.offset);
+        for target in targets {
+            let Some(replacement) = replace(&target) else {
+                continue;
+            };
+            // Links end with their target, while components list their
+            // attributes before any children.
+            let found = match node {
+                Node::Link(_) | Node::Definition(_) => content[start..end].rfind(&target),
+                _ => content[start..end].find(&target),
+            };
+            if let Some(idx) = found {
+                edits.push((start + idx, start + idx + target.len(), replacement));
+            }
+        }
+    }
+
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        collect_link_edits(child, content, replace, edits);
+    }
+}
+
+pub(crate) fn link_attributes(attributes: &[AttributeContent]) -> Vec<String> {
+    attributes
+        .iter()
+        .filter_map(|attr| match attr {
+            AttributeContent::Property(MdxJsxAttribute {
+                name,
+                value: Some(AttributeValue::Literal(value)),
+            }) if LINK_ATTRIBUTES.contains(&name.as_str()) => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The pages created when a document was split.
+#[derive(Debug, Default)]
+pub struct SplitSummary {
+    /// The paths of the new documents, relative to the project root
+    pub pages: Vec<PathBuf>,
+    /// The number of links that were updated
+    pub links: usize,
+    /// The number of documents containing updated links
+    pub files: usize,
+}
+
+/// Split a document into a folder with a page for each heading of the given
+/// level. Content before the first heading becomes the folder's index page,
+/// so the document keeps its url. Each page carries the document's front
+/// matter, and links to the moved sections are updated across the project.
+/// The index page records where each section's anchors moved, so old
+/// bookmarks are sent to the new pages.
+pub fn split_document(project: &Project, file: &Path, level: u8) -> Result<SplitSummary> {
+    let document = project
+        .root_folder
+        .iter_all_documents()
+        .find(|d| d.file_path.relative_path == file)
+        .ok_or_else(|| Error::new(format!("No document found at {}", file.display())))?;
+    if document.file_path.is_index() {
+        return Err(Error::new("Index documents can't be split"));
+    }
+    let folder = file.with_extension("");
+    if project.path.disk_path().join(&folder).exists() {
+        return Err(Error::new(format!(
+            "Folder already exists: {}",
+            folder.display()
+        )));
+    }
+
+    let content = String::from_utf8(document.file_path.read()?.to_vec())?;
+    let (frontmatter, body_start) = split_frontmatter(&content)?;
+    let ast = crate::document::parse_source(&content, &project.details.markdown)?;
+    let headings = ast
+        .children()
+        .map(|c| c.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|node| match (node, node.position()) {
+            (Node::Heading(h), Some(p)) if h.depth == level => {
+                Some((p.start.offset, p.end.offset, plain_text(node)))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if headings.is_empty() {
+        return Err(Error::new(format!(
+            "No level {} headings found in {}",
+            level,
+            file.display()
+        )));
+    }
+
+    // Map each anchor in a section to the section's new page. Pages are
+    // named after their heading, numbered when headings repeat or have no
+    // text to name them by.
+    let mut sections: Vec<(String, String, String)> = vec![];
+    let mut moved_anchors = serde_yaml::Mapping::new();
+    for (idx, (start, heading_end, title)) in headings.iter().enumerate() {
+        let end = headings.get(idx + 1).map(|h| h.0).unwrap_or(content.len());
+        let anchor = slugify(title);
+        let base = if anchor.is_empty() {
+            format!("section-{}", idx + 1)
+        } else {
+            anchor.clone()
+        };
+        let mut slug = base.clone();
+        let mut n = 1;
+        while sections.iter().any(|(s, _, _)| *s == slug) || slug == "index" {
+            n += 1;
+            slug = format!("{}-{}", base, n);
+        }
+        let url = format!("{}/{}", document.url, slug);
+        let body = shift_heading_levels(
+            &content[*heading_end..end],
+            -(level as i8),
+            &project.details.markdown,
+        )?
+        .0;
+        if !anchor.is_empty() && !moved_anchors.contains_key(anchor.as_str()) {
+            moved_anchors.insert(anchor.into(), url.clone().into());
+        }
+        for node in crate::document::parse_source(&content[*start..end], &project.details.markdown)?
+            .children()
+            .map(|c| c.as_slice())
+            .unwrap_or_default()
+        {
+            if let Node::Heading(h) = node {
+                if h.depth > level {
+                    let anchor = slugify(&plain_text(node));
+                    moved_anchors
+                        .insert(anchor.clone().into(), format!("{}#{}", url, anchor).into());
+                }
+            }
+        }
+        sections.push((slug, title.clone(), body));
+    }
+    let moved = |path: &str, anchor: Option<&str>| -> Option<String> {
+        if !path.is_empty() && path != document.url {
+            return None;
+        }
+        moved_anchors
+            .get(anchor?)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+
+    let rewrites = rewrite_project_links(project, &[document], |path, anchor| moved(path, anchor))?;
+    let mut summary = SplitSummary {
+        links: rewrites.links,
+        files: rewrites.files(),
+        ..Default::default()
+    };
+    let folder_path = project.path.disk_path().join(&folder);
+    std::fs::create_dir_all(&folder_path)?;
+    for (position, (slug, title, body)) in sections.iter().enumerate() {
+        let mut page_frontmatter = frontmatter.clone();
+        page_frontmatter.insert("title".into(), title.clone().into());
+        if let Some(parent_title) = frontmatter.get("title") {
+            page_frontmatter.insert("subtitle".into(), parent_title.clone());
+        }
+        page_frontmatter.insert("menu_position".into(), (position as i64).into());
+        page_frontmatter.remove("json_schema");
+        // The index keeps the document's id
+        page_frontmatter.retain(|key, _| key.as_str() != Some("id"));
+        let body = rewrite_links(body, &project.details.markdown, |url| {
+            local_link(url, moved)
+        })?
+        .0;
+        let page = folder.join(format!("{}.md", slug));
+        std::fs::write(
+            project.path.disk_path().join(&page),
+            join_frontmatter(&page_frontmatter, body.trim_start())?,
+        )?;
+        summary.pages.push(page);
+    }
+
+    let mut index_frontmatter = frontmatter.clone();
+    index_frontmatter.insert("moved_anchors".into(), moved_anchors.clone().into());
+    let intro = rewrite_links(
+        &content[body_start..headings[0].0],
+        &project.details.markdown,
+        |url| local_link(url, moved),
+    )?
+    .0;
+    std::fs::write(
+        folder_path.join("index.md"),
+        join_frontmatter(&index_frontmatter, intro.trim_start())?,
+    )?;
+    std::fs::remove_file(document.file_path.disk_path())?;
+    rewrites.write()?;
+    Ok(summary)
+}
+
+/// The pages merged into a single document.
+#[derive(Debug, Default)]
+pub struct MergeSummary {
+    /// The path of the merged document, relative to the project root
+    pub file: PathBuf,
+    /// The number of pages merged into the document
+    pub pages: usize,
+    /// The number of links that were updated
+    pub links: usize,
+    /// The number of documents containing updated links
+    pub files: usize,
+}
+
+/// Merge the documents in a folder into a single document. This is the
+/// reverse of [`split_document`]. The folder's index page becomes the start
+/// of the document and every other page becomes a section, with a heading of
+/// the given level. Links to the pages are updated across the project and
+/// redirects are added from each page's url to its section.
+pub fn merge_folder(project: &Project, folder: &Path, level: u8) -> Result<MergeSummary> {
+    let source = project
+        .root_folder
+        .iter_all_folders()
+        .find(|f| f.path.relative_path == folder)
+        .ok_or_else(|| Error::new(format!("No folder found at {}", folder.display())))?;
+    if source
+        .folders
+        .iter()
+        .any(|f| f.iter_all_documents().next().is_some())
+    {
+        return Err(Error::new(
+            "Folders containing other folders can't be merged",
+        ));
+    }
+    let file = PathBuf::from(format!("{}.md", folder.display()));
+    let file_path = project.path.disk_path().join(&file);
+    if file_path.exists() {
+        return Err(Error::new(format!(
+            "File already exists: {}",
+            file.display()
+        )));
+    }
+
+    let index = source.documents.iter().find(|d| d.file_path.is_index());
+    let mut pages = source
+        .documents
+        .iter()
+        .filter(|d| !d.file_path.is_index())
+        .collect::<Vec<_>>();
+    pages.sort_by_key(|p| (p.frontmatter.menu_position, p.frontmatter.title.clone()));
+
+    let (mut frontmatter, mut body) = match index {
+        Some(index) => {
+            let content = String::from_utf8(index.file_path.read()?.to_vec())?;
+            let (frontmatter, body_start) = split_frontmatter(&content)?;
+            (frontmatter, content[body_start..].trim().to_string())
+        }
+        None => (serde_yaml::Mapping::new(), String::new()),
+    };
+    frontmatter.remove("moved_anchors");
+    if !frontmatter.contains_key("title") {
+        frontmatter.insert("title".into(), source.get_name().into());
+    }
+
+    let folder_url = source.path.document_url();
+    let mut redirects = vec![];
+    for page in pages.iter() {
+        let content = String::from_utf8(page.file_path.read()?.to_vec())?;
+        let (_, body_start) = split_frontmatter(&content)?;
+        let section = shift_heading_levels(
+            &content[body_start..],
+            level as i8,
+            &project.details.markdown,
+        )?
+        .0;
+        body.push_str(&format!(
+            "\n\n{} {}\n\n{}",
+            "#".repeat(level as usize),
+            page.frontmatter.title,
+            section.trim()
+        ));
+        redirects.push((
+            page.url.clone(),
+            format!("{}#{}", folder_url, slugify(&page.frontmatter.title)),
+        ));
+    }
+
+    let moved = |path: &str, anchor: Option<&str>| {
+        let (_, to) = redirects.iter().find(|(from, _)| from == path)?;
+        Some(match anchor {
+            Some(anchor) => format!("{}#{}", folder_url, anchor),
+            None => to.clone(),
+        })
+    };
+    let body = rewrite_links(&body, &project.details.markdown, |url| {
+        let (path, anchor) = match url.split_once('#') {
+            Some((path, anchor)) => (path, Some(anchor)),
+            None => (url, None),
+        };
+        (!path.is_empty() && !path.contains("://"))
+            .then(|| moved(&normalize_url(path), anchor))
+            .flatten()
+    })?
+    .0;
+    let merged = source.documents.iter().collect::<Vec<_>>();
+    let rewrites = rewrite_project_links(project, &merged, moved)?;
+    std::fs::write(
+        &file_path,
+        join_frontmatter(&frontmatter, body.trim_start())?,
+    )?;
+    rewrites.write()?;
+    for document in merged {
+        std::fs::remove_file(document.file_path.disk_path())?;
+    }
+    let folder_path = project.path.disk_path().join(folder);
+    if folder_path.join("group.yml").exists() {
+        std::fs::remove_file(folder_path.join("group.yml"))?;
+    }
+    // Other files, such as images, are left in place
+    let _ = std::fs::remove_dir(folder_path);
+    for (from, to) in redirects.iter() {
+        add_redirect(project, from, to)?;
+    }
+
+    Ok(MergeSummary {
+        file,
+        pages: pages.len(),
+        links: rewrites.links,
+        files: rewrites.files(),
+    })
+}
+
+/// Rewrite a link inside a document being split, including links to its
+/// own anchors.
+fn local_link(url: &str, moved: impl Fn(&str, Option<&str>) -> Option<String>) -> Option<String> {
+    let (path, anchor) = url.split_once('#')?;
+    let path = if path.is_empty() {
+        String::new()
+    } else {
+        normalize_url(path)
+    };
+    moved(&path, Some(anchor))
+}
+
+/// Move every heading in a document down by the given number of levels, or
+/// up when the number is negative. Heading anchors are generated from the
+/// heading text, so links to them remain valid. Returns the number of
+/// headings changed.
+pub fn shift_headings(path: &Path, by: i8, markdown: &MarkdownDetails) -> Result<usize> {
+    let content = std::fs::read_to_string(path)?;
+    let (updated, count) = shift_heading_levels(&content, by, markdown)?;
+    std::fs::write(path, updated)?;
+    Ok(count)
+}
+
+pub(crate) fn shift_heading_levels(
+    content: &str,
+    by: i8,
+    markdown: &MarkdownDetails,
+) -> Result<(String, usize)> {
+    let ast = crate::document::parse_source(content, markdown)?;
+    let mut edits = vec![];
+    collect_heading_edits(&ast, content, by, &mut edits)?;
+
+    let mut output = content.to_string();
+    for (start, end, replacement) in edits.iter().rev() {
+        output.replace_range(start..end, replacement);
+    }
+    Ok((output, edits.len()))
+}
+
+fn collect_heading_edits(
+    node: &Node,
+    content: &str,
+    by: i8,
+    edits: &mut Vec<(usize, usize, String)>,
+) -> Result<()> {
+    if let (Node::Heading(heading), Some(position)) = (node, node.position()) {
+        let depth = heading.depth as i8 + by;
+        if !(1..=6).contains(&depth) {
+            return Err(Error::new(format!(
+                "Heading on line {} would be level {}, headings must be between 1 and 6",
+                position.start.line, depth
+            )));
+        }
+        let source = &content[position.start.offset..position.end.offset];
+        let marker = "#".repeat(depth as usize);
+        if source.starts_with('#') {
+            let level = source.chars().take_while(|c| *c == '#').count();
+            let start = position.start.offset;
+            edits.push((start, start + level, marker));
+        } else {
+            // Setext headings are underlined, so they are rewritten in the
+            // ATX style which supports every level.
+            let text = source
+                .rsplit_once('\n')
+                .map(|(text, _)| text)
+                .unwrap_or(source);
+            edits.push((
+                position.start.offset,
+                position.end.offset,
+                format!("{} {}", marker, text.trim()),
+            ));
+        }
+        return Ok(());
+    }
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        collect_heading_edits(child, content, by, edits)?;
+    }
+    Ok(())
+}
+
+fn normalize_url(url: &str) -> String {
+    format!("/{}", url.trim_matches('/'))
+}
+
+/// Add a redirect to the `redirects` section of the project's `codex.yml`.
+/// The file is edited in place so existing comments and formatting are kept.
+fn add_redirect(project: &Project, from: &str, to: &str) -> Result<()> {
+    let config_path = project.path.disk_path().join("codex.yml");
+    let config = if config_path.exists() {
+        std::fs::read_to_string(&config_path)?
+    } else {
+        String::new()
+    };
+    let entry = format!("  {}: {}", from, to);
+    let mut lines = config.lines().map(|l| l.to_string()).collect::<Vec<_>>();
+    match lines.iter().position(|l| l.trim_end() == "redirects:") {
+        Some(idx) => lines.insert(idx + 1, entry),
+        None => {
+            lines.push("redirects:".to_string());
+            lines.push(entry);
+        }
+    }
+    std::fs::write(config_path, format!("{}\n", lines.join("\n")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::{Path, PathBuf};
+
+    use crate::{MarkdownDetails, Project};
+
+    use super::{merge_folder, rename_url, rewrite_links, shift_heading_levels, split_document};
+
+    /// Copy the fixture project to a temporary folder so it can be modified.
+    pub(crate) fn writable_fixture(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("codex-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for entry in walkdir::WalkDir::new("test/fixture")
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let target = root.join(entry.path().strip_prefix("test/fixture").unwrap());
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(target).unwrap();
+            } else {
+                std::fs::copy(entry.path(), target).unwrap();
+            }
+        }
+        root
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let content = "See [the guide](/guide#setup \"/guide\") and <Card href=\"/guide\">[x](/guide)</Card>.\n\n[ref]: /guide\n";
+        let (output, count) = rewrite_links(content, &MarkdownDetails::default(), |url| {
+            url.strip_prefix("/guide")
+                .map(|rest| format!("/manual{}", rest))
+        })
+        .unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(
+            output,
+            "See [the guide](/manual#setup \"/guide\") and <Card href=\"/manual\">[x](/manual)</Card>.\n\n[ref]: /manual\n"
+        );
+    }
+
+    #[test]
+    fn test_rename_url() {
+        let root = writable_fixture("rename");
+        std::fs::write(
+            root.join("other/links.md"),
+            "[Alert](/other/alert#example)\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+
+        let summary = rename_url(&project, "/other/alert", "/components/alert").unwrap();
+        assert_eq!(summary.to, Path::new("components/alert.md"));
+        assert_eq!(summary.links, 1);
+        assert!(root.join("components/alert.md").exists());
+        assert!(!root.join("other/alert.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("other/links.md")).unwrap(),
+            "[Alert](/components/alert#example)\n"
+        );
+
+        let project = Project::load(&root, false).unwrap();
+        assert_eq!(
+            project.details.redirects.get("/other/alert").unwrap(),
+            "/components/alert"
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_shift_headings() {
+        let content = "# Title\n\nText\n\n## Section\n\nSetext\n------\n";
+        let (output, count) =
+            shift_heading_levels(content, 1, &MarkdownDetails::default()).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(output, "## Title\n\nText\n\n### Section\n\n### Setext\n");
+
+        let (output, _) = shift_heading_levels(&output, -1, &MarkdownDetails::default()).unwrap();
+        assert_eq!(output, "# Title\n\nText\n\n## Section\n\n## Setext\n");
+
+        let err = shift_heading_levels(content, -1, &MarkdownDetails::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Heading on line 1 would be level 0, headings must be between 1 and 6"
+        );
+    }
+
+    #[test]
+    fn test_split_and_merge() {
+        let root = writable_fixture("split");
+        std::fs::write(
+            root.join("other/guide.md"),
+            "---\ntitle: Guide\nid: guide\ntags: [setup]\n---\n\nIntro, see [usage](#options).\n\n## Install\n\nRun it.\n\n## Usage\n\nUse it.\n\n### Options\n\nSome options.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("other/links.md"),
+            "[Usage](/other/guide#usage) [Options](/other/guide#options)\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+
+        let summary = split_document(&project, Path::new("other/guide.md"), 2).unwrap();
+        assert_eq!(
+            summary.pages,
+            vec![
+                Path::new("other/guide/install.md"),
+                Path::new("other/guide/usage.md")
+            ]
+        );
+        assert!(!root.join("other/guide.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("other/guide/usage.md")).unwrap(),
+            "---\ntitle: Usage\ntags:\n- setup\nsubtitle: Guide\nmenu_position: 1\n---\n\nUse it.\n\n# Options\n\nSome options.\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("other/links.md")).unwrap(),
+            "[Usage](/other/guide/usage) [Options](/other/guide/usage#options)\n"
+        );
+
+        let project = Project::load(&root, false).unwrap();
+        let index = project.get_document_for_url("/other/guide").unwrap();
+        assert_eq!(
+            index.frontmatter.moved_anchors.get("install").unwrap(),
+            "/other/guide/install"
+        );
+        assert_eq!(index.frontmatter.id.as_deref(), Some("guide"));
+        assert!(std::fs::read_to_string(root.join("other/guide/index.md"))
+            .unwrap()
+            .contains("Intro, see [usage](/other/guide/usage#options)."));
+
+        let summary = merge_folder(&project, Path::new("other/guide"), 2).unwrap();
+        assert_eq!(summary.pages, 2);
+        assert!(!root.join("other/guide").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("other/guide.md")).unwrap(),
+            "---\ntitle: Guide\nid: guide\ntags:\n- setup\n---\n\nIntro, see [usage](/other/guide#options).\n\n## Install\n\nRun it.\n\n## Usage\n\nUse it.\n\n### Options\n\nSome options.\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("other/links.md")).unwrap(),
+            "[Usage](/other/guide#usage) [Options](/other/guide#options)\n"
+        );
+        let project = Project::load(&root, false).unwrap();
+        assert_eq!(
+            project.details.redirects.get("/other/guide/usage").unwrap(),
+            "/other/guide#usage"
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_split_repeated_headings() {
+        let root = writable_fixture("split-repeated");
+        std::fs::write(
+            root.join("other/guide.md"),
+            "---\ntitle: Guide\n---\n\n## Examples\n\nOne.\n\n## Examples\n\nTwo.\n\n## `!`\n\nThree.\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+        let summary = split_document(&project, Path::new("other/guide.md"), 2).unwrap();
+        assert_eq!(
+            summary.pages,
+            vec![
+                Path::new("other/guide/examples.md"),
+                Path::new("other/guide/examples-2.md"),
+                Path::new("other/guide/section-3.md")
+            ]
+        );
+        assert!(
+            std::fs::read_to_string(root.join("other/guide/examples-2.md"))
+                .unwrap()
+                .contains("Two.")
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_rename_leaves_project_unchanged_on_error() {
+        let root = writable_fixture("rename-error");
+        std::fs::write(root.join("other/linking.md"), "[Alert](/other/alert)\n").unwrap();
+        std::fs::write(
+            root.join("other/broken.md"),
+            "[Alert](/other/alert)\n\n<Alert>\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+        assert!(rename_url(&project, "/other/alert", "/other/warning").is_err());
+        assert!(root.join("other/alert.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join("other/linking.md")).unwrap(),
+            "[Alert](/other/alert)\n"
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
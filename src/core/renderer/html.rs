@@ -1,10 +1,73 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::OnceLock};
 
-use crate::{error::Result, render_template, DataContext, Error};
+use crate::{
+    error::Result, events::WarningKind, DataContext, Error, Folder, OutputTarget, Project,
+};
 use handlebars::html_escape;
 use markdown::mdast::Node;
+use syntect::{
+    highlighting::ThemeSet,
+    html::ClassStyle,
+    parsing::{ParseState, ScopeStack, SyntaxSet},
+};
 
-use super::{RenderContext, Renderer};
+use super::{PrintContext, PrintPage, RenderContext, Renderer};
+
+/// The folder in the build the shared page parts are written to when pages
+/// include them.
+pub const INCLUDES_FOLDER: &str = "_includes";
+const FOOTER_INCLUDE: &str = "footer.html";
+
+/// Render one of the page templates in `_internal/templates`.
+fn render_page_template<T: serde::Serialize>(
+    project: &Project,
+    name: &str,
+    data: T,
+) -> Result<String> {
+    project.render_template_file(&format!("_internal/templates/{}", name), data)
+}
+
+/// The name of the navigation menu include for a language. Each language
+/// has its own menu.
+fn navigation_include(project: &Project, language: &str) -> String {
+    if project.details.translations.is_empty() {
+        "nav.html".to_string()
+    } else {
+        format!("nav.{}.html", language)
+    }
+}
+
+/// Render the navigation menu for a language. The menu is the same for every
+/// page, the current page is marked with `mark_active`.
+fn render_navigation(project: &Project, language: &str) -> Result<String> {
+    let data = DataContext {
+        project: project.details.clone(),
+        sitemap: super::SiteMapFolder::for_language(project, language),
+        language: language.to_string(),
+        ..Default::default()
+    };
+    render_page_template(project, "nav.html", &data)
+}
+
+/// Mark the link to the current page in a rendered navigation menu, found by
+/// its `data-url` attribute.
+fn mark_active(menu: &str, url: &str) -> String {
+    let attribute = format!(r#"data-url="{}""#, url);
+    let Some(tag_start) = menu
+        .find(&attribute)
+        .and_then(|offset| menu[..offset].rfind('<'))
+    else {
+        return menu.to_string();
+    };
+    let tag_end = menu[tag_start..]
+        .find('>')
+        .map(|i| tag_start + i)
+        .unwrap_or(menu.len());
+    let tag = menu[tag_start..tag_end]
+        .replacen("inactive-menu-item", "active-menu-item", 1)
+        .replacen(' ', r#" aria-current="page" "#, 1);
+    format!("{}{}{}", &menu[..tag_start], tag, &menu[tag_end..])
+}
 
 pub struct HtmlRenderer<'a> {
     pub render_context: RenderContext<'a>,
@@ -14,6 +77,69 @@ impl<'a> HtmlRenderer<'a> {
     pub fn new(render_context: RenderContext<'a>) -> Self {
         Self { render_context }
     }
+    /// Render all documents in a folder, and its sub folders, into a single
+    /// page intended for printing.
+    pub fn render_print_view(project: &Project, folder: &Folder) -> Result<String> {
+        let (head_html, body_html) = project.injected_html()?;
+        let mut data = PrintContext {
+            project: project.details.clone(),
+            name: folder.get_name(),
+            pages: vec![],
+            messages: crate::i18n::messages(&project.details, &project.details.default_language),
+            head_html,
+            body_html,
+            highlight_css: highlight_css(&project.details.theme)?,
+        };
+        for document in folder
+            .iter_all_documents()
+            .filter(|d| d.has_format(OutputTarget::Pdf))
+        {
+            let renderer = HtmlRenderer::new(RenderContext::new(project, document));
+            data.pages.push(PrintPage {
+                title: document.frontmatter.title.clone(),
+                subtitle: document.frontmatter.subtitle.clone(),
+                body: renderer.render_article()?,
+            });
+        }
+        render_page_template(project, "print.html", data)
+    }
+
+    /// Render the parts shared by every page, which pages include with
+    /// server side includes when `includes` is set. Returns the name of
+    /// each file in the includes folder and its content.
+    pub fn render_includes(project: &Project) -> Result<Vec<(String, String)>> {
+        let mut includes = vec![];
+        for language in project.details.languages() {
+            includes.push((
+                navigation_include(project, language),
+                render_navigation(project, language)?,
+            ));
+        }
+        let data = DataContext {
+            project: project.details.clone(),
+            messages: crate::i18n::messages(&project.details, &project.details.default_language),
+            ..Default::default()
+        };
+        includes.push((
+            FOOTER_INCLUDE.to_string(),
+            render_page_template(project, "footer.html", &data)?,
+        ));
+        Ok(includes)
+    }
+
+    /// Render the document's article without the site layout, for
+    /// embedding in another application. The project's fragment template
+    /// wraps the article when one is set.
+    pub fn render_fragment(&self) -> Result<String> {
+        let project = self.render_context.project;
+        let mut data = self.page_data()?;
+        data.asset_url = project.details.fragments.asset_url.clone();
+        match project.details.fragments.template {
+            Some(ref template) => project.render_template_file(template, data),
+            None => Ok(data.body),
+        }
+    }
+
     /// Wrap a list of nodes in HTML with the provided start and end fragments
     fn wrap_nodes(&self, start: &str, end: &str, nodes: &[Node]) -> Result<String> {
         Ok(format!("{}{}{}", start, self.render_nodes(nodes)?, end))
@@ -25,37 +151,103 @@ impl<'a> HtmlRenderer<'a> {
         mut attrs: HashMap<String, String>,
         children: &[Node],
     ) -> Result<String> {
-        let cmp_path = self
-            .render_context
-            .document
-            .file_path
-            .new_path(format!("_internal/components/{}.html", name.to_lowercase()));
+        let cmp_path = format!("_internal/components/{}.html", name.to_lowercase());
 
-        if cmp_path.exists() {
+        if self
+            .render_context
+            .project
+            .path
+            .new_path(&cmp_path)
+            .exists()
+        {
             attrs.insert("children".to_string(), self.render_nodes(children)?);
-            render_template(attrs, &String::from_utf8(cmp_path.read()?.to_vec())?)
+            let mut data = serde_json::to_value(attrs)?;
+            data["messages"] = serde_json::to_value(crate::i18n::messages(
+                &self.render_context.project.details,
+                &self.render_context.document.language,
+            ))?;
+            self.render_context
+                .project
+                .render_template_file(&cmp_path, data)
         } else {
-            Ok("<pre>Unknown Component</pre>".to_string())
+            let project = self.render_context.project;
+            let suggestion = super::suggest_component(project, name)
+                .map(|s| format!(", did you mean <{}>?", s))
+                .unwrap_or_default();
+            self.warn(
+                WarningKind::UnknownComponent,
+                format!("Unknown component <{}>{}", name, suggestion),
+            );
+            Ok(format!(
+                "<pre>{}</pre>",
+                html_escape(&format!(
+                    "Unknown component <{}> in {}{}",
+                    name,
+                    self.render_context
+                        .document
+                        .file_path
+                        .relative_path
+                        .display(),
+                    suggestion
+                ))
+            ))
         }
     }
 }
 
 impl Renderer for HtmlRenderer<'_> {
-    fn get_context(&self) -> &RenderContext {
+    fn get_context(&self) -> &RenderContext<'_> {
         &self.render_context
     }
-    fn finalize_render(&self, data: DataContext) -> Result<String> {
-        crate::render_template(
-            data,
-            &String::from_utf8(
-                self.render_context
-                    .project
-                    .path
-                    .new_path("_internal/templates/article.html")
-                    .read()?
-                    .to_vec(),
-            )?,
-        )
+    fn reports_warnings(&self) -> bool {
+        true
+    }
+
+    /// A compact error card naming the part of the page that failed and
+    /// where it is in the document.
+    fn render_node_error(
+        &self,
+        label: &str,
+        line: Option<usize>,
+        message: &str,
+        inline: bool,
+    ) -> Option<String> {
+        let path = &self.render_context.document.file_path.relative_path;
+        let location = match line {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        };
+        let (tag, class) = if inline {
+            ("span", "render-error badge text-bg-danger")
+        } else {
+            ("div", "render-error alert alert-danger")
+        };
+        Some(format!(
+            r#"<{tag} class="{class}" role="alert"><strong>{} failed to render</strong> <code>{}</code> {}</{tag}>"#,
+            html_escape(label),
+            html_escape(&location),
+            html_escape(message),
+        ))
+    }
+    fn finalize_render(&self, mut data: DataContext<'_>) -> Result<String> {
+        let project = self.render_context.project;
+        match project.details.includes {
+            Some(mode) => {
+                let url = |name: String| {
+                    format!("{}{}/{}", project.details.base_url, INCLUDES_FOLDER, name)
+                };
+                data.navigation = mode.directive(&url(navigation_include(project, &data.language)));
+                data.footer = mode.directive(&url(FOOTER_INCLUDE.to_string()));
+            }
+            None => {
+                let menu = project.navigation(&data.language, || {
+                    render_navigation(project, &data.language)
+                })?;
+                data.navigation = mark_active(&menu, &data.current_url);
+                data.footer = render_page_template(project, "footer.html", &data)?;
+            }
+        }
+        render_page_template(project, "article.html", data)
     }
     fn render_blockquote(&self, children: &[Node]) -> Result<String> {
         self.wrap_nodes(r#"<p class="lead">"#, "</p>", children)
@@ -93,12 +285,64 @@ impl Renderer for HtmlRenderer<'_> {
         self.wrap_nodes(r#"<span class="fst-italic">"#, "</span>", children)
     }
 
-    fn render_image(&self, url: &str, title: Option<String>) -> Result<String> {
-        Ok(format!(
-            r#"<img class="img-fluid" src="{}" title="{}""#,
-            url,
-            title.unwrap_or_default()
-        ))
+    fn render_image(&self, url: &str, alt: &str, title: Option<String>) -> Result<String> {
+        let caption = title.filter(|t| !t.trim().is_empty());
+        let class = if caption.is_some() {
+            "figure-img img-fluid"
+        } else {
+            "img-fluid"
+        };
+        // Resized copies generated by the build are listed so the browser
+        // can pick the smallest that fits
+        let sources = crate::images::sources(self.render_context.project, url);
+        let mut img = format!(
+            r#"<img class="{}" src="{}" alt="{}""#,
+            class,
+            html_escape(url),
+            html_escape(alt),
+        );
+        if let Some(ref sources) = sources {
+            img.push_str(&format!(r#" srcset="{}""#, html_escape(&sources.srcset)));
+        }
+        img.push_str(" />");
+        if let Some(webp) = sources.and_then(|s| s.webp) {
+            img = format!(
+                r#"<picture><source type="image/webp" srcset="{}" />{}</picture>"#,
+                html_escape(&webp),
+                img
+            );
+        }
+        // The title is shown as the image's caption
+        Ok(match caption {
+            Some(caption) => format!(
+                r#"<figure class="figure">{}<figcaption class="figure-caption">{}</figcaption></figure>"#,
+                img,
+                html_escape(&caption)
+            ),
+            None => img,
+        })
+    }
+
+    fn render_html(&self, html: &str) -> Result<String> {
+        Ok(html.to_string())
+    }
+
+    fn render_math(&self, math: &str, display: bool) -> Result<String> {
+        let math = math
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        if display {
+            Ok(format!(
+                r#"<div class="math math-display">\[{}\]</div>"#,
+                math
+            ))
+        } else {
+            Ok(format!(
+                r#"<span class="math math-inline">\({}\)</span>"#,
+                math
+            ))
+        }
     }
 
     fn render_link(&self, url: &str, title: Option<String>, children: &[Node]) -> Result<String> {
@@ -119,16 +363,33 @@ impl Renderer for HtmlRenderer<'_> {
         code: &str,
         lang: Option<String>,
         filepath: Option<std::path::PathBuf>,
+        options: &super::CodeOptions,
     ) -> Result<String> {
-        let lines = if let Some(ref lang) = lang {
-            highlight(lang, code.trim()).unwrap_or(
+        // Diffs are colored by line rather than by syntax
+        let lines = if options.diff {
+            html_escape(code)
+                .lines()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+        } else if let Some(ref lang) = lang {
+            highlight(
+                self.render_context.project.syntaxes()?,
+                lang,
+                code.trim_end(),
+            )
+            .unwrap_or(
                 html_escape(code)
                     .lines()
                     .map(|s| s.to_string())
                     .collect::<Vec<_>>(),
             )
         } else if let Some(ref filepath) = filepath {
-            highlight_by_extension(filepath, code.trim()).unwrap_or(
+            highlight_by_extension(
+                self.render_context.project.syntaxes()?,
+                filepath,
+                code.trim_end(),
+            )
+            .unwrap_or(
                 html_escape(code)
                     .lines()
                     .map(|s| s.to_string())
@@ -140,27 +401,21 @@ impl Renderer for HtmlRenderer<'_> {
                 .map(|s| s.to_string())
                 .collect::<Vec<_>>()
         };
-        let template = self
-            .render_context
-            .document
-            .file_path
-            .new_path("_internal/templates/code.html");
         let data = super::CodeContext {
-            lines,
+            lines: options.build_lines(code, lines),
             lang: lang.clone().unwrap_or_default(),
+            line_numbers: options.line_numbers,
+            title: options.title.clone(),
         };
-        template
-            .read()
-            .map(|d| d.to_vec())
-            .map_err(crate::Error::from)
-            .and_then(|d| {
-                render_template(data, &String::from_utf8(d).unwrap_or_default())
-                    .map_err(crate::Error::from)
-            })
+        render_page_template(self.render_context.project, "code.html", data)
     }
 
     fn render_heading(&self, depth: u8, children: &[Node]) -> Result<String> {
         let Some(text) = self.get_text(children) else {
+            self.warn(
+                WarningKind::EmptyHeading,
+                "Heading without text".to_string(),
+            );
             return Ok("<pre>No header text found</pre>".to_string());
         };
         let slug = self.slug(&text);
@@ -225,50 +480,282 @@ impl Renderer for HtmlRenderer<'_> {
     }
 
     fn render_paragraph(&self, children: &[Node]) -> Result<String> {
+        // A figure can't be placed inside a paragraph
+        if let [Node::Image(img)] = children {
+            if img.title.as_ref().is_some_and(|t| !t.trim().is_empty()) {
+                return self.render_nodes(children);
+            }
+        }
         self.wrap_nodes("<p>", "</p>", children)
     }
+
+    fn render_steps(&self, content: String) -> Result<String> {
+        let document = self.render_context.document;
+        self.render_context.project.render_template_file(
+            "_internal/components/steps.html",
+            serde_json::json!({
+                "children": content,
+                "runbook": document.frontmatter.page_type == crate::PageType::Runbook,
+                "url": document.url,
+                "messages": crate::i18n::messages(
+                    &self.render_context.project.details,
+                    &document.language,
+                ),
+            }),
+        )
+    }
+
+    fn render_step(
+        &self,
+        number: usize,
+        title: &str,
+        slug: &str,
+        children: &[Node],
+    ) -> Result<String> {
+        let mut attrs = HashMap::from([
+            ("number".to_string(), number.to_string()),
+            ("title".to_string(), title.to_string()),
+            ("slug".to_string(), slug.to_string()),
+        ]);
+        if self.render_context.document.frontmatter.page_type == crate::PageType::Runbook {
+            attrs.insert("runbook".to_string(), "true".to_string());
+        }
+        self.apply_component("Step", attrs, children)
+    }
+
+    fn render_question(&self, slug: &str, question: &[Node], answer: &[Node]) -> Result<String> {
+        let attrs = HashMap::from([
+            ("slug".to_string(), slug.to_string()),
+            ("question".to_string(), self.render_nodes(question)?),
+        ]);
+        self.apply_component("Question", attrs, answer)
+    }
+
+    fn render_qr_code(
+        &self,
+        url: &str,
+        mut attrs: HashMap<String, String>,
+        children: &[Node],
+    ) -> Result<String> {
+        let size = attrs
+            .get("size")
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(160);
+        let svg = qrcode::QrCode::new(url.as_bytes())?
+            .render::<qrcode::render::svg::Color>()
+            .min_dimensions(size, size)
+            .build();
+        let svg = match svg.find("<svg") {
+            Some(idx) => svg[idx..].to_string(),
+            None => svg,
+        };
+        attrs.insert("svg".to_string(), svg);
+        self.apply_component("QrCode", attrs, children)
+    }
+
+    fn render_backlinks(&self, links: &[super::PageLink]) -> Result<String> {
+        let links = links
+            .iter()
+            .map(|link| super::PageLink {
+                title: link.title.clone(),
+                url: self.with_base_url(&link.url),
+            })
+            .collect::<Vec<_>>();
+        self.render_context.project.render_template_file(
+            "_internal/components/backlinks.html",
+            HashMap::from([("links", links)]),
+        )
+    }
+
+    fn render_glossary(&self, terms: &[crate::glossary::GlossaryTerm]) -> Result<String> {
+        let terms = terms
+            .iter()
+            .map(|term| crate::glossary::GlossaryTerm {
+                references: term
+                    .references
+                    .iter()
+                    .map(|r| crate::glossary::TermReference {
+                        url: match r.anchor {
+                            Some(ref anchor) => {
+                                format!("{}#{}", self.with_base_url(&r.url), anchor)
+                            }
+                            None => self.with_base_url(&r.url),
+                        },
+                        ..r.clone()
+                    })
+                    .collect(),
+                ..term.clone()
+            })
+            .collect::<Vec<_>>();
+        self.render_context.project.render_template_file(
+            "_internal/components/glossary.html",
+            HashMap::from([("terms", terms)]),
+        )
+    }
+
+    fn render_gallery(&self, images: &[String], columns: usize) -> Result<String> {
+        let data = super::GalleryContext {
+            images: images
+                .iter()
+                .map(|i| super::GalleryImage::new(self.static_url(i), i))
+                .collect(),
+            column_width: (12 / columns).max(1),
+        };
+        self.render_context
+            .project
+            .render_template_file("_internal/components/gallery.html", data)
+    }
+}
+
+/// syntect's bundled syntaxes. Loading them takes far longer than
+/// highlighting a block, so they are loaded once and shared by every render.
+fn default_syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
 }
 
-fn highlight_by_extension(path: &std::path::Path, s: &str) -> Result<Vec<String>> {
-    let ss = syntect::parsing::SyntaxSet::load_defaults_newlines();
-    let syn = ss
-        .find_syntax_for_file(path)?
-        .ok_or_else(|| Error::new("Syntax not found"))?;
+/// syntect's bundled themes, loaded once like the syntaxes.
+fn default_themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
 
-    highlight_content(syn, s, &ss)
+/// Syntaxes defined by the project take precedence over syntect's defaults.
+fn highlight_by_extension(
+    project: Option<&SyntaxSet>,
+    path: &std::path::Path,
+    s: &str,
+) -> Result<Vec<String>> {
+    for ss in project.into_iter().chain([default_syntaxes()]) {
+        if let Some(syn) = ss.find_syntax_for_file(path)? {
+            return highlight_content(syn, s, ss);
+        }
+    }
+    Err(Error::new("Syntax not found"))
 }
-fn highlight(name: &str, s: &str) -> Result<Vec<String>> {
-    let ss = syntect::parsing::SyntaxSet::load_defaults_newlines();
-    let syn = ss
-        .find_syntax_by_name(name)
-        .ok_or_else(|| Error::new("Syntax not found"))?;
-    highlight_content(syn, s, &ss)
+fn highlight(project: Option<&SyntaxSet>, name: &str, s: &str) -> Result<Vec<String>> {
+    for ss in project.into_iter().chain([default_syntaxes()]) {
+        if let Some(syn) = ss
+            .find_syntax_by_name(name)
+            .or_else(|| ss.find_syntax_by_token(name))
+        {
+            return highlight_content(syn, s, ss);
+        }
+    }
+    Err(Error::new("Syntax not found"))
 }
 
+/// Highlighted code is marked up with classes rather than colors, so the
+/// stylesheet from [`highlight_css`] can color it for the light and dark
+/// modes.
+const HIGHLIGHT_CLASSES: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Highlight each line of the code on its own, closing the spans left open
+/// at the end of a line and reopening them on the next.
 fn highlight_content(
     syn: &syntect::parsing::SyntaxReference,
     s: &str,
-    ss: &syntect::parsing::SyntaxSet,
+    ss: &SyntaxSet,
 ) -> Result<Vec<String>> {
-    let ts = syntect::highlighting::ThemeSet::load_defaults();
-    let theme = ts
-        .themes
-        .get("Solarized (dark)")
-        .ok_or_else(|| Error::new("Theme not found"))?;
-
-    let mut h = syntect::easy::HighlightLines::new(syn, theme);
-    let res = s
-        .lines()
-        .map(|s| {
-            let hl = h.highlight_line(s, ss).unwrap();
-            syntect::html::styled_line_to_highlighted_html(
-                &hl[..],
-                syntect::html::IncludeBackground::No,
+    let mut state = ParseState::new(syn);
+    let mut stack = ScopeStack::new();
+    s.lines()
+        .map(|line| {
+            let mut html = stack
+                .as_slice()
+                .iter()
+                .map(|scope| {
+                    let classes = scope
+                        .build_string()
+                        .split('.')
+                        .map(|atom| format!("hl-{}", atom))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    format!("<span class=\"{}\">", classes)
+                })
+                .collect::<String>();
+            let ops = state
+                .parse_line(line, ss)
+                .map_err(|e| Error::new(e.to_string()))?;
+            let (spans, _) = syntect::html::line_tokens_to_classed_spans(
+                line,
+                &ops,
+                HIGHLIGHT_CLASSES,
+                &mut stack,
             )
-            .unwrap()
+            .map_err(|e| Error::new(e.to_string()))?;
+            html.push_str(&spans);
+            html.push_str(&"</span>".repeat(stack.len()));
+            Ok(html)
         })
-        .collect::<Vec<_>>();
-    Ok(res)
+        .collect()
+}
+
+/// The stylesheet coloring highlighted code with the project's light theme,
+/// and its dark theme when the page is in dark mode.
+pub fn highlight_css(theme: &crate::ThemeDetails) -> Result<String> {
+    let load = |name: &str| {
+        default_themes()
+            .themes
+            .get(name)
+            .ok_or_else(|| Error::new(format!("Unknown code theme {}", name)))
+    };
+    let light = load(&theme.code_light)?;
+    let dark = load(&theme.code_dark)?;
+    let css = |theme: &syntect::highlighting::Theme| {
+        syntect::html::css_for_theme_with_class_style(theme, HIGHLIGHT_CLASSES)
+            .map_err(|e| Error::new(e.to_string()))
+    };
+    Ok(format!(
+        "{}{}{}{}",
+        code_colors(":root", light),
+        css(light)?,
+        code_colors("[data-bs-theme=dark]", dark),
+        scope_css("[data-bs-theme=dark]", &css(dark)?)
+    ))
+}
+
+/// Variables with a theme's foreground and background, used for the code
+/// blocks themselves.
+fn code_colors(selector: &str, theme: &syntect::highlighting::Theme) -> String {
+    let color = |c: Option<syntect::highlighting::Color>| {
+        c.map(|c| format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b))
+    };
+    let mut vars = String::new();
+    if let Some(fg) = color(theme.settings.foreground) {
+        vars.push_str(&format!(" --codex-code-fg: {};\n", fg));
+    }
+    if let Some(bg) = color(theme.settings.background) {
+        vars.push_str(&format!(" --codex-code-bg: {};\n", bg));
+    }
+    format!("{} {{\n{}}}\n", selector, vars)
+}
+
+/// Prefix every selector of a stylesheet with `scope`.
+fn scope_css(scope: &str, css: &str) -> String {
+    let mut out = String::new();
+    let mut rest = css;
+    while let Some(start) = rest.find('{') {
+        let selectors = rest[..start]
+            .split(',')
+            .map(|s| match s.rfind("*/") {
+                Some(end) => s[end + 2..].trim(),
+                None => s.trim(),
+            })
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("{} {}", scope, s))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let end = rest[start..]
+            .find('}')
+            .map_or(rest.len(), |e| start + e + 1);
+        out.push_str(&selectors);
+        out.push(' ');
+        out.push_str(&rest[start..end]);
+        out.push('\n');
+        rest = &rest[end..];
+    }
+    out
 }
 
 #[cfg(test)]
@@ -285,4 +772,400 @@ mod tests {
         };
         assert_eq!(renderer.render_body().unwrap(), result);
     }
+
+    #[test]
+    pub fn test_csv_table_options() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/csv_options").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.starts_with("<table class=\"table table-sm table-striped\"><thead><tr><td>position</td><td>name</td></tr></thead><tbody><tr><td>engineer</td><td>alice</td></tr><tr><td>engineer</td><td>bob</td></tr></tbody></table>"));
+        assert!(body.ends_with("<table class=\"table table-sm table-striped\"><tbody><tr><td>alice</td><td>engineer</td></tr><tr><td>bob</td><td>engineer</td></tr></tbody></table>"));
+    }
+
+    #[test]
+    pub fn test_data_table() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/data_table").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        assert_eq!(
+            renderer.render_body().unwrap(),
+            "<table class=\"table table-sm table-striped\"><thead><tr><td>name</td><td>version</td></tr></thead><tbody><tr><td>codex</td><td><code class=\"inline\">0.4.5</code></td></tr><tr><td>syntect</td><td><code class=\"inline\">5.1.0</code></td></tr></tbody></table>"
+        );
+    }
+
+    #[test]
+    pub fn test_markdown_options() {
+        let mut project = project_fixture();
+        let doc = project.get_document_for_url("/other/math").unwrap().clone();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, &doc),
+        };
+        assert!(!renderer.render_body().unwrap().contains("math"));
+
+        project.details.markdown.math = true;
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, &doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"<span class="math math-inline">\(\pi r^2\)</span>"#));
+        assert!(body.contains(r#"<span class="math math-inline">\(r &lt; 1\)</span>"#));
+        assert!(body.contains(r#"<div class="math math-display">\[E = mc^2\]</div>"#));
+
+        project.details.markdown.html = true;
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, &doc),
+        };
+        let body = renderer.parse_ast("<div class=\"note\">Raw</div>").unwrap();
+        assert_eq!(
+            renderer.render_node(&body).unwrap(),
+            "<div class=\"note\">Raw</div>"
+        );
+    }
+
+    #[test]
+    pub fn test_code_options() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/code_options").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"class="line-numbers""#));
+        assert!(body.contains(r#"<code data-line="1">"#));
+        assert!(body.contains(r#"<code data-line="2" class="highlighted">"#));
+        assert!(body.contains(r#"<code data-line="3">"#));
+        assert!(body.contains(r#"<div class="code-title">src/main.rs</div>"#));
+        // Lines are counted the same way as in LaTeX, including leading
+        // blank lines
+        assert!(body.contains(r#"<code data-line="2" class="highlighted">marked line</code>"#));
+        // Titles are escaped by the template
+        assert!(body.contains(r#"<div class="code-title">&lt;b&gt;Title&lt;/b&gt;</div>"#));
+    }
+
+    #[test]
+    pub fn test_diff() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/diff").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"<code data-line="2" class="removed">-    println!"#));
+        assert!(body.contains(r#"<code data-line="3" class="added">+    println!"#));
+        assert!(body.contains(r#"<code data-line="1"> fn main() {</code>"#));
+        // CodeFile compares the two files
+        assert!(body.contains(r#"class="removed">-    println!(&quot;Hello&quot;);</code>"#));
+        assert!(body.contains(r#"class="added">+    println!(&quot;Hello, world&quot;);</code>"#));
+    }
+
+    #[test]
+    pub fn test_custom_syntax() {
+        let project = project_fixture();
+        let doc = project
+            .get_document_for_url("/other/custom_syntax")
+            .unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        // Keywords from the project's syntax definition are marked up
+        assert!(body.contains(
+            r#"<code data-line="1"><span class="hl-source hl-pipeline"><span class="hl-keyword hl-control hl-pipeline">step</span>"#
+        ));
+        // The syntax can be selected by its file extension
+        assert_eq!(
+            body.matches(r#"<span class="hl-keyword hl-control hl-pipeline">run</span>"#)
+                .count(),
+            2
+        );
+        // Each line closes the spans it leaves open
+        assert!(body.contains("&quot;build&quot;</span></span></code>"));
+    }
+
+    #[test]
+    pub fn test_highlight_css() {
+        let mut theme = crate::ThemeDetails::default();
+        let css = super::highlight_css(&theme).unwrap();
+        assert!(css.starts_with(":root {\n --codex-code-fg:"));
+        assert!(css.contains("\n.hl-keyword {"));
+        assert!(css.contains("[data-bs-theme=dark] {\n --codex-code-fg:"));
+        assert!(css.contains("[data-bs-theme=dark] .hl-keyword {"));
+        assert!(!css.contains("[data-bs-theme=dark] /*"));
+
+        theme.code_dark = "Missing".into();
+        assert!(super::highlight_css(&theme).is_err());
+    }
+
+    #[test]
+    pub fn test_gallery() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/gallery").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(
+            r#"<img class="img-fluid img-thumbnail" src="/gallery/one.png" alt="One" />"#
+        ));
+        assert!(body.contains(r#"<img class="d-block w-100" src="/gallery/two.png" alt="Two" />"#));
+        assert!(body.contains(
+            r#"src="/gallery/release_1.0 &amp; notes.png" alt="Release 1.0 &amp; notes""#
+        ));
+        assert!(body.contains(r#"class="col-6""#));
+        assert!(!body.contains("notes.txt"));
+    }
+
+    #[test]
+    pub fn test_alert_style() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        assert!(renderer
+            .render_body()
+            .unwrap()
+            .contains(r#"<div class="alert alert-warning">"#));
+    }
+
+    #[test]
+    pub fn test_print_view() {
+        let project = project_fixture();
+        let folder = project
+            .get_folder_for_print_url("/other/print.html")
+            .unwrap();
+        let output = super::HtmlRenderer::render_print_view(&project, folder).unwrap();
+        assert!(output.contains("hello Alice"));
+        assert!(output.contains("<td>charlie</td>"));
+        assert!(!output.contains(r#"id="menu""#));
+    }
+
+    #[test]
+    pub fn test_steps() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/steps").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"<li class="step" id="step-install-codex">"#));
+        assert!(body.contains(r##"<a class="step-number" href="#step-2">2</a>"##));
+        assert!(body.contains("<p>Run the build command</p>"));
+    }
+
+    #[test]
+    pub fn test_runbook() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/runbook").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let page = renderer.render().unwrap();
+        assert!(page.contains(r#"<div class="runbook" data-runbook="/other/runbook">"#));
+        assert!(page.contains(r#"data-step="step-drain-the-node">"#));
+        assert!(page.contains(r#"data-step="step-2" aria-label="2">"#));
+        assert!(page.contains("js/runbook.js"));
+    }
+
+    #[test]
+    pub fn test_faq() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/faq").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.starts_with("<p>Common questions about codex.</p>"));
+        assert!(body.contains(r#"<details class="faq-question" id="how-do-i-install-codex">"#));
+        assert!(body.contains(r#"<summary class="fw-bold">Where is the output?</summary>"#));
+        // Top level headings end the question before them
+        assert!(body.contains("</details>\n<h4 class=\"mt-4\" id=\"still-stuck\">"));
+        let page = renderer.render().unwrap();
+        assert!(page.contains(r#"<script type="application/ld+json">{"@context":"https://schema.org","@type":"FAQPage""#));
+    }
+
+    #[test]
+    pub fn test_qr_code() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/qr_code").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"<div class="qr-code">"#));
+        assert!(body.contains(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+        assert!(!body.contains("<?xml"));
+        assert!(body.contains("https://example.com"));
+    }
+
+    #[test]
+    pub fn test_proto_schema() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/proto_schema").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"id="service-userservice">Service UserService"#));
+        assert!(body.contains("<p>Manages user accounts</p>"));
+        assert!(body.contains(r#"id="message-user">Message User"#));
+        assert!(body.contains(r#"id="enum-status">Enum Status"#));
+        // One field per rpc, message field, and enum value
+        assert_eq!(body.matches("Overridden").count(), 7);
+    }
+
+    #[test]
+    pub fn test_image() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/image").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(
+            r#"<figure class="figure"><img class="figure-img img-fluid" src="/images/diagram.png" alt="Architecture diagram" /><figcaption class="figure-caption">Overview</figcaption></figure>"#
+        ));
+        assert!(!body.contains("<p><figure"));
+        assert!(
+            body.contains(r#"<img class="img-fluid" src="/images/logo.png" alt="Company logo" />"#)
+        );
+        assert!(body.contains(
+            r#"<img class="figure-img img-fluid" src="/images/quote.png" alt="Say &quot;hi&quot; &amp; wave" /><figcaption class="figure-caption">A &amp; B</figcaption>"#
+        ));
+    }
+
+    #[test]
+    pub fn test_id_links() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/id_links").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"<a href="/other/id_target" alt="">deploy guide</a>"#));
+        assert!(body.contains(r#"<a href="/other/id_target#environments" alt="">"#));
+    }
+
+    #[test]
+    pub fn test_attachment_urls() {
+        let mut project = project_fixture();
+        project.details.base_url = "/docs/".to_string();
+        let doc = project
+            .get_document_for_url("/docs/other/attachments")
+            .unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::new(&project, doc),
+        };
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(r#"src="/docs/other/attachments.assets/diagram.png""#));
+        assert!(body.contains(r#"href="/docs/other/attachments.assets/nested/notes.txt""#));
+        // Other documents' folders are left to the browser
+        assert!(body.contains(r#"href="other.assets/notes.txt""#));
+    }
+
+    #[test]
+    pub fn test_fragment() {
+        let root = crate::refactor::tests::writable_fixture("fragment");
+        std::fs::write(
+            root.join("fragment.html"),
+            r#"<section data-title="{{document.title}}">{{{body}}}</section><link href="{{asset "css/article.css"}}">"#,
+        )
+        .unwrap();
+        let mut project = crate::Project::load(&root, false).unwrap();
+        project.details.fragments.asset_url = Some("https://cdn.example.com/".to_string());
+        let doc = project.get_document_for_url("/other/attachments").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::fragment(&project, doc),
+        };
+        let fragment = renderer.render_fragment().unwrap();
+        assert!(fragment.starts_with("<figure"));
+        assert!(!fragment.contains("<html"));
+        assert!(fragment
+            .contains(r#"src="https://cdn.example.com/other/attachments.assets/diagram.png""#));
+        // Page links still use the base url
+        let doc = project.get_document_for_url("/other/id_links").unwrap();
+        let renderer = super::HtmlRenderer {
+            render_context: RenderContext::fragment(&project, doc),
+        };
+        assert!(renderer
+            .render_fragment()
+            .unwrap()
+            .contains(r#"href="/other/id_target""#));
+
+        project.details.fragments.template = Some("fragment.html".to_string());
+        let doc = project.get_document_for_url("/other/attachments").unwrap();
+        let fragment = doc.render_fragment(&project).unwrap();
+        assert!(fragment.starts_with(r#"<section data-title="Attachments"><figure"#));
+        assert!(fragment.ends_with(r#"<link href="https://cdn.example.com/css/article.css">"#));
+    }
+
+    #[test]
+    pub fn test_includes() {
+        let mut project = project_fixture();
+        let doc = project
+            .get_document_for_url("/other/alert")
+            .unwrap()
+            .clone();
+        let page = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        assert!(page.contains(r#"id="menu""#));
+        assert!(page.contains(r#"<a aria-current="page" class="ps-2 active-menu-item"#));
+
+        project.details.includes = Some(crate::IncludeMode::Ssi);
+        let page = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        assert!(!page.contains(r#"id="menu""#));
+        assert!(page.contains(r#"<!--#include virtual="/_includes/nav.html" -->"#));
+        assert!(page.contains(r#"<!--#include virtual="/_includes/footer.html" -->"#));
+        project.details.includes = Some(crate::IncludeMode::Esi);
+        let page = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        assert!(page.contains(r#"<esi:include src="/_includes/nav.html" />"#));
+
+        let includes = super::HtmlRenderer::render_includes(&project).unwrap();
+        assert_eq!(
+            includes.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(),
+            vec!["nav.html", "footer.html"]
+        );
+        // The shared menu links to every page, the current one is marked in
+        // the browser
+        assert!(includes[0].1.contains(r#"href="/other/alert""#));
+        assert!(!includes[0].1.contains("ps-2 active-menu-item"));
+        assert!(includes[0].1.contains("<script>"));
+        assert!(includes[1].1.contains("modified-time"));
+    }
+
+    #[test]
+    pub fn test_navigation_cache() {
+        let project = project_fixture();
+        let render = |url: &str| {
+            project
+                .get_document_for_url(url)
+                .unwrap()
+                .render(&project, crate::OutputFormat::Html)
+                .unwrap()
+        };
+        let alert = render("/other/alert");
+        let pages = render("/pages");
+        // The menu is rendered once and reused by later pages
+        let menu = project
+            .navigation("en", || panic!("The menu wasn't cached"))
+            .unwrap();
+        assert!(!menu.contains("ps-2 active-menu-item"));
+        assert!(!menu.contains("<script>"));
+
+        let active = |url: &str| {
+            format!(
+                r#"<a aria-current="page" class="ps-2 active-menu-item text-truncate fs-6 fw-light text-capitalize d-block link-underline-opacity-0 link-dark link-underline-opacity-75-hover"
+          data-url="{}""#,
+                url
+            )
+        };
+        assert!(alert.contains(&active("/other/alert")));
+        assert!(!alert.contains(&active("/pages")));
+        assert!(pages.contains(&active("/pages")));
+        assert_eq!(pages.matches("aria-current").count(), 1);
+    }
 }
@@ -1,25 +1,157 @@
-use std::path::PathBuf;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+};
 
-use crate::{RenderContext, Renderer};
+use crate::{
+    events::{Event, WarningKind},
+    Folder, OutputTarget, RenderContext, Renderer,
+};
 
 pub struct LatexRenderer<'a> {
     pub render_context: RenderContext<'a>,
+    /// How many boxes, tables, or macro arguments enclose the current node.
+    /// Margin notes can only be placed outside of them.
+    nesting: Cell<usize>,
+    /// QR codes for the links in the paragraph being rendered, placed in the
+    /// margin once the paragraph ends. `None` outside of a top level
+    /// paragraph.
+    qr_codes: RefCell<Option<Vec<String>>>,
 }
 
 impl<'a> LatexRenderer<'a> {
     pub fn new(render_context: RenderContext<'a>) -> Self {
-        Self { render_context }
+        Self {
+            render_context,
+            nesting: Cell::new(0),
+            qr_codes: RefCell::new(None),
+        }
+    }
+
+    /// Render content inside a box, table, or macro argument.
+    fn nested<T>(&self, render: impl FnOnce() -> T) -> T {
+        self.nesting.set(self.nesting.get() + 1);
+        let out = render();
+        self.nesting.set(self.nesting.get() - 1);
+        out
     }
     fn escape(&self, text: &str) -> String {
-        text.replace('&', "\\&")
-            .replace('%', "\\%")
-            .replace('$', "\\$")
-            .replace('#', "\\#")
-            .replace('_', "\\_")
-            .replace('{', "\\{")
-            .replace('}', "\\}")
-            .replace('~', "\\~")
-            .replace('^', "\\^")
+        escape(text)
+    }
+
+    /// The document's section of the LaTeX output, laid out as its front
+    /// matter asks.
+    pub fn render_section(&self) -> crate::Result<String> {
+        let document = self.render_context.document;
+        let frontmatter = &document.frontmatter;
+        let layout = &frontmatter.latex;
+        let slug = Self::section_label(&document.url);
+
+        let mut section = String::new();
+        if layout.landscape {
+            section.push_str("\\begin{landscape}\n");
+        }
+        if let Some(subtitle) = &frontmatter.subtitle {
+            section.push_str(&format!(
+                "\\section[{}]{{{}{{\\hfill\\normalsize\\color{{subtitle}} {}}}}}\\label{{sec:{}}}\n",
+                frontmatter.title, frontmatter.title, subtitle, slug
+            ));
+        } else {
+            section.push_str(&format!(
+                "\\section{{{}}}\\label{{sec:{}}}\n",
+                frontmatter.title, slug
+            ));
+        }
+        let body = self.render()?;
+        if layout.columns > 1 {
+            section.push_str(&format!(
+                "\\begin{{multicols}}{{{}}}\n{}\\end{{multicols}}\n",
+                layout.columns, body
+            ));
+        } else {
+            section.push_str(&body);
+        }
+        if layout.landscape {
+            section.push_str("\\end{landscape}\n");
+        } else if layout.newpage {
+            section.push_str("\\pagebreak\n");
+        }
+        Ok(section)
+    }
+
+    /// The label of a page's section, from its url.
+    pub fn section_label(url: &str) -> String {
+        super::slugify(&url.trim_matches('/').replace(['/', '#', '_'], "-"))
+    }
+
+    /// An overview of a folder introducing its part of the document: the
+    /// folder's description and a list of its sections and sub folders
+    /// with their page numbers. Folders without documents in the LaTeX
+    /// output have none.
+    pub fn render_folder_overview(folder: &Folder) -> Option<String> {
+        let included = |folder: &Folder| {
+            folder
+                .iter_all_documents()
+                .any(|d| d.has_format(OutputTarget::Pdf))
+        };
+        if !included(folder) {
+            return None;
+        }
+        let mut out = format!(
+            "\\section*{{{}}}\\label{{folder:{}}}\n",
+            escape(&folder.get_name()),
+            Self::section_label(&folder.path.root_url())
+        );
+        if let Some(ref description) = folder.details.description {
+            out.push_str(&format!("{}\n\n", escape(description)));
+        }
+        out.push_str("\\begin{itemize}\n");
+        let labels = folder
+            .documents
+            .iter()
+            .filter(|d| d.has_format(OutputTarget::Pdf))
+            .map(|d| format!("sec:{}", Self::section_label(&d.url)))
+            .chain(
+                folder
+                    .folders
+                    .iter()
+                    .filter(|f| included(f))
+                    .map(|f| format!("folder:{}", Self::section_label(&f.path.root_url()))),
+            );
+        for label in labels {
+            out.push_str(&format!(
+                "\\item \\nameref{{{}}} \\dotfill \\pageref{{{}}}\n",
+                label, label
+            ));
+        }
+        out.push_str("\\end{itemize}\n\\pagebreak\n");
+        Some(out)
+    }
+
+    /// The path of an image relative to the project or its static folder,
+    /// which are both searched for graphics. Remote images, images that
+    /// don't exist, and paths LaTeX can't take in an argument are left out.
+    fn image_path(&self, url: &str) -> Option<String> {
+        let project = self.render_context.project;
+        let path = url
+            .strip_prefix(&project.details.base_url)
+            .unwrap_or(url)
+            .trim_start_matches('/');
+        if url.contains("://") || path.contains(['%', '#', '{', '}', '\\']) {
+            return None;
+        }
+        if project.path.join("static").join(path).exists_on_disk()
+            || project.path.join(path).exists_on_disk()
+        {
+            Some(path.to_string())
+        } else {
+            project.events.emit(Event::Warning {
+                kind: WarningKind::MissingAsset,
+                path: Some(self.render_context.document.file_path.relative_path.clone()),
+                message: format!("Image not found, it was left out of the PDF: {}", url),
+            });
+            None
+        }
     }
 
     pub fn render_table_header_row(&self, row: &markdown::mdast::Node) -> crate::Result<String> {
@@ -40,12 +172,24 @@ impl<'a> LatexRenderer<'a> {
     }
 }
 
+fn escape(text: &str) -> String {
+    text.replace('&', "\\&")
+        .replace('%', "\\%")
+        .replace('$', "\\$")
+        .replace('#', "\\#")
+        .replace('_', "\\_")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace('~', "\\~")
+        .replace('^', "\\^")
+}
+
 impl<'a> Renderer for LatexRenderer<'a> {
-    fn get_context(&self) -> &RenderContext {
+    fn get_context(&self) -> &RenderContext<'_> {
         &self.render_context
     }
 
-    fn finalize_render(&self, data: crate::DataContext) -> crate::Result<String> {
+    fn finalize_render(&self, data: crate::DataContext<'_>) -> crate::Result<String> {
         Ok(data.body)
     }
 
@@ -92,22 +236,45 @@ impl<'a> Renderer for LatexRenderer<'a> {
         children: &[markdown::mdast::Node],
     ) -> crate::Result<String> {
         match name {
-            "Field" => Ok(format!(
-                "\\field{{{}}}{{{}}}{{{}}}{{\n{}\n}}\n",
-                attrs
-                    .get("name")
-                    .map(|s| self.escape(s))
-                    .unwrap_or_default(),
-                attrs
-                    .get("type")
-                    .map(|s| self.escape(s))
-                    .unwrap_or_default(),
-                attrs
-                    .get("type_link")
-                    .map(|s| self.escape(s))
-                    .unwrap_or_default(),
-                self.render_nodes(children)?.trim()
-            )),
+            "Field" => self.nested(|| {
+                Ok(format!(
+                    "\\field{{{}}}{{{}}}{{{}}}{{\n{}\n}}\n",
+                    attrs
+                        .get("name")
+                        .map(|s| self.escape(s))
+                        .unwrap_or_default(),
+                    attrs
+                        .get("type")
+                        .map(|s| self.escape(s))
+                        .unwrap_or_default(),
+                    attrs
+                        .get("type_link")
+                        .map(|s| self.escape(s))
+                        .unwrap_or_default(),
+                    self.render_nodes(children)?.trim()
+                ))
+            }),
+            "Alert" => {
+                let color = match attrs.get("style").map(|s| s.as_str()) {
+                    Some(
+                        style @ ("primary" | "secondary" | "success" | "danger" | "warning"
+                        | "info"),
+                    ) => format!("alert{}", style),
+                    _ => "alertsecondary".to_string(),
+                };
+                let title = attrs
+                    .get("title")
+                    .map(|t| format!(",title={{{}}}", self.escape(t)))
+                    .unwrap_or_default();
+                Ok(format!(
+                    "\\begin{{tcolorbox}}[colback={}!8!white,colframe={},coltitle=white{}]\n{}\n\\end{{tcolorbox}}\n",
+                    color,
+                    color,
+                    title,
+                    self.nested(|| self.render_nodes(children))?.trim()
+                ))
+            }
+            "ExtractBlock" => self.render_nodes(children),
             _ => Ok(String::new()),
         }
     }
@@ -139,33 +306,76 @@ impl<'a> Renderer for LatexRenderer<'a> {
         code: &str,
         lang: Option<String>,
         filepath: Option<PathBuf>,
+        options: &super::CodeOptions,
     ) -> crate::Result<String> {
+        let mut listing_options = vec![if options.line_numbers {
+            "numbers=left".to_string()
+        } else {
+            "numbers=none".to_string()
+        }];
+        if let Some(ref title) = options.title {
+            listing_options.push(format!("title={{{}}}", self.escape(title)));
+        }
+        // Highlighted and changed lines start with a colored bar, drawn
+        // behind the line through an escape to LaTeX.
+        let changes = options.line_changes(code);
+        let color = |i: usize| {
+            if options.is_highlighted(i + 1) {
+                Some("codehighlight")
+            } else {
+                changes
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .map(|change| match change {
+                        "added" => "codeadded",
+                        _ => "coderemoved",
+                    })
+            }
+        };
+        let code = if (0..code.lines().count()).all(|i| color(i).is_none()) {
+            code.to_string()
+        } else {
+            listing_options.push("escapeinside={(*@}{@*)}".to_string());
+            code.lines()
+                .enumerate()
+                .map(|(i, line)| match color(i) {
+                    Some(color) => format!(
+                        "(*@\\makebox[0pt][l]{{\\color{{{}}}\\rule[-0.3em]{{\\linewidth}}{{1.1em}}}}@*){}",
+                        color, line
+                    ),
+                    None => line.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
         Ok(format!(
-            "\\vspace{{8pt}}\\begin{{lstlisting}}[]\n{}\n\\end{{lstlisting}}\\vspace{{3pt}}\n",
+            "\\vspace{{8pt}}\\begin{{lstlisting}}[{}]\n{}\n\\end{{lstlisting}}\\vspace{{3pt}}\n",
+            listing_options.join(", "),
             code
         ))
     }
 
-    fn handle_table(&self, children: &[markdown::mdast::Node]) -> crate::Result<String> {
-        let mut col_count = 0;
-        for child in children {
-            if let markdown::mdast::Node::TableRow(row) = child {
-                col_count = row.children.len();
-                break;
-            }
-        }
+    fn handle_table_rows(
+        &self,
+        header: Option<&markdown::mdast::Node>,
+        rows: &[markdown::mdast::Node],
+    ) -> crate::Result<String> {
+        let col_count = header
+            .or(rows.first())
+            .and_then(|row| row.children())
+            .map(|cells| cells.len())
+            .unwrap_or_default();
 
         let mut out = String::new();
         out.push_str(&format!(
             "\\begin{{tabular}}{{{}}}\n",
             "l ".repeat(col_count).trim()
         ));
-        let mut i = children.iter();
-        let Some(header_row) = i.next() else {
-            return Ok(out);
-        };
-        out.push_str(&self.render_table_header_row(header_row)?);
-        out.push_str(&self.render_nodes(i.as_slice())?);
+        if let Some(header_row) = header {
+            out.push_str(&self.render_table_header_row(header_row)?);
+        }
+        out.push_str(&self.nested(|| self.render_nodes(rows))?);
         out.push_str("\\end{tabular}\n");
         Ok(out)
     }
@@ -186,6 +396,14 @@ impl<'a> Renderer for LatexRenderer<'a> {
         Ok(out)
     }
 
+    fn render_math(&self, math: &str, display: bool) -> crate::Result<String> {
+        if display {
+            Ok(format!("\\[\n{}\n\\]\n", math.trim()))
+        } else {
+            Ok(format!("${}$", math))
+        }
+    }
+
     fn render_link(
         &self,
         url: &str,
@@ -204,6 +422,11 @@ impl<'a> Renderer for LatexRenderer<'a> {
                 out
             ))
         } else {
+            if self.render_context.project.details.latex.link_qr_codes {
+                if let Some(codes) = self.qr_codes.borrow_mut().as_mut() {
+                    codes.push(self.escape(url));
+                }
+            }
             Ok(format!("\\href{{{}}}{{{}}}", url, out))
         }
     }
@@ -227,15 +450,347 @@ impl<'a> Renderer for LatexRenderer<'a> {
         Ok(match depth {
             1 => format!("\\subsection{{{}}}\\label{{sec:{}}}\n", out, url),
             2 => format!("\\subsubsection*{{{}}}\\label{{sec:{}}}\n", out, url),
-            _ => format!("\\subsubsection*{{{}}}\n\n", out),
+            // Deeper headings share the lowest level but keep their label,
+            // so links to them work after headings are demoted
+            _ => format!("\\subsubsection*{{{}}}\\label{{sec:{}}}\n\n", out, url),
         })
     }
 
     fn render_paragraph(&self, children: &[markdown::mdast::Node]) -> crate::Result<String> {
+        // Margin notes break inside boxes and tables, so links there don't
+        // get a QR code
+        let outer = self.nesting.get() == 0 && self.qr_codes.borrow().is_none();
+        if outer {
+            *self.qr_codes.borrow_mut() = Some(vec![]);
+        }
+        let out = self.render_nodes(children);
+        let codes = if outer {
+            self.qr_codes.take().unwrap_or_default()
+        } else {
+            vec![]
+        };
+        let mut out = out?;
+        for url in codes {
+            out.push_str(&format!("\\marginpar{{\\qrcode[height=1.5cm]{{{}}}}}", url));
+        }
+        out.push_str("\n\n");
+        Ok(out)
+    }
+
+    fn render_glossary(&self, terms: &[crate::glossary::GlossaryTerm]) -> crate::Result<String> {
+        if terms.is_empty() {
+            return Ok(String::new());
+        }
+        let mut out = "\\begin{description}\n".to_string();
+        for term in terms {
+            out.push_str(&format!("\\item[{}] ", self.escape(&term.term)));
+            if let Some(ref definition) = term.definition {
+                out.push_str(&self.escape(definition));
+            }
+            // Sections are labelled from their page's url and anchor
+            let references = term
+                .references
+                .iter()
+                .map(|r| {
+                    let label = format!("{}/{}", r.url, r.anchor.as_deref().unwrap_or_default())
+                        .trim_matches('/')
+                        .replace(['/', '#', '_'], "-");
+                    format!("\\pageref{{sec:{}}}", label)
+                })
+                .collect::<Vec<_>>();
+            if !references.is_empty() {
+                out.push_str(&format!(" \\dotfill {}", references.join(", ")));
+            }
+            out.push('\n');
+        }
+        out.push_str("\\end{description}\n");
+        Ok(out)
+    }
+
+    fn render_backlinks(&self, links: &[super::PageLink]) -> crate::Result<String> {
+        if links.is_empty() {
+            return Ok(String::new());
+        }
+        let mut out = "\\begin{itemize}\n".to_string();
+        for link in links {
+            out.push_str(&format!("\\item {}\n", self.escape(&link.title)));
+        }
+        out.push_str("\\end{itemize}\n");
+        Ok(out)
+    }
+
+    fn render_steps(&self, content: String) -> crate::Result<String> {
+        Ok(format!(
+            "\\begin{{enumerate}}\n{}\\end{{enumerate}}\n",
+            content
+        ))
+    }
+
+    fn render_step(
+        &self,
+        number: usize,
+        title: &str,
+        slug: &str,
+        children: &[markdown::mdast::Node],
+    ) -> crate::Result<String> {
+        let label = format!("{}/{}", self.render_context.document.url, slug)
+            .trim_matches('/')
+            .replace(['/', '#', '_'], "-");
+        Ok(format!(
+            "\\item \\textbf{{{}}}\\label{{sec:{}}}\\par\n{}\n",
+            self.escape(title),
+            label,
+            self.render_nodes(children)?.trim()
+        ))
+    }
+
+    fn render_qr_code(
+        &self,
+        url: &str,
+        attrs: std::collections::HashMap<String, String>,
+        children: &[markdown::mdast::Node],
+    ) -> crate::Result<String> {
+        Ok(format!(
+            "\\begin{{center}}\\qrcode[height=3cm]{{{}}}\\par\\small\\url{{{}}}\\end{{center}}\n",
+            self.escape(url),
+            url
+        ))
+    }
+
+    fn render_image(&self, url: &str, _alt: &str, title: Option<String>) -> crate::Result<String> {
+        let Some(path) = self.image_path(url) else {
+            return Ok(String::new());
+        };
+        let graphic = format!(
+            "\\includegraphics[width=\\linewidth,height=0.4\\textheight,keepaspectratio]{{\\detokenize{{{}}}}}",
+            path
+        );
+        let caption = title.filter(|t| !t.trim().is_empty());
+        // Figures float, which isn't possible inside boxes and tables
+        if self.nesting.get() > 0 {
+            return Ok(match caption {
+                Some(caption) => format!("{}\\par\\emph{{{}}}", graphic, self.escape(&caption)),
+                None => graphic,
+            });
+        }
+        Ok(match caption {
+            Some(caption) => format!(
+                "\\begin{{figure}}[h]\n\\centering\n{}\n\\caption{{{}}}\n\\end{{figure}}\n",
+                graphic,
+                self.escape(&caption)
+            ),
+            None => format!("\\begin{{center}}\n{}\n\\end{{center}}\n", graphic),
+        })
+    }
+
+    fn render_gallery(&self, images: &[String], columns: usize) -> crate::Result<String> {
+        let width = 0.95 / columns as f32;
         let mut out = String::new();
-        for child in children {
-            out.push_str(&self.render_node(child)?);
+        out.push_str("\\begin{figure}[h]\n\\centering\n");
+        // Paths are read literally, except for characters LaTeX can't take
+        // in an argument at all. Those images are left out.
+        let images = images
+            .iter()
+            .map(|image| image.trim_start_matches('/'))
+            .filter(|image| !image.contains(['%', '#', '{', '}', '\\']));
+        for (idx, image) in images.enumerate() {
+            out.push_str(&format!(
+                "\\begin{{minipage}}{{{:.2}\\textwidth}}\\includegraphics[width=\\linewidth]{{\\detokenize{{{}}}}}\\end{{minipage}}",
+                width,
+                image
+            ));
+            if (idx + 1) % columns == 0 {
+                out.push_str("\\par\\medskip\n");
+            } else {
+                out.push_str("\\hfill\n");
+            }
         }
-        Ok(format!("{}\n\n", out))
+        out.push_str("\\end{figure}\n");
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{project::tests::project_fixture, RenderContext, Renderer};
+
+    #[test]
+    pub fn test_alert() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.starts_with(
+            "\\begin{tcolorbox}[colback=alertwarning!8!white,colframe=alertwarning,coltitle=white,title={Be careful}]"
+        ));
+        assert!(body.contains("Watch \\textbf{out}"));
+        assert!(body.trim_end().ends_with("\\end{tcolorbox}"));
+    }
+
+    #[test]
+    pub fn test_folder_overview() {
+        let project = project_fixture();
+        let folder = project
+            .root_folder
+            .folders
+            .iter()
+            .find(|f| f.path.root_url() == "/other")
+            .unwrap();
+        let overview = super::LatexRenderer::render_folder_overview(folder).unwrap();
+        assert!(overview.starts_with("\\section*{"));
+        assert!(overview.contains("\\label{folder:other}"));
+        assert!(overview
+            .contains("\\item \\nameref{sec:other-alert} \\dotfill \\pageref{sec:other-alert}"));
+    }
+
+    #[test]
+    pub fn test_section_layout() {
+        let project = project_fixture();
+        let mut doc = project
+            .get_document_for_url("/other/alert")
+            .unwrap()
+            .clone();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, &doc));
+        let section = renderer.render_section().unwrap();
+        assert!(section.starts_with("\\section{"));
+        assert!(section.ends_with("\\pagebreak\n"));
+
+        doc.frontmatter.latex = crate::LatexLayout {
+            landscape: true,
+            columns: 2,
+            newpage: false,
+        };
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, &doc));
+        let section = renderer.render_section().unwrap();
+        assert!(section.starts_with("\\begin{landscape}\n\\section{"));
+        assert!(section.contains("\\begin{multicols}{2}\n"));
+        assert!(section.ends_with("\\end{multicols}\n\\end{landscape}\n"));
+    }
+
+    #[test]
+    pub fn test_link_qr_codes() {
+        let mut project = project_fixture();
+        let doc = project
+            .get_document_for_url("/elements/external_link")
+            .unwrap()
+            .clone();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, &doc));
+        assert!(!renderer.render_body().unwrap().contains("qrcode"));
+
+        project.details.latex.link_qr_codes = true;
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, &doc));
+        assert_eq!(
+            renderer.render_body().unwrap().trim(),
+            "\\href{https://example.com}{Test}\\marginpar{\\qrcode[height=1.5cm]{https://example.com}}"
+        );
+
+        // Margin notes can't be placed inside boxes, tables, or headings
+        let doc = project
+            .get_document_for_url("/elements/nested_external_link")
+            .unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert_eq!(body.matches("\\href{https://example.com}").count(), 4);
+        assert_eq!(body.matches("\\marginpar").count(), 1);
+        assert!(body.contains("\\href{https://example.com}{Outside}\\marginpar"));
+    }
+
+    #[test]
+    pub fn test_heading_labels() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/pages/large").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(
+            "\\subsubsection*{Third level depth}\\label{sec:pages-large-third-level-depth}"
+        ));
+    }
+
+    #[test]
+    pub fn test_image_captions() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/attachments").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains("\\centering\n\\includegraphics[width=\\linewidth,height=0.4\\textheight,keepaspectratio]{\\detokenize{other/attachments.assets/diagram.png}}\n\\caption{System overview}\n\\end{figure}"));
+
+        // Missing images are left out
+        let doc = project.get_document_for_url("/other/image").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        assert!(!renderer.render_body().unwrap().contains("includegraphics"));
+    }
+
+    #[test]
+    pub fn test_steps() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/steps").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.starts_with("\\begin{enumerate}\n\\item \\textbf{Install codex}\\label{sec:other-steps-step-install-codex}"));
+        assert!(body.contains("\\item \\textbf{}\\label{sec:other-steps-step-2}"));
+    }
+
+    #[test]
+    pub fn test_json_schema_descriptions() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/json_schema").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains("\\field{id}{String}{}"));
+        assert!(body.contains("\\textbf{\\color{magenta}id}"));
+        assert!(body.contains("\\hyperref[sec:accounts]{accounts}"));
+        assert!(body.contains("Must be < 10"));
+    }
+
+    #[test]
+    pub fn test_csv_table_options() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/csv_options").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(
+            "\\begin{tabular}{l l}\n\\textbf{position} & \\textbf{name} \\\\\n\\hline\\vspace{2pt}\nengineer & alice \\\\\nengineer & bob \\\\\n\\end{tabular}"
+        ));
+        assert!(body.contains(
+            "\\begin{tabular}{l l}\nalice & engineer \\\\\nbob & engineer \\\\\n\\end{tabular}"
+        ));
+    }
+
+    #[test]
+    pub fn test_code_options() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/code_options").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(
+            "\\begin{lstlisting}[numbers=left, title={src/main.rs}, escapeinside={(*@}{@*)}]"
+        ));
+        assert!(body.contains("fn main() {\n(*@\\makebox[0pt][l]"));
+        assert!(body.contains("\n\n(*@\\makebox[0pt][l]{\\color{codehighlight}\\rule[-0.3em]{\\linewidth}{1.1em}}@*)marked line"));
+    }
+
+    #[test]
+    pub fn test_gallery() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/gallery").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(
+            body.contains("\\includegraphics[width=\\linewidth]{\\detokenize{gallery/one.png}}")
+        );
+        assert!(body.contains("{\\detokenize{gallery/release_1.0 & notes.png}}"));
+        assert!(!body.contains("100%"));
+    }
+
+    #[test]
+    pub fn test_diff() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/diff").unwrap();
+        let renderer = super::LatexRenderer::new(RenderContext::new(&project, doc));
+        let body = renderer.render_body().unwrap();
+        assert!(body.contains(
+            "\\color{coderemoved}\\rule[-0.3em]{\\linewidth}{1.1em}}@*)-    println!(\"Hello\");"
+        ));
+        assert!(body.contains("\\color{codeadded}\\rule[-0.3em]{\\linewidth}{1.1em}}@*)+    println!(\"Hello, world\");"));
     }
 }
@@ -1,24 +1,47 @@
 #![allow(unused_variables)]
 mod html;
-pub use html::HtmlRenderer;
+pub use html::{HtmlRenderer, INCLUDES_FOLDER};
 mod latex;
 pub use latex::LatexRenderer;
+mod text;
+pub use text::TextRenderer;
 
-use crate::{assets::CodexPath, error::Result, Document, FrontMatter, Project};
+use crate::{
+    assets::CodexPath,
+    error::Result,
+    events::{Event, WarningKind},
+    Document, FrontMatter, Project,
+};
 use markdown::mdast::{
     AttributeContent, AttributeValue, MdxJsxAttribute, Node, TableCell, TableRow,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub struct RenderContext<'a> {
     pub project: &'a Project,
     pub document: &'a Document,
+    /// Whether the document is rendered as a fragment for embedding
+    pub fragment: bool,
 }
 
 impl<'a> RenderContext<'a> {
     pub fn new(project: &'a Project, document: &'a Document) -> Self {
-        Self { project, document }
+        Self {
+            project,
+            document,
+            fragment: false,
+        }
+    }
+
+    /// A context for rendering the document as a fragment, see
+    /// [`HtmlRenderer::render_fragment`].
+    pub fn fragment(project: &'a Project, document: &'a Document) -> Self {
+        Self {
+            project,
+            document,
+            fragment: true,
+        }
     }
 }
 
@@ -29,21 +52,46 @@ pub struct TocEntry {
     pub slug: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct SiteMapFolder {
+/// The pages and folders in the navigation menu. Pages borrow the project's
+/// documents instead of copying them.
+#[derive(Debug, Serialize, Default)]
+pub struct SiteMapFolder<'a> {
     pub name: String,
-    pub folders: Vec<SiteMapFolder>,
-    pub pages: Vec<Document>,
+    pub folders: Vec<SiteMapFolder<'a>>,
+    pub pages: Vec<&'a Document>,
     pub menu_position: i32,
 }
 
-impl From<&crate::Folder> for SiteMapFolder {
-    fn from(folder: &crate::Folder) -> Self {
+impl<'a> From<&'a crate::Folder> for SiteMapFolder<'a> {
+    fn from(folder: &'a crate::Folder) -> Self {
+        SiteMapFolder::filtered(folder, &|_| true)
+    }
+}
+
+impl<'a> SiteMapFolder<'a> {
+    /// The site map shown on pages in a language. Projects with a top level
+    /// folder for the language use that folder, otherwise the pages in the
+    /// language are listed.
+    pub fn for_language(project: &'a Project, language: &str) -> Self {
+        if project.details.translations.is_empty() {
+            return (&project.root_folder).into();
+        }
+        let root = &project.root_folder;
+        let folder = root
+            .folders
+            .iter()
+            .find(|f| f.name == language)
+            .unwrap_or(root);
+        SiteMapFolder::filtered(folder, &|d| d.language == language)
+    }
+
+    fn filtered(folder: &'a crate::Folder, include: &dyn Fn(&Document) -> bool) -> Self {
         let mut pages = folder
             .documents
             .iter()
-            .filter(|d| !d.frontmatter.menu_exclude)
-            .cloned()
+            .filter(|d| {
+                !d.frontmatter.menu_exclude && d.has_format(crate::OutputTarget::Html) && include(d)
+            })
             .collect::<Vec<_>>();
 
         pages.sort_by_key(|p| (p.frontmatter.menu_position, p.frontmatter.title.clone()));
@@ -51,8 +99,17 @@ impl From<&crate::Folder> for SiteMapFolder {
         let mut folders = folder
             .folders
             .iter()
-            .filter(|f| !f.details.menu_exclude)
-            .map(SiteMapFolder::from)
+            .filter(|f| {
+                !f.details.menu_exclude
+                    && f.details
+                        .formats
+                        .as_ref()
+                        .is_none_or(|formats| formats.contains(&crate::OutputTarget::Html))
+                    // Folders holding only other languages' documents
+                    && (f.iter_all_documents().next().is_none()
+                        || f.iter_all_documents().any(include))
+            })
+            .map(|f| SiteMapFolder::filtered(f, include))
             .collect::<Vec<_>>();
 
         folders.sort_by_key(|f| (f.menu_position, f.name.clone()));
@@ -64,83 +121,466 @@ impl From<&crate::Folder> for SiteMapFolder {
             name: folder.get_name(),
         }
     }
+
+    /// Iterate over all pages in the order they appear in the navigation menu.
+    pub fn iter_pages(&self) -> Box<dyn Iterator<Item = &'a Document> + '_> {
+        Box::new(
+            self.pages
+                .iter()
+                .copied()
+                .chain(self.folders.iter().flat_map(|f| f.iter_pages())),
+        )
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct DataContext {
+/// A step in the breadcrumb trail leading to a page, from the project root
+/// to the page itself.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Breadcrumb {
+    pub name: String,
+    /// The folder's index page, folders without one have no url
+    pub url: Option<String>,
+}
+
+/// A link to another page used for previous and next page navigation.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PageLink {
+    pub title: String,
+    pub url: String,
+}
+
+impl From<&Document> for PageLink {
+    fn from(document: &Document) -> Self {
+        Self {
+            title: document.frontmatter.title.clone(),
+            url: document.url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct DataContext<'a> {
     pub document: FrontMatter,
-    pub sitemap: SiteMapFolder,
+    /// The pages in the navigation menu, only set when the menu is rendered
+    pub sitemap: SiteMapFolder<'a>,
     pub body: String,
     pub project: crate::ProjectDetails,
     pub toc: Vec<TocEntry>,
     pub modified: Option<String>,
+    /// The last commit that changed the document
+    pub revision: Option<crate::history::Revision>,
+    /// The url of the editor for the document's file in the repository
+    pub edit_url: Option<String>,
+    /// The fully qualified url of the page, when the project's `site_url`
+    /// is set
+    pub canonical_url: Option<String>,
+    /// The summary of the document for search engines and link previews
+    pub description: Option<String>,
+    /// The image shown in link previews
+    pub image: Option<String>,
+    /// The kind of card shown in link previews
+    pub social_card: Option<crate::SocialCard>,
+    /// JSON-LD describing the page to search engines, such as the
+    /// questions of a FAQ page
+    pub structured_data: Option<String>,
+    /// The authors of the commits changing the document, when enabled
+    pub contributors: Option<Vec<crate::history::Contributor>>,
     pub current_url: String,
+    pub previous_page: Option<PageLink>,
+    pub next_page: Option<PageLink>,
+    /// The URL of the printable view for the document's folder
+    pub print_url: Option<String>,
+    /// The files used to generate parts of the document
+    pub sources: Vec<crate::manifest::SourceFile>,
+    /// The url the `asset` helper links static files from, when it isn't
+    /// the base url
+    pub asset_url: Option<String>,
+    /// The folders containing the document, ending with the document
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// The pages linking to the document
+    pub backlinks: Vec<PageLink>,
+    /// The language the document is written in
+    pub language: String,
+    /// The interface text in the document's language
+    pub messages: BTreeMap<String, String>,
+    /// HTML from the project's `inject` option added at the end of the head
+    pub head_html: String,
+    /// HTML from the project's `inject` option added at the end of the body
+    pub body_html: String,
+    /// The stylesheet coloring highlighted code in the light and dark modes
+    pub highlight_css: String,
+    /// The navigation menu, or the directive including it
+    pub navigation: String,
+    /// The page footer, or the directive including it
+    pub footer: String,
+    /// The versions of the document in other languages
+    pub translations: Vec<crate::i18n::TranslationLink>,
+}
+
+/// A page rendered into a folder's printable view.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PrintPage {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct PrintContext {
+    pub project: crate::ProjectDetails,
+    pub name: String,
+    pub pages: Vec<PrintPage>,
+    /// The interface text, in the default language
+    pub messages: BTreeMap<String, String>,
+    /// HTML added at the end of the head
+    pub head_html: String,
+    /// HTML added at the end of the body
+    pub body_html: String,
+    /// The stylesheet coloring highlighted code
+    pub highlight_css: String,
+}
+
+/// The formats a document can be rendered to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A complete HTML page using the project's article template
+    Html,
+    /// A LaTeX fragment for inclusion in the project's PDF
+    Latex,
+    /// Plain text, with wrapped paragraphs and ASCII tables
+    Text,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct CodeContext {
-    lines: Vec<String>,
+    lines: Vec<CodeLine>,
     lang: String,
+    line_numbers: bool,
+    title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CodeLine {
+    number: usize,
+    content: String,
+    highlighted: bool,
+    /// `added` or `removed` for the changed lines of a diff
+    change: Option<String>,
+}
+
+/// Display options for a code block, read from the meta string of a fenced
+/// code block or the attributes of a CodeFile component.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeOptions {
+    /// Inclusive ranges of line numbers to highlight, starting at 1
+    pub highlight: Vec<(usize, usize)>,
+    /// Display a line number gutter
+    pub line_numbers: bool,
+    /// A caption shown above the code, usually a file name
+    pub title: Option<String>,
+    /// Treat the code as a diff, marking lines that start with `+` or `-` as
+    /// added or removed
+    pub diff: bool,
 }
 
+impl CodeOptions {
+    /// Parse the meta string of a fenced code block, such as
+    /// `{1,3-5} showLineNumbers title="src/main.rs"`.
+    pub fn from_meta(meta: &str) -> Self {
+        let mut options = Self::default();
+        for token in meta_tokens(meta) {
+            if let Some(ranges) = token.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+                options.highlight.extend(parse_line_ranges(ranges));
+            } else if token == "showLineNumbers" {
+                options.line_numbers = true;
+            } else if let Some(title) = token.strip_prefix("title=") {
+                options.title = Some(title.trim_matches('"').to_string());
+            }
+        }
+        options
+    }
+
+    /// Read the `highlight`, `line_numbers`, and `title` attributes of a
+    /// component.
+    pub fn from_attrs(attrs: &HashMap<String, String>) -> Self {
+        Self {
+            highlight: attrs
+                .get("highlight")
+                .map(|h| parse_line_ranges(h))
+                .unwrap_or_default(),
+            line_numbers: attrs.get("line_numbers").is_some_and(|v| v == "true"),
+            title: attrs.get("title").cloned(),
+            diff: false,
+        }
+    }
+
+    /// Pair the rendered lines of `code` with their display options.
+    fn build_lines(&self, code: &str, lines: Vec<String>) -> Vec<CodeLine> {
+        let changes = self.line_changes(code);
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, content)| CodeLine {
+                number: i + 1,
+                highlighted: self.is_highlighted(i + 1),
+                change: changes.get(i).copied().flatten().map(|c| c.to_string()),
+                content,
+            })
+            .collect()
+    }
+
+    /// Whether a line, numbered from 1, is highlighted.
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlight
+            .iter()
+            .any(|(from, to)| (*from..=*to).contains(&line))
+    }
+
+    /// Whether each line of a diff was `added` or `removed`. File headers,
+    /// a `--- ` line directly followed by a `+++ ` line, are neither, so
+    /// changed lines such as `++i;` are still marked.
+    fn line_changes(&self, code: &str) -> Vec<Option<&'static str>> {
+        let lines = code.lines().collect::<Vec<_>>();
+        let mut changes = vec![None; lines.len()];
+        if !self.diff {
+            return changes;
+        }
+        let mut i = 0;
+        while i < lines.len() {
+            if lines[i].starts_with("--- ")
+                && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ "))
+            {
+                i += 2;
+                continue;
+            }
+            if lines[i].starts_with('+') {
+                changes[i] = Some("added");
+            } else if lines[i].starts_with('-') {
+                changes[i] = Some("removed");
+            }
+            i += 1;
+        }
+        changes
+    }
+}
+
+/// Split a code block's meta string on whitespace, keeping quoted values
+/// together.
+fn meta_tokens(meta: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in meta.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Parse a comma separated list of line numbers and ranges, such as
+/// `1,3-5`, into inclusive ranges. Invalid entries are ignored.
+fn parse_line_ranges(ranges: &str) -> Vec<(usize, usize)> {
+    let mut lines = vec![];
+    for range in ranges.split(',').map(|r| r.trim()) {
+        let (from, to) = range.split_once('-').unwrap_or((range, range));
+        if let (Ok(from), Ok(to)) = (from.trim().parse::<usize>(), to.trim().parse::<usize>()) {
+            lines.push((from, to));
+        }
+    }
+    lines
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct GalleryContext {
+    images: Vec<GalleryImage>,
+    column_width: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct GalleryImage {
+    url: String,
+    /// Alt text taken from the file name, such as `Login screen` for
+    /// `login-screen.png`
+    alt: String,
+}
+
+impl GalleryImage {
+    fn new(url: String, image: &str) -> Self {
+        let alt = std::path::Path::new(image)
+            .file_stem()
+            .map(|s| s.to_string_lossy().replace(['-', '_'], " "))
+            .unwrap_or_default();
+        let mut chars = alt.trim().chars();
+        let alt = chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect())
+            .unwrap_or_default();
+        Self { url, alt }
+    }
+}
+
+/// File extensions picked up when a gallery is built from a folder.
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "svg", "webp"];
+
 pub trait Renderer {
-    fn get_context(&self) -> &RenderContext;
-    fn finalize_render(&self, data: DataContext) -> Result<String>;
+    fn get_context(&self) -> &RenderContext<'_>;
+    fn finalize_render(&self, data: DataContext<'_>) -> Result<String>;
     fn render_body(&self) -> Result<String> {
         let ast = self.parse(&self.get_context().document.file_path)?;
         let body = self.render_node(&ast)?;
         Ok(body)
     }
 
-    fn render(&self) -> Result<String> {
+    /// Render the document body along with any content generated from the
+    /// document's front matter.
+    fn render_article(&self) -> Result<String> {
         let ctx = self.get_context();
-        let sitemap = (&ctx.project.root_folder).into();
-
-        let mut data = DataContext {
-            body: self.render_body()?,
-            document: ctx.document.frontmatter.clone(),
-            sitemap,
-            current_url: ctx.document.url.clone(),
-            project: ctx.project.details.clone(),
-            toc: self
-                .parse(&ctx.document.file_path)?
-                .children()
-                .map(|v| self.toc(v))
-                .unwrap_or_default(),
-            modified: self.last_modified().ok(),
-        };
+        let mut body = self.render_body()?;
 
         if let Some(ref schema_file) = ctx.document.frontmatter.json_schema {
-            data.body.push_str(&self.render_heading(
+            body.push_str(&self.render_heading(
                 1,
                 &[Node::Text(markdown::mdast::Text {
-                    value: "Fields".to_string(),
+                    value: crate::i18n::message(
+                        &ctx.project.details,
+                        &ctx.document.language,
+                        "fields",
+                    ),
                     position: Default::default(),
                 })],
             )?);
 
-            data.body.push_str(&self.handle_jsx_element(
+            body.push_str(&self.handle_jsx_element(
                 "JsonSchemaFields",
                 HashMap::from([("file".to_string(), schema_file.to_string())]),
                 &[],
             )?);
-            data.body.push_str(&self.render_heading(
+            body.push_str(&self.render_heading(
                 1,
                 &[Node::Text(markdown::mdast::Text {
-                    value: "Example".to_string(),
+                    value: crate::i18n::message(
+                        &ctx.project.details,
+                        &ctx.document.language,
+                        "example",
+                    ),
                     position: Default::default(),
                 })],
             )?);
-            data.body.push_str(&self.handle_jsx_element(
+            body.push_str(&self.handle_jsx_element(
                 "JsonSchemaExample",
                 HashMap::from([("file".to_string(), schema_file.to_string())]),
                 &[],
             )?);
         }
 
+        Ok(body)
+    }
+
+    fn render(&self) -> Result<String> {
+        let data = self.page_data()?;
         self.finalize_render(data)
     }
 
+    /// The data the page templates are rendered with.
+    fn page_data(&self) -> Result<DataContext<'_>> {
+        let ctx = self.get_context();
+        let sitemap = SiteMapFolder::for_language(ctx.project, &ctx.document.language);
+        let (previous_page, next_page) = self.page_neighbours(&sitemap);
+        let details = &ctx.project.details;
+        let (head_html, body_html) = ctx.project.injected_html()?;
+        let image = ctx
+            .document
+            .image(ctx.project)
+            .map(|image| details.absolute_url(&image).unwrap_or(image));
+
+        let data = DataContext {
+            body: self.render_article()?,
+            document: ctx.document.frontmatter.clone(),
+            current_url: ctx.document.url.clone(),
+            project: ctx.project.details.clone(),
+            toc: self
+                .parse(&ctx.document.file_path)?
+                .children()
+                .map(|v| self.toc(v))
+                .unwrap_or_default(),
+            modified: self.last_modified().ok(),
+            revision: ctx.project.revision(ctx.document).cloned(),
+            edit_url: ctx
+                .project
+                .details
+                .edit_url(&ctx.document.file_path.relative_path),
+            contributors: ctx.project.contributors(ctx.document),
+            canonical_url: details.absolute_url(&ctx.document.url),
+            head_html,
+            body_html,
+            highlight_css: html::highlight_css(&details.theme)?,
+            description: ctx.document.description(),
+            social_card: Some(
+                ctx.document
+                    .frontmatter
+                    .social_card
+                    .unwrap_or(if image.is_some() {
+                        crate::SocialCard::SummaryLargeImage
+                    } else {
+                        crate::SocialCard::Summary
+                    }),
+            ),
+            image,
+            structured_data: match ctx.document.frontmatter.page_type {
+                crate::PageType::Faq => Some(
+                    crate::faq::structured_data(&crate::faq::entries(ctx.project, ctx.document)?)
+                        .to_string()
+                        // Keep the answers from closing the script tag
+                        .replace("</", "<\\/"),
+                ),
+                crate::PageType::Article | crate::PageType::Runbook => None,
+            },
+            previous_page,
+            next_page,
+            print_url: ctx
+                .project
+                .details
+                .print_views
+                .then(|| ctx.document.file_path.folder_path().print_url()),
+            sources: crate::manifest::document_sources(ctx.project, ctx.document)?,
+            asset_url: None,
+            breadcrumbs: self.breadcrumbs(),
+            backlinks: ctx
+                .project
+                .backlinks(ctx.document)?
+                .into_iter()
+                .map(PageLink::from)
+                .collect(),
+            language: ctx.document.language.clone(),
+            messages: crate::i18n::messages(&ctx.project.details, &ctx.document.language),
+            translations: crate::i18n::translations_of(ctx.project, ctx.document)
+                .into_iter()
+                .map(|d| crate::i18n::TranslationLink {
+                    language: d.language.clone(),
+                    title: d.frontmatter.title.clone(),
+                    url: d.url.clone(),
+                })
+                .collect(),
+            sitemap: SiteMapFolder::default(),
+            navigation: String::new(),
+            footer: String::new(),
+        };
+        Ok(data)
+    }
+
     fn render_nodes(&self, nodes: &[Node]) -> Result<String> {
         nodes.iter().try_fold(String::new(), |acc, child| {
             self.render_node(child)
@@ -192,7 +632,7 @@ pub trait Renderer {
         Ok(String::new())
     }
 
-    fn render_image(&self, url: &str, title: Option<String>) -> Result<String> {
+    fn render_image(&self, url: &str, alt: &str, title: Option<String>) -> Result<String> {
         Ok(String::new())
     }
 
@@ -204,11 +644,23 @@ pub trait Renderer {
         Ok(text.to_string())
     }
 
+    /// Render raw HTML, which is only parsed when `markdown.html` is enabled.
+    fn render_html(&self, html: &str) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Render a math expression, which is only parsed when `markdown.math` is
+    /// enabled. Display math is a block of its own rather than inline.
+    fn render_math(&self, math: &str, display: bool) -> Result<String> {
+        Ok(String::new())
+    }
+
     fn render_code(
         &self,
         code: &str,
         lang: Option<String>,
         filepath: Option<std::path::PathBuf>,
+        options: &CodeOptions,
     ) -> Result<String> {
         Ok(String::new())
     }
@@ -242,6 +694,174 @@ pub trait Renderer {
         Ok(String::new())
     }
 
+    /// Render a grid of images. Image URLs are relative to the project's
+    /// static folder and do not include the base url.
+    fn render_gallery(&self, images: &[String], columns: usize) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Render a list of the pages linking to the document.
+    fn render_backlinks(&self, links: &[PageLink]) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Render the glossary, with links to the sections using each term.
+    fn render_glossary(&self, terms: &[crate::glossary::GlossaryTerm]) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Wrap the rendered steps of a procedure.
+    fn render_steps(&self, content: String) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Render a single numbered step of a procedure.
+    fn render_step(
+        &self,
+        number: usize,
+        title: &str,
+        slug: &str,
+        children: &[Node],
+    ) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Render a question of a FAQ page and its answer. Formats without
+    /// collapsible content keep the question as a heading.
+    fn render_question(&self, slug: &str, question: &[Node], answer: &[Node]) -> Result<String> {
+        Ok(format!(
+            "{}{}",
+            self.render_heading(2, question)?,
+            self.render_nodes(answer)?
+        ))
+    }
+
+    /// Render the content of a FAQ page, with each level two heading and
+    /// the content below it as a question.
+    fn handle_faq(&self, nodes: &[Node]) -> Result<String> {
+        crate::faq::parts(nodes)
+            .into_iter()
+            .try_fold(String::new(), |acc, part| {
+                let html = match part {
+                    crate::faq::FaqPart::Content(nodes) => self.render_nodes(nodes)?,
+                    crate::faq::FaqPart::Question(heading, answer) => {
+                        let slug = self.slug(&self.get_text(&heading.children).unwrap_or_default());
+                        self.render_question(&slug, &heading.children, answer)?
+                    }
+                };
+                Ok(format!("{}{}", acc, html))
+            })
+    }
+
+    /// Render a QR code linking to the given url.
+    fn render_qr_code(
+        &self,
+        url: &str,
+        attrs: HashMap<String, String>,
+        children: &[Node],
+    ) -> Result<String> {
+        Ok(String::new())
+    }
+
+    /// Parse a description from a schema file as markdown. Descriptions that
+    /// can't be parsed, such as those containing an unclosed `<`, are kept as
+    /// plain text.
+    fn parse_description(&self, text: &str) -> Vec<Node> {
+        self.parse_ast(text)
+            .ok()
+            .and_then(|ast| ast.children().cloned())
+            .unwrap_or_else(|| {
+                vec![Node::Paragraph(markdown::mdast::Paragraph {
+                    children: vec![Node::Text(markdown::mdast::Text {
+                        value: text.to_string(),
+                        position: None,
+                    })],
+                    position: None,
+                })]
+            })
+    }
+
+    /// Render the services, messages, and enums of a protobuf file. Each
+    /// entry gets a heading and its members are rendered as fields.
+    fn render_proto_schema(&self, proto: &crate::proto_schema::ProtoFile) -> Result<String> {
+        let heading = |kind: &str, name: &str, text: &str| -> Result<String> {
+            let mut output = self.render_heading(
+                2,
+                &[Node::Text(markdown::mdast::Text {
+                    value: format!("{} {}", kind, name),
+                    position: None,
+                })],
+            )?;
+            output.push_str(&self.render_nodes(&self.parse_description(text))?);
+            Ok(output)
+        };
+        let field = |name: &str, data_type: String, required: bool, text: &str| {
+            let mut attrs = HashMap::from([
+                ("name".to_string(), name.to_string()),
+                ("type".to_string(), data_type.clone()),
+            ]);
+            if required {
+                attrs.insert("required".to_string(), "true".to_string());
+            }
+            let type_name = data_type
+                .rsplit(' ')
+                .next()
+                .unwrap_or_default()
+                .trim_matches(['(', ')']);
+            if let Some(heading) = proto.heading_for(type_name) {
+                attrs.insert("type_link".to_string(), format!("#{}", self.slug(&heading)));
+            }
+            self.render_jsx_element("Field", attrs, &self.parse_description(text))
+        };
+
+        let mut output = String::new();
+        for service in proto.services.iter() {
+            output.push_str(&heading("Service", &service.name, &service.description)?);
+            for method in service.methods.iter() {
+                let stream = |streaming: bool| if streaming { "stream " } else { "" };
+                let signature = format!(
+                    "({}{}) returns ({}{})",
+                    stream(method.client_streaming),
+                    method.input,
+                    stream(method.server_streaming),
+                    method.output
+                );
+                output.push_str(&field(&method.name, signature, false, &method.description)?);
+            }
+        }
+        for message in proto.messages.iter() {
+            output.push_str(&heading("Message", &message.name, &message.description)?);
+            for f in message.fields.iter() {
+                let data_type = match &f.label {
+                    Some(label) if label == "repeated" => format!("repeated {}", f.data_type),
+                    _ => f.data_type.clone(),
+                };
+                let mut text = f.description.clone();
+                if let Some(ref oneof) = f.oneof {
+                    text.push_str(&format!("\n\nPart of the `{}` oneof.", oneof));
+                }
+                output.push_str(&field(
+                    &f.name,
+                    data_type,
+                    f.label.as_deref() == Some("required"),
+                    &text,
+                )?);
+            }
+        }
+        for proto_enum in proto.enums.iter() {
+            output.push_str(&heading("Enum", &proto_enum.name, &proto_enum.description)?);
+            for value in proto_enum.values.iter() {
+                output.push_str(&field(
+                    &value.name,
+                    value.number.clone(),
+                    false,
+                    &value.description,
+                )?);
+            }
+        }
+        Ok(output)
+    }
+
     fn handle_jsx_element(
         &self,
         name: &str,
@@ -256,22 +876,40 @@ pub trait Renderer {
                         .ok_or_else(|| crate::Error::new("No file specified"))?,
                 );
                 let data = schema_filename.read()?;
-                let fields = crate::json_schema::parse_schema(&data)?;
+                let fields = crate::json_schema::parse_schema(&data, &schema_filename)?;
                 let mut output = String::new();
                 for field in fields.into_iter() {
                     let mut attrs = HashMap::from([
                         ("name".to_string(), field.name),
                         ("type".to_string(), field.data_type),
-                        ("children".to_string(), field.children),
                         ("required".to_string(), field.required.to_string()),
                     ]);
                     if field.deprecated {
                         attrs.insert("deprecated".to_string(), "true".to_string());
                     }
-                    output.push_str(&self.render_jsx_element("Field", attrs, children)?);
+                    if let Some(message) = field.deprecated_message {
+                        attrs.insert("deprecated_message".to_string(), message);
+                    }
+                    if let Some(default) = field.default {
+                        attrs.insert("default".to_string(), default);
+                    }
+                    output.push_str(&self.render_jsx_element(
+                        "Field",
+                        attrs,
+                        &self.parse_description(&field.children),
+                    )?);
                 }
                 Ok(output)
             }
+            "ProtoSchema" => {
+                let proto_filename = self.get_context().document.file_path.new_path(
+                    attrs
+                        .get("file")
+                        .ok_or_else(|| crate::Error::new("No file specified"))?,
+                );
+                let proto = crate::proto_schema::parse_proto(&proto_filename.read()?)?;
+                self.render_proto_schema(&proto)
+            }
             "JsonSchemaExample" => {
                 let schema_filename = self.get_context().document.file_path.new_path(
                     attrs
@@ -279,10 +917,22 @@ pub trait Renderer {
                         .ok_or_else(|| crate::Error::new("No file specified"))?,
                 );
                 let schema_str = schema_filename.read()?;
-                let content = crate::json_schema::build_example(&schema_str)?;
+                let content = crate::json_schema::build_example(&schema_str, &schema_filename)?;
 
-                self.render_code(&content, Some("JSON".to_string()), None)
+                self.render_code(
+                    &content,
+                    Some("JSON".to_string()),
+                    None,
+                    &CodeOptions::default(),
+                )
             }
+            "Image" => self.render_image(
+                attrs
+                    .get("src")
+                    .ok_or_else(|| crate::Error::new("No src specified"))?,
+                attrs.get("alt").map(|a| a.as_str()).unwrap_or_default(),
+                attrs.get("title").cloned(),
+            ),
             "CodeFile" => {
                 let source_file_path = self.get_context().document.file_path.new_path(
                     attrs
@@ -290,11 +940,31 @@ pub trait Renderer {
                         .ok_or_else(|| crate::Error::new("No file specified"))?,
                 );
 
-                self.render_code(
-                    &String::from_utf8(source_file_path.read()?.to_vec())?,
-                    None,
-                    Some(source_file_path.disk_path()),
-                )
+                let content = String::from_utf8(source_file_path.read()?.to_vec())?;
+                let content = code_excerpt(
+                    &content,
+                    attrs.get("lines").map(|l| l.as_str()),
+                    attrs.get("region").map(|r| r.as_str()),
+                )?;
+                let mut options = CodeOptions::from_attrs(&attrs);
+
+                if let Some(original) = attrs.get("diff") {
+                    let original = self.get_context().document.file_path.new_path(original);
+                    let original = code_excerpt(
+                        &String::from_utf8(original.read()?.to_vec())?,
+                        attrs.get("lines").map(|l| l.as_str()),
+                        attrs.get("region").map(|r| r.as_str()),
+                    )?;
+                    options.diff = true;
+                    return self.render_code(
+                        &diff_lines(&original, &content),
+                        Some("diff".to_string()),
+                        None,
+                        &options,
+                    );
+                }
+
+                self.render_code(&content, None, Some(source_file_path.disk_path()), &options)
             }
             "CsvTable" => {
                 let csv_file_name = self.get_context().document.file_path.new_path(
@@ -302,42 +972,257 @@ pub trait Renderer {
                         .get("file")
                         .ok_or_else(|| crate::Error::new("No file specified"))?,
                 );
+                let delimiter = match attrs.get("delimiter").map(|d| d.as_str()) {
+                    None => b',',
+                    Some("tab") | Some("\\t") => b'\t',
+                    Some(d) if d.len() == 1 => d.as_bytes()[0],
+                    Some(d) => {
+                        return Err(crate::Error::new(format!(
+                            "Invalid delimiter: {}, delimiters must be a single character",
+                            d
+                        )))
+                    }
+                };
+                let headers = attrs.get("headers").map(|h| h != "false").unwrap_or(true);
 
                 let mut reader = csv::ReaderBuilder::new()
                     .has_headers(false)
+                    .delimiter(delimiter)
+                    .flexible(true)
                     .from_path(csv_file_name.disk_path())?;
-
-                let rows = reader
+                let mut records = reader
                     .records()
-                    .map(|r| {
+                    .map(|r| Ok(r?.iter().map(|c| c.to_string()).collect::<Vec<_>>()))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter();
+                let header = if headers { records.next() } else { None };
+                let mut records = records.collect::<Vec<_>>();
+                if let Some(limit) = attrs.get("limit") {
+                    let limit = limit
+                        .parse::<usize>()
+                        .map_err(|_| crate::Error::new(format!("Invalid limit: {}", limit)))?;
+                    records.truncate(limit);
+                }
+
+                // Columns are selected by header name or by their position,
+                // starting at 1.
+                let columns = attrs
+                    .get("columns")
+                    .map(|columns| {
+                        columns
+                            .split(',')
+                            .map(|column| {
+                                let column = column.trim();
+                                header
+                                    .as_ref()
+                                    .and_then(|h| {
+                                        h.iter().position(|c| c.trim().eq_ignore_ascii_case(column))
+                                    })
+                                    .or_else(|| {
+                                        column
+                                            .parse::<usize>()
+                                            .ok()
+                                            .filter(|i| *i > 0)
+                                            .map(|i| i - 1)
+                                    })
+                                    .ok_or_else(|| {
+                                        crate::Error::new(format!("Column not found: {}", column))
+                                    })
+                            })
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .transpose()?;
+
+                let to_row = |record: Vec<String>| {
+                    let cells = match columns {
+                        Some(ref columns) => columns
+                            .iter()
+                            .map(|i| record.get(*i).cloned().unwrap_or_default())
+                            .collect(),
+                        None => record,
+                    };
+                    Node::TableRow(TableRow {
+                        children: cells
+                            .into_iter()
+                            .map(|c| {
+                                Node::TableCell(TableCell {
+                                    children: vec![Node::Text(markdown::mdast::Text {
+                                        value: c,
+                                        position: Default::default(),
+                                    })],
+                                    position: Default::default(),
+                                })
+                            })
+                            .collect::<Vec<_>>(),
+                        position: Default::default(),
+                    })
+                };
+
+                let header = header.map(to_row);
+                let rows = records.into_iter().map(to_row).collect::<Vec<_>>();
+                self.handle_table_rows(header.as_ref(), &rows)
+            }
+            "DataTable" => {
+                let data_file_name = self.get_context().document.file_path.new_path(
+                    attrs
+                        .get("file")
+                        .ok_or_else(|| crate::Error::new("No file specified"))?,
+                );
+                let options = crate::data_table::DataTableOptions::from_attrs(&attrs)?;
+                let table = crate::data_table::build_data_table(
+                    &data_file_name.read()?,
+                    &data_file_name,
+                    &options,
+                )?;
+
+                let cell = |value: Node| {
+                    Node::TableCell(TableCell {
+                        children: vec![value],
+                        position: Default::default(),
+                    })
+                };
+                let text = |value: String| {
+                    Node::Text(markdown::mdast::Text {
+                        value,
+                        position: Default::default(),
+                    })
+                };
+                let header = Node::TableRow(TableRow {
+                    children: table.headers.into_iter().map(|h| cell(text(h))).collect(),
+                    position: Default::default(),
+                });
+                let rows = table
+                    .rows
+                    .into_iter()
+                    .map(|row| {
                         Node::TableRow(TableRow {
-                            children: r
-                                .unwrap()
-                                .iter()
+                            children: row
+                                .into_iter()
                                 .map(|c| {
-                                    Node::TableCell(TableCell {
-                                        children: vec![Node::Text(markdown::mdast::Text {
-                                            value: c.to_string(),
+                                    if c.code {
+                                        cell(Node::InlineCode(markdown::mdast::InlineCode {
+                                            value: c.text,
                                             position: Default::default(),
-                                        })],
-                                        position: Default::default(),
-                                    })
+                                        }))
+                                    } else {
+                                        cell(text(c.text))
+                                    }
                                 })
-                                .collect::<Vec<_>>(),
+                                .collect(),
                             position: Default::default(),
                         })
                     })
                     .collect::<Vec<_>>();
+                self.handle_table_rows(Some(&header), &rows)
+            }
+            "Gallery" => {
+                let images = if let Some(folder) = attrs.get("folder") {
+                    let folder_path = self.get_context().project.path.join("static").join(folder);
+                    let mut images = vec![];
+                    for entry in folder_path.disk_path().read_dir()? {
+                        let path = entry?.path();
+                        let is_image = path
+                            .extension()
+                            .and_then(|s| s.to_str())
+                            .map(|s| IMAGE_EXTENSIONS.contains(&s.to_lowercase().as_str()))
+                            .unwrap_or(false);
+                        if let (true, Some(file_name)) = (is_image, path.file_name()) {
+                            images.push(format!(
+                                "/{}/{}",
+                                folder.trim_matches('/'),
+                                file_name.to_string_lossy()
+                            ));
+                        }
+                    }
+                    images.sort();
+                    images
+                } else if let Some(list) = attrs.get("images") {
+                    list.split(',')
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| format!("/{}", s.trim_start_matches('/')))
+                        .collect()
+                } else {
+                    return Err(crate::Error::new("No folder or images specified"));
+                };
+                let columns = attrs
+                    .get("columns")
+                    .and_then(|c| c.parse::<usize>().ok())
+                    .unwrap_or(3)
+                    .clamp(1, 12);
 
-                self.handle_table(&rows)
+                self.render_gallery(&images, columns)
+            }
+            "Steps" => {
+                let mut content = String::new();
+                let mut number = 0;
+                for child in children {
+                    let Node::MdxJsxFlowElement(el) = child else {
+                        continue;
+                    };
+                    if el.name.as_deref() != Some("Step") {
+                        continue;
+                    }
+                    number += 1;
+                    let step_attrs = self.convert_component_attributes(&el.attributes);
+                    let title = step_attrs.get("title").cloned().unwrap_or_default();
+                    let slug = step_slug(&step_attrs, number);
+                    content.push_str(&self.render_step(number, &title, &slug, &el.children)?);
+                }
+                self.render_steps(content)
+            }
+            "Backlinks" => {
+                let ctx = self.get_context();
+                let links = ctx
+                    .project
+                    .backlinks(ctx.document)?
+                    .into_iter()
+                    .map(PageLink::from)
+                    .collect::<Vec<_>>();
+                self.render_backlinks(&links)
+            }
+            "ExtractBlock" => {
+                let mut attrs = attrs;
+                let id = attrs
+                    .get("id")
+                    .ok_or_else(|| crate::Error::new("No id specified"))?;
+                crate::snippets::validate_id(id)?;
+                let file = crate::snippets::file_name(id, attrs.get("lang").map(|l| l.as_str()));
+                let base_url = &self.get_context().project.details.base_url;
+                attrs.insert(
+                    "url".to_string(),
+                    format!("{}{}/{}", base_url, crate::snippets::SNIPPETS_FOLDER, file),
+                );
+                attrs.insert("file".to_string(), file);
+                self.render_jsx_element(name, attrs, children)
+            }
+            "Glossary" => {
+                self.render_glossary(&crate::glossary::build(self.get_context().project)?)
+            }
+            "QrCode" => {
+                let url = attrs
+                    .get("url")
+                    .cloned()
+                    .ok_or_else(|| crate::Error::new("No url specified"))?;
+                self.render_qr_code(&url, attrs, children)
+            }
+            "Alert" => {
+                let mut attrs = attrs;
+                let style = alert_style(attrs.get("style").map(|s| s.as_str()));
+                attrs.insert("style".to_string(), style);
+                self.render_jsx_element(name, attrs, children)
             }
             _ => self.render_jsx_element(name, attrs, children),
         }
     }
 
     fn render_node(&self, node: &markdown::mdast::Node) -> Result<String> {
-        let ctx = self.get_context();
         match node {
+            Node::Root(root)
+                if self.get_context().document.frontmatter.page_type == crate::PageType::Faq =>
+            {
+                self.handle_faq(&root.children)
+            }
             Node::Root(root) => self.render_nodes(&root.children),
             Node::BlockQuote(block_quote) => self.render_blockquote(&block_quote.children),
             Node::FootnoteDefinition(f) => self.render_footnote_definition(&f.identifier, &f.label),
@@ -348,7 +1233,10 @@ pub trait Renderer {
                     .as_ref()
                     .ok_or_else(|| crate::Error::new("Missing name"))?;
 
-                self.handle_jsx_element(name, attrs, &el.children)
+                let result = self
+                    .handle_jsx_element(name, attrs, &el.children)
+                    .map_err(|e| self.component_error(e, name, el.position.as_ref()));
+                self.isolate_error(result, node)
             }
             Node::MdxJsxTextElement(el) => {
                 let attrs = self.convert_component_attributes(&el.attributes);
@@ -357,7 +1245,10 @@ pub trait Renderer {
                     .as_ref()
                     .ok_or_else(|| crate::Error::new("Missing name"))?;
 
-                self.handle_jsx_element(name, attrs, &el.children)
+                let result = self
+                    .handle_jsx_element(name, attrs, &el.children)
+                    .map_err(|e| self.component_error(e, name, el.position.as_ref()));
+                self.isolate_error(result, node)
             }
             Node::List(list) => self.render_list(list.ordered, &list.children),
             Node::MdxjsEsm(_) => Ok("".to_string()),
@@ -365,31 +1256,55 @@ pub trait Renderer {
             Node::Yaml(_) => Ok("".to_string()),
             Node::Break(_) => Ok("".to_string()),
             Node::InlineCode(c) => self.render_inline_code(&c.value),
-            Node::InlineMath(_) => Ok("".to_string()),
+            Node::InlineMath(math) => self.render_math(&math.value, false),
             Node::Delete(d) => self.render_delete(&d.children),
             Node::Emphasis(em) => self.render_emphasis(&em.children),
             Node::MdxTextExpression(_) => Ok("".to_string()),
             Node::FootnoteReference(_) => Ok("".to_string()),
-            Node::Html(_) => Ok("".to_string()),
-            Node::Image(img) => self.render_image(&img.url, img.title.clone()),
+            Node::Html(html) => self.render_html(&html.value),
+            Node::Image(img) => self.render_image(
+                &match self.checked_attachment_url(&img.url) {
+                    Some(url) => self.static_url(&url),
+                    None if self.get_context().fragment => self.static_url(&img.url),
+                    None => img.url.clone(),
+                },
+                &img.alt,
+                img.title.clone(),
+            ),
             Node::ImageReference(_) => Ok("".to_string()),
-            Node::Link(link) => {
-                let url = if link.url.starts_with('/') {
-                    format!(
-                        "{}{}",
-                        ctx.project.details.base_url,
-                        link.url.clone().trim_start_matches('/'),
-                    )
-                } else {
-                    link.url.clone()
-                };
-                self.render_link(&url, link.title.clone(), &link.children)
-            }
+            Node::Link(link) => self.render_link(
+                &match self.id_url(&link.url)? {
+                    Some(url) => self.with_base_url(&url),
+                    None => match self.attachment_url(&link.url) {
+                        Some(url) => self.static_url(&url),
+                        None => {
+                            self.check_link(&link.url);
+                            self.with_base_url(&self.page_url(&link.url))
+                        }
+                    },
+                },
+                link.title.clone(),
+                &link.children,
+            ),
             Node::LinkReference(_) => Ok("".to_string()),
             Node::Strong(bold) => self.render_bold(&bold.children),
             Node::Text(text) => self.render_text(&text.value),
-            Node::Code(code) => self.render_code(&code.value, code.lang.clone(), None),
-            Node::Math(_) => Ok("".to_string()),
+            Node::Code(code) => {
+                let result = self.render_code(
+                    &code.value,
+                    code.lang.clone(),
+                    None,
+                    &CodeOptions {
+                        diff: code
+                            .lang
+                            .as_deref()
+                            .is_some_and(|l| l.eq_ignore_ascii_case("diff")),
+                        ..CodeOptions::from_meta(code.meta.as_deref().unwrap_or_default())
+                    },
+                );
+                self.isolate_error(result, node)
+            }
+            Node::Math(math) => self.render_math(&math.value, true),
             Node::MdxFlowExpression(exp) => self.render_expression(&exp.value),
             Node::Heading(h) => self.render_heading(h.depth, &h.children),
             Node::Table(table) => self.handle_table(&table.children),
@@ -403,19 +1318,247 @@ pub trait Renderer {
         }
     }
 
+    /// Render a component or code block that failed as an error in its
+    /// place when the project's `safe_mode` is on, so one broken part of a
+    /// page doesn't stop the rest of it rendering. The error is reported
+    /// as a warning. Renderers that can't show errors fail the page, as
+    /// does every error with `strict_templates`.
+    fn isolate_error(&self, result: Result<String>, node: &Node) -> Result<String> {
+        let error = match result {
+            Ok(output) => return Ok(output),
+            Err(error) => error,
+        };
+        let details = &self.get_context().project.details;
+        if !details.safe_mode || details.strict_templates {
+            return Err(error);
+        }
+        let (label, inline) = match node {
+            Node::MdxJsxFlowElement(el) => (
+                format!("<{}>", el.name.as_deref().unwrap_or_default()),
+                false,
+            ),
+            Node::MdxJsxTextElement(el) => (
+                format!("<{}>", el.name.as_deref().unwrap_or_default()),
+                true,
+            ),
+            _ => ("Code block".to_string(), false),
+        };
+        let line = node.position().map(|p| p.start.line);
+        let Some(output) = self.render_node_error(&label, line, &error.message, inline) else {
+            return Err(error);
+        };
+        let location = line.map(|l| format!(" on line {}", l)).unwrap_or_default();
+        self.warn(
+            WarningKind::RenderError,
+            format!("{}{} failed to render: {}", label, location, error.message),
+        );
+        Ok(output)
+    }
+
+    /// The output shown in place of a component or code block that failed
+    /// to render, or `None` to fail the page instead.
+    fn render_node_error(
+        &self,
+        label: &str,
+        line: Option<usize>,
+        message: &str,
+        inline: bool,
+    ) -> Option<String> {
+        None
+    }
+
+    /// Point an error from a component at the component in the document,
+    /// so it can be found without searching for the message.
+    fn component_error(
+        &self,
+        error: crate::Error,
+        name: &str,
+        position: Option<&markdown::unist::Position>,
+    ) -> crate::Error {
+        let file_path = &self.get_context().document.file_path;
+        // Positions are in the content the document was parsed from
+        let source = file_path
+            .read()
+            .ok()
+            .and_then(|content| String::from_utf8(content.to_vec()).ok())
+            .map(|content| crate::review::strip_review_comments(&content).0);
+        error.in_component(
+            name,
+            crate::SourceLocation {
+                path: file_path.relative_path.clone(),
+                span: position.map(|p| p.start.offset..p.end.offset),
+                line: position.map(|p| p.start.line),
+                column: position.map(|p| p.start.column),
+            },
+            source,
+        )
+    }
+
     fn handle_table(&self, children: &[Node]) -> Result<String> {
         let mut i = children.iter().filter(|i| matches!(i, Node::TableRow(_)));
         let Some(header_row) = i.next() else {
             return Ok(String::new());
         };
         let rows = i.cloned().collect::<Vec<_>>();
+        self.handle_table_rows(Some(header_row), &rows)
+    }
 
+    /// Render a table from its rows. Tables without a header row only have a
+    /// body.
+    fn handle_table_rows(&self, header: Option<&Node>, rows: &[Node]) -> Result<String> {
         let mut out = String::new();
-        out.push_str(&self.render_table_header(self.render_node(header_row)?)?);
-        out.push_str(&self.render_table_body(self.render_nodes(&rows)?)?);
+        if let Some(header) = header {
+            out.push_str(&self.render_table_header(self.render_node(header)?)?);
+        }
+        out.push_str(&self.render_table_body(self.render_nodes(rows)?)?);
         self.render_table(out)
     }
 
+    /// Whether the renderer reports problems with the document as warning
+    /// events. Only one output format needs to, so the same problem isn't
+    /// reported once for each.
+    fn reports_warnings(&self) -> bool {
+        false
+    }
+
+    /// Report a problem with the document that doesn't stop it rendering.
+    fn warn(&self, kind: WarningKind, message: String) {
+        if !self.reports_warnings() {
+            return;
+        }
+        let ctx = self.get_context();
+        ctx.project.events.emit(Event::Warning {
+            kind,
+            path: Some(ctx.document.file_path.relative_path.clone()),
+            message,
+        });
+    }
+
+    /// The attachment url of an image, like `attachment_url`, warning when
+    /// a root relative image or attachment doesn't exist.
+    fn checked_attachment_url(&self, url: &str) -> Option<String> {
+        let attachment = self.attachment_url(url);
+        if !self.reports_warnings() {
+            return attachment;
+        }
+        let project = self.get_context().project;
+        let exists = match attachment {
+            Some(ref url) => project.path.from_url(url).exists(),
+            None if url.starts_with('/') => {
+                let path = url.split(['#', '?']).next().unwrap_or_default();
+                project.path.from_url(&format!("/static{}", path)).exists()
+                    || project.path.from_url(path).exists()
+            }
+            None => true,
+        };
+        if !exists {
+            self.warn(
+                WarningKind::MissingAsset,
+                format!("Image not found: {}", url),
+            );
+        }
+        attachment
+    }
+
+    /// Warn when a root relative link to a page doesn't match a document,
+    /// redirect, or print view. Links to other files, such as feeds
+    /// generated by the build, aren't checked.
+    fn check_link(&self, url: &str) {
+        if !url.starts_with('/') || !self.reports_warnings() {
+            return;
+        }
+        let project = self.get_context().project;
+        let path = url.split(['#', '?']).next().unwrap_or_default();
+        let name = path.rsplit('/').next().unwrap_or_default();
+        if name.contains('.') && !name.ends_with(".html") {
+            return;
+        }
+        let prefixed = format!(
+            "{}{}",
+            project.details.base_url,
+            path.trim_start_matches('/')
+        );
+        if project.get_document_for_link(path).is_none()
+            && project.get_redirect_for_url(&prefixed).is_none()
+            && project.get_folder_for_print_url(&prefixed).is_none()
+        {
+            self.warn(WarningKind::BrokenLink, format!("Broken link: {}", url));
+        }
+    }
+
+    /// The url of the document a link targets by id, such as
+    /// `id:3f2a1b9c#usage`.
+    fn id_url(&self, url: &str) -> Result<Option<String>> {
+        let Some(target) = url.strip_prefix(crate::ids::ID_LINK_PREFIX) else {
+            return Ok(None);
+        };
+        let (id, anchor) = match target.split_once('#') {
+            Some((id, anchor)) => (id, format!("#{}", anchor)),
+            None => (target, String::new()),
+        };
+        let document = self
+            .get_context()
+            .project
+            .get_document_for_id(id)
+            .ok_or_else(|| crate::Error::new(format!("No document has the id {}", id)))?;
+        Ok(Some(format!("{}{}", document.url, anchor)))
+    }
+
+    /// The root relative url of a file in the document's assets folder,
+    /// for urls written relative to the markdown file such as
+    /// `page.assets/diagram.png`.
+    fn attachment_url(&self, url: &str) -> Option<String> {
+        let assets = self.get_context().document.assets_path();
+        let prefix = format!("{}/", assets.basename()?);
+        let file = url.trim_start_matches("./").strip_prefix(&prefix)?;
+        Some(format!("{}/{}", assets.root_url(), file))
+    }
+
+    /// The url of a root relative link to a page in the project's url style
+    /// and trailing slash policy, so links can be written without the
+    /// `.html` of file urls. Other urls are returned unchanged.
+    fn page_url(&self, url: &str) -> String {
+        let project = self.get_context().project;
+        let unchanged = project.details.url_style == crate::UrlStyle::Pretty
+            && project.details.trailing_slash.is_none();
+        if unchanged || !url.starts_with('/') {
+            return url.to_string();
+        }
+        let split = url.find(['#', '?']).unwrap_or(url.len());
+        match project.get_document_for_link(&url[..split]) {
+            Some(document) => format!("{}{}", document.url, &url[split..]),
+            None => url.to_string(),
+        }
+    }
+
+    /// Prefix root relative urls with the project's base url. Other urls are
+    /// returned unchanged.
+    fn with_base_url(&self, url: &str) -> String {
+        if url.starts_with('/') {
+            format!(
+                "{}{}",
+                self.get_context().project.details.base_url,
+                url.trim_start_matches('/'),
+            )
+        } else {
+            url.to_string()
+        }
+    }
+
+    /// The url of a static file or attachment. Fragments link to them
+    /// through `fragments.asset_url` when it is set.
+    fn static_url(&self, url: &str) -> String {
+        let ctx = self.get_context();
+        match ctx.project.details.fragments.asset_url {
+            Some(ref asset_url) if ctx.fragment && url.starts_with('/') => format!(
+                "{}/{}",
+                asset_url.trim_end_matches('/'),
+                url.trim_start_matches('/')
+            ),
+            _ => self.with_base_url(url),
+        }
+    }
+
     fn convert_component_attributes(
         &self,
         attrs: &[markdown::mdast::AttributeContent],
@@ -434,50 +1577,25 @@ pub trait Renderer {
     }
 
     fn slug(&self, text: &str) -> String {
-        text.to_lowercase().replace(' ', "-").replace(
-            [
-                ':', '?', '!', '.', ',', ';', '(', ')', '[', ']', '{', '}', '\'', '\\', '/', '\\',
-                '<', '>', '|',
-            ],
-            "",
-        )
+        slugify(text)
     }
 
     fn parse(&self, file_path: &CodexPath) -> Result<Node> {
         let content = String::from_utf8(file_path.read()?.to_vec())?;
+        let (content, _) = crate::review::strip_review_comments(&content);
 
         // Parse the markdown into an AST
         match self.parse_ast(&content) {
             Ok(ast) => Ok(ast),
-            Err(e) => {
-                self.parse_ast(&format!(r#"<Alert title="Parsing error" style="danger">{}</Alert>"#, e.to_string()))
-            }
+            Err(e) => self.parse_ast(&format!(
+                r#"<Alert title="Parsing error" style="danger">{}</Alert>"#,
+                e
+            )),
         }
     }
 
     fn parse_ast(&self, content: &str) -> Result<Node> {
-        let parser_options = markdown::ParseOptions {
-            constructs: markdown::Constructs {
-                code_indented: false,
-                frontmatter: true,
-                mdx_jsx_flow: true,
-                html_flow: false,
-                html_text: false,
-                mdx_esm: true,
-                mdx_expression_flow: true,
-                mdx_expression_text: true,
-                gfm_task_list_item: true,
-                gfm_strikethrough: true,
-                mdx_jsx_text: true,
-                gfm_table: true,
-                ..Default::default()
-            },
-            mdx_expression_parse: Some(Box::new(parse_expression)),
-            gfm_strikethrough_single_tilde: true,
-            math_text_single_dollar: true,
-            ..markdown::ParseOptions::mdx()
-        };
-        Ok(markdown::to_mdast(content, &parser_options)?)
+        crate::document::parse_ast(content, &self.get_context().project.details.markdown)
     }
 
     fn get_text(&self, nodes: &[Node]) -> Option<String> {
@@ -490,13 +1608,90 @@ pub trait Renderer {
         })
     }
 
+    /// When the document was last changed: the date of the last commit
+    /// changing it, or the file's modification time when it isn't committed.
     fn last_modified(&self) -> Result<String> {
+        let ctx = self.get_context();
+        if let Some(revision) = ctx.project.revision(ctx.document) {
+            return Ok(revision.date.clone());
+        }
         let file_time =
             std::fs::metadata(self.get_context().document.file_path.disk_path())?.modified()?;
         let dt: chrono::DateTime<chrono::Local> = chrono::DateTime::from(file_time);
         Ok(dt.to_utc().format("%Y-%m-%dT%H:%M:%S%z").to_string())
     }
 
+    /// The folders from the project root to the current document, each
+    /// linking to its index page in the document's language. The top level
+    /// folders holding a language's documents are left out.
+    fn breadcrumbs(&self) -> Vec<Breadcrumb> {
+        let ctx = self.get_context();
+        let document = ctx.document;
+        fn index_of<'a>(folder: &'a crate::Folder, language: &str) -> Option<&'a Document> {
+            folder
+                .documents
+                .iter()
+                .find(|d| d.language == language && d.is_index())
+        }
+        let mut folders = vec![&ctx.project.root_folder];
+        while let Some(folder) = folders.last().and_then(|f| {
+            f.folders.iter().find(|f| {
+                document
+                    .file_path
+                    .relative_path
+                    .starts_with(&f.path.relative_path)
+            })
+        }) {
+            folders.push(folder);
+        }
+
+        let mut crumbs: Vec<Breadcrumb> = vec![];
+        for (depth, folder) in folders.into_iter().enumerate() {
+            let index = index_of(folder, &document.language);
+            let language_folder = depth == 1
+                && !ctx.project.details.translations.is_empty()
+                && ctx.project.details.languages().any(|l| l == folder.name);
+            if language_folder {
+                if let Some(root) = crumbs.first_mut() {
+                    root.url = index.map(|d| d.url.clone());
+                }
+                if index.is_some_and(|d| d.url == document.url) {
+                    crumbs.clear();
+                }
+                continue;
+            }
+            if index.is_some_and(|d| d.url == document.url) {
+                break;
+            }
+            crumbs.push(Breadcrumb {
+                name: if depth == 0 {
+                    ctx.project.details.name.clone()
+                } else {
+                    folder.get_name()
+                },
+                url: index.map(|d| d.url.clone()),
+            });
+        }
+        crumbs.push(Breadcrumb {
+            name: document.frontmatter.title.clone(),
+            url: Some(document.url.clone()),
+        });
+        crumbs
+    }
+
+    /// Find the pages before and after the current document in menu order.
+    fn page_neighbours(&self, sitemap: &SiteMapFolder) -> (Option<PageLink>, Option<PageLink>) {
+        let url = &self.get_context().document.url;
+        let pages = sitemap.iter_pages().collect::<Vec<_>>();
+        let Some(idx) = pages.iter().position(|p| &p.url == url) else {
+            return (None, None);
+        };
+        (
+            idx.checked_sub(1).map(|i| PageLink::from(pages[i])),
+            pages.get(idx + 1).map(|p| PageLink::from(*p)),
+        )
+    }
+
     fn toc(&self, nodes: &[Node]) -> Vec<TocEntry> {
         let mut s = vec![];
         for node in nodes.iter() {
@@ -515,10 +1710,473 @@ pub trait Renderer {
     }
 }
 
+/// The components rendered by Codex itself rather than a template. Templates
+/// are named in lowercase, so these are also used to suggest components by
+/// their proper name.
+const BUILTIN_COMPONENTS: [&str; 16] = [
+    "Alert",
+    "Backlinks",
+    "CodeFile",
+    "CsvTable",
+    "DataTable",
+    "ExtractBlock",
+    "Field",
+    "Gallery",
+    "Glossary",
+    "Image",
+    "JsonSchemaExample",
+    "JsonSchemaFields",
+    "ProtoSchema",
+    "QrCode",
+    "Step",
+    "Steps",
+];
+
+/// Whether Codex renders a component itself when there is no template for
+/// it.
+pub(crate) fn is_builtin_component(name: &str) -> bool {
+    BUILTIN_COMPONENTS.contains(&name)
+}
+
+/// The names of the components a document can use: the built in ones and
+/// those with a template in the project or Codex's own `_internal/components`.
+pub(crate) fn component_names(project: &Project) -> Vec<String> {
+    let folder = project.path.join("_internal").join("components");
+    let on_disk = std::fs::read_dir(folder.disk_path())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok());
+    let embedded = crate::assets::EmbeddedAsset::iter()
+        .filter_map(|f| Some(f.strip_prefix("_internal/components/")?.to_string()));
+    let mut names = BUILTIN_COMPONENTS
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>();
+    for file in on_disk.chain(embedded) {
+        let Some(stem) = file.strip_suffix(".html") else {
+            continue;
+        };
+        if !names.iter().any(|n| n.to_lowercase() == stem) {
+            names.push(stem.to_string());
+        }
+    }
+    names
+}
+
+/// The component a misspelled name most likely meant, if any is close
+/// enough.
+pub(crate) fn suggest_component(project: &Project, name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    component_names(project)
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= (name.chars().count() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of single character insertions, deletions, and substitutions
+/// needed to turn one string into another.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// The anchor of a step: its `id`, or one made from its title or number.
+pub(crate) fn step_slug(attrs: &HashMap<String, String>, number: usize) -> String {
+    match (attrs.get("id"), attrs.get("title")) {
+        (Some(id), _) => id.clone(),
+        (None, Some(title)) if !title.is_empty() => format!("step-{}", slugify(title)),
+        _ => format!("step-{}", number),
+    }
+}
+
+/// Convert heading text into the anchor used to link to it.
+pub(crate) fn slugify(text: &str) -> String {
+    text.to_lowercase().replace(' ', "-").replace(
+        [
+            ':', '?', '!', '.', ',', ';', '(', ')', '[', ']', '{', '}', '\'', '\\', '/', '\\', '<',
+            '>', '|',
+        ],
+        "",
+    )
+}
+
+/// Select part of a source file for the CodeFile component. `lines` is a
+/// 1-based, inclusive range such as `10-40`, `10-`, or `12`. `region` selects
+/// the lines between `codex:start <name>` and `codex:end <name>` marker
+/// comments. Marker lines are removed from the excerpt and its common
+/// indentation is stripped.
+pub(crate) fn code_excerpt(
+    content: &str,
+    lines: Option<&str>,
+    region: Option<&str>,
+) -> Result<String> {
+    if lines.is_none() && region.is_none() {
+        return Ok(content.to_string());
+    }
+    let mut selected = content.lines().collect::<Vec<_>>();
+
+    if let Some(name) = region {
+        let is_marker = |line: &str, marker: &str| {
+            line.split_once(marker)
+                .map(|(_, rest)| rest.split_whitespace().next().unwrap_or_default() == name)
+                .unwrap_or(false)
+        };
+        let start = selected
+            .iter()
+            .position(|line| is_marker(line, "codex:start"))
+            .ok_or_else(|| crate::Error::new(format!("Region not found: {}", name)))?;
+        let end = selected[start..]
+            .iter()
+            .position(|line| is_marker(line, "codex:end"))
+            .map(|i| start + i)
+            .ok_or_else(|| crate::Error::new(format!("Region is never closed: {}", name)))?;
+        selected = selected[start + 1..end].to_vec();
+    }
+
+    if let Some(range) = lines {
+        let invalid = || crate::Error::new(format!("Invalid line range: {}", range));
+        let (from, to) = range.split_once('-').unwrap_or((range, range));
+        let from = from.trim().parse::<usize>().map_err(|_| invalid())?;
+        let to = match to.trim() {
+            "" => selected.len(),
+            to => to.parse::<usize>().map_err(|_| invalid())?,
+        };
+        if from == 0 || from > to || from > selected.len() {
+            return Err(invalid());
+        }
+        selected = selected[from - 1..to.min(selected.len())].to_vec();
+    }
+
+    selected.retain(|line| !line.contains("codex:start") && !line.contains("codex:end"));
+    let indent = selected
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    Ok(selected
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Compare two versions of a file line by line. Returns the lines of both
+/// in diff notation, where removed lines start with `-`, added lines start
+/// with `+`, and unchanged lines start with a space.
+pub(crate) fn diff_lines(original: &str, changed: &str) -> String {
+    // Myers' algorithm runs in linear space, large files are compared
+    // without allocating a table of every pair of lines
+    let old = original.lines().collect::<Vec<_>>();
+    let new = changed.lines().collect::<Vec<_>>();
+    similar::TextDiff::configure()
+        .algorithm(similar::Algorithm::Myers)
+        .diff_slices(&old, &new)
+        .iter_all_changes()
+        .map(|change| {
+            let sign = match change.tag() {
+                similar::ChangeTag::Equal => ' ',
+                similar::ChangeTag::Delete => '-',
+                similar::ChangeTag::Insert => '+',
+            };
+            format!("{}{}", sign, change.value())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Normalize the style name of an Alert component so every renderer receives
+/// one of the standard theme colors. Unrecognized styles are passed through.
+fn alert_style(style: Option<&str>) -> String {
+    match style.map(|s| s.trim().to_lowercase()).as_deref() {
+        None | Some("") => "info".to_string(),
+        Some("warn") => "warning".to_string(),
+        Some("error") => "danger".to_string(),
+        Some("note") => "info".to_string(),
+        Some("tip") => "success".to_string(),
+        Some(s) => s.to_string(),
+    }
+}
+
 #[allow(dead_code)]
 fn parse_expression(_value: &str, _kind: &markdown::MdxExpressionKind) -> markdown::MdxSignal {
     markdown::MdxSignal::Ok
 }
 
 #[cfg(test)]
-pub mod tests {}
+pub mod tests {
+    use super::{code_excerpt, diff_lines, Breadcrumb, CodeOptions, PageLink, SiteMapFolder};
+    use crate::{project::tests::project_fixture, HtmlRenderer, RenderContext, Renderer};
+
+    #[test]
+    fn test_render_warnings() {
+        let root = crate::refactor::tests::writable_fixture("render_warnings");
+        std::fs::write(
+            root.join("other").join("warnings.md"),
+            "# Links\n\n[Alert](/other/alert) [Gone](/other/gone#top) [Feed](/rss.xml)\n\n\
+             ![Missing](/images/missing.png)\n\n<Alrt />\n\n## ![Logo](/favicon.ico)\n",
+        )
+        .unwrap();
+        let mut project = crate::Project::load(&root, false).unwrap();
+        let warnings = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let received = warnings.clone();
+        project.events.subscribe(move |event| {
+            if let crate::events::Event::Warning {
+                kind,
+                path,
+                message,
+            } = event
+            {
+                received
+                    .lock()
+                    .unwrap()
+                    .push((*kind, path.clone(), message.clone()));
+            }
+        });
+        let doc = project.get_document_for_url("/other/warnings").unwrap();
+        doc.render(&project, crate::OutputFormat::Text).unwrap();
+        // Only HTML rendering reports problems
+        assert!(warnings.lock().unwrap().is_empty());
+
+        let html = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        assert!(html.contains(&format!(
+            "<pre>Unknown component &lt;Alrt&gt; in {}, did you mean &lt;Alert&gt;?</pre>",
+            std::path::Path::new("other").join("warnings.md").display()
+        )));
+        let path = Some(std::path::PathBuf::from("other").join("warnings.md"));
+        use crate::events::WarningKind::*;
+        assert_eq!(
+            warnings
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(kind, path, _)| (*kind, path.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (BrokenLink, path.clone()),
+                (MissingAsset, path.clone()),
+                (UnknownComponent, path.clone()),
+                (EmptyHeading, path),
+            ]
+        );
+        assert_eq!(
+            warnings.lock().unwrap()[2].2,
+            "Unknown component <Alrt>, did you mean <Alert>?"
+        );
+        assert_eq!(
+            super::suggest_component(&project, "Codefile").as_deref(),
+            Some("CodeFile")
+        );
+        assert_eq!(super::suggest_component(&project, "Timeline"), None);
+    }
+
+    #[test]
+    fn test_component_error_location() {
+        let root = crate::refactor::tests::writable_fixture("component_error");
+        std::fs::write(
+            root.join("other").join("broken.md"),
+            "# Photos\n\nSome text\n\n<Gallery columns=\"2\" />\n",
+        )
+        .unwrap();
+        let mut project = crate::Project::load(&root, false).unwrap();
+        let warnings = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let received = warnings.clone();
+        project.events.subscribe(move |event| {
+            if let crate::events::Event::Warning { kind, message, .. } = event {
+                received.lock().unwrap().push((*kind, message.clone()));
+            }
+        });
+        let doc = project
+            .get_document_for_url("/other/broken")
+            .unwrap()
+            .clone();
+
+        // The component is rendered as an error, and the rest of the page
+        // as usual
+        let html = doc.render(&project, crate::OutputFormat::Html).unwrap();
+        assert!(html.contains("Some text"));
+        assert!(html.contains(&format!(
+            "<strong>&lt;Gallery&gt; failed to render</strong> <code>{}:5</code> \
+             No folder or images specified</div>",
+            std::path::Path::new("other").join("broken.md").display()
+        )));
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec![(
+                crate::events::WarningKind::RenderError,
+                "<Gallery> on line 5 failed to render: No folder or images specified".to_string()
+            )]
+        );
+
+        // Without safe mode the page fails, pointing at the component
+        project.details.safe_mode = false;
+        let error = doc.render(&project, crate::OutputFormat::Html).unwrap_err();
+        assert_eq!(error.kind, crate::ErrorKind::Component);
+        assert_eq!(error.component.as_deref(), Some("Gallery"));
+        let location = error.location.as_ref().unwrap();
+        assert_eq!(location.line, Some(5));
+        assert_eq!(location.span, Some(21..44));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{}:5:1: <Gallery> No folder or images specified",
+                std::path::Path::new("other").join("broken.md").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_page_neighbours() {
+        let project = project_fixture();
+        let sitemap = SiteMapFolder::from(&project.root_folder);
+        let pages = sitemap.iter_pages().collect::<Vec<_>>();
+
+        let renderer = HtmlRenderer::new(RenderContext::new(&project, pages[1]));
+        let (previous, next) = renderer.page_neighbours(&sitemap);
+        assert_eq!(previous, Some(PageLink::from(pages[0])));
+        assert_eq!(next, Some(PageLink::from(pages[2])));
+
+        let renderer = HtmlRenderer::new(RenderContext::new(&project, pages[0]));
+        assert_eq!(renderer.page_neighbours(&sitemap).0, None);
+
+        let renderer = HtmlRenderer::new(RenderContext::new(&project, pages[pages.len() - 1]));
+        assert_eq!(renderer.page_neighbours(&sitemap).1, None);
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        let project = project_fixture();
+        let crumb = |name: &str, url: Option<&str>| Breadcrumb {
+            name: name.into(),
+            url: url.map(String::from),
+        };
+        let breadcrumbs = |url: &str| {
+            let document = project.get_document_for_url(url).unwrap();
+            HtmlRenderer::new(RenderContext::new(&project, document)).breadcrumbs()
+        };
+
+        assert_eq!(
+            breadcrumbs("/internal/nested/escalation"),
+            vec![
+                crumb("Testing Project", None),
+                crumb("internal", None),
+                crumb("nested", None),
+                crumb("Escalation", Some("/internal/nested/escalation")),
+            ]
+        );
+        // A folder's index page ends the trail at the folder
+        assert_eq!(
+            breadcrumbs("/pages"),
+            vec![
+                crumb("Testing Project", None),
+                crumb("Pages", Some("/pages")),
+            ]
+        );
+        assert_eq!(
+            breadcrumbs("/pages/large")[1],
+            crumb("pages", Some("/pages"))
+        );
+    }
+
+    #[test]
+    fn test_code_excerpt() {
+        let content = "use std::fs;\n\nfn main() {\n    // codex:start read\n    let data = fs::read(\"a\");\n    println!(\"{:?}\", data);\n    // codex:end read\n}";
+        assert_eq!(code_excerpt(content, None, None).unwrap(), content);
+        assert_eq!(
+            code_excerpt(content, Some("3-4"), None).unwrap(),
+            "fn main() {"
+        );
+        assert_eq!(
+            code_excerpt(content, None, Some("read")).unwrap(),
+            "let data = fs::read(\"a\");\nprintln!(\"{:?}\", data);"
+        );
+        assert_eq!(
+            code_excerpt(content, Some("2"), Some("read")).unwrap(),
+            "println!(\"{:?}\", data);"
+        );
+        assert!(code_excerpt(content, None, Some("missing")).is_err());
+        assert!(code_excerpt(content, Some("5-2"), None).is_err());
+    }
+
+    #[test]
+    fn test_code_options() {
+        assert_eq!(
+            CodeOptions::from_meta("{1,3-5} showLineNumbers"),
+            CodeOptions {
+                highlight: vec![(1, 1), (3, 5)],
+                line_numbers: true,
+                title: None,
+                diff: false,
+            }
+        );
+        assert_eq!(
+            CodeOptions::from_meta(r#"title="My file.rs" {2}"#),
+            CodeOptions {
+                highlight: vec![(2, 2)],
+                line_numbers: false,
+                title: Some("My file.rs".to_string()),
+                diff: false,
+            }
+        );
+        assert_eq!(CodeOptions::from_meta(""), CodeOptions::default());
+        // Large ranges are stored as bounds rather than every line
+        let options = CodeOptions::from_meta("{2-4000000000}");
+        assert_eq!(options.highlight, vec![(2, 4000000000)]);
+        assert!(options.is_highlighted(3999999999));
+        assert!(!options.is_highlighted(1));
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(diff_lines("a\nb\nc\nd", "a\nc\nd\ne"), " a\n-b\n c\n d\n+e");
+        assert_eq!(diff_lines("a", "b"), "-a\n+b");
+        let options = CodeOptions {
+            diff: true,
+            ..CodeOptions::default()
+        };
+        assert_eq!(
+            options
+                .line_changes("--- a/file.rs\n+++ b/file.rs\n same\n+added\n-removed\n++i;\n--- x"),
+            vec![
+                None,
+                None,
+                None,
+                Some("added"),
+                Some("removed"),
+                Some("added"),
+                Some("removed")
+            ]
+        );
+        assert_eq!(CodeOptions::default().line_changes("+added"), vec![None]);
+
+        // Large files are compared without a quadratic table
+        let original = (0..20000)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let changed = original.replacen("\n100\n", "\n100\nnew\n", 1);
+        let diff = diff_lines(&original, &changed);
+        assert_eq!(
+            diff.lines()
+                .filter(|l| l.starts_with('+'))
+                .collect::<Vec<_>>(),
+            vec!["+new"]
+        );
+    }
+}
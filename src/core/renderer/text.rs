@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use markdown::mdast::Node;
+
+use crate::{error::Result, glossary::GlossaryTerm, DataContext};
+
+use super::{CodeOptions, PageLink, RenderContext, Renderer};
+
+/// The column paragraphs are wrapped at.
+const WRAP_WIDTH: usize = 78;
+
+/// Renders documents as readable plain text, for the search index, email
+/// and other places HTML can't go.
+pub struct TextRenderer<'a> {
+    pub render_context: RenderContext<'a>,
+    /// Follow link text with the link's url
+    pub link_urls: bool,
+}
+
+impl<'a> TextRenderer<'a> {
+    pub fn new(render_context: RenderContext<'a>) -> Self {
+        Self {
+            render_context,
+            link_urls: true,
+        }
+    }
+
+    /// Render inline content as a single line, collapsing the whitespace
+    /// of soft line breaks.
+    fn inline(&self, children: &[Node]) -> Result<String> {
+        Ok(collapse_whitespace(&self.render_nodes(children)?))
+    }
+}
+
+impl<'a> Renderer for TextRenderer<'a> {
+    fn get_context(&self) -> &RenderContext<'_> {
+        &self.render_context
+    }
+
+    fn finalize_render(&self, data: DataContext<'_>) -> Result<String> {
+        let mut out = String::new();
+        if !data.document.title.is_empty() {
+            out.push_str(&underline(&data.document.title, '='));
+            if let Some(ref subtitle) = data.document.subtitle {
+                out.push_str(subtitle);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out.push_str(data.body.trim());
+        out.push('\n');
+        Ok(out)
+    }
+
+    /// Parts of a page that fail are left out of its text. The HTML
+    /// rendering of the page reports them.
+    fn render_node_error(
+        &self,
+        label: &str,
+        line: Option<usize>,
+        message: &str,
+        inline: bool,
+    ) -> Option<String> {
+        Some(String::new())
+    }
+
+    fn render_body(&self) -> Result<String> {
+        let ast = self.parse(&self.get_context().document.file_path)?;
+        Ok(format!("{}\n", self.render_node(&ast)?.trim()))
+    }
+
+    fn render_paragraph(&self, children: &[Node]) -> Result<String> {
+        Ok(format!("{}\n\n", wrap(&self.inline(children)?, WRAP_WIDTH)))
+    }
+
+    fn render_heading(&self, depth: u8, children: &[Node]) -> Result<String> {
+        let text = self.inline(children)?;
+        Ok(match depth {
+            1 => format!("{}\n", underline(&text, '-')),
+            _ => format!("{}\n\n", text),
+        })
+    }
+
+    fn render_blockquote(&self, children: &[Node]) -> Result<String> {
+        Ok(format!(
+            "{}\n\n",
+            indent(self.render_nodes(children)?.trim_end(), "> ")
+        ))
+    }
+
+    fn render_list(&self, ordered: bool, children: &[Node]) -> Result<String> {
+        let mut out = String::new();
+        for (i, child) in children.iter().enumerate() {
+            let marker = if ordered {
+                format!("{}. ", i + 1)
+            } else {
+                "- ".to_string()
+            };
+            let item = self.render_node(child)?;
+            out.push_str(&marker);
+            out.push_str(indent(item.trim(), &" ".repeat(marker.len())).trim_start());
+            out.push('\n');
+        }
+        out.push('\n');
+        Ok(out)
+    }
+
+    fn render_list_item(&self, checked: Option<bool>, children: &[Node]) -> Result<String> {
+        let check = match checked {
+            Some(true) => "[x] ",
+            Some(false) => "[ ] ",
+            None => "",
+        };
+        Ok(format!("{}{}", check, self.render_nodes(children)?.trim()))
+    }
+
+    fn render_text(&self, text: &str) -> Result<String> {
+        Ok(text.to_string())
+    }
+
+    fn render_inline_code(&self, code: &str) -> Result<String> {
+        Ok(code.to_string())
+    }
+
+    fn render_bold(&self, children: &[Node]) -> Result<String> {
+        self.render_nodes(children)
+    }
+
+    fn render_emphasis(&self, children: &[Node]) -> Result<String> {
+        self.render_nodes(children)
+    }
+
+    fn render_delete(&self, children: &[Node]) -> Result<String> {
+        self.render_nodes(children)
+    }
+
+    fn render_link(&self, url: &str, title: Option<String>, children: &[Node]) -> Result<String> {
+        let text = self.render_nodes(children)?;
+        if !self.link_urls || text == url {
+            return Ok(text);
+        }
+        // Links to pages already include the base url
+        let details = &self.render_context.project.details;
+        let url = url
+            .strip_prefix(&details.base_url)
+            .and_then(|path| details.absolute_url(&format!("/{}", path)))
+            .unwrap_or_else(|| url.to_string());
+        Ok(format!("{} ({})", text, url))
+    }
+
+    fn render_image(&self, url: &str, alt: &str, title: Option<String>) -> Result<String> {
+        Ok(if alt.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", alt)
+        })
+    }
+
+    fn render_math(&self, math: &str, display: bool) -> Result<String> {
+        Ok(if display {
+            format!("{}\n\n", indent(math.trim(), "    "))
+        } else {
+            math.to_string()
+        })
+    }
+
+    fn render_code(
+        &self,
+        code: &str,
+        lang: Option<String>,
+        filepath: Option<std::path::PathBuf>,
+        options: &CodeOptions,
+    ) -> Result<String> {
+        let mut out = String::new();
+        if let Some(ref title) = options.title {
+            out.push_str(&format!("{}:\n", title));
+        }
+        out.push_str(&indent(code.trim_end(), "    "));
+        out.push_str("\n\n");
+        Ok(out)
+    }
+
+    fn render_thematic_break(&self) -> Result<String> {
+        Ok(format!("{}\n\n", "-".repeat(WRAP_WIDTH)))
+    }
+
+    /// Tables are drawn with ASCII borders, with every column as wide as its
+    /// widest cell.
+    fn handle_table_rows(&self, header: Option<&Node>, rows: &[Node]) -> Result<String> {
+        let cells = |row: &Node| -> Result<Vec<String>> {
+            row.children()
+                .map(|cells| {
+                    cells
+                        .iter()
+                        .map(|cell| {
+                            self.inline(cell.children().map(|c| c.as_slice()).unwrap_or_default())
+                        })
+                        .collect()
+                })
+                .unwrap_or_else(|| Ok(vec![]))
+        };
+        let header = header.map(cells).transpose()?;
+        let rows = rows.iter().map(cells).collect::<Result<Vec<_>>>()?;
+        let mut widths: Vec<usize> = vec![];
+        for row in header.iter().chain(rows.iter()) {
+            for (i, cell) in row.iter().enumerate() {
+                let width = cell.chars().count();
+                match widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(width),
+                    None => widths.push(width),
+                }
+            }
+        }
+        let border = |fill: char| {
+            widths.iter().fold("+".to_string(), |acc, w| {
+                format!("{}{}+", acc, fill.to_string().repeat(w + 2))
+            })
+        };
+        let line = |row: &Vec<String>| {
+            widths
+                .iter()
+                .enumerate()
+                .fold("|".to_string(), |acc, (i, w)| {
+                    let cell = row.get(i).map(|s| s.as_str()).unwrap_or_default();
+                    format!("{} {}{} |", acc, cell, " ".repeat(w - cell.chars().count()))
+                })
+        };
+
+        let mut out = format!("{}\n", border('-'));
+        if let Some(ref header) = header {
+            out.push_str(&format!("{}\n{}\n", line(header), border('=')));
+        }
+        for row in rows.iter() {
+            out.push_str(&format!("{}\n", line(row)));
+        }
+        if !rows.is_empty() {
+            out.push_str(&format!("{}\n", border('-')));
+        }
+        out.push('\n');
+        Ok(out)
+    }
+
+    fn render_jsx_element(
+        &self,
+        name: &str,
+        attrs: HashMap<String, String>,
+        children: &[Node],
+    ) -> Result<String> {
+        let content = self.render_nodes(children)?;
+        match name {
+            "Field" => {
+                let mut out = attrs.get("name").cloned().unwrap_or_default();
+                if let Some(data_type) = attrs.get("type") {
+                    out.push_str(&format!(" ({})", data_type));
+                }
+                Ok(format!(
+                    "{}\n{}\n\n",
+                    out,
+                    indent(content.trim_end(), "    ")
+                ))
+            }
+            "Alert" => {
+                let mut out = String::new();
+                if let Some(title) = attrs.get("title") {
+                    out.push_str(&format!("{}\n\n", title));
+                }
+                out.push_str(content.trim_end());
+                Ok(format!("{}\n\n", indent(&out, "| ")))
+            }
+            _ => Ok(content),
+        }
+    }
+
+    fn render_steps(&self, content: String) -> Result<String> {
+        Ok(content)
+    }
+
+    fn render_step(
+        &self,
+        number: usize,
+        title: &str,
+        slug: &str,
+        children: &[Node],
+    ) -> Result<String> {
+        let body = self.render_nodes(children)?;
+        // Runbook steps are a markdown task list, so they can be pasted
+        // into a ticket and checked off
+        if self.render_context.document.frontmatter.page_type == crate::PageType::Runbook {
+            return Ok(format!(
+                "{}\n\n{}\n\n",
+                format!("- [ ] {}. {}", number, title).trim_end(),
+                indent(body.trim_end(), "  ")
+            ));
+        }
+        Ok(format!(
+            "{}. {}\n\n{}\n\n",
+            number,
+            title,
+            indent(body.trim_end(), "   ")
+        ))
+    }
+
+    fn render_backlinks(&self, links: &[PageLink]) -> Result<String> {
+        Ok(links.iter().fold(String::new(), |acc, link| {
+            format!("{}- {}\n", acc, link.title)
+        }) + "\n")
+    }
+
+    fn render_glossary(&self, terms: &[GlossaryTerm]) -> Result<String> {
+        let mut out = String::new();
+        for term in terms {
+            out.push_str(&term.term);
+            out.push('\n');
+            if let Some(ref definition) = term.definition {
+                out.push_str(&indent(&wrap(definition, WRAP_WIDTH - 4), "    "));
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Break text into lines no longer than `width`, except for words that are
+/// longer on their own.
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Start every non-empty line with `prefix`.
+fn indent(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                prefix.trim_end().to_string()
+            } else {
+                format!("{}{}", prefix, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn underline(text: &str, c: char) -> String {
+    format!("{}\n{}\n", text, c.to_string().repeat(text.chars().count()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{project::tests::project_fixture, RenderContext, Renderer};
+
+    use super::TextRenderer;
+
+    #[test]
+    pub fn test_text_table() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/csv").unwrap();
+        let renderer = TextRenderer::new(RenderContext::new(&project, doc));
+        assert_eq!(
+            renderer.render_body().unwrap(),
+            "+---------+-----+----------+\n\
+             | name    | age | position |\n\
+             +=========+=====+==========+\n\
+             | alice   | 18  | engineer |\n\
+             | bob     | 19  | engineer |\n\
+             | charlie | 20  | manager  |\n\
+             +---------+-----+----------+\n"
+        );
+    }
+
+    #[test]
+    pub fn test_text_alert() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let renderer = TextRenderer::new(RenderContext::new(&project, doc));
+        let text = renderer.render().unwrap();
+        // Documents without a title start with their content
+        assert!(text.starts_with("| Be careful\n|\n| Watch out"));
+    }
+
+    #[test]
+    pub fn test_wrap() {
+        assert_eq!(super::wrap("a bb  ccc\ndddd", 6), "a bb\nccc\ndddd");
+        assert_eq!(super::indent("one\n\ntwo", "> "), "> one\n>\n> two");
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::BTreeMap, ops::Range, path::PathBuf};
+
+use crate::{Project, Result};
+
+const COMMENT_START: &str = "<!--";
+const COMMENT_END: &str = "-->";
+const REVIEW_PREFIX: &str = "review(";
+
+/// An editorial note left in a document, such as
+/// `<!-- review(alice): needs update for v2 -->`. Review comments are
+/// removed before documents are rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewComment {
+    pub assignee: String,
+    pub text: String,
+    /// The line of the document the comment starts on
+    pub line: usize,
+}
+
+/// The review comments in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageComments {
+    pub url: String,
+    pub path: PathBuf,
+    pub comments: Vec<ReviewComment>,
+}
+
+/// The review comments in a project, grouped by assignee and then by page.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReviewReport {
+    pub assignees: BTreeMap<String, Vec<PageComments>>,
+}
+
+impl ReviewReport {
+    pub fn len(&self) -> usize {
+        self.assignees
+            .values()
+            .flatten()
+            .map(|p| p.comments.len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Collect the review comments in every document of the project.
+pub fn collect(project: &Project) -> Result<ReviewReport> {
+    let mut report = ReviewReport::default();
+    for document in project.root_folder.iter_all_documents() {
+        let content = String::from_utf8(document.file_path.read()?.to_vec())?;
+        let (_, comments) = strip_review_comments(&content);
+        let mut by_assignee: BTreeMap<String, Vec<ReviewComment>> = BTreeMap::new();
+        for comment in comments {
+            by_assignee
+                .entry(comment.assignee.clone())
+                .or_default()
+                .push(comment);
+        }
+        for (assignee, comments) in by_assignee {
+            report
+                .assignees
+                .entry(assignee)
+                .or_default()
+                .push(PageComments {
+                    url: document.url.clone(),
+                    path: document.file_path.relative_path.clone(),
+                    comments,
+                });
+        }
+    }
+    for pages in report.assignees.values_mut() {
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+    }
+    Ok(report)
+}
+
+/// Remove the review comments from a document's markdown, returning the
+/// markdown without them and the comments. Lines that only held a comment
+/// are removed.
+pub fn strip_review_comments(content: &str) -> (String, Vec<ReviewComment>) {
+    let found = find_review_comments(content);
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, _) in found.iter() {
+        let line_start = content[..range.start]
+            .rfind('\n')
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let line_end = end_of_line(content, range.end);
+        let before = &content[last.max(line_start)..range.start];
+        let after = &content[range.end..line_end];
+        if line_start >= last && before.trim().is_empty() && after.trim().is_empty() {
+            // The comment was on a line of its own
+            out.push_str(&content[last..line_start]);
+            last = line_end;
+        } else if before.trim().is_empty() {
+            out.push_str(&content[last..range.start]);
+            last = range.end + (after.len() - after.trim_start_matches(' ').len());
+        } else {
+            out.push_str(content[last..range.start].trim_end_matches(' '));
+            last = range.end;
+        }
+    }
+    out.push_str(&content[last..]);
+    (out, found.into_iter().map(|(_, c)| c).collect())
+}
+
+/// Replace the review comments in a document's markdown with spaces, so it
+/// can be parsed while node positions still match the original content.
+pub fn mask_review_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, _) in find_review_comments(content) {
+        out.push_str(&content[last..range.start]);
+        out.extend(
+            content[range.clone()]
+                .bytes()
+                .map(|b| if b == b'\n' { '\n' } else { ' ' }),
+        );
+        last = range.end;
+    }
+    out.push_str(&content[last..]);
+    out
+}
+
+/// Find the review comments in a document's markdown, along with where they
+/// are. Comments in fenced code blocks are ignored.
+fn find_review_comments(content: &str) -> Vec<(Range<usize>, ReviewComment)> {
+    let mut found = vec![];
+    let mut fence: Option<&str> = None;
+    let mut line_start = 0;
+    while line_start < content.len() {
+        let mut line_end = end_of_line(content, line_start);
+        let trimmed = content[line_start..line_end].trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => {}
+        }
+        let mut from = line_start;
+        while fence.is_none() {
+            let Some((range, assignee, text)) = find_review_comment(content, from, line_end) else {
+                break;
+            };
+            from = range.end;
+            // A comment can end on a later line
+            line_end = line_end.max(end_of_line(content, range.end));
+            let line = content[..range.start].matches('\n').count() + 1;
+            found.push((
+                range,
+                ReviewComment {
+                    assignee,
+                    text,
+                    line,
+                },
+            ));
+        }
+        line_start = line_end;
+    }
+    found
+}
+
+/// Find a review comment starting between `from` and `to`, returning where
+/// it is, its assignee and its text.
+fn find_review_comment(
+    content: &str,
+    from: usize,
+    to: usize,
+) -> Option<(Range<usize>, String, String)> {
+    let mut offset = from;
+    while let Some(start) = content[offset..to].find(COMMENT_START) {
+        let start = offset + start;
+        offset = start + COMMENT_START.len();
+        let end = content[offset..].find(COMMENT_END)? + offset;
+        let Some(body) = content[offset..end]
+            .trim_start()
+            .strip_prefix(REVIEW_PREFIX)
+        else {
+            continue;
+        };
+        let Some((assignee, text)) = body.split_once(')') else {
+            continue;
+        };
+        let Some(text) = text.trim_start().strip_prefix(':') else {
+            continue;
+        };
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        return Some((
+            start..end + COMMENT_END.len(),
+            assignee.trim().to_string(),
+            text,
+        ));
+    }
+    None
+}
+
+/// The offset after the end of the line containing `offset`.
+fn end_of_line(content: &str, offset: usize) -> usize {
+    content[offset..]
+        .find('\n')
+        .map(|i| offset + i + 1)
+        .unwrap_or(content.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    use super::{collect, mask_review_comments, strip_review_comments, ReviewComment};
+
+    #[test]
+    fn test_strip_review_comments() {
+        let content = "# Setup\n\n<!-- review(alice): needs update for v2 -->\nInstall it. <!-- review(bob): link\n  the installer --> Then run it.\n<!-- a regular comment -->\n\n```\n<!-- review(carol): not a comment -->\n```\n";
+        let (stripped, comments) = strip_review_comments(content);
+        assert_eq!(
+            stripped,
+            "# Setup\n\nInstall it. Then run it.\n<!-- a regular comment -->\n\n```\n<!-- review(carol): not a comment -->\n```\n"
+        );
+        assert_eq!(
+            comments,
+            vec![
+                ReviewComment {
+                    assignee: "alice".into(),
+                    text: "needs update for v2".into(),
+                    line: 3,
+                },
+                ReviewComment {
+                    assignee: "bob".into(),
+                    text: "link the installer".into(),
+                    line: 4,
+                },
+            ]
+        );
+
+        let masked = mask_review_comments(content);
+        assert_eq!(masked.len(), content.len());
+        assert!(masked
+            .starts_with("# Setup\n\n                                           \nInstall it."));
+    }
+
+    #[test]
+    fn test_collect() {
+        let project = project_fixture();
+        let report = collect(&project).unwrap();
+        let alice = report.assignees.get("alice").unwrap();
+        assert_eq!(alice[0].url, "/other/review");
+        assert_eq!(alice[0].comments.len(), 2);
+        assert_eq!(report.assignees.get("bob").unwrap()[0].comments.len(), 1);
+        assert_eq!(report.len(), 3);
+
+        let doc = project.get_document_for_url("/other/review").unwrap();
+        let html = doc.body(&project).unwrap();
+        assert!(!html.contains("review("));
+        assert!(html.contains("Deploy the service"));
+    }
+}
@@ -0,0 +1,138 @@
+use markdown::mdast::Node;
+
+use crate::{
+    renderer::step_slug, Document, PageType, Project, RenderContext, Renderer, Result, TextRenderer,
+};
+
+/// A step of a runbook, for tools that walk operators through it or run
+/// its commands.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RunbookStep {
+    /// The number of the step within its procedure
+    pub number: usize,
+    pub title: String,
+    /// The anchor linking to the step on its page
+    pub anchor: String,
+    /// The plain text of the step
+    pub text: String,
+    /// The code blocks of the step
+    pub commands: Vec<String>,
+}
+
+/// The steps of every procedure on a runbook page, empty for other pages.
+pub fn steps(project: &Project, document: &Document) -> Result<Vec<RunbookStep>> {
+    if document.frontmatter.page_type != PageType::Runbook {
+        return Ok(vec![]);
+    }
+    let mut renderer = TextRenderer::new(RenderContext::new(project, document));
+    renderer.link_urls = false;
+    let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+    let mut steps = vec![];
+    collect_steps(&renderer, &ast, &mut steps)?;
+    Ok(steps)
+}
+
+/// Flatten a runbook into a standalone checklist for an incident ticket,
+/// with code included from other files and the steps as a markdown task
+/// list.
+pub fn checklist(project: &Project, document: &Document, markdown: bool) -> Result<String> {
+    if document.frontmatter.page_type != PageType::Runbook {
+        return Err(crate::Error::new(format!(
+            "{} isn't a runbook, set `type: runbook` in its front matter",
+            document.url
+        )));
+    }
+    let renderer = TextRenderer::new(RenderContext::new(project, document));
+    if !markdown {
+        return renderer.render();
+    }
+    let mut out = String::new();
+    if !document.frontmatter.title.is_empty() {
+        out.push_str(&format!("# {}\n\n", document.frontmatter.title));
+    }
+    let url = project.details.page_url(&document.url, document.is_index());
+    if let Some(url) = project.details.absolute_url(&url) {
+        out.push_str(&format!("Runbook: <{}>\n\n", url));
+    }
+    out.push_str(&renderer.render_body()?);
+    Ok(out)
+}
+
+/// Find the `Steps` components below a node, numbering the steps of each
+/// as they are on the page.
+fn collect_steps(renderer: &TextRenderer, node: &Node, steps: &mut Vec<RunbookStep>) -> Result<()> {
+    let children = node.children().map(|c| c.as_slice()).unwrap_or_default();
+    match node {
+        Node::MdxJsxFlowElement(el) if el.name.as_deref() == Some("Steps") => {
+            let mut number = 0;
+            for child in children {
+                let Node::MdxJsxFlowElement(step) = child else {
+                    continue;
+                };
+                if step.name.as_deref() != Some("Step") {
+                    continue;
+                }
+                number += 1;
+                let attrs = renderer.convert_component_attributes(&step.attributes);
+                let mut commands = vec![];
+                collect_commands(child, &mut commands);
+                steps.push(RunbookStep {
+                    number,
+                    title: attrs.get("title").cloned().unwrap_or_default(),
+                    anchor: step_slug(&attrs, number),
+                    text: renderer.render_nodes(&step.children)?.trim().to_string(),
+                    commands,
+                });
+            }
+        }
+        _ => {
+            for child in children {
+                collect_steps(renderer, child, steps)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn collect_commands(node: &Node, commands: &mut Vec<String>) {
+    match node {
+        Node::Code(code) => commands.push(code.value.clone()),
+        _ => {
+            for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+                collect_commands(child, commands);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    #[test]
+    pub fn test_runbook_steps() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/other/runbook").unwrap();
+        let steps = super::steps(&project, doc).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].title, "Drain the node");
+        assert_eq!(steps[0].anchor, "step-drain-the-node");
+        assert_eq!(
+            steps[0].commands,
+            vec!["kubectl cordon node-1", "kubectl drain node-1"]
+        );
+        assert_eq!(steps[1].number, 2);
+        assert_eq!(steps[1].anchor, "step-2");
+        assert!(steps[1].text.starts_with("Wait for the pods to move."));
+
+        let checklist = super::checklist(&project, doc, true).unwrap();
+        assert!(checklist.starts_with("# Replace a node\n\n- [ ] 1. Drain the node\n\n"));
+        assert!(checklist.contains("\n\n      kubectl drain node-1\n"));
+        assert!(checklist.contains("- [ ] 2.\n\n  Wait for the pods to move."));
+
+        // Steps on other pages aren't exported
+        let doc = project.get_document_for_url("/other/steps").unwrap();
+        assert!(super::steps(&project, doc).unwrap().is_empty());
+        assert!(super::checklist(&project, doc, true).is_err());
+    }
+}
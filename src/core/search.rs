@@ -0,0 +1,312 @@
+use std::collections::{BTreeMap, HashMap};
+
+use markdown::mdast::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    renderer::slugify, Document, OutputTarget, Project, RenderContext, Renderer, Result,
+    SearchWeights, TextRenderer,
+};
+
+/// A searchable section of a document.
+/// Each document produces one record for the content before its first
+/// heading, and one record for each heading.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SearchRecord {
+    /// The title of the document containing the section
+    pub title: String,
+    /// The heading text of the section. This is empty for the content
+    /// before the first heading.
+    pub heading: String,
+    /// The url of the section, including the heading anchor
+    pub url: String,
+    /// The plain text content of the section
+    pub text: String,
+    /// The description of the document, set on the document's first record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    /// Multiplies the score of the record, from the project's folder boosts
+    pub boost: f32,
+}
+
+/// The search index loaded by the site's search box.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SearchIndex {
+    /// The field weights used by the client side ranker
+    pub weights: SearchWeights,
+    /// Lowercased synonyms for each word. Synonyms apply in both directions,
+    /// so every configured synonym also maps back to its term.
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    /// Lowercased words removed from search queries and from the text of
+    /// each record
+    pub stop_words: Vec<String>,
+    pub records: Vec<SearchRecord>,
+}
+
+impl SearchIndex {
+    /// Build the search index for every document in the project.
+    pub fn build(project: &Project) -> Result<Self> {
+        let details = &project.details.search;
+        let stop_words: Vec<String> = details
+            .stop_words
+            .iter()
+            .map(|w| w.to_lowercase())
+            .collect();
+        let mut records = vec![];
+        for document in project
+            .root_folder
+            .iter_all_documents()
+            .filter(|d| d.has_format(OutputTarget::Html))
+        {
+            records.append(&mut document_records(project, document)?);
+        }
+        for record in records.iter_mut() {
+            record.text = remove_stop_words(&record.text, &stop_words);
+        }
+        Ok(Self {
+            weights: details.weights.clone(),
+            synonyms: synonym_map(&details.synonyms),
+            stop_words,
+            records,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Split a document into one record per heading section.
+pub fn document_records(project: &Project, document: &Document) -> Result<Vec<SearchRecord>> {
+    let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+    let page_url = format!(
+        "{}{}",
+        project.details.base_url,
+        document.url.trim_start_matches('/')
+    );
+    let boost = folder_boost(project, document);
+    let new_record = |heading: String, anchor: Option<String>| SearchRecord {
+        title: document.frontmatter.title.clone(),
+        url: match anchor {
+            Some(anchor) => format!("{}#{}", page_url, anchor),
+            None => page_url.clone(),
+        },
+        description: None,
+        heading,
+        text: String::new(),
+        tags: document.frontmatter.tags.clone(),
+        boost,
+    };
+
+    // Sections are indexed as they read, with the content of components
+    // such as CSV tables, but without link urls
+    let renderer = TextRenderer {
+        link_urls: false,
+        ..TextRenderer::new(RenderContext::new(project, document))
+    };
+    let mut records = vec![new_record(String::new(), None)];
+    for node in ast.children().map(|c| c.as_slice()).unwrap_or_default() {
+        if let Node::Heading(heading) = node {
+            let anchor = heading.children.iter().find_map(|child| match child {
+                Node::Text(text) => Some(slugify(&text.value)),
+                _ => None,
+            });
+            records.push(new_record(plain_text(node), anchor));
+        } else if let Some(record) = records.last_mut() {
+            let text = index_text(&renderer.render_node(node)?);
+            if !text.is_empty() {
+                if !record.text.is_empty() {
+                    record.text.push(' ');
+                }
+                record.text.push_str(&text);
+            }
+        }
+    }
+
+    let mut records = records
+        .into_iter()
+        .filter(|r| !r.heading.is_empty() || !r.text.is_empty())
+        .collect::<Vec<_>>();
+    if let Some(record) = records.first_mut() {
+        record.description = document.description();
+    }
+    Ok(records)
+}
+
+/// Join the words of rendered text, leaving out the borders of tables and
+/// other words without a letter or digit.
+fn index_text(text: &str) -> String {
+    text.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Remove the stop words from a record's text, they would only make the
+/// index larger since queries never search for them.
+fn remove_stop_words(text: &str, stop_words: &[String]) -> String {
+    if stop_words.is_empty() {
+        return text.to_string();
+    }
+    text.split_whitespace()
+        .filter(|word| {
+            let word = word
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            !stop_words.contains(&word)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Build a lookup of synonyms for each word, adding the reverse of every
+/// configured synonym.
+fn synonym_map(synonyms: &HashMap<String, Vec<String>>) -> BTreeMap<String, Vec<String>> {
+    let mut map: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut add = |word: String, synonym: String| {
+        let entry = map.entry(word).or_default();
+        if !entry.contains(&synonym) {
+            entry.push(synonym);
+        }
+    };
+    for (term, words) in synonyms {
+        let term = term.to_lowercase();
+        for word in words.iter().map(|w| w.to_lowercase()) {
+            add(term.clone(), word.clone());
+            add(word, term.clone());
+        }
+    }
+    map
+}
+
+/// The boost for the most specific configured folder containing the
+/// document. A folder's index page shares the folder's url, so it is
+/// boosted along with the rest of the folder.
+fn folder_boost(project: &Project, document: &Document) -> f32 {
+    let url = document.url.trim_matches('/');
+    project
+        .details
+        .search
+        .folder_boosts
+        .iter()
+        .map(|(folder, boost)| (folder.trim_matches('/'), *boost))
+        .filter(|(folder, _)| {
+            url.strip_prefix(folder)
+                .is_some_and(|rest| folder.is_empty() || rest.is_empty() || rest.starts_with('/'))
+        })
+        .max_by_key(|(folder, _)| folder.len())
+        .map(|(_, boost)| boost)
+        .unwrap_or(1.0)
+}
+
+/// Extract the readable text from a node, separating block level content
+/// with spaces.
+pub(crate) fn plain_text(node: &Node) -> String {
+    match node {
+        Node::Yaml(_)
+        | Node::Toml(_)
+        | Node::MdxjsEsm(_)
+        | Node::MdxFlowExpression(_)
+        | Node::MdxTextExpression(_)
+        | Node::Html(_) => String::new(),
+        Node::Text(t) => t.value.clone(),
+        Node::InlineCode(c) => c.value.clone(),
+        Node::Code(c) => c.value.clone(),
+        Node::Root(_)
+        | Node::BlockQuote(_)
+        | Node::List(_)
+        | Node::ListItem(_)
+        | Node::Table(_)
+        | Node::TableRow(_)
+        | Node::TableCell(_)
+        | Node::MdxJsxFlowElement(_) => node
+            .children()
+            .map(|children| {
+                children
+                    .iter()
+                    .map(plain_text)
+                    .filter(|t| !t.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default(),
+        _ => node
+            .children()
+            .map(|children| children.iter().map(plain_text).collect::<String>())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::project::tests::project_fixture;
+
+    use super::{document_records, SearchIndex};
+
+    #[test]
+    fn test_heading_sections() {
+        let project = project_fixture();
+        let doc = project.get_document_for_url("/pages/large").unwrap();
+        let records = document_records(&project, doc).unwrap();
+
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].heading, "Heading generator");
+        assert_eq!(records[0].url, "/pages/large#heading-generator");
+        assert!(records[0].text.starts_with("Lorem ipsum"));
+        assert_eq!(records[1].heading, "Second level depth");
+        assert!(records[1].text.starts_with("Scelerisque purus"));
+        assert_eq!(records[3].url, "/pages/large#third-level-depth");
+        assert!(records[3].text.contains("pub struct CodexPath"));
+        assert_eq!(records[0].boost, 1.0);
+        assert!(records[0]
+            .description
+            .as_ref()
+            .is_some_and(|d| d.starts_with("Lorem ipsum")));
+        assert!(records[1].description.is_none());
+    }
+
+    #[test]
+    fn test_folder_boosts() {
+        let mut project = project_fixture();
+        let boosts = &mut project.details.search.folder_boosts;
+        boosts.insert("/pages/".to_string(), 0.5);
+        boosts.insert("other/nested".to_string(), 2.0);
+        boosts.insert("pag".to_string(), 4.0);
+
+        let doc = project.get_document_for_url("/pages/large").unwrap();
+        let records = document_records(&project, doc).unwrap();
+        assert_eq!(records[0].boost, 0.5);
+
+        let doc = project.get_document_for_url("/pages").unwrap();
+        let records = document_records(&project, doc).unwrap();
+        assert_eq!(records[0].boost, 0.5);
+
+        let doc = project.get_document_for_url("/other/alert").unwrap();
+        let records = document_records(&project, doc).unwrap();
+        assert_eq!(records[0].boost, 1.0);
+    }
+
+    #[test]
+    fn test_synonyms_and_stop_words() {
+        let mut project = project_fixture();
+        project.details.search.synonyms.insert(
+            "Auth".to_string(),
+            vec!["authentication".to_string(), "login".to_string()],
+        );
+        project.details.search.stop_words = vec!["The".to_string(), "a".to_string()];
+
+        let index = SearchIndex::build(&project).unwrap();
+        assert_eq!(index.synonyms["auth"], vec!["authentication", "login"]);
+        assert_eq!(index.synonyms["authentication"], vec!["auth"]);
+        assert_eq!(index.synonyms["login"], vec!["auth"]);
+        assert_eq!(index.stop_words, vec!["the", "a"]);
+
+        let record = index
+            .records
+            .iter()
+            .find(|r| r.url == "/other/math")
+            .unwrap();
+        assert!(record.text.starts_with("area is"));
+    }
+}
@@ -0,0 +1,192 @@
+use markdown::mdast::{AttributeContent, AttributeValue, MdxJsxAttribute, Node};
+
+use crate::{Document, Error, OutputTarget, Project, Result};
+
+/// The folder in the build extracted blocks are written to.
+pub const SNIPPETS_FOLDER: &str = "snippets";
+
+/// The raw content of an `ExtractBlock` component, published as a file so
+/// scripts shown in the documentation can be downloaded as they are, such
+/// as `curl https://docs.example.com/snippets/install-script.sh | sh`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub id: String,
+    pub language: Option<String>,
+    pub content: String,
+    /// The url of the page the block is in
+    pub page: String,
+}
+
+impl Snippet {
+    /// The name of the snippet's file in the snippets folder.
+    pub fn file_name(&self) -> String {
+        file_name(&self.id, self.language.as_deref())
+    }
+}
+
+/// The name of a snippet's file, from its id and the extension used for
+/// its language.
+pub fn file_name(id: &str, language: Option<&str>) -> String {
+    let extension = match language.map(|l| l.to_lowercase()) {
+        None => "txt".to_string(),
+        Some(l) => match l.as_str() {
+            "bash" | "sh" | "shell" | "zsh" => "sh".to_string(),
+            "python" | "py" => "py".to_string(),
+            "javascript" | "js" => "js".to_string(),
+            "typescript" | "ts" => "ts".to_string(),
+            "powershell" | "ps1" => "ps1".to_string(),
+            "ruby" | "rb" => "rb".to_string(),
+            "yaml" | "yml" => "yml".to_string(),
+            "text" | "plain" => "txt".to_string(),
+            _ => l,
+        },
+    };
+    format!("{}.{}", id, extension)
+}
+
+/// Snippet ids are used as file names, so they are limited to letters,
+/// numbers, `-` and `_`.
+pub fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::new(format!(
+            "Invalid ExtractBlock id \"{}\", ids can only contain letters, numbers, - and _",
+            id
+        )));
+    }
+    Ok(())
+}
+
+/// Collect the extracted blocks of every page in the site. Ids have to be
+/// unique across the project since every snippet shares one folder.
+pub fn collect(project: &Project) -> Result<Vec<Snippet>> {
+    let mut snippets: Vec<Snippet> = vec![];
+    for document in project
+        .root_folder
+        .iter_all_documents()
+        .filter(|d| d.has_format(OutputTarget::Html))
+    {
+        for snippet in document_snippets(project, document)? {
+            if let Some(other) = snippets.iter().find(|s| s.id == snippet.id) {
+                return Err(Error::new(format!(
+                    "The ExtractBlock id {} is used by both {} and {}",
+                    snippet.id, other.page, snippet.page
+                )));
+            }
+            snippets.push(snippet);
+        }
+    }
+    Ok(snippets)
+}
+
+/// The snippet published under a file name, for the development server.
+pub fn find(project: &Project, name: &str) -> Result<Option<Snippet>> {
+    Ok(collect(project)?
+        .into_iter()
+        .find(|s| s.file_name() == name))
+}
+
+/// The extracted blocks in a document.
+pub fn document_snippets(project: &Project, document: &Document) -> Result<Vec<Snippet>> {
+    let ast = crate::document::parse(&document.file_path, &project.details.markdown)?;
+    let mut snippets = vec![];
+    find_blocks(&ast, &document.url, &mut snippets)?;
+    Ok(snippets)
+}
+
+fn find_blocks(node: &Node, page: &str, snippets: &mut Vec<Snippet>) -> Result<()> {
+    if let Node::MdxJsxFlowElement(el) = node {
+        if el.name.as_deref() == Some("ExtractBlock") {
+            let attr = |attr_name: &str| {
+                el.attributes.iter().find_map(|a| match a {
+                    AttributeContent::Property(MdxJsxAttribute {
+                        name,
+                        value: Some(AttributeValue::Literal(value)),
+                    }) if name == attr_name => Some(value.clone()),
+                    _ => None,
+                })
+            };
+            let id = attr("id").ok_or_else(|| Error::new("No id specified"))?;
+            validate_id(&id)?;
+            snippets.push(Snippet {
+                id,
+                language: attr("lang"),
+                content: block_content(&el.children),
+                page: page.to_string(),
+            });
+            return Ok(());
+        }
+    }
+    for child in node.children().map(|c| c.as_slice()).unwrap_or_default() {
+        find_blocks(child, page, snippets)?;
+    }
+    Ok(())
+}
+
+/// The raw content of a block: its code blocks, or its text when it has
+/// none.
+fn block_content(children: &[Node]) -> String {
+    let code = children
+        .iter()
+        .filter_map(|n| match n {
+            Node::Code(code) => Some(code.value.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut content = if code.is_empty() {
+        children
+            .iter()
+            .map(crate::search::plain_text)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else {
+        code.join("\n\n")
+    };
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{refactor::tests::writable_fixture, Project};
+
+    use super::{collect, file_name, find};
+
+    #[test]
+    fn test_snippets() {
+        assert_eq!(file_name("install", Some("bash")), "install.sh");
+        assert_eq!(file_name("notes", None), "notes.txt");
+        assert_eq!(file_name("config", Some("toml")), "config.toml");
+
+        let root = writable_fixture("snippets");
+        std::fs::write(
+            root.join("other/install.md"),
+            "---\ntitle: Install\n---\n\n<ExtractBlock id=\"install-script\" lang=\"bash\">\n\n```bash\ncurl -O https://example.com/codex\nchmod +x codex\n```\n\n</ExtractBlock>\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+        let snippet = find(&project, "install-script.sh").unwrap().unwrap();
+        assert_eq!(
+            snippet.content,
+            "curl -O https://example.com/codex\nchmod +x codex\n"
+        );
+        assert_eq!(snippet.page, "/other/install");
+        let doc = project.get_document_for_url("/other/install").unwrap();
+        let html = doc.body(&project).unwrap();
+        assert!(html.contains("chmod"));
+        assert!(html.contains(r#"href="/snippets/install-script.sh""#));
+
+        std::fs::write(
+            root.join("other/again.md"),
+            "---\ntitle: Again\n---\n\n<ExtractBlock id=\"install-script\">\nsh\n</ExtractBlock>\n",
+        )
+        .unwrap();
+        let project = Project::load(&root, false).unwrap();
+        assert!(collect(&project).is_err());
+    }
+}
@@ -68,7 +68,324 @@ fn join_url(
     Ok(())
 }
 
+/// The URL of a static file, using its fingerprinted name when the build
+/// assigned one: `{{asset "css/article.css"}}`.
+fn asset(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let name = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+    let project = &ctx.data()["project"];
+    let base_url = ctx.data()["asset_url"]
+        .as_str()
+        .or(project["base_url"].as_str())
+        .unwrap_or("/");
+    let name = project["assets"][name].as_str().unwrap_or(name);
+    let _ = out.write(&format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        name.trim_start_matches('/')
+    ));
+    Ok(())
+}
+
+/// The subresource integrity attributes of a static file, when the build
+/// computed them: `<script src="{{asset "js/theme.js"}}"{{integrity "js/theme.js"}}>`.
+fn integrity(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let name = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+    if let Some(hash) = ctx.data()["project"]["integrity"][name].as_str() {
+        let _ = out.write(&format!(r#" integrity="{}" crossorigin="anonymous""#, hash));
+    }
+    Ok(())
+}
+
+/// Escape a value for use in HTML, since templates output values as they
+/// are: `<meta name="description" content="{{escape description}}">`.
+fn escape(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+    let _ = out.write(&handlebars::html_escape(value));
+    Ok(())
+}
+
+/// A message of the interface text in the page's language, falling back to
+/// the message's name: `{{t "on_this_page"}}`.
+fn t(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let name = h.param(0).and_then(|v| v.value().as_str()).unwrap_or("");
+    let message = ctx.data()["messages"][name].as_str().unwrap_or(name);
+    let _ = out.write(message);
+    Ok(())
+}
+
+/// The string value of a helper's parameter, empty when it is missing or
+/// isn't a string.
+fn param_str<'a>(h: &'a Helper, index: usize) -> &'a str {
+    h.param(index)
+        .and_then(|v| v.value().as_str())
+        .unwrap_or("")
+}
+
+/// Format a date, such as a page's `modified` time, with a chrono format
+/// string: `{{date modified "%d %B %Y"}}`. Defaults to `%B %-d, %Y`.
+/// Values that aren't dates are output as they are.
+fn date(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = param_str(h, 0);
+    let format = h
+        .param(1)
+        .and_then(|v| v.value().as_str())
+        .unwrap_or("%B %-d, %Y");
+    let date = chrono::DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%z")
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(value))
+        .map(|d| d.naive_local())
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default())
+        });
+    let Ok(date) = date else {
+        let _ = out.write(value);
+        return Ok(());
+    };
+    // Formatting panics on an invalid specifier, so it is checked first
+    let items = chrono::format::StrftimeItems::new(format).collect::<Vec<_>>();
+    if items
+        .iter()
+        .any(|item| matches!(item, chrono::format::Item::Error))
+    {
+        return Err(handlebars::RenderErrorReason::Other(format!(
+            "Invalid date format \"{}\"",
+            format
+        ))
+        .into());
+    }
+    let _ = out.write(&date.format_with_items(items.into_iter()).to_string());
+    Ok(())
+}
+
+fn upper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let _ = out.write(&param_str(h, 0).to_uppercase());
+    Ok(())
+}
+
+fn lower(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let _ = out.write(&param_str(h, 0).to_lowercase());
+    Ok(())
+}
+
+/// Upper case the first letter of a string.
+fn capitalize_word(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn capitalize(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let _ = out.write(&capitalize_word(param_str(h, 0)));
+    Ok(())
+}
+
+/// Upper case the first letter of every word: `{{title_case "getting started"}}`.
+fn title_case(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let words = param_str(h, 0)
+        .split(' ')
+        .map(capitalize_word)
+        .collect::<Vec<_>>();
+    let _ = out.write(&words.join(" "));
+    Ok(())
+}
+
+/// The anchor a heading with the text gets: `{{slugify title}}`.
+fn slugify(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let _ = out.write(&crate::renderer::slugify(param_str(h, 0)));
+    Ok(())
+}
+
+/// A value as pretty printed JSON, for debugging templates or passing data
+/// to scripts: `<script>const toc = {{json toc}};</script>`.
+fn json(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = h.param(0).map(|v| v.value().clone()).unwrap_or_default();
+    let _ = out.write(&serde_json::to_string_pretty(&value).unwrap_or_default());
+    Ok(())
+}
+
+/// Shorten a string to at most a number of characters, ending it with an
+/// ellipsis when it is cut: `{{truncate description 80}}`.
+fn truncate(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = param_str(h, 0);
+    let length = h.param(1).and_then(|v| v.value().as_u64()).unwrap_or(100) as usize;
+    if value.chars().count() <= length {
+        let _ = out.write(value);
+    } else {
+        let cut = value.chars().take(length).collect::<String>();
+        let _ = out.write(&format!("{}…", cut.trim_end()));
+    }
+    Ok(())
+}
+
+/// Renders markdown into HTML for the `markdown` helper, given the markdown
+/// and the data the template is rendered with.
+pub type MarkdownRenderer<'a> =
+    dyn Fn(&str, &serde_json::Value) -> Result<String> + Send + Sync + 'a;
+
+/// Render a markdown string, such as a field of a data file, into HTML:
+/// `{{markdown item.description}}`.
+struct MarkdownHelper<'a> {
+    render: &'a MarkdownRenderer<'a>,
+}
+
+impl HelperDef for MarkdownHelper<'_> {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let html = (self.render)(param_str(h, 0), ctx.data())
+            .map_err(|e| handlebars::RenderErrorReason::Other(e.to_string()))?;
+        let _ = out.write(&html);
+        Ok(())
+    }
+}
+
+/// The helpers available to every template. The comparison and logic
+/// helpers are provided by handlebars itself.
+pub const BUILTIN_HELPERS: [&str; 26] = [
+    "id",
+    "mul",
+    "join_url",
+    "asset",
+    "integrity",
+    "t",
+    "escape",
+    "date",
+    "upper",
+    "lower",
+    "capitalize",
+    "title_case",
+    "slugify",
+    "json",
+    "truncate",
+    "markdown",
+    "eq",
+    "ne",
+    "gt",
+    "gte",
+    "lt",
+    "lte",
+    "and",
+    "or",
+    "not",
+    "len",
+];
+
+/// How a template is rendered.
+#[derive(Default)]
+pub struct TemplateOptions<'a> {
+    /// The name errors refer to the template by, usually its path
+    pub name: &'a str,
+    /// Helpers written as rhai scripts, given as the helper's name and its
+    /// script
+    pub helpers: &'a [(String, String)],
+    /// Renders markdown for the `markdown` helper, which is only available
+    /// when it is set
+    pub markdown: Option<&'a MarkdownRenderer<'a>>,
+    /// Fail when the template outputs a variable that is missing, instead
+    /// of leaving it empty
+    pub strict: bool,
+}
+
 pub fn render_template<T>(data: T, template: &str) -> Result<String>
+where
+    T: serde::Serialize,
+{
+    render_template_with_options(
+        data,
+        template,
+        &TemplateOptions {
+            name: "template",
+            ..Default::default()
+        },
+    )
+}
+
+/// Render a template with the project's helpers and settings. Errors name
+/// the template and the line they occurred on.
+pub fn render_template_with_options<T>(
+    data: T,
+    template: &str,
+    options: &TemplateOptions,
+) -> Result<String>
 where
     T: serde::Serialize,
 {
@@ -76,8 +393,97 @@ where
     handlebars.register_helper("id", Box::<IdHelper>::default());
     handlebars.register_helper("mul", Box::new(mul));
     handlebars.register_helper("join_url", Box::new(join_url));
-    handlebars.register_template_string("template", template)?;
+    handlebars.register_helper("asset", Box::new(asset));
+    handlebars.register_helper("integrity", Box::new(integrity));
+    handlebars.register_helper("t", Box::new(t));
+    handlebars.register_helper("escape", Box::new(escape));
+    handlebars.register_helper("date", Box::new(date));
+    handlebars.register_helper("upper", Box::new(upper));
+    handlebars.register_helper("lower", Box::new(lower));
+    handlebars.register_helper("capitalize", Box::new(capitalize));
+    handlebars.register_helper("title_case", Box::new(title_case));
+    handlebars.register_helper("slugify", Box::new(slugify));
+    handlebars.register_helper("json", Box::new(json));
+    handlebars.register_helper("truncate", Box::new(truncate));
+    if let Some(render) = options.markdown {
+        handlebars.register_helper("markdown", Box::new(MarkdownHelper { render }));
+    }
+    for (name, script) in options.helpers {
+        handlebars
+            .register_script_helper(name, script)
+            .map_err(|e| crate::Error::new(format!("Helper {} is invalid: {}", name, e)))?;
+    }
+    handlebars.set_strict_mode(options.strict);
+    handlebars.register_template_string(options.name, template)?;
     handlebars.unregister_escape_fn();
-    let res = handlebars.render("template", &data)?;
+    let res = handlebars
+        .render(options.name, &data)
+        .map_err(|e| match e.reason() {
+            handlebars::RenderErrorReason::MissingVariable(Some(variable)) => {
+                crate::Error::new(format!(
+                    "Error rendering \"{}\" line {}, col {}: {} is missing",
+                    options.name,
+                    e.line_no.unwrap_or_default(),
+                    e.column_no.unwrap_or_default(),
+                    variable
+                ))
+            }
+            _ => e.into(),
+        })?;
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{render_template, render_template_with_options, TemplateOptions};
+
+    #[test]
+    pub fn test_builtin_helpers() {
+        let data = serde_json::json!({
+            "modified": "2024-03-05T10:00:00+0000",
+            "title": "getting started",
+            "tags": ["a"],
+        });
+        let render = |template: &str| render_template(&data, template).unwrap();
+        assert_eq!(render("{{date modified}}"), "March 5, 2024");
+        assert_eq!(render(r#"{{date "2024-03-05" "%d/%m/%Y"}}"#), "05/03/2024");
+        assert_eq!(render(r#"{{date "soon"}}"#), "soon");
+        let error = render_template(&data, r#"{{date modified "%Q"}}"#).unwrap_err();
+        assert!(error.to_string().contains("Invalid date format \"%Q\""));
+        assert_eq!(
+            render("{{upper title}} {{capitalize title}}"),
+            "GETTING STARTED Getting started"
+        );
+        assert_eq!(render("{{title_case title}}"), "Getting Started");
+        assert_eq!(render(r#"{{slugify "Why, though?"}}"#), "why-though");
+        assert_eq!(
+            render("{{truncate title 7}}|{{truncate title 20}}"),
+            "getting…|getting started"
+        );
+        assert_eq!(render("{{json tags}}"), "[\n  \"a\"\n]");
+        assert_eq!(
+            render(r#"{{#if (eq title "getting started")}}yes{{/if}}"#),
+            "yes"
+        );
+    }
+
+    #[test]
+    pub fn test_strict_templates() {
+        let template = "<h1>{{title}}</h1>\n<p>{{summary}}</p>";
+        let options = |strict| TemplateOptions {
+            name: "_internal/templates/article.html",
+            strict,
+            ..Default::default()
+        };
+        let data = serde_json::json!({ "title": "Install" });
+        assert_eq!(
+            render_template_with_options(&data, template, &options(false)).unwrap(),
+            "<h1>Install</h1>\n<p></p>"
+        );
+        let error = render_template_with_options(&data, template, &options(true)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"Error rendering "_internal/templates/article.html" line 2, col 4: summary is missing"#
+        );
+    }
+}
@@ -1,10 +1,21 @@
+mod metrics;
+mod progress;
 mod server;
+mod watcher;
 
 use anyhow::Result;
 use console::style;
-use core::{assets::EmbeddedAsset, HtmlRenderer, LatexRenderer, Project, Renderer};
+use core::{
+    assets::EmbeddedAsset,
+    build_state::{page_hash, BuildState},
+    HtmlRenderer, LatexRenderer, OutputTarget, Project,
+};
 use human_repr::{HumanCount, HumanDuration};
-use std::{io::Write, path::PathBuf};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use clap::{
     builder::{
@@ -19,12 +30,22 @@ enum RootCommands {
     /// The serve command starts a local web server to preview the site
     ///
     /// The web server will automatically re-scan the project folder and rebuild
-    /// the site with every request.
+    /// the site with every request. Open pages are reloaded when project files
+    /// change. Changes to stylesheets are applied without reloading the page.
     #[command()]
     Serve {
-        /// Port to listen on
+        /// Address to listen on, `0.0.0.0` serves the project to other
+        /// machines
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on, `0` picks a free port
         #[arg(short, long, default_value = "8080")]
         port: u16,
+        /// Serve `/_debug/ast?url=<page>`, describing how a page is parsed
+        /// and which templates render it, and mark the output of each
+        /// template in pages with comments
+        #[arg(long)]
+        debug: bool,
     },
     /// Build a static version of the site
     ///
@@ -34,7 +55,27 @@ enum RootCommands {
     /// It is recommended to use a continuous deployment system to automatically
     /// build and deploy the site, using this command.
     #[command()]
-    Build,
+    Build {
+        /// Fail the build if `codex lint` reports any issues, such as images
+        /// without alt text, or if rendering warns about a document, such as
+        /// a broken link or an unknown component
+        #[arg(long)]
+        strict: bool,
+        /// Fail the build if any file or folder is larger than its budget
+        #[arg(long)]
+        strict_budgets: bool,
+        /// Fail the build if a template outputs a variable that doesn't
+        /// exist, overriding `strict_templates`
+        #[arg(long)]
+        strict_templates: bool,
+        /// Build each page's article without the site layout, for embedding
+        /// in another application
+        ///
+        /// Fragments are built to `fragments.build_path`, and can be wrapped
+        /// in the template set by `fragments.template`.
+        #[arg(long)]
+        fragment: bool,
+    },
     /// Generate scaffolding for a new project.
     ///
     /// This will create a new folder with a basic configuration file.
@@ -57,6 +98,231 @@ enum RootCommands {
     /// Generate a LaTeX document
     #[command()]
     Latex,
+
+    /// Check the project for pages that may be out of date
+    ///
+    /// Pages generated from schema or data files record the hash of each
+    /// source in the build manifest as it was when the page was last edited.
+    /// This command warns about pages whose sources changed since then, so
+    /// the surrounding prose can be reviewed.
+    #[command()]
+    Check,
+
+    /// Create a new document
+    ///
+    /// Documents can be created from an archetype, a template stored in the
+    /// project's `_internal/archetypes` folder. The archetype's front matter
+    /// and content are copied into the new document.
+    #[command()]
+    New {
+        /// Path to the new document, relative to the project root
+        path: String,
+        /// The name of the archetype to create the document from
+        #[arg(short, long)]
+        archetype: Option<String>,
+        /// The title of the document. Defaults to the file name.
+        #[arg(short, long)]
+        title: Option<String>,
+    },
+
+    /// Render a single document and print it
+    ///
+    /// The text format gives readable plain text with wrapped paragraphs and
+    /// ASCII tables, for pasting into emails and other plain text places.
+    #[command()]
+    Render {
+        /// Path to the document, relative to the project root
+        file: String,
+        /// The format to render the document in
+        #[arg(short, long, default_value = "text", value_parser = ["html", "latex", "text"])]
+        format: String,
+    },
+
+    /// Give every document a stable id
+    ///
+    /// Documents without an `id` in their front matter are given one
+    /// generated from their url. Links to `id:<id>` target a document by its
+    /// id and keep working when the document moves. The ids are recorded in
+    /// `ids.yml` in the project root.
+    #[command()]
+    Ids,
+
+    /// Report which documents are missing a translation
+    ///
+    /// Each document in the default language is listed with the languages
+    /// it hasn't been translated to, and the translations that are older
+    /// than the document.
+    #[command()]
+    Translations,
+
+    /// Check documents for structural and content problems
+    ///
+    /// Documents are checked for missing and duplicate titles, headings that
+    /// skip a level, images without alt text, code blocks with trailing
+    /// whitespace and, when `lint.max_line_length` is set, long lines.
+    /// Documents created from an archetype are checked for the sections the
+    /// archetype requires. Rules can be turned off with `lint.ignore`.
+    #[command()]
+    Lint {
+        /// The format to report issues in
+        #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+        format: String,
+    },
+
+    /// Build the site and copy it to a deploy target
+    ///
+    /// Only files that changed since the last deploy are copied. The hash of
+    /// each deployed file is stored in the target, so deploys are fast and
+    /// running one twice makes no changes.
+    #[command()]
+    Deploy {
+        /// The folder to deploy to. Defaults to `deploy.target` in codex.yml
+        #[arg(short, long)]
+        target: Option<String>,
+        /// Remove files from previous deploys that are no longer built
+        #[arg(long)]
+        delete_orphans: bool,
+        /// Deploy a preview of the current branch
+        ///
+        /// The site is built with a base url for the branch and copied to the
+        /// matching folder in the target, set by `deploy.preview_path`.
+        #[arg(long)]
+        preview: bool,
+        /// The branch name used for a preview. Defaults to the branch from
+        /// the CI environment or git.
+        #[arg(long, requires = "preview")]
+        branch: Option<String>,
+    },
+
+    /// Restructure the project's documents
+    #[command(subcommand)]
+    Refactor(RefactorCommands),
+
+    /// Work with review comments left in documents
+    #[command(subcommand)]
+    Review(ReviewCommands),
+
+    /// Export the project's content for other tools
+    #[command(subcommand)]
+    Export(ExportCommands),
+
+    /// Work with runbook pages
+    #[command(subcommand)]
+    Runbook(RunbookCommands),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum RunbookCommands {
+    /// Flatten a runbook into a checklist for an incident ticket
+    ///
+    /// The page's steps become a task list that can be checked off, and code
+    /// included from other files is copied in. Pages are runbooks when
+    /// their front matter has `type: runbook`.
+    #[command()]
+    Export {
+        /// The url of the runbook, such as `/operations/failover`
+        url: String,
+        /// The format of the checklist
+        #[arg(short, long, default_value = "markdown", value_parser = ["markdown", "text"])]
+        format: String,
+        /// The file to write the checklist to. Defaults to standard output.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ExportCommands {
+    /// Export the plain text of every page as JSON lines for embedding
+    ///
+    /// Pages are chunked at their headings. Each record has the page's url,
+    /// the section's anchor, the path of headings containing it, its text,
+    /// and the page's tags.
+    #[command()]
+    Embeddings {
+        /// The file to write the records to. Defaults to standard output.
+        #[arg(short, long)]
+        output: Option<String>,
+        /// The most tokens in a chunk, estimated at four characters a token
+        #[arg(long, default_value = "500")]
+        max_tokens: usize,
+        /// The tokens repeated from the end of the previous chunk when a
+        /// section is split
+        #[arg(long, default_value = "50")]
+        overlap: usize,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ReviewCommands {
+    /// List the review comments in the project
+    ///
+    /// Review comments such as `<!-- review(alice): needs update -->` are
+    /// removed from the built site. The comments are listed grouped by
+    /// assignee and page.
+    #[command()]
+    Comments {
+        /// Only list the comments assigned to this person
+        #[arg(short, long)]
+        assignee: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum RefactorCommands {
+    /// Move a document to a new url
+    ///
+    /// The document's file is moved to match the new url and links to the old
+    /// url in every document are updated. A redirect from the old url is added
+    /// to the project configuration.
+    #[command()]
+    RenameUrl { from: String, to: String },
+    /// Move every heading in a document down one or more levels
+    ///
+    /// Heading anchors are generated from the heading text, so links to them
+    /// continue to work.
+    #[command()]
+    Demote {
+        /// Path to the document, relative to the project root
+        file: String,
+        /// Number of levels to move headings by
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u8).range(1..=5))]
+        by: u8,
+    },
+    /// Split a document into a folder with a page for each heading
+    ///
+    /// Content before the first heading becomes the folder's index page, so
+    /// the document keeps its url. Links to the moved sections are updated and
+    /// visitors following old anchors are redirected to the new pages.
+    #[command()]
+    Split {
+        /// Path to the document, relative to the project root
+        file: String,
+        /// The heading level to split the document at
+        #[arg(long, default_value = "2")]
+        by_heading: u8,
+    },
+    /// Merge the documents in a folder into a single document
+    ///
+    /// Each page becomes a section of the new document. Links to the pages
+    /// are updated and redirects are added from each page to its section.
+    #[command()]
+    Merge {
+        /// Path to the folder, relative to the project root
+        folder: String,
+        /// The heading level used for each page's section
+        #[arg(long, default_value = "2")]
+        heading_level: u8,
+    },
+    /// Move every heading in a document up one or more levels
+    #[command()]
+    Promote {
+        /// Path to the document, relative to the project root
+        file: String,
+        /// Number of levels to move headings by
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u8).range(1..=5))]
+        by: u8,
+    },
 }
 
 /// Custom styles for clap
@@ -80,6 +346,11 @@ pub struct Args {
     #[arg(short, long, default_value = ".")]
     root_path: String,
 
+    /// Print build events to stderr as JSON lines, for tools that report
+    /// build progress
+    #[arg(long)]
+    events: bool,
+
     #[command(subcommand)]
     command: RootCommands,
 }
@@ -88,10 +359,21 @@ fn main() {
     let args = Args::parse();
     match args.command {
         RootCommands::Serve { .. } => handle_command(server::serve),
-        RootCommands::Build => handle_command(command_build),
+        RootCommands::Build { .. } => handle_command(command_build),
         RootCommands::Init { .. } => handle_command(command_init),
         RootCommands::Eject => handle_command(eject_static_files),
         RootCommands::Latex => handle_command(command_latex),
+        RootCommands::Check => handle_command(command_check),
+        RootCommands::Refactor(_) => handle_command(command_refactor),
+        RootCommands::Review(_) => handle_command(command_review),
+        RootCommands::Export(_) => handle_command(command_export),
+        RootCommands::Runbook(_) => handle_command(command_runbook),
+        RootCommands::New { .. } => handle_command(command_new),
+        RootCommands::Render { .. } => handle_command(command_render),
+        RootCommands::Lint { .. } => handle_command(command_lint),
+        RootCommands::Ids => handle_command(command_ids),
+        RootCommands::Translations => handle_command(command_translations),
+        RootCommands::Deploy { .. } => handle_command(command_deploy),
     }
 }
 
@@ -116,7 +398,6 @@ fn command_init(args: &Args) -> Result<()> {
             core::assets::get_bytes("_internal/templates/scaffold_config.yml"),
         )?;
 
-
         let index_path = p.join("index.md");
         std::fs::write(
             index_path,
@@ -126,11 +407,45 @@ fn command_init(args: &Args) -> Result<()> {
     Ok(())
 }
 
+/// Set when the build is interrupted with Ctrl-C. Builds stop after the
+/// current file so they can be resumed.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Stop long running builds cleanly on Ctrl-C. A second Ctrl-C exits
+/// immediately.
+fn handle_cancel() {
+    let _ = ctrlc::set_handler(|| {
+        if CANCELLED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        eprintln!(
+            "{}",
+            style("Stopping after the current file, press Ctrl-C again to stop now").yellow()
+        );
+    });
+}
+
+/// Save the progress of a cancelled build and describe what remains.
+fn cancel_build(state: &BuildState, state_path: &Path, total: usize) -> anyhow::Error {
+    if let Err(e) = state.save(state_path) {
+        return anyhow::anyhow!("Build cancelled, and its progress couldn't be saved: {}", e);
+    }
+    anyhow::anyhow!(
+        "Build cancelled with {} of {} documents remaining, run the build again to resume",
+        total.saturating_sub(state.pages.len()),
+        total
+    )
+}
+
 /// Convienence function to handle commands
 fn handle_command(f: impl Fn(&Args) -> Result<()>) {
     let args = Args::parse();
     if let Err(e) = f(&args) {
-        eprintln!("Error: {}", e);
+        // Errors from rendering documents point at the component that failed
+        match e.downcast::<core::Error>() {
+            Ok(e) => eprintln!("{:?}", miette::Report::new(e)),
+            Err(e) => eprintln!("Error: {}", e),
+        }
         std::process::exit(1);
     }
 }
@@ -140,13 +455,26 @@ pub fn command_latex(args: &Args) -> Result<()> {
     let now = std::time::Instant::now();
     let root_path = PathBuf::from(&args.root_path);
     let project = Project::load(root_path, false)?;
-    let latex = build_latext(&project)?;
-
     let build_path = project.path.disk_path().join("dist");
     if !build_path.exists() {
         std::fs::create_dir(&build_path)?;
     }
 
+    handle_cancel();
+    let state_path = build_path.join(core::build_state::LATEX_STATE_FILE);
+    let mut state = BuildState::load(&state_path, &project)?;
+    let latex = build_latext(&project, &mut state);
+    if CANCELLED.load(Ordering::SeqCst) {
+        let total = project
+            .root_folder
+            .iter_all_documents()
+            .filter(|d| d.has_format(OutputTarget::Pdf))
+            .count();
+        return Err(cancel_build(&state, &state_path, total));
+    }
+    let latex = latex?;
+    BuildState::clear(&state_path)?;
+
     let mut f = std::fs::File::create(build_path.join("main.tex"))?;
     let size = latex.len();
     f.write_all(latex.as_bytes())?;
@@ -156,37 +484,307 @@ pub fn command_latex(args: &Args) -> Result<()> {
 
 /// internal command to build the site
 fn command_build(args: &Args) -> Result<()> {
+    let mut project = load_project(args)?;
+    build_project(args, &mut project)
+}
+
+/// Load the project for a build, printing its events when `--events` is set.
+fn load_project(args: &Args) -> Result<Project> {
+    let mut events = core::events::Events::default();
+    if args.events {
+        events.subscribe(|event| {
+            if let Ok(json) = serde_json::to_string(event) {
+                eprintln!("{}", json);
+            }
+        });
+    }
+    Ok(Project::load_with_events(
+        PathBuf::from(&args.root_path),
+        false,
+        events,
+    )?)
+}
+
+/// Whether `codex build --fragment` is building page fragments.
+fn is_fragment_build(args: &Args) -> bool {
+    matches!(args.command, RootCommands::Build { fragment: true, .. })
+}
+
+/// Record a file written to the build folder.
+fn file_written(project: &Project, path: &std::path::Path, size: usize) {
+    project.events.emit(core::events::Event::FileWritten {
+        path: path.to_path_buf(),
+        size,
+    });
+}
+
+/// Write a file to the build folder, creating its folder if needed.
+fn write_file(path: &std::path::Path, data: &[u8]) -> Result<usize> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, data)?;
+    Ok(data.len())
+}
+
+/// Remove the output of the previous build. The build folder is only removed
+/// when it is inside the project, so a misconfigured `build_path` can't
+/// delete the project itself.
+fn clean_build_folder(root_path: &std::path::Path, build_path: &std::path::Path) -> Result<()> {
+    if !build_path.exists() {
+        return Ok(());
+    }
+    let root = root_path.canonicalize()?;
+    let build = build_path.canonicalize()?;
+    if build == root || !build.starts_with(&root) {
+        return Ok(());
+    }
+    std::fs::remove_dir_all(build)?;
+    Ok(())
+}
+
+/// Build the static site for a loaded project.
+fn build_project(args: &Args, project: &mut Project) -> Result<()> {
+    // Verbose output lists every file, which would fight with the bar
+    let progress = progress::BuildProgress::attach(project, args.verbose || args.events);
+    if is_fragment_build(args) {
+        project.details.build_path = project.details.fragments.build_path.clone();
+    }
+    if let RootCommands::Build {
+        strict_templates: true,
+        ..
+    } = args.command
+    {
+        project.details.strict_templates = true;
+    }
+    if project.details.fingerprint_assets {
+        project.details.assets = core::assets::fingerprint_assets(project)?;
+    }
+    if project.details.subresource_integrity {
+        project.details.integrity = core::assets::integrity_hashes(project)?;
+    }
+    let project = &*project;
     let root_path = PathBuf::from(&args.root_path);
-    let project = Project::load(&root_path, false)?;
-    let build_path = root_path.join("dist");
+    if let RootCommands::Build { strict: true, .. } = args.command {
+        let issues = core::lint::lint(project)?;
+        if !issues.is_empty() {
+            print_lint_issues(&issues);
+            return Err(anyhow::anyhow!(
+                "{} issues found, the build was stopped because --strict was set",
+                issues.len()
+            ));
+        }
+    }
+    // Read before anything is written so `check` compares against the
+    // sources as they were when each page was last edited
+    let previous_manifest = core::manifest::BuildManifest::load(project)?;
+    let build_path = root_path.join(&project.details.build_path);
+    let state_path = build_path.join(core::build_state::BUILD_STATE_FILE);
+    let mut state = BuildState::load(&state_path, project)?;
+    // Start from an empty folder so removed or renamed pages aren't left
+    // behind. An interrupted build is resumed into the folder it left.
+    if state.pages.is_empty() {
+        clean_build_folder(&root_path, &build_path)?;
+    }
     if !build_path.exists() {
-        std::fs::create_dir(&build_path)?;
+        std::fs::create_dir_all(&build_path)?;
     }
 
+    project.events.emit(core::events::Event::BuildStarted {
+        documents: project
+            .root_folder
+            .iter_all_documents()
+            .filter(|d| d.has_format(OutputTarget::Html))
+            .count(),
+    });
+    handle_cancel();
     let now = std::time::Instant::now();
-    let doc_count = build_folder(args, &project, &project.root_folder)?;
+    let doc_count = build_folder(args, project, &project.root_folder, &mut state);
+    if CANCELLED.load(Ordering::SeqCst) {
+        let total = project
+            .root_folder
+            .iter_all_documents()
+            .filter(|d| d.has_format(OutputTarget::Html))
+            .count();
+        return Err(cancel_build(&state, &state_path, total));
+    }
+    let doc_count = doc_count?;
+    BuildState::clear(&state_path)?;
     let doc_time = now.elapsed();
+    if project.details.search.enabled {
+        let now = std::time::Instant::now();
+        let index = core::search::SearchIndex::build(project)?.to_json()?;
+        let index_path = root_path
+            .join(&project.details.build_path)
+            .join("search.json");
+        std::fs::write(&index_path, &index)?;
+        file_written(project, &index_path, index.len());
+        print_file_built("search.json", index.len(), now.elapsed());
+    }
+    if project.details.includes.is_some() && !is_fragment_build(args) {
+        let now = std::time::Instant::now();
+        for (name, content) in HtmlRenderer::render_includes(project)? {
+            let content = minify_page(project, content);
+            let path = build_path.join(core::INCLUDES_FOLDER).join(&name);
+            let l = write_file(&path, content.as_bytes())?;
+            file_written(project, &path, l);
+            if args.verbose {
+                print_file_built(
+                    &format!("{}/{}", core::INCLUDES_FOLDER, name),
+                    l,
+                    now.elapsed(),
+                );
+            }
+        }
+    }
+    // Redirects are full pages, they can't be embedded
+    if !is_fragment_build(args) {
+        for (from, to) in project.details.redirects.iter() {
+            build_redirect(project, from, to)?;
+        }
+    }
+    let now = std::time::Instant::now();
+    let mut manifest = core::manifest::BuildManifest::build(project)?;
+    if let Some(ref previous) = previous_manifest {
+        manifest = manifest.since(previous);
+    }
+    let manifest = manifest.to_json()?;
+    let manifest_path = root_path
+        .join(&project.details.build_path)
+        .join(core::manifest::MANIFEST_FILE);
+    std::fs::write(&manifest_path, &manifest)?;
+    file_written(project, &manifest_path, manifest.len());
+    if args.verbose {
+        print_file_built(core::manifest::MANIFEST_FILE, manifest.len(), now.elapsed());
+    }
+    if project.details.corpus.enabled && !is_fragment_build(args) {
+        let now = std::time::Instant::now();
+        for document in project
+            .root_folder
+            .iter_all_documents()
+            .filter(|d| d.has_format(OutputTarget::Html))
+        {
+            let chunks = core::corpus::document_chunks(
+                project,
+                document,
+                project.details.corpus.chunk_tokens,
+            )?;
+            let name = format!(
+                "{}/{}",
+                core::corpus::CORPUS_FOLDER,
+                core::corpus::corpus_file(document)
+            );
+            let path = build_path.join(&name);
+            let l = write_file(&path, core::corpus::corpus_text(&chunks).as_bytes())?;
+            file_written(project, &path, l);
+            if args.verbose {
+                print_file_built(&name, l, now.elapsed());
+            }
+        }
+        let llms = core::corpus::llms_txt(project)?;
+        let path = build_path.join(core::corpus::LLMS_FILE);
+        let l = write_file(&path, llms.as_bytes())?;
+        file_written(project, &path, l);
+        print_file_built(core::corpus::LLMS_FILE, l, now.elapsed());
+    }
+    let now = std::time::Instant::now();
+    for snippet in core::snippets::collect(project)? {
+        let name = format!(
+            "{}/{}",
+            core::snippets::SNIPPETS_FOLDER,
+            snippet.file_name()
+        );
+        let path = build_path.join(&name);
+        let l = write_file(&path, snippet.content.as_bytes())?;
+        file_written(project, &path, l);
+        if args.verbose {
+            print_file_built(&name, l, now.elapsed());
+        }
+    }
+    let now = std::time::Instant::now();
+    let profile = if is_fragment_build(args) {
+        "fragments"
+    } else {
+        "site"
+    };
+    let provenance = core::provenance::Provenance::build(project, profile)?.to_json()?;
+    let provenance_path = build_path.join(core::provenance::PROVENANCE_FILE);
+    std::fs::write(&provenance_path, &provenance)?;
+    file_written(project, &provenance_path, provenance.len());
+    if args.verbose {
+        print_file_built(
+            core::provenance::PROVENANCE_FILE,
+            provenance.len(),
+            now.elapsed(),
+        );
+    }
     let now = std::time::Instant::now();
     let mut static_count = 0;
     let mut total_static_size = 0;
-    for file in core::assets::static_files(&project)? {
+    for file in core::assets::static_files(project)? {
+        let Some(name) = core::assets::static_name(&file) else {
+            continue;
+        };
         static_count += 1;
         let static_now = std::time::Instant::now();
-        let static_size = file.write(
-            &PathBuf::from(&project.details.build_path),
-            PathBuf::from("static"),
-        )?;
+        // Files are copied as they are unless they have to be compiled,
+        // optimized, or minified first
+        let mut image_read = core::images::is_processed(&name);
+        let compiled = if core::assets::is_scss(&file) {
+            Some(core::assets::compile_scss(&file)?.into_bytes())
+        } else if project.details.images.optimize && core::images::is_processed(&name) {
+            match core::images::optimize(&project.details.images, &name, &file.read()?) {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    image_skipped(project, &name, e);
+                    image_read = false;
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let minified = if project.details.minify_static {
+            let content = match compiled {
+                Some(ref data) => std::borrow::Cow::Borrowed(data.as_slice()),
+                None => file.read()?,
+            };
+            core::minify::static_file(&name, &content)
+                .map_err(|e| anyhow::anyhow!("Could not minify {}: {}", name, e))?
+        } else {
+            None
+        };
+        let content = minified.or(compiled);
+        let build_path = root_path.join(&project.details.build_path);
+        let dst = build_path.join(&name);
+        let static_size = match content {
+            Some(ref data) => write_file(&dst, data)?,
+            None => file.write_to(&dst)?,
+        };
+        file_written(project, &dst, static_size);
         if args.verbose {
-            print_file_built(
-                file.disk_path().file_name().unwrap().to_str().unwrap(),
-                static_size,
-                static_now.elapsed(),
-            );
+            print_file_built(&name, static_size, static_now.elapsed());
         }
         total_static_size += static_size;
+        if image_read {
+            total_static_size += build_image_variants(project, &file, &name, &build_path)?;
+        }
+        // The original is kept for files that link to each other, such as
+        // fonts referenced by stylesheets
+        if let Some(name) = project.details.assets.get(&name) {
+            let dst = build_path.join(name);
+            let size = match content {
+                Some(ref data) => write_file(&dst, data)?,
+                None => file.write_to(&dst)?,
+            };
+            file_written(project, &dst, size);
+        }
     }
     let static_time = now.elapsed();
 
+    progress.finish(project);
+    let warnings = progress.print_warnings();
+
     print_file_built(&format!("{} documents", doc_count.0), doc_count.1, doc_time);
     print_file_built(
         &format!("{} static_files", static_count),
@@ -194,6 +792,465 @@ fn command_build(args: &Args) -> Result<()> {
         static_time,
     );
 
+    let violations = core::budget::check_budgets(
+        &project.details.budgets,
+        &root_path.join(&project.details.build_path),
+    )?;
+    for violation in violations.iter() {
+        project.events.emit(core::events::Event::Warning {
+            kind: core::events::WarningKind::Budget,
+            path: None,
+            message: format!(
+                "{} is {} bytes, over the {} budget of {} bytes",
+                violation.path, violation.size, violation.kind, violation.limit
+            ),
+        });
+        println!(
+            "{} {} is {}, over the {} budget of {}",
+            style("Warning:").yellow().bold(),
+            style(&violation.path).bold(),
+            violation.size.human_count_bytes(),
+            violation.kind,
+            violation.limit.human_count_bytes(),
+        );
+    }
+    if let RootCommands::Build {
+        strict_budgets: true,
+        ..
+    } = args.command
+    {
+        if !violations.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} files or folders are over budget",
+                violations.len()
+            ));
+        }
+    }
+    if let RootCommands::Build { strict: true, .. } = args.command {
+        if warnings > 0 {
+            return Err(anyhow::anyhow!(
+                "{} warnings found, the build failed because --strict was set",
+                warnings
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// internal command to render a single document
+fn command_render(args: &Args) -> Result<()> {
+    let RootCommands::Render { file, format } = &args.command else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let document = project
+        .get_document(project.path.disk_path().join(file))
+        .ok_or_else(|| anyhow::anyhow!("No document found at {}", file))?;
+    let format = match format.as_str() {
+        "html" => core::OutputFormat::Html,
+        "latex" => core::OutputFormat::Latex,
+        _ => core::OutputFormat::Text,
+    };
+    print!("{}", document.render(&project, format)?);
+    Ok(())
+}
+
+/// internal command to create a document
+fn command_new(args: &Args) -> Result<()> {
+    let RootCommands::New {
+        path,
+        archetype,
+        title,
+    } = &args.command
+    else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let mut file_path = project.path.disk_path().join(path);
+    if file_path.extension().is_none() {
+        file_path.set_extension("md");
+    }
+    if file_path.exists() {
+        return Err(anyhow::anyhow!("File already exists: {}", path));
+    }
+    let title = title.clone().unwrap_or_else(|| {
+        file_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().replace(['-', '_'], " "))
+            .unwrap_or_default()
+    });
+    let content = match archetype {
+        Some(name) => core::archetype::Archetype::load(&project, name)?.new_document(&title)?,
+        None => core::archetype::blank_document(&title)?,
+    };
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&file_path, content)?;
+    println!(
+        "{} {}",
+        style("Created").dim(),
+        style(file_path.display()).bold()
+    );
+    Ok(())
+}
+
+/// internal command to check documents for problems
+fn command_lint(args: &Args) -> Result<()> {
+    let RootCommands::Lint { format } = &args.command else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let issues = core::lint::lint(&project)?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&issues)?);
+        return match issues.len() {
+            0 => Ok(()),
+            n => Err(anyhow::anyhow!("{} issues found", n)),
+        };
+    }
+    print_lint_issues(&issues);
+    if issues.is_empty() {
+        println!("No issues found");
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("{} issues found", issues.len()))
+    }
+}
+
+fn command_ids(args: &Args) -> Result<()> {
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let assigned = core::ids::assign_ids(&project)?;
+    for path in assigned.iter() {
+        println!(
+            "{} {}",
+            style("Added id to").dim(),
+            style(path.display()).bold()
+        );
+    }
+    println!(
+        "{} {} {}",
+        style("Recorded ids in").dim(),
+        style(core::ids::ID_REGISTRY_FILE).bold(),
+        style(format!("({} added)", assigned.len())).dim(),
+    );
+    Ok(())
+}
+
+/// internal command to report the translation status of documents
+fn command_translations(args: &Args) -> Result<()> {
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    if project.details.translations.is_empty() {
+        println!("No translations are configured in codex.yml");
+        return Ok(());
+    }
+    let report = core::i18n::translation_status(&project)?;
+    let mut incomplete = 0;
+    for status in report.iter() {
+        let pending = status
+            .translations
+            .iter()
+            .filter(|(_, state)| **state != core::i18n::TranslationState::Current)
+            .map(|(language, state)| match state {
+                core::i18n::TranslationState::Missing => {
+                    style(format!("{} missing", language)).red()
+                }
+                _ => style(format!("{} outdated", language)).yellow(),
+            })
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            continue;
+        }
+        incomplete += 1;
+        println!(
+            "{} {} {}",
+            style(&status.url).bold(),
+            style(format!("({})", status.path.display())).dim(),
+            pending.join(", ")
+        );
+    }
+    println!(
+        "{} of {} documents are fully translated",
+        report.len() - incomplete,
+        report.len()
+    );
+    Ok(())
+}
+
+fn print_lint_issues(issues: &[core::lint::LintIssue]) {
+    for issue in issues.iter() {
+        let location = match issue.line {
+            Some(line) => format!("{}:{}:", issue.path.display(), line),
+            None => format!("{}:", issue.path.display()),
+        };
+        println!(
+            "{} {} {}",
+            style(location).bold(),
+            issue.message,
+            style(format!("[{}]", issue.rule)).dim()
+        );
+    }
+}
+
+/// internal command to report review comments
+fn command_review(args: &Args) -> Result<()> {
+    let RootCommands::Review(ReviewCommands::Comments { assignee }) = &args.command else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let mut report = core::review::collect(&project)?;
+    if let Some(assignee) = assignee {
+        report.assignees.retain(|a, _| a == assignee);
+    }
+    for (assignee, pages) in report.assignees.iter() {
+        println!("{}", style(assignee).bold());
+        for page in pages.iter() {
+            println!(
+                "  {} {}",
+                style(&page.url).bold(),
+                style(format!("({})", page.path.display())).dim()
+            );
+            for comment in page.comments.iter() {
+                println!(
+                    "    {} {}",
+                    style(format!("{}:", comment.line)).dim(),
+                    comment.text
+                );
+            }
+        }
+    }
+    println!("{} review comments", report.len());
+    Ok(())
+}
+
+fn command_export(args: &Args) -> Result<()> {
+    let RootCommands::Export(ExportCommands::Embeddings {
+        output,
+        max_tokens,
+        overlap,
+    }) = &args.command
+    else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let mut lines = String::new();
+    for document in project
+        .root_folder
+        .iter_all_documents()
+        .filter(|d| d.has_format(OutputTarget::Html))
+    {
+        for record in core::corpus::embedding_records(&project, document, *max_tokens, *overlap)? {
+            lines.push_str(&serde_json::to_string(&record)?);
+            lines.push('\n');
+        }
+    }
+    match output {
+        Some(path) => {
+            std::fs::write(path, &lines)?;
+            eprintln!("{} records written to {}", lines.lines().count(), path);
+        }
+        None => std::io::stdout().write_all(lines.as_bytes())?,
+    }
+    Ok(())
+}
+
+fn command_runbook(args: &Args) -> Result<()> {
+    let RootCommands::Runbook(RunbookCommands::Export {
+        url,
+        format,
+        output,
+    }) = &args.command
+    else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let document = project
+        .get_document_for_link(url)
+        .ok_or_else(|| anyhow::anyhow!("No page found at {}", url))?;
+    let checklist = core::runbook::checklist(&project, document, format == "markdown")?;
+    match output {
+        Some(path) => std::fs::write(path, &checklist)?,
+        None => std::io::stdout().write_all(checklist.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// internal command to restructure documents
+fn command_refactor(args: &Args) -> Result<()> {
+    let RootCommands::Refactor(command) = &args.command else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    match command {
+        RefactorCommands::RenameUrl { from, to } => {
+            let summary = core::refactor::rename_url(&project, from, to)?;
+            println!(
+                "{} {} {} {}",
+                style("Moved").dim(),
+                style(summary.from.display()).bold(),
+                style("to").dim(),
+                style(summary.to.display()).bold(),
+            );
+            print_links_updated(summary.links, summary.files);
+            println!(
+                "{} {} {} {}",
+                style("Added redirect from").dim(),
+                style(from).bold(),
+                style("to").dim(),
+                style(to).bold(),
+            );
+        }
+        RefactorCommands::Split { file, by_heading } => {
+            let summary =
+                core::refactor::split_document(&project, &PathBuf::from(file), *by_heading)?;
+            for page in summary.pages.iter() {
+                println!(
+                    "{} {}",
+                    style("Created").dim(),
+                    style(page.display()).bold()
+                );
+            }
+            print_links_updated(summary.links, summary.files);
+        }
+        RefactorCommands::Merge {
+            folder,
+            heading_level,
+        } => {
+            let summary =
+                core::refactor::merge_folder(&project, &PathBuf::from(folder), *heading_level)?;
+            println!(
+                "{} {} {} {}",
+                style("Merged").dim(),
+                style(summary.pages).bold(),
+                style("pages into").dim(),
+                style(summary.file.display()).bold(),
+            );
+            print_links_updated(summary.links, summary.files);
+        }
+        RefactorCommands::Demote { file, by } | RefactorCommands::Promote { file, by } => {
+            let by = match command {
+                RefactorCommands::Promote { .. } => -(*by as i8),
+                _ => *by as i8,
+            };
+            let path = project.path.disk_path().join(file);
+            let count = core::refactor::shift_headings(&path, by, &project.details.markdown)?;
+            println!(
+                "{} {} {} {}",
+                style("Updated").dim(),
+                style(count).bold(),
+                style("headings in").dim(),
+                style(file).bold(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the number of links updated by a refactor
+fn print_links_updated(links: usize, files: usize) {
+    println!(
+        "{} {} {} {} {}",
+        style("Updated").dim(),
+        style(links).bold(),
+        style("links in").dim(),
+        style(files).bold(),
+        style("documents").dim(),
+    );
+}
+
+/// internal command to check for pages with changed sources
+fn command_check(args: &Args) -> Result<()> {
+    let project = Project::load(PathBuf::from(&args.root_path), false)?;
+    let Some(previous) = core::manifest::BuildManifest::load(&project)? else {
+        println!("No build manifest found, run the build command first");
+        return Ok(());
+    };
+    let stale = core::manifest::BuildManifest::build(&project)?.stale_sources(&previous);
+    for page in stale.iter() {
+        println!(
+            "{} {} was generated from {}, which changed since the last build",
+            style("Warning:").yellow().bold(),
+            style(&page.url).bold(),
+            style(&page.source).bold(),
+        );
+    }
+    if stale.is_empty() {
+        println!("No issues found");
+    }
+    Ok(())
+}
+
+fn command_deploy(args: &Args) -> Result<()> {
+    let RootCommands::Deploy {
+        target,
+        delete_orphans,
+        preview,
+        branch,
+    } = &args.command
+    else {
+        return Err(anyhow::anyhow!("Invalid command"));
+    };
+    let root_path = PathBuf::from(&args.root_path);
+    let mut project = load_project(args)?;
+    let Some(target) = target.as_ref().or(project.details.deploy.target.as_ref()) else {
+        return Err(anyhow::anyhow!(
+            "No deploy target specified, use --target or set deploy.target in codex.yml"
+        ));
+    };
+    let mut target = root_path.join(target);
+    if *preview {
+        let Some(branch) = branch
+            .clone()
+            .or_else(|| core::deploy::current_branch(&root_path))
+        else {
+            return Err(anyhow::anyhow!(
+                "Could not determine the current branch, use --branch to set it"
+            ));
+        };
+        let path = core::deploy::preview_path(&project.details.deploy.preview_path, &branch);
+        project.details.base_url = format!("{}{}/", project.details.base_url, path);
+        target = target.join(&path);
+    }
+    build_project(args, &mut project)?;
+
+    let summary = core::deploy::deploy(
+        &root_path.join(&project.details.build_path),
+        &target,
+        *delete_orphans || project.details.deploy.delete_orphans,
+    )?;
+    if args.verbose {
+        for file in summary.uploaded.iter() {
+            println!("{} {}", style("Uploaded").dim(), style(file).bold());
+        }
+    }
+    for file in summary.deleted.iter() {
+        println!("{} {}", style("Deleted").dim(), style(file).bold());
+    }
+    if !summary.orphaned.is_empty() {
+        println!(
+            "{} {} files are no longer built, use --delete-orphans to remove them",
+            style("Warning:").yellow().bold(),
+            summary.orphaned.len()
+        );
+    }
+    println!(
+        "{} {} files, {} unchanged",
+        style("Deployed").dim(),
+        style(summary.uploaded.len()).bold(),
+        summary.unchanged
+    );
+    if *preview {
+        let url = project
+            .details
+            .deploy
+            .url
+            .as_ref()
+            .map(|url| format!("{}{}", url.trim_end_matches('/'), project.details.base_url))
+            .unwrap_or(project.details.base_url.clone());
+        println!("{} {}", style("Preview").dim(), style(url).bold());
+    }
     Ok(())
 }
 
@@ -216,51 +1273,132 @@ fn eject_static_files(args: &Args) -> Result<()> {
 }
 
 /// Build static site files for a folder and all its sub folders and documents.
-fn build_folder(args: &Args, project: &Project, folder: &core::Folder) -> Result<(usize, usize)> {
+/// Documents completed by an interrupted build are skipped if they haven't
+/// changed.
+fn build_folder(
+    args: &Args,
+    project: &Project,
+    folder: &core::Folder,
+    state: &mut BuildState,
+) -> Result<(usize, usize)> {
     let mut count = 0;
     let mut size = 0;
     for folder in folder.folders.iter() {
-        let (c, s) = build_folder(args, project, folder)?;
+        let (c, s) = build_folder(args, project, folder, state)?;
         count += c;
         size += s;
     }
-    for document in folder.documents.iter() {
+    for document in folder
+        .documents
+        .iter()
+        .filter(|d| d.has_format(OutputTarget::Html))
+    {
+        if CANCELLED.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("Build cancelled"));
+        }
         count += 1;
-        size += build_document(args, project, document)?;
+        for file in document.attachments()? {
+            let build_path = PathBuf::from(&project.details.build_path);
+            let l = file.write(&build_path, PathBuf::new())?;
+            file_written(project, &file.destination(&build_path, PathBuf::new())?, l);
+        }
+        let hash = page_hash(project, document)?;
+        if let Some(page) = state.completed(&document.url, &hash) {
+            project.events.emit(core::events::Event::DocumentRestored {
+                url: document.url.clone(),
+                size: page.size,
+            });
+            size += page.size;
+            continue;
+        }
+        let l = build_document(args, project, document)?;
+        state.record(&document.url, hash, l, None);
+        size += l;
+    }
+    if CANCELLED.load(Ordering::SeqCst) {
+        return Err(anyhow::anyhow!("Build cancelled"));
+    }
+    if project.details.print_views
+        && !is_fragment_build(args)
+        && folder.iter_all_documents().next().is_some()
+    {
+        size += build_print_view(args, project, folder)?;
     }
     Ok((count, size))
 }
 
+/// Build the printable view for a folder.
+fn build_print_view(args: &Args, project: &Project, folder: &core::Folder) -> Result<usize> {
+    let now = std::time::Instant::now();
+    let content = minify_page(project, HtmlRenderer::render_print_view(project, folder)?);
+    let folder_path = folder.path.relative_to(
+        &project
+            .path
+            .disk_path()
+            .join(PathBuf::from(project.details.build_path.clone())),
+    );
+    if !folder_path.exists() {
+        std::fs::create_dir_all(&folder_path)?;
+    }
+
+    let l = content.len();
+    if args.verbose {
+        print_file_built(&folder.path.print_url(), l, now.elapsed());
+    }
+    std::fs::write(folder_path.join("print.html"), content)?;
+    file_written(project, &folder_path.join("print.html"), l);
+    Ok(l)
+}
+
+/// Build a page that redirects from an old url to its new location.
+fn build_redirect(project: &Project, from: &str, to: &str) -> Result<()> {
+    if project
+        .root_folder
+        .iter_all_documents()
+        .any(|d| d.url.trim_matches('/') == from.trim_matches('/'))
+    {
+        return Err(anyhow::anyhow!(
+            "The redirect from {} would replace the page at that url",
+            from
+        ));
+    }
+    // Redirects to a page use the page's url, in the project's url style
+    let to = project
+        .get_document_for_link(to)
+        .map(|d| d.url.as_str())
+        .unwrap_or(to);
+    let url = format!("{}{}", project.details.base_url, to.trim_start_matches('/'));
+    let content = project.render_template_file(
+        "_internal/templates/redirect.html",
+        serde_json::json!({ "url": url }),
+    )?;
+    let folder_path = project
+        .path
+        .disk_path()
+        .join(&project.details.build_path)
+        .join(from.trim_matches('/'));
+    std::fs::create_dir_all(&folder_path)?;
+    std::fs::write(folder_path.join("index.html"), &content)?;
+    file_written(project, &folder_path.join("index.html"), content.len());
+    Ok(())
+}
+
 /// Build static site files for a document.
 fn build_document(args: &Args, project: &Project, doc: &core::Document) -> Result<usize> {
     let now = std::time::Instant::now();
-    let renderer = HtmlRenderer {
-        render_context: core::RenderContext {
-            project,
-            document: doc,
-        },
-    };
-    let content = renderer.render()?;
-    let file_path = if doc.file_path.is_index() {
-        doc.file_path
-            .relative_to(
-                &project
-                    .path
-                    .disk_path()
-                    .join(PathBuf::from(project.details.build_path.clone())),
-            )
-            .with_extension("html")
+    let content = if is_fragment_build(args) {
+        doc.render_fragment(project)?
     } else {
-        doc.file_path
-            .relative_to(
-                &project
-                    .path
-                    .disk_path()
-                    .join(PathBuf::from(project.details.build_path.clone())),
-            )
-            .with_extension("")
-            .join("index.html")
+        doc.render(project, core::OutputFormat::Html)?
     };
+    let content = minify_page(project, content);
+    // Pages are written to the folder matching their url, which starts with
+    // the language of translations
+    let file_path = project
+        .path
+        .disk_path()
+        .join(&project.details.build_path)
+        .join(project.details.url_style.output_file(&doc.url));
 
     if !file_path.parent().unwrap().exists() {
         std::fs::create_dir_all(file_path.parent().unwrap())?;
@@ -280,39 +1418,79 @@ fn build_document(args: &Args, project: &Project, doc: &core::Document) -> Resul
             now.elapsed(),
         );
     }
-    std::fs::write(file_path, content)?;
+    std::fs::write(&file_path, content)?;
+    file_written(project, &file_path, l);
+    if project.details.writes_metadata() {
+        // `index.meta.json` for directory urls, `<page>.meta.json` for file urls
+        let metadata_path = file_path.with_extension("meta.json");
+        let metadata = serde_json::to_string(&doc.metadata(project)?)?;
+        std::fs::write(&metadata_path, &metadata)?;
+        file_written(project, &metadata_path, metadata.len());
+    }
     Ok(l)
 }
 
-/// Build a LaTeX document from the project
-fn build_latext(project: &Project) -> Result<String> {
-    let mut output = String::new();
-    for document in project
-        .root_folder
-        .iter_all_documents()
-        .filter(|d| !d.frontmatter.pdf_exclude)
-    {
-        let renderer = LatexRenderer {
-            render_context: core::RenderContext { project, document },
-        };
-        let res = renderer.render()?;
+/// Write the resized and WebP copies of a static image.
+fn build_image_variants(
+    project: &Project,
+    file: &core::assets::CodexPath,
+    name: &str,
+    build_path: &std::path::Path,
+) -> Result<usize> {
+    let details = &project.details.images;
+    if !details.webp && details.sizes.is_empty() {
+        return Ok(0);
+    }
+    let content = file.read()?;
+    let variants = match core::images::variants_of(details, name, &content) {
+        Ok(variants) => variants,
+        Err(e) => {
+            image_skipped(project, name, e);
+            return Ok(0);
+        }
+    };
+    let mut size = 0;
+    for variant in variants {
+        let dst = build_path.join(&variant.name);
+        let l = write_file(
+            &dst,
+            &core::images::build_variant(details, &variant, &content)?,
+        )?;
+        file_written(project, &dst, l);
+        size += l;
+    }
+    Ok(size)
+}
 
-        let slug = renderer.slug(&document.url.trim_matches('/').replace(['/', '#', '_'], "-"));
+/// Report an image that couldn't be read. It is copied as it is.
+fn image_skipped(project: &Project, name: &str, error: core::Error) {
+    project.events.emit(core::events::Event::Warning {
+        kind: core::events::WarningKind::Other,
+        path: None,
+        message: format!("Could not process the image {}: {}", name, error),
+    });
+    println!(
+        "{} Could not process the image {}: {}",
+        style("Warning:").yellow().bold(),
+        style(name).bold(),
+        error,
+    );
+}
 
-        if let Some(subtitle) = &document.frontmatter.subtitle {
-            output.push_str(&format!(
-                "\\section[{}]{{{}{{\\hfill\\normalsize\\color{{subtitle}} {}}}}}\\label{{sec:{}}}\n",
-                document.frontmatter.title, document.frontmatter.title, subtitle, slug
-            ));
-        } else {
-            output.push_str(&format!(
-                "\\section{{{}}}\\label{{sec:{}}}\n",
-                document.frontmatter.title, slug
-            ));
-        }
-        output.push_str(&res);
-        output.push_str("\\pagebreak\n");
+/// Minify a built page when the project's `minify` option is set.
+fn minify_page(project: &Project, content: String) -> String {
+    if project.details.minify {
+        core::minify::html(&content)
+    } else {
+        content
     }
+}
+
+/// Build a LaTeX document from the project. Sections completed by an
+/// interrupted build are reused if their documents haven't changed.
+fn build_latext(project: &Project, state: &mut BuildState) -> Result<String> {
+    let mut output = String::new();
+    build_latex_folder(project, &project.root_folder, state, &mut output)?;
 
     let prelude = String::from_utf8(
         project
@@ -339,6 +1517,47 @@ fn build_latext(project: &Project) -> Result<String> {
     Ok(buffer)
 }
 
+/// Render the sections of a folder's documents, and its sub folders, each
+/// folder starting with an overview of its contents.
+fn build_latex_folder(
+    project: &Project,
+    folder: &core::Folder,
+    state: &mut BuildState,
+    output: &mut String,
+) -> Result<()> {
+    if folder.path != project.root_folder.path {
+        if let Some(overview) = LatexRenderer::render_folder_overview(folder) {
+            output.push_str(&overview);
+        }
+    }
+    for document in folder
+        .documents
+        .iter()
+        .filter(|d| d.has_format(OutputTarget::Pdf))
+    {
+        let hash = page_hash(project, document)?;
+        if let Some(section) = state
+            .completed(&document.url, &hash)
+            .and_then(|page| page.output.as_ref())
+        {
+            output.push_str(section);
+            continue;
+        }
+        if CANCELLED.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("Build cancelled"));
+        }
+
+        let renderer = LatexRenderer::new(core::RenderContext::new(project, document));
+        let section = renderer.render_section()?;
+        output.push_str(&section);
+        state.record(&document.url, hash, section.len(), Some(section));
+    }
+    for subfolder in folder.folders.iter() {
+        build_latex_folder(project, subfolder, state, output)?;
+    }
+    Ok(())
+}
+
 /// Generate styled string for size and time
 fn size_in_time(size: usize, time: std::time::Duration) -> String {
     let mut out = String::new();
@@ -0,0 +1,241 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+
+/// The upper bounds, in seconds, of the render duration histogram buckets.
+const RENDER_BUCKETS: [f64; 9] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// Counters describing what the server has done since it started, reported
+/// in the Prometheus text format.
+#[derive(Debug)]
+pub struct Metrics {
+    started: SystemTime,
+    /// Requests served, keyed by the kind of request and the status code
+    requests: BTreeMap<(&'static str, u16), u64>,
+    /// Page renders counted into each bucket of `RENDER_BUCKETS`, plus a
+    /// final bucket for slower renders
+    render_buckets: [u64; RENDER_BUCKETS.len() + 1],
+    render_seconds: f64,
+    render_errors: u64,
+    reloads: u64,
+    reload_failures: u64,
+    last_reload: Option<(SystemTime, bool)>,
+    /// Requests served from the loaded project because no files changed
+    cache_hits: u64,
+    cache_misses: u64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started: SystemTime::now(),
+            requests: BTreeMap::new(),
+            render_buckets: Default::default(),
+            render_seconds: 0.0,
+            render_errors: 0,
+            reloads: 0,
+            reload_failures: 0,
+            last_reload: None,
+            cache_hits: 0,
+            cache_misses: 0,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn record_request(&mut self, kind: &'static str, status: u16) {
+        *self.requests.entry((kind, status)).or_default() += 1;
+    }
+
+    /// Record the time taken to render a page and whether it failed.
+    pub fn record_render(&mut self, duration: Duration, ok: bool) {
+        let seconds = duration.as_secs_f64();
+        let bucket = RENDER_BUCKETS
+            .iter()
+            .position(|b| seconds <= *b)
+            .unwrap_or(RENDER_BUCKETS.len());
+        self.render_buckets[bucket] += 1;
+        self.render_seconds += seconds;
+        if !ok {
+            self.render_errors += 1;
+        }
+    }
+
+    pub fn record_reload(&mut self, ok: bool) {
+        self.reloads += 1;
+        self.cache_misses += 1;
+        if !ok {
+            self.reload_failures += 1;
+        }
+        self.last_reload = Some((SystemTime::now(), ok));
+    }
+
+    pub fn record_cache_hit(&mut self) {
+        self.cache_hits += 1;
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        metric(
+            &mut out,
+            "codex_start_time_seconds",
+            "gauge",
+            "Time the server started, in seconds since the epoch.",
+        );
+        let _ = writeln!(
+            out,
+            "codex_start_time_seconds {}",
+            epoch_seconds(self.started)
+        );
+
+        metric(
+            &mut out,
+            "codex_http_requests_total",
+            "counter",
+            "Requests served, by kind of request and status code.",
+        );
+        for ((kind, status), count) in &self.requests {
+            let _ = writeln!(
+                out,
+                "codex_http_requests_total{{kind=\"{}\",status=\"{}\"}} {}",
+                kind, status, count
+            );
+        }
+
+        metric(
+            &mut out,
+            "codex_render_duration_seconds",
+            "histogram",
+            "Time taken to render pages.",
+        );
+        let mut cumulative = 0;
+        for (i, count) in self.render_buckets.iter().enumerate() {
+            cumulative += count;
+            let le = RENDER_BUCKETS
+                .get(i)
+                .map(|b| b.to_string())
+                .unwrap_or_else(|| "+Inf".to_string());
+            let _ = writeln!(
+                out,
+                "codex_render_duration_seconds_bucket{{le=\"{}\"}} {}",
+                le, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "codex_render_duration_seconds_sum {}",
+            self.render_seconds
+        );
+        let _ = writeln!(out, "codex_render_duration_seconds_count {}", cumulative);
+
+        metric(
+            &mut out,
+            "codex_render_errors_total",
+            "counter",
+            "Pages that failed to render.",
+        );
+        let _ = writeln!(out, "codex_render_errors_total {}", self.render_errors);
+
+        metric(
+            &mut out,
+            "codex_project_reloads_total",
+            "counter",
+            "Times the project was loaded again after files changed.",
+        );
+        let _ = writeln!(out, "codex_project_reloads_total {}", self.reloads);
+        metric(
+            &mut out,
+            "codex_project_reload_failures_total",
+            "counter",
+            "Times loading the project failed.",
+        );
+        let _ = writeln!(
+            out,
+            "codex_project_reload_failures_total {}",
+            self.reload_failures
+        );
+        if let Some((time, ok)) = self.last_reload {
+            metric(
+                &mut out,
+                "codex_last_reload_success",
+                "gauge",
+                "Whether the last project reload succeeded.",
+            );
+            let _ = writeln!(out, "codex_last_reload_success {}", ok as u8);
+            metric(
+                &mut out,
+                "codex_last_reload_timestamp_seconds",
+                "gauge",
+                "Time of the last project reload, in seconds since the epoch.",
+            );
+            let _ = writeln!(
+                out,
+                "codex_last_reload_timestamp_seconds {}",
+                epoch_seconds(time)
+            );
+        }
+
+        metric(
+            &mut out,
+            "codex_cache_hits_total",
+            "counter",
+            "Requests served from the loaded project without reloading it.",
+        );
+        let _ = writeln!(out, "codex_cache_hits_total {}", self.cache_hits);
+        metric(
+            &mut out,
+            "codex_cache_misses_total",
+            "counter",
+            "Requests that loaded the project again because files changed.",
+        );
+        let _ = writeln!(out, "codex_cache_misses_total {}", self.cache_misses);
+        out
+    }
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn epoch_seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Metrics;
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = Metrics::default();
+        metrics.record_request("page", 200);
+        metrics.record_request("page", 200);
+        metrics.record_request("page", 404);
+        metrics.record_render(Duration::from_millis(20), true);
+        metrics.record_render(Duration::from_secs(10), false);
+        metrics.record_reload(true);
+        metrics.record_cache_hit();
+
+        let out = metrics.render();
+        assert!(out.contains("codex_http_requests_total{kind=\"page\",status=\"200\"} 2\n"));
+        assert!(out.contains("codex_http_requests_total{kind=\"page\",status=\"404\"} 1\n"));
+        // Histogram buckets are cumulative
+        assert!(out.contains("codex_render_duration_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(out.contains("codex_render_duration_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(out.contains("codex_render_duration_seconds_bucket{le=\"5\"} 1\n"));
+        assert!(out.contains("codex_render_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(out.contains("codex_render_duration_seconds_count 2\n"));
+        assert!(out.contains("codex_render_errors_total 1\n"));
+        assert!(out.contains("codex_last_reload_success 1\n"));
+        assert!(out.contains("codex_cache_hits_total 1\n"));
+        assert!(out.contains("codex_cache_misses_total 1\n"));
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use console::style;
+use core::{
+    events::{Event, WarningKind},
+    Project,
+};
+use human_repr::HumanCount;
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// A problem found in a document while it was rendered.
+type DocumentWarning = (WarningKind, PathBuf, String);
+
+/// Shows the documents rendered during a build and summarizes the output by
+/// folder once it finishes. The display is driven by the project's events.
+pub struct BuildProgress {
+    bar: ProgressBar,
+    sizes: Arc<Mutex<HashMap<String, usize>>>,
+    warnings: Arc<Mutex<Vec<DocumentWarning>>>,
+}
+
+impl BuildProgress {
+    /// Subscribe a progress display to the project's events. The bar is
+    /// hidden when `hidden` is set or the output isn't a terminal.
+    pub fn attach(project: &mut Project, hidden: bool) -> Self {
+        let bar = if hidden {
+            ProgressBar::hidden()
+        } else {
+            ProgressBar::new(0)
+        };
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.blue} [{bar:30.blue/white}] {pos}/{len} documents, {eta} left {wide_msg:.dim}",
+            )
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> "),
+        );
+        let sizes = Arc::new(Mutex::new(HashMap::new()));
+        let warnings = Arc::new(Mutex::new(vec![]));
+
+        let event_bar = bar.clone();
+        let event_sizes = sizes.clone();
+        let event_warnings = warnings.clone();
+        project.events.subscribe(move |event| match event {
+            Event::BuildStarted { documents } => event_bar.set_length(*documents as u64),
+            Event::DocumentRendered { url, size } | Event::DocumentRestored { url, size } => {
+                if let Ok(mut sizes) = event_sizes.lock() {
+                    sizes.insert(url.clone(), *size);
+                }
+                event_bar.inc(1);
+                event_bar.set_message(url.clone());
+            }
+            // Pages rendered more than once, such as translations sharing
+            // a source, report the same problem once
+            Event::Warning {
+                kind,
+                path: Some(path),
+                message,
+            } => {
+                let warning = (*kind, path.clone(), message.clone());
+                if let Ok(mut warnings) = event_warnings.lock() {
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                }
+            }
+            _ => {}
+        });
+        Self {
+            bar,
+            sizes,
+            warnings,
+        }
+    }
+
+    /// Print the problems found in documents during the build, with a count
+    /// of each kind, and return how many there were.
+    pub fn print_warnings(&self) -> usize {
+        let Ok(warnings) = self.warnings.lock() else {
+            return 0;
+        };
+        if warnings.is_empty() {
+            return 0;
+        }
+        let mut counts: Vec<(WarningKind, usize)> = vec![];
+        for (kind, path, message) in warnings.iter() {
+            println!(
+                "{} {}: {}",
+                style("Warning:").yellow().bold(),
+                style(path.display()).bold(),
+                message
+            );
+            match counts.iter_mut().find(|(k, _)| k == kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*kind, 1)),
+            }
+        }
+        let summary = counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{} {} warnings ({})",
+            style("Found").yellow(),
+            warnings.len(),
+            summary
+        );
+        warnings.len()
+    }
+
+    /// Clear the progress bar and print the number of documents and their
+    /// size for each top level folder.
+    pub fn finish(&self, project: &Project) {
+        self.bar.finish_and_clear();
+        let Ok(sizes) = self.sizes.lock() else {
+            return;
+        };
+        let size_of = |urls: &mut dyn Iterator<Item = &String>| {
+            urls.filter_map(|url| sizes.get(url))
+                .fold((0, 0), |(c, s), size| (c + 1, s + size))
+        };
+
+        let mut rows = vec![(
+            "/".to_string(),
+            size_of(&mut project.root_folder.documents.iter().map(|d| &d.url)),
+        )];
+        for folder in project.root_folder.folders.iter() {
+            rows.push((
+                folder.get_name(),
+                size_of(&mut folder.iter_all_documents().map(|d| &d.url)),
+            ));
+        }
+        rows.retain(|(_, (count, _))| *count > 0);
+        if rows.is_empty() {
+            return;
+        }
+
+        let width = rows
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        println!(
+            "{}",
+            style(format!(
+                "{:<width$}  {:>9}  {:>10}",
+                "Folder", "Documents", "Size"
+            ))
+            .dim()
+        );
+        for (name, (count, size)) in rows {
+            // Pad the name before styling it, escape codes would count
+            // towards the width
+            println!(
+                "{}{}  {:>9}  {:>10}",
+                style(&name).bold(),
+                " ".repeat(width - name.len()),
+                count,
+                size.human_count_bytes().to_string(),
+            );
+        }
+    }
+}
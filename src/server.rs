@@ -1,90 +1,577 @@
-use core::{Project, Renderer};
+use core::Project;
+use std::{
+    cell::Cell,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use console::style;
 use human_repr::{HumanCount, HumanDuration};
 use tiny_http::{Request, Response, Server};
 
-use crate::RootCommands;
+use crate::{
+    metrics::Metrics,
+    watcher::{self, ChangeKind, ChangeState},
+    RootCommands,
+};
+
+/// Script injected into every rendered page to reload it when project files
+/// change.
+const LIVE_RELOAD_SCRIPT: &str = r#"<script src="/_codex/livereload.js"></script>"#;
 
 struct ServerHandler {
     project: Project,
+    changes: Arc<Mutex<ChangeState>>,
+    metrics: Metrics,
+    /// The change version the project was last loaded at
+    loaded_version: Option<u64>,
+    /// Why the project couldn't be loaded again after its files changed.
+    /// Pages are served from the project as it was last loaded.
+    load_error: Option<String>,
+    /// The status code of the response to the current request
+    status: Cell<u16>,
 }
 
 pub fn serve(args: &crate::Args) -> anyhow::Result<()> {
-    let RootCommands::Serve { port } = &args.command else {
+    let RootCommands::Serve { host, port, debug } = &args.command else {
         panic!("Expected Serve command");
     };
-    let server_url = format!("0.0.0.0:{}", port);
+    let server = Server::http((host.as_str(), *port))
+        .map_err(|e| anyhow::anyhow!("Failed to listen on {}:{}: {}", host, port, e))?;
+    // The port the system picked when the port is 0
+    let server_url = match server.server_addr().to_ip() {
+        Some(addr) => format!("http://{}", addr),
+        None => format!("http://{}:{}", host, port),
+    };
     let term = console::Term::stdout();
     let _ = term.write_line(&format!(
         "{} {}",
         style("Starting server on").green().dim(),
         style(&server_url).bright().underlined().bold()
     ));
-    let server = Server::http(server_url).unwrap();
+    let mut project = Project::load(&args.root_path, true).expect("Failed to load project");
+    // Pages are served whole, there is no server to process includes. This
+    // is applied again when the configuration is reloaded.
+    project.details.includes = None;
+    project.details.trace_templates = *debug;
+    let changes = watcher::watch(
+        PathBuf::from(&args.root_path),
+        vec![
+            PathBuf::from(&project.details.build_path),
+            PathBuf::from(".git"),
+        ],
+        Duration::from_millis(500),
+        |paths, kind| {
+            let term = console::Term::stdout();
+            let reload = match kind {
+                ChangeKind::Style => "style reload",
+                ChangeKind::Full => "full reload",
+            };
+            for path in paths {
+                let _ = term.write_line(&format!(
+                    "{:>20} {}",
+                    style(reload).dim(),
+                    style(path.display()).bold()
+                ));
+            }
+        },
+    );
     let mut handler = ServerHandler {
-        project: Project::load(&args.root_path, true).expect("Failed to load project"),
+        project,
+        changes,
+        metrics: Metrics::default(),
+        loaded_version: Some(0),
+        load_error: None,
+        status: Cell::new(200),
     };
 
     server.incoming_requests().for_each(|request| {
         let now = std::time::Instant::now();
         let url = request.url().to_string();
+        let path = url.split('?').next().unwrap_or_default().to_string();
 
-        #[allow(clippy::blocks_in_if_conditions)]
-        if url == "/" {
-            let _ = handler.project.reload();
-            output_log(&url, now.elapsed(), handler.handle_file(request));
+        #[allow(clippy::blocks_in_conditions)]
+        let kind = if path == "/_codex/changes" {
+            handler.handle_changes(request);
+            "changes"
+        } else if path == "/_codex/livereload.js" {
+            handler.handle_live_reload_script(request);
+            "livereload"
+        } else if path == "/search.json" && handler.project.details.search.enabled {
+            handler.reload();
+            let size = handler.handle_search_index(request);
+            output_log(&url, now.elapsed(), size);
+            "search"
+        } else if url == "/" {
+            handler.reload();
+            let size = handler.handle_file(request);
+            output_log(&url, now.elapsed(), size);
+            "page"
         } else if {
             handler
                 .project
                 .path
-                .from_url(&format!("/static{}", request.url()))
+                .from_url(&format!("/static{}", path))
                 .exists()
         } {
             let size = handler.handle_static(request);
             if args.verbose {
                 output_log(&url, now.elapsed(), size);
             }
+            "static"
+        } else if let Some(source) =
+            core::assets::stylesheet_source(&handler.project, &format!("/static{}", path))
+        {
+            let size = handler.handle_stylesheet(request, &source);
+            if args.verbose {
+                output_log(&url, now.elapsed(), size);
+            }
+            "stylesheet"
+        } else if let Some((source, variant)) =
+            core::images::variant_for_url(&handler.project, &path)
+        {
+            let size = handler.handle_image_variant(request, &source, &variant);
+            if args.verbose {
+                output_log(&url, now.elapsed(), size);
+            }
+            "image"
+        } else if let Some(file) = handler.project.get_attachment_for_url(&path) {
+            let size = handler.handle_attachment(request, &file);
+            if args.verbose {
+                output_log(&url, now.elapsed(), size);
+            }
+            "attachment"
+        } else if handler.project.details.writes_metadata() && path.ends_with(".meta.json") {
+            handler.reload();
+            let size = handler.handle_metadata(request, &path);
+            output_log(&url, now.elapsed(), size);
+            "metadata"
+        } else if handler.project.details.print_views && url.ends_with("/print.html") {
+            handler.reload();
+            let size = handler.handle_print(request);
+            output_log(&url, now.elapsed(), size);
+            "print"
+        } else if let Some(snippet) = path.strip_prefix("/snippets/").and_then(|name| {
+            handler.reload();
+            core::snippets::find(&handler.project, name).ok().flatten()
+        }) {
+            let size = snippet.content.len();
+            let _ = request.respond(Response::from_string(snippet.content).with_header(
+                tiny_http::Header {
+                    field: "Content-Type".parse().unwrap(),
+                    value: "text/plain; charset=utf-8".parse().unwrap(),
+                },
+            ));
+            output_log(&url, now.elapsed(), size);
+            "snippet"
+        } else if let Some(location) = handler.project.get_redirect_for_url(&path) {
+            handler.status.set(301);
+            let _ = request.respond(Response::empty(301).with_header(tiny_http::Header {
+                field: "Location".parse().unwrap(),
+                value: location.parse().unwrap(),
+            }));
+            "redirect"
+        } else if path == "/_debug/ast" && *debug {
+            handler.reload();
+            handler.handle_debug_ast(request);
+            "debug"
+        } else if path == "/healthz" && handler.is_endpoint_url(&path) {
+            handler.handle_health(request);
+            "health"
+        } else if path == "/readyz" && {
+            handler.reload();
+            handler.is_endpoint_url(&path)
+        } {
+            handler.handle_ready(request);
+            "ready"
+        } else if path == "/metrics" && {
+            handler.reload();
+            handler.is_endpoint_url(&path)
+        } {
+            handler.handle_metrics(request);
+            "metrics"
         } else {
-            let _ = handler.project.reload();
+            handler.reload();
             let size = handler.handle_file(request);
             output_log(&url, now.elapsed(), size);
-        }
+            "page"
+        };
+        let status = handler.status.replace(200);
+        handler.metrics.record_request(kind, status);
     });
     Ok(())
 }
 
 impl ServerHandler {
+    /// Load the project again if any of its files changed since it was last
+    /// loaded.
+    fn reload(&mut self) {
+        let (version, config_changed) = {
+            let changes = self.changes.lock().unwrap();
+            let since = self.loaded_version.unwrap_or_default();
+            (changes.version, changes.config_changed(since))
+        };
+        if self.loaded_version == Some(version) {
+            self.metrics.record_cache_hit();
+            return;
+        }
+        let result = if config_changed || self.load_error.is_some() {
+            self.reload_details()
+        } else {
+            self.project.reload()
+        };
+        self.metrics.record_reload(result.is_ok());
+        self.loaded_version = result.is_ok().then_some(version);
+        self.load_error = result.err().map(|e| e.to_string());
+    }
+
+    /// Load the project with the settings in `codex.yml` as they are now,
+    /// logging the settings that changed.
+    fn reload_details(&mut self) -> core::Result<()> {
+        let previous = self.project.details.clone();
+        self.project.reload_details(true)?;
+        self.project.details.includes = None;
+        self.project.details.trace_templates = previous.trace_templates;
+        let term = console::Term::stdout();
+        for setting in previous.changed_settings(&self.project.details) {
+            let _ = term.write_line(&format!(
+                "{:>20} {}",
+                style("config changed").dim(),
+                style(setting).bold()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Render a page with settings overridden by the query parameters of
+    /// the request, so authors can preview variants of it. `theme` sets the
+    /// color mode, and other parameters set the front matter field of the
+    /// same name. The project is left as it was afterwards.
+    fn render_with_overrides(
+        &mut self,
+        mut doc: core::Document,
+        params: &[(String, String)],
+    ) -> core::Result<String> {
+        let mut mode = self.project.details.theme.mode;
+        let mut fields = vec![];
+        for (name, value) in params {
+            match name.as_str() {
+                "theme" => {
+                    mode = serde_json::from_value(serde_json::Value::String(value.clone()))
+                        .map_err(|_| {
+                            core::Error::new(format!(
+                                "Unknown theme {}, expected auto, light or dark",
+                                value
+                            ))
+                        })?
+                }
+                _ => fields.push((name.clone(), value.clone())),
+            }
+        }
+        if !fields.is_empty() {
+            doc.frontmatter = doc.frontmatter.with_overrides(&fields)?;
+        }
+        let configured = std::mem::replace(&mut self.project.details.theme.mode, mode);
+        let rendered = doc.render(&self.project, core::OutputFormat::Html);
+        self.project.details.theme.mode = configured;
+        rendered
+    }
+
+    /// Whether a url is free for one of the server's own endpoints, such as
+    /// `/metrics`, rather than the url of a page.
+    fn is_endpoint_url(&self, path: &str) -> bool {
+        let url = self.project.details.page_url(path, false);
+        self.project.get_document_for_url(&url).is_none()
+    }
+
+    /// Report that the server is running, for liveness probes.
+    pub fn handle_health(&self, request: Request) {
+        respond_json(request, 200, serde_json::json!({ "status": "ok" }));
+    }
+
+    /// Report whether the project loaded, for readiness probes. A server
+    /// whose project failed to load after a change isn't ready until the
+    /// problem is fixed. Requests are handled one at a time, so a probe
+    /// sent while the project is loading waits for it to finish.
+    pub fn handle_ready(&self, request: Request) {
+        let body = match self.load_error {
+            Some(ref error) => {
+                self.status.set(503);
+                serde_json::json!({ "ready": false, "error": error })
+            }
+            None => serde_json::json!({
+                "ready": true,
+                "version": self.loaded_version.unwrap_or_default(),
+            }),
+        };
+        respond_json(request, self.status.get(), body);
+    }
+
+    /// Describe how the page at the `url` query parameter is parsed and
+    /// which components and templates render it.
+    pub fn handle_debug_ast(&self, request: Request) {
+        let query = request.url().split_once('?').unwrap_or_default().1;
+        let params = query_params(query);
+        let url = params
+            .iter()
+            .find(|(name, _)| name == "url")
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default();
+        let Some(doc) = self.project.get_document_for_link(url) else {
+            self.status.set(404);
+            let error = format!("No page at {}", url);
+            respond_json(request, 404, serde_json::json!({ "error": error }));
+            return;
+        };
+        match core::debug::page_debug(&self.project, doc)
+            .and_then(|debug| Ok(serde_json::to_value(debug)?))
+        {
+            Ok(body) => respond_json(request, 200, body),
+            Err(e) => {
+                self.respond_error(request, e);
+            }
+        }
+    }
+
+    /// Report the server's metrics for Prometheus to scrape.
+    pub fn handle_metrics(&self, request: Request) {
+        let _ = request.respond(Response::from_string(self.metrics.render()).with_header(
+            tiny_http::Header {
+                field: "Content-Type".parse().unwrap(),
+                value: "text/plain; version=0.0.4; charset=utf-8".parse().unwrap(),
+            },
+        ));
+    }
+
+    /// Respond with an error page for a request that failed.
+    fn respond_error(&self, request: Request, error: impl std::fmt::Display) -> usize {
+        self.status.set(500);
+        let _ = request.respond(Response::from_string(error.to_string()).with_status_code(500));
+        0
+    }
+
+    fn respond404(&self, request: Request) -> usize {
+        self.status.set(404);
+        respond404(&self.project, request)
+    }
+
+    /// Report the current change version and whether a full page reload is
+    /// required since the version passed in the `since` query parameter.
+    pub fn handle_changes(&self, request: Request) {
+        let since = request
+            .url()
+            .split_once("since=")
+            .and_then(|(_, v)| v.split('&').next())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or_default();
+        let changes = self.changes.lock().unwrap();
+        let body = serde_json::json!({
+            "version": changes.version,
+            "full": changes.needs_full_reload(since),
+        });
+        let _ = request.respond(Response::from_string(body.to_string()).with_header(
+            tiny_http::Header {
+                field: "Content-Type".parse().unwrap(),
+                value: "application/json".parse().unwrap(),
+            },
+        ));
+    }
+
+    pub fn handle_live_reload_script(&self, request: Request) {
+        let _ = request.respond(
+            Response::from_string(core::assets::get_str("_internal/livereload.js")).with_header(
+                tiny_http::Header {
+                    field: "Content-Type".parse().unwrap(),
+                    value: "text/javascript".parse().unwrap(),
+                },
+            ),
+        );
+    }
+
+    pub fn handle_search_index(&self, request: Request) -> usize {
+        let index = match core::search::SearchIndex::build(&self.project).and_then(|i| i.to_json())
+        {
+            Ok(index) => index,
+            Err(e) => {
+                return self.respond_error(request, e);
+            }
+        };
+        let l = index.len();
+        let _ = request.respond(Response::from_string(index).with_header(tiny_http::Header {
+            field: "Content-Type".parse().unwrap(),
+            value: "application/json".parse().unwrap(),
+        }));
+        l
+    }
+
     #[allow(dead_code)]
     pub fn handle_static(&self, request: Request) -> usize {
-        let static_path = self
-            .project
-            .path
-            .from_url(&format!("/static{}", request.url()));
+        let path = request.url().split('?').next().unwrap_or_default();
+        let static_path = self.project.path.from_url(&format!("/static{}", path));
         if let Ok(data) = static_path.read() {
             let len = data.len();
             let _ = request.respond(Response::from_data(data));
             len
         } else {
-            respond404(&self.project, request)
+            self.respond404(request)
         }
     }
-    pub fn handle_file(&self, request: Request) -> usize {
-        let url = if request.url() == "/" {
-            request.url()
-        } else {
-            request.url().trim_end_matches('/')
+    /// Serve a stylesheet compiled from SCSS.
+    pub fn handle_stylesheet(&self, request: Request, source: &core::assets::CodexPath) -> usize {
+        let css = match core::assets::compile_scss(source) {
+            Ok(css) => css,
+            Err(e) => {
+                return self.respond_error(request, e);
+            }
         };
-        if let Some(doc) = self.project.get_document_for_url(url) {
-            let renderer = core::HtmlRenderer {
-                render_context: core::RenderContext {
-                    project: &self.project,
-                    document: doc,
-                },
+        let len = css.len();
+        let _ = request.respond(Response::from_string(css).with_header(tiny_http::Header {
+            field: "Content-Type".parse().unwrap(),
+            value: "text/css".parse().unwrap(),
+        }));
+        len
+    }
+
+    /// Serve a resized or WebP copy of a static image, generated the same
+    /// way the build generates it.
+    pub fn handle_image_variant(
+        &self,
+        request: Request,
+        source: &core::assets::CodexPath,
+        variant: &core::images::ImageVariant,
+    ) -> usize {
+        let data = source.read().and_then(|data| {
+            core::images::build_variant(&self.project.details.images, variant, &data)
+        });
+        match data {
+            Ok(data) => {
+                let len = data.len();
+                let _ = request.respond(Response::from_data(data));
+                len
+            }
+            Err(e) => self.respond_error(request, e),
+        }
+    }
+
+    /// Serve a file from a document's assets folder.
+    pub fn handle_attachment(&self, request: Request, file: &core::assets::CodexPath) -> usize {
+        match file.read() {
+            Ok(data) => {
+                let len = data.len();
+                let _ = request.respond(Response::from_data(data));
+                len
+            }
+            Err(_) => self.respond404(request),
+        }
+    }
+
+    pub fn handle_metadata(&self, request: Request, path: &str) -> usize {
+        let url = match self.project.details.url_style {
+            core::UrlStyle::Pretty => {
+                let url = path
+                    .strip_suffix(core::PAGE_METADATA_FILE)
+                    .unwrap_or_default()
+                    .trim_end_matches('/');
+                self.project
+                    .details
+                    .page_url(if url.is_empty() { "/" } else { url }, false)
+            }
+            core::UrlStyle::File => format!("{}.html", path.trim_end_matches(".meta.json")),
+        };
+        let Some(doc) = self
+            .project
+            .get_document_for_url(&url)
+            .filter(|d| d.has_format(core::OutputTarget::Html))
+        else {
+            return self.respond404(request);
+        };
+        let content = match doc.metadata(&self.project) {
+            Ok(metadata) => serde_json::to_string(&metadata).unwrap_or_default(),
+            Err(e) => {
+                return self.respond_error(request, e);
+            }
+        };
+        let l = content.len();
+        let _ = request.respond(
+            Response::from_string(content).with_header(tiny_http::Header {
+                field: "Content-Type".parse().unwrap(),
+                value: "application/json".parse().unwrap(),
+            }),
+        );
+        l
+    }
+
+    pub fn handle_print(&mut self, request: Request) -> usize {
+        if let Some(folder) = self.project.get_folder_for_print_url(request.url()) {
+            let start = std::time::Instant::now();
+            let rendered = core::HtmlRenderer::render_print_view(&self.project, folder);
+            self.metrics
+                .record_render(start.elapsed(), rendered.is_ok());
+            let page_content = match rendered {
+                Ok(i) => inject_live_reload(i),
+                Err(e) => inject_live_reload(format!(
+                    r#"<div class="alert alert-danger">{}</div>"#,
+                    handlebars::html_escape(&e.to_string())
+                )),
             };
-            let page_content = match renderer.render() {
-                Ok(i) => i,
-                Err(e) => format!(r#"<div class="alert alert-danger">{}</div>"#, e),
+            let l = page_content.len();
+            let response = Response::from_string(page_content).with_header(tiny_http::Header {
+                field: "Content-Type".parse().unwrap(),
+                value: "text/html".parse().unwrap(),
+            });
+            let _ = request.respond(response);
+            l
+        } else {
+            self.respond404(request)
+        }
+    }
+    pub fn handle_file(&mut self, request: Request) -> usize {
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+        let (path, query) = (path.to_string(), query.to_string());
+        let url = match self.project.details.url_style {
+            // Folder urls are served their index.html, like static hosts do
+            core::UrlStyle::File if path.ends_with('/') => format!("{}index.html", path),
+            core::UrlStyle::File => path.clone(),
+            core::UrlStyle::Pretty => {
+                let url = path.trim_end_matches('/');
+                self.project
+                    .details
+                    .page_url(if url.is_empty() { "/" } else { url }, false)
+            }
+        };
+        if let Some(doc) = self
+            .project
+            .get_document_for_url(&url)
+            .filter(|d| d.has_format(core::OutputTarget::Html))
+        {
+            // Pages are only served at urls following the trailing slash policy
+            if self.project.details.url_style == core::UrlStyle::Pretty
+                && self.project.details.trailing_slash.is_some()
+                && url != path
+            {
+                let location = match query.as_str() {
+                    "" => url,
+                    query => format!("{}?{}", url, query),
+                };
+                self.status.set(301);
+                let _ = request.respond(Response::empty(301).with_header(tiny_http::Header {
+                    field: "Location".parse().unwrap(),
+                    value: location.parse().unwrap(),
+                }));
+                return 0;
+            }
+            let doc = doc.clone();
+            let start = std::time::Instant::now();
+            let rendered = self.render_with_overrides(doc, &query_params(&query));
+            self.metrics
+                .record_render(start.elapsed(), rendered.is_ok());
+            let page_content = match rendered {
+                Ok(i) => inject_live_reload(i),
+                Err(e) => inject_live_reload(format!(
+                    r#"<div class="alert alert-danger">{}</div>"#,
+                    handlebars::html_escape(&e.to_string())
+                )),
             };
             let l = page_content.len();
             let response = Response::from_string(page_content).with_header(tiny_http::Header {
@@ -94,14 +581,68 @@ impl ServerHandler {
             let _ = request.respond(response);
             l
         } else {
-            respond404(&self.project, request)
+            self.respond404(request)
         }
     }
 }
 
+/// The parameters of a query string, decoded. Parameters without a value
+/// are left out.
+fn query_params(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (percent_decode(name), percent_decode(value)))
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 2;
+            }
+            (b'+', _) => out.push(b' '),
+            (byte, _) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Add the live reload script to a rendered page.
+fn inject_live_reload(mut page: String) -> String {
+    match page.rfind("</body>") {
+        Some(idx) => page.insert_str(idx, LIVE_RELOAD_SCRIPT),
+        None => page.push_str(LIVE_RELOAD_SCRIPT),
+    }
+    page
+}
+
+fn respond_json(request: Request, status: u16, body: serde_json::Value) {
+    let _ = request.respond(
+        Response::from_string(body.to_string())
+            .with_header(tiny_http::Header {
+                field: "Content-Type".parse().unwrap(),
+                value: "application/json".parse().unwrap(),
+            })
+            .with_status_code(status),
+    );
+}
+
 pub fn respond404(project: &Project, request: Request) -> usize {
     let ctx = core::DataContext {
         project: project.details.clone(),
+        messages: core::i18n::messages(&project.details, &project.details.default_language),
         ..Default::default()
     };
     let data = core::render_template(ctx, &core::assets::get_str("_internal/templates/404.html"))
@@ -124,3 +665,18 @@ fn output_log(url: &str, time: std::time::Duration, size: usize) {
     let stats = format!("{} in {}", size.human_count_bytes(), time.human_duration());
     let _ = term.write_line(&format!("{:>20} {}", style(stats).dim(), style(url).bold()));
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_query_params() {
+        assert_eq!(
+            super::query_params("theme=dark&title=Hello%20World+again&empty=&flag"),
+            vec![
+                ("theme".to_string(), "dark".to_string()),
+                ("title".to_string(), "Hello World again".to_string()),
+            ]
+        );
+        assert_eq!(super::percent_decode("100%"), "100%");
+    }
+}
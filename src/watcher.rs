@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+/// The project configuration file, relative to the project root.
+const CONFIG_FILE: &str = "codex.yml";
+
+/// The kind of reload a change to a file requires in the browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only stylesheets changed. The browser can swap them in place.
+    Style,
+    /// Content, templates, or other assets changed and the page must be
+    /// rendered again.
+    Full,
+}
+
+impl ChangeKind {
+    /// Classify a changed file by its path, relative to the project root.
+    pub fn for_path(path: &Path) -> Self {
+        let is_static = path.starts_with("static");
+        let is_css = matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("css") | Some("scss")
+        );
+        if is_static && is_css {
+            ChangeKind::Style
+        } else {
+            ChangeKind::Full
+        }
+    }
+
+    /// The reload needed for a set of changed files. Only a change made up
+    /// entirely of stylesheets can avoid a full reload.
+    pub fn for_paths(paths: &[PathBuf]) -> Self {
+        if paths
+            .iter()
+            .all(|p| ChangeKind::for_path(p) == ChangeKind::Style)
+        {
+            ChangeKind::Style
+        } else {
+            ChangeKind::Full
+        }
+    }
+}
+
+/// Tracks how many changes have been seen and which of them required a full
+/// reload. Clients remember the last version they saw and ask what has
+/// changed since then.
+#[derive(Debug, Default)]
+pub struct ChangeState {
+    pub version: u64,
+    pub last_full: u64,
+    /// The version of the last change to the project configuration
+    pub last_config: u64,
+}
+
+impl ChangeState {
+    fn record(&mut self, kind: ChangeKind) {
+        self.version += 1;
+        if kind == ChangeKind::Full {
+            self.last_full = self.version;
+        }
+    }
+
+    /// Record a change, noting whether it included the project
+    /// configuration.
+    fn record_paths(&mut self, paths: &[PathBuf], kind: ChangeKind) {
+        self.record(kind);
+        if paths.iter().any(|p| p == Path::new(CONFIG_FILE)) {
+            self.last_config = self.version;
+        }
+    }
+
+    /// Whether the project configuration changed after the given version.
+    pub fn config_changed(&self, since: u64) -> bool {
+        self.last_config > since
+    }
+
+    /// Whether any change after the given version requires a full reload.
+    pub fn needs_full_reload(&self, since: u64) -> bool {
+        self.last_full > since
+    }
+}
+
+/// Watch the project folder for changes by polling file modification times.
+pub fn watch(
+    root: PathBuf,
+    ignore: Vec<PathBuf>,
+    interval: Duration,
+    on_change: impl Fn(&[PathBuf], ChangeKind) + Send + 'static,
+) -> Arc<Mutex<ChangeState>> {
+    let state = Arc::new(Mutex::new(ChangeState::default()));
+    let thread_state = state.clone();
+    std::thread::spawn(move || {
+        let mut snapshot = scan(&root, &ignore);
+        loop {
+            std::thread::sleep(interval);
+            let current = scan(&root, &ignore);
+            let changed = changed_paths(&snapshot, &current);
+            snapshot = current;
+            if changed.is_empty() {
+                continue;
+            }
+            let kind = ChangeKind::for_paths(&changed);
+            thread_state.lock().unwrap().record_paths(&changed, kind);
+            on_change(&changed, kind);
+        }
+    });
+    state
+}
+
+/// The files added, modified, or removed between two scans.
+fn changed_paths(
+    snapshot: &HashMap<PathBuf, SystemTime>,
+    current: &HashMap<PathBuf, SystemTime>,
+) -> Vec<PathBuf> {
+    let mut changed = current
+        .iter()
+        .filter(|(path, modified)| snapshot.get(*path) != Some(*modified))
+        .map(|(path, _)| path.clone())
+        .chain(
+            snapshot
+                .keys()
+                .filter(|path| !current.contains_key(*path))
+                .cloned(),
+        )
+        .collect::<Vec<_>>();
+    changed.sort();
+    changed
+}
+
+/// Collect the modification time of every file in the project, keyed by its
+/// path relative to the project root.
+fn scan(root: &Path, ignore: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| {
+            let relative = e.path().strip_prefix(root).unwrap_or(e.path());
+            !ignore.iter().any(|i| relative.starts_with(i))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let modified = e.metadata().ok()?.modified().ok()?;
+            let relative = e.path().strip_prefix(root).ok()?.to_path_buf();
+            Some((relative, modified))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    };
+
+    use super::{changed_paths, ChangeKind, ChangeState};
+
+    #[test]
+    fn test_change_kind() {
+        assert_eq!(
+            ChangeKind::for_path(Path::new("static/css/site.css")),
+            ChangeKind::Style
+        );
+        assert_eq!(
+            ChangeKind::for_path(Path::new("static/css/site.scss")),
+            ChangeKind::Style
+        );
+        assert_eq!(
+            ChangeKind::for_path(Path::new("static/js/site.js")),
+            ChangeKind::Full
+        );
+        assert_eq!(
+            ChangeKind::for_path(Path::new("_internal/theme.css")),
+            ChangeKind::Full
+        );
+        assert_eq!(
+            ChangeKind::for_path(Path::new("guide/index.md")),
+            ChangeKind::Full
+        );
+        assert_eq!(
+            ChangeKind::for_paths(&[PathBuf::from("static/a.css"), PathBuf::from("static/b.css")]),
+            ChangeKind::Style
+        );
+        assert_eq!(
+            ChangeKind::for_paths(&[PathBuf::from("static/a.css"), PathBuf::from("index.md")]),
+            ChangeKind::Full
+        );
+    }
+
+    #[test]
+    fn test_changed_paths() {
+        let time = SystemTime::UNIX_EPOCH;
+        let later = time + Duration::from_secs(1);
+        let snapshot = HashMap::from([
+            (PathBuf::from("same.md"), time),
+            (PathBuf::from("edited.md"), time),
+            (PathBuf::from("removed.md"), time),
+        ]);
+        let current = HashMap::from([
+            (PathBuf::from("same.md"), time),
+            (PathBuf::from("edited.md"), later),
+            (PathBuf::from("added.md"), time),
+        ]);
+        assert_eq!(
+            changed_paths(&snapshot, &current),
+            vec![
+                PathBuf::from("added.md"),
+                PathBuf::from("edited.md"),
+                PathBuf::from("removed.md")
+            ]
+        );
+        assert!(changed_paths(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_change_state() {
+        let mut state = ChangeState::default();
+        state.record(ChangeKind::Style);
+        assert_eq!(state.version, 1);
+        assert!(!state.needs_full_reload(0));
+
+        state.record(ChangeKind::Full);
+        state.record(ChangeKind::Style);
+        assert_eq!(state.version, 3);
+        // A client that saw the first change still needs the full reload,
+        // one that saw the full reload only needs styles
+        assert!(state.needs_full_reload(1));
+        assert!(!state.needs_full_reload(2));
+
+        state.record_paths(&[PathBuf::from("codex.yml")], ChangeKind::Full);
+        state.record_paths(&[PathBuf::from("index.md")], ChangeKind::Full);
+        assert!(state.config_changed(3));
+        assert!(!state.config_changed(4));
+    }
+}